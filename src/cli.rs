@@ -1,24 +1,239 @@
+mod completions;
+mod explain;
 mod help_version;
 mod parse;
 mod types;
 mod utilities;
 mod validation;
 
+use self::completions::print_completions;
+use self::explain::print_explanation;
 use self::parse::*;
+pub use self::parse::{parse_join, parse_selection_token};
 use self::types::*;
 use self::utilities::parse_delimiter_token;
 use self::validation::*;
+pub use self::validation::{validate_join_mode, validate_selections};
 use crate::types::*;
 
 use fancy_regex::Regex as FancyRegex;
 use regex::Regex as SimpleRegex;
+use regex::bytes::Regex as BytesRegex;
 use std::env;
 use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+/// The byte to split on when a delimiter is one literal byte, like `,` or `/:/`, so it can
+/// skip the regex engine. A lone metacharacter only counts when given as a literal.
+fn single_byte_delimiter(delimiter: &Delimiter) -> Option<u8> {
+    match delimiter {
+        Delimiter::Literal(value) => match value.as_bytes() {
+            [byte] => Some(*byte),
+            _ => None,
+        },
+        Delimiter::Regex(value) => match value.as_bytes() {
+            [byte] if regex::escape(value) == *value => Some(*byte),
+            _ => None,
+        },
+    }
+}
+
+/// Several delimiters become one regex that matches any of them. Literals are escaped and each
+/// regex is grouped, so its alternations and inline flags stay its own. An empty one is kept
+/// as-is so it is reported like a lone empty delimiter.
+fn combine_delimiters(mut delimiters: Vec<Delimiter>) -> Option<Delimiter> {
+    if delimiters.len() < 2 {
+        return delimiters.pop();
+    }
+    let mut alternatives: Vec<String> = Vec::with_capacity(delimiters.len());
+    for delimiter in delimiters {
+        match delimiter {
+            Delimiter::Literal(value) | Delimiter::Regex(value) if value.is_empty() => {
+                return Some(Delimiter::Literal(value));
+            }
+            Delimiter::Literal(value) => alternatives.push(regex::escape(&value)),
+            Delimiter::Regex(value) => alternatives.push(format!("(?:{value})")),
+        }
+    }
+    Some(Delimiter::Regex(alternatives.join("|")))
+}
+
+/// Both regex engines read `\0` as a backreference and reject it, so in a delimiter it's
+/// taken to mean a NUL byte, as `\x00` would. Escaped backslashes are left alone.
+fn nul_escapes(pattern: &str) -> String {
+    let mut output = String::with_capacity(pattern.len());
+    let mut characters = pattern.chars().peekable();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            output.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('0') if !characters.peek().is_some_and(char::is_ascii_digit) => {
+                output.push_str(r"\x00");
+            }
+            Some(next) => {
+                output.push('\\');
+                output.push(next);
+            }
+            None => output.push('\\'),
+        }
+    }
+    output
+}
+
+/// Picks the engine that splits fields on a delimiter: a byte or literal search where the
+/// delimiter allows it, otherwise a compiled regex.
+pub fn delimiter_engine(
+    delimiter: Delimiter,
+    fixed: bool,
+    ignore_case: bool,
+) -> Result<RegexEngine, String> {
+    let empty_error = || "empty string is not a valid delimiter".to_string();
+    if fixed {
+        // --fixed takes the delimiter verbatim, so /.../ keeps its slashes
+        let literal = match delimiter {
+            Delimiter::Literal(value) => value,
+            Delimiter::Regex(value) => format!("/{value}/"),
+        };
+        if literal.is_empty() {
+            return Err(empty_error());
+        }
+        return if ignore_case {
+            // memmem can't fold case, so match the escaped literal with a regex instead
+            compile_regex(&format!("(?i){}", regex::escape(&literal)))
+        } else if let [byte] = literal.as_bytes() {
+            Ok(RegexEngine::Byte(*byte))
+        } else {
+            Ok(RegexEngine::Literal(literal.into_bytes()))
+        };
+    }
+    if !ignore_case && let Some(byte) = single_byte_delimiter(&delimiter) {
+        return Ok(RegexEngine::Byte(byte));
+    }
+
+    let delimiter_pattern = match delimiter {
+        Delimiter::Literal(value) if !value.is_empty() => regex::escape(&value),
+        Delimiter::Regex(value) if !value.is_empty() => nul_escapes(&value),
+        _ => return Err(empty_error()),
+    };
+    // A NUL can't be passed as a literal argument, but it's still a single byte to search for
+    if !ignore_case && delimiter_pattern == r"\x00" {
+        return Ok(RegexEngine::Byte(0));
+    }
+    // Leading (?i) still lets inline flags in the pattern switch it back off
+    if ignore_case {
+        compile_regex(&format!("(?i){delimiter_pattern}"))
+    } else {
+        compile_regex(&delimiter_pattern)
+    }
+}
+
+/// Matches one selection token, such as `2`, `-1`, `first-3` or `1-10:2`.
+pub fn selection_regex() -> SimpleRegex {
+    SimpleRegex::new(
+        r"^(?i)(?P<start>start|first|end|last|-?\d+)(?:-(?P<end>start|first|end|last|-?\d+)(?::(?P<step>-?\d+))?)?$",
+    )
+    .unwrap()
+}
+
+/// --slice-syntax: matches `a:b`, `a:`, `:b`, `:` and `a:b:step` slices, or a single index.
+/// The colon can't also mean a step, so the usual `a-b` ranges aren't accepted alongside.
+pub fn slice_selection_regex() -> SimpleRegex {
+    SimpleRegex::new(
+        r"^(?i)(?:(?P<start>start|first|end|last|-?\d+)|(?P<slice>(?P<slice_start>-?\d+)?:(?P<slice_end>-?\d+)?(?::(?P<step>-?\d+))?))$",
+    )
+    .unwrap()
+}
+
+/// A percent selection takes a slot among the others, so the order they're written in is kept.
+/// The slot holds field 1 until process_fields swaps in the record's own field.
+fn push_percent_selection(cli_arguments: &mut CLIArguments, (start, end): (u32, u32)) {
+    cli_arguments.percent_selections.push(PercentSelection {
+        slot: cli_arguments.selections.len(),
+        start,
+        end,
+    });
+    cli_arguments.selections.push((1, 1, 1));
+}
+
+/// True if a bare argument reads like a selection rather than a file name, having no path
+/// separator or extension, like `x` or `1-x`.
+fn looks_like_selection(arg: &str) -> bool {
+    arg.chars()
+        .all(|character| character.is_ascii_alphanumeric() || matches!(character, '-' | ':' | '+'))
+}
+
+/// Compile with the fast regex engine, falling back to fancy-regex for lookaround etc.
+/// If neither accepts the pattern, both errors are reported, since either could be the one meant.
+fn compile_regex(pattern: &str) -> Result<RegexEngine, String> {
+    let simple_error = match BytesRegex::new(pattern) {
+        Ok(regex) => return Ok(RegexEngine::Simple(regex)),
+        Err(error) => error,
+    };
+    let fancy_error = match FancyRegex::new(pattern) {
+        Ok(regex) => return Ok(RegexEngine::Fancy(regex)),
+        Err(error) => error,
+    };
+
+    let mut message = format!(
+        "failed to compile regex '{pattern}'\n  regex: {}\n  fancy-regex: {fancy_error}",
+        simple_error
+            .to_string()
+            .lines()
+            .last()
+            .unwrap_or_default()
+            .trim()
+            .trim_start_matches("error: ")
+    );
+    if let Some(hint) = regex_error_hint(pattern) {
+        message.push_str("\nhint: ");
+        message.push_str(hint);
+    }
+    Err(message)
+}
+
+/// Suggests a fix for the usual reasons a pattern fails to compile.
+fn regex_error_hint(pattern: &str) -> Option<&'static str> {
+    let mut parens: i32 = 0;
+    let mut in_class = false;
+    let mut chars = pattern.chars();
+    while let Some(character) = chars.next() {
+        match character {
+            '\\' => {
+                chars.next();
+            }
+            '[' if !in_class => in_class = true,
+            ']' if in_class => in_class = false,
+            '(' if !in_class => parens += 1,
+            ')' if !in_class => parens -= 1,
+            _ => {}
+        }
+    }
+    if in_class || parens != 0 {
+        return Some(
+            "brackets or parentheses are unbalanced; escape literal ones with '\\', or use --fixed for a literal delimiter",
+        );
+    }
+    if pattern.contains("(?<=") || pattern.contains("(?<!") {
+        return Some(
+            "lookbehind must match a fixed length, so it cannot contain '+', '*' or '{n,m}'",
+        );
+    }
+    None
+}
 
 /// Parse command line arguments and return Instructions
 pub fn get_instructions() -> Result<Option<Instructions>, String> {
     let args: Vec<String> = env::args().skip(1).collect();
 
+    // A hidden subcommand, kept out of parsing so it can't clash with selections
+    if args.first().is_some_and(|arg| arg == "completions") {
+        print_completions(&args[1..])?;
+        return Ok(None);
+    }
+
     // So the logic here is this:
     // - If previous token was consuming flag, treat arg as input for that flag
     //     - join flag
@@ -37,21 +252,103 @@ pub fn get_instructions() -> Result<Option<Instructions>, String> {
 
     let mut cli_arguments = CLIArguments {
         output: None,
-        input: None,
+        output_append: false,
+        trim_newline: false,
+        input: Vec::new(),
+        selections_files: Vec::new(),
+        delimiter_file: None,
+        spec: None,
         join: None,
-        delimiter: None,
-        placeholder: None,
+        field_terminator: None,
+        delimiters: Vec::new(),
+        fixed: false,
+        ignore_case: false,
+        csv: false,
+        capture: None,
+        json_input: None,
+        field_pattern: None,
+        field_length: None,
+        field_length_basis: None,
+        record_separator: None,
+        require: None,
+        pick: None,
+        from: None,
+        to: None,
+        exclude_anchors: false,
+        comment_prefixes: Vec::new(),
+        placeholders: Vec::new(),
+        pad_char: None,
+        align_tabs: false,
+        separators: None,
+        replacements: Vec::new(),
+        max_fields: None,
+        max_records: None,
+        tail: None,
+        input_encoding: None,
+        max_record_bytes: None,
+        header: None,
+        escape: true,
         align: Align::None,
         input_mode: InputMode::PerLine,
         selection_mode: SelectionMode::Fields,
+        codepoints: false,
         count: false,
+        count_nonempty: false,
+        count_unit: None,
+        count_total: false,
+        histogram: false,
+        escape_output: false,
+        stats: false,
+        complement_count: false,
+        index_pair_separator: None,
+        json: false,
+        transpose: false,
+        shell_quote: false,
+        with_original: None,
+        original_separator: None,
+        encoding: None,
+        decoding: None,
+        crlf_output: false,
+        output_terminator: None,
+        fail_on_empty_output: false,
+        number: false,
+        explain: false,
+        unordered: false,
         invert: false,
+        invert_keep_positions: false,
+        redact: false,
+        null_on_missing: false,
+        offsets: false,
         skip_empty: false,
+        squash_delimiters: false,
+        keep_trailing_empty: false,
+        trim: Trim::None,
+        only_delimited: false,
+        unique: false,
+        sort: false,
+        sort_numeric: false,
+        sort_reverse: false,
+        reverse: false,
+        dedup_key: None,
+        jobs: None,
+        reorder_window: None,
+        batch_bytes: None,
+        read_buffer: None,
+        line_buffered: false,
+        progress: false,
+        assert_sorted: None,
+        numeric: false,
+        assert_uniform: false,
         strict_utf8: false,
+        keep_bom: false,
+        strip_cr: false,
         strict_return: false,
+        strict_empty: false,
         strict_bounds: false,
         strict_range_order: true,
+        strict_require: false,
         selections: Vec::new(),
+        percent_selections: Vec::new(),
     };
 
     let mut consuming = Consuming {
@@ -61,14 +358,54 @@ pub fn get_instructions() -> Result<Option<Instructions>, String> {
         join: false,
         placeholder: false,
         align: false,
+        capture: false,
+        json_input: false,
+        field_pattern: false,
+        field_length: false,
+        field_length_basis: false,
+        count_unit: false,
+        record_separator: false,
+        require: false,
+        pick: false,
+        from: false,
+        to: false,
+        comment: false,
+        dedup_key: false,
+        assert_sorted: false,
+        jobs: false,
+        reorder_window: false,
+        batch_bytes: false,
+        read_buffer: false,
+        pad_char: false,
+        align_with: false,
+        separators: false,
+        replace: false,
+        max_fields: false,
+        max_records: false,
+        tail: false,
+        output_terminator: false,
+        field_terminator: false,
+        input_encoding: false,
+        max_record_bytes: false,
+        header: false,
+        selections_file: false,
+        delimiter_file: false,
+        spec: false,
+        original_separator: false,
     };
 
-    let selection_regex = SimpleRegex::new(
-        r"^(?i)(?P<start>start|first|end|last|-?\d+)(?:-(?P<end>start|first|end|last|-?\d+))?$",
-    )
-    .unwrap();
+    // Read ahead, as selections before the flag are parsed with the same syntax as those after
+    let slice_syntax = args
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg == "--slice-syntax");
+    let selection_regex = match slice_syntax {
+        true => slice_selection_regex(),
+        false => selection_regex(),
+    };
 
     let mut flags_finished = false;
+    let mut implicit_delimiter: Option<String> = None;
     for arg in args {
         if !flags_finished {
             match parse_flags(&arg, &mut consuming, &mut cli_arguments) {
@@ -86,6 +423,11 @@ pub fn get_instructions() -> Result<Option<Instructions>, String> {
             continue;
         }
 
+        if let Some(percent) = parse_percent_token(&arg) {
+            push_percent_selection(&mut cli_arguments, percent?);
+            continue;
+        }
+
         // First, check if the whole arg is a single selection token (e.g., "2" or "1-3")
         if selection_regex.is_match(&arg) {
             let parse = parse_selection_token(&arg, &selection_regex);
@@ -94,16 +436,14 @@ pub fn get_instructions() -> Result<Option<Instructions>, String> {
                     cli_arguments.selections.push(selection);
                     continue;
                 }
-                Err(_) => {
-                    // This should never happen, but just in case
-                    return Err(format!("invalid selection: {}", arg));
-                }
+                // The regex has matched, so only a zero step can fail here
+                Err(error) => return Err(error),
             }
         }
 
         // If it contains commas or spaces, split and check each part
         if arg.contains(',') || arg.contains(' ') {
-            let tokens: Vec<&str> = arg.split(|c| c == ',' || c == ' ').collect();
+            let tokens: Vec<&str> = arg.split([',', ' ']).collect();
             let mut first_non_empty: Option<&str> = None;
             for token in &tokens {
                 let trimmed = token.trim();
@@ -116,52 +456,212 @@ pub fn get_instructions() -> Result<Option<Instructions>, String> {
             }
 
             // If first non-empty part is a selection, all parts must be selections
-            if let Some(first) = first_non_empty {
-                if selection_regex.is_match(first) {
-                    for token in &tokens {
-                        let trimmed = token.trim();
-                        if trimmed.is_empty() {
-                            continue;
-                        }
-                        if !selection_regex.is_match(trimmed) {
-                            return Err(format!("invalid selection: {}", trimmed));
-                        }
-                        let parse = parse_selection_token(trimmed, &selection_regex);
-                        match parse {
-                            Ok(selection) => cli_arguments.selections.push(selection),
-                            Err(error) => return Err(error),
-                        }
+            if let Some(first) = first_non_empty
+                && (selection_regex.is_match(first) || parse_percent_token(first).is_some())
+            {
+                for token in &tokens {
+                    let trimmed = token.trim();
+                    if trimmed.is_empty() {
+                        continue;
+                    }
+                    if let Some(percent) = parse_percent_token(trimmed) {
+                        push_percent_selection(&mut cli_arguments, percent?);
+                        continue;
+                    }
+                    if !selection_regex.is_match(trimmed) {
+                        return Err(format!("invalid selection: {}", trimmed));
+                    }
+                    let parse = parse_selection_token(trimmed, &selection_regex);
+                    match parse {
+                        Ok(selection) => cli_arguments.selections.push(selection),
+                        Err(error) => return Err(error),
                     }
-                    continue;
                 }
+                continue;
             }
         }
         // The only possibility left is a bad flag or implicit delimiter
         // First, make sure it isn't a bad flag
-        if !flags_finished && arg.starts_with("-") {
+        if !flags_finished && arg.starts_with("-") && arg != "-" {
             return Err(format!("invalid flag: {}", arg));
         }
         // If it's not a selection or flag and we have no delimiter yet, assume it's an implicit
-        if cli_arguments.delimiter.is_none() {
-            cli_arguments.delimiter = Some(parse_delimiter_token(&arg));
+        if cli_arguments.delimiters.is_empty() {
+            cli_arguments.delimiters.push(parse_delimiter_token(&arg));
+            implicit_delimiter = Some(arg);
             continue;
         }
-        // We already have a delimiter, nothing left for it to be
-        return Err(format!("invalid argument: {}", arg));
+        // We already have a delimiter, so anything left is an input file. A word that could be
+        // a mistyped selection is only a file if it exists or comes after --
+        if !flags_finished
+            && arg != "-"
+            && looks_like_selection(&arg)
+            && !std::path::Path::new(&arg).exists()
+        {
+            return Err(format!("invalid selection: {}", arg));
+        }
+        cli_arguments.input.push(PathBuf::from(arg));
+    }
+
+    // Selections from files follow any given on the command line
+    for path in &cli_arguments.selections_files {
+        let selections = parse_selections_file(path, &selection_regex)?;
+        cli_arguments.selections.extend(selections);
+    }
+
+    // A spec only fills in what the flags left unset, so a flag always wins over it
+    if let Some(spec) = &cli_arguments.spec {
+        let spec = parse_spec(spec)?;
+        if let Some(delimiter) = spec.delimiter
+            && cli_arguments.delimiter_file.is_none()
+        {
+            // With the delimiter in the spec, a bare argument taken as the delimiter was a file
+            if let Some(arg) = implicit_delimiter.take() {
+                cli_arguments.delimiters.clear();
+                cli_arguments.input.insert(0, PathBuf::from(arg));
+            }
+            if cli_arguments.delimiters.is_empty() {
+                cli_arguments
+                    .delimiters
+                    .push(parse_delimiter_token(&delimiter));
+            }
+        }
+        if let Some(selections) = spec.selections
+            && cli_arguments.selections.is_empty()
+        {
+            for token in selections.split(',').map(str::trim) {
+                if let Some(percent) = parse_percent_token(token) {
+                    push_percent_selection(&mut cli_arguments, percent?);
+                    continue;
+                }
+                if !selection_regex.is_match(token) {
+                    return Err(format!("invalid spec: invalid selection '{token}'"));
+                }
+                let selection = parse_selection_token(token, &selection_regex)?;
+                cli_arguments.selections.push(selection);
+            }
+        }
+        if let Some(join) = spec.join
+            && cli_arguments.join.is_none()
+        {
+            cli_arguments.join = Some(join.into_bytes());
+        }
+        if let Some(placeholder) = spec.placeholder
+            && cli_arguments.placeholders.is_empty()
+            && !cli_arguments.null_on_missing
+        {
+            cli_arguments.placeholders.push(placeholder.into_bytes());
+        }
+    }
+
+    if let Some(path) = &cli_arguments.delimiter_file {
+        // With the delimiter in a file, a bare argument taken as the delimiter was a file too
+        if let Some(arg) = implicit_delimiter.take() {
+            cli_arguments.input.insert(0, PathBuf::from(arg));
+        } else if !cli_arguments.delimiters.is_empty() {
+            return Err("--delimiter-file cannot be combined with --delimiter".to_string());
+        }
+        cli_arguments.delimiters = vec![parse_delimiter_token(&read_delimiter_file(path)?)];
+    }
+
+    // Names are left for the reader to look up in the header record
+    let header: Option<Arc<HeaderSelections>> = match &cli_arguments.header {
+        Some(header) => Some(Arc::new(HeaderSelections {
+            tokens: parse_header_selections(header, &selection_regex)?,
+            resolved: OnceLock::new(),
+        })),
+        None => None,
+    };
+
+    let delimiter_inferred =
+        implicit_delimiter.is_some() && cli_arguments.selection_mode == SelectionMode::Fields;
+
+    // Only fields mode uses a delimiter, so elsewhere the first bare argument was a file
+    if cli_arguments.selection_mode != SelectionMode::Fields
+        && let Some(arg) = implicit_delimiter
+    {
+        cli_arguments.delimiters.clear();
+        cli_arguments.input.insert(0, PathBuf::from(arg));
+    }
+
+    // As in Python, a slice that runs backwards on a record is empty rather than an error.
+    // Slices are the only ranges --slice-syntax accepts, so range order never applies
+    if slice_syntax {
+        cli_arguments.strict_range_order = false;
     }
 
     // Handle validations
-    let join: Option<JoinMode> = match cli_arguments.join {
-        Some(join) => {
+    validate_selections(&cli_arguments.selections, cli_arguments.selection_mode)
+        .map_err(|e| e.to_string())?;
+    if let Some(header) = &header {
+        let indexes: Vec<(i32, i32, i32)> = header
+            .tokens
+            .iter()
+            .filter_map(|token| match token {
+                HeaderSelection::Index(selection) => Some(*selection),
+                HeaderSelection::Name(_) => None,
+            })
+            .collect();
+        validate_selections(&indexes, cli_arguments.selection_mode).map_err(|e| e.to_string())?;
+    }
+    validate_field_terminator(
+        cli_arguments.field_terminator.is_some(),
+        cli_arguments.join.is_some(),
+    )
+    .map_err(|e| e.to_string())?;
+    let join: Option<JoinMode> = match (cli_arguments.field_terminator, cli_arguments.join) {
+        // A field terminator is a plain join, which byte mode also honours
+        (Some(terminator), _) => Some(JoinMode::String(terminator)),
+        (None, Some(join)) => {
             validate_join_mode(&join, cli_arguments.selection_mode).map_err(|e| e.to_string())?;
-            parse_join(&join)
+            parse_join(&join, cli_arguments.escape)
         }
-        None => None,
+        (None, None) => None,
     };
 
-    let placeholder: Option<Vec<u8>> = match cli_arguments.placeholder {
-        Some(placeholder) => parse_placeholder(&placeholder),
-        None => None,
+    validate_null_on_missing(
+        cli_arguments.null_on_missing,
+        !cli_arguments.placeholders.is_empty(),
+    )
+    .map_err(|e| e.to_string())?;
+    let placeholders: Vec<Vec<u8>> = match cli_arguments.null_on_missing {
+        true => vec![vec![0]],
+        false => cli_arguments
+            .placeholders
+            .iter()
+            .filter_map(|placeholder| parse_placeholder(placeholder, cli_arguments.escape))
+            .collect(),
+    };
+
+    let original_separator: Vec<u8> = match &cli_arguments.original_separator {
+        Some(separator) => parse_placeholder(separator, cli_arguments.escape).unwrap_or_default(),
+        None => b"\t".to_vec(),
+    };
+
+    let separators: Vec<Vec<u8>> = match &cli_arguments.separators {
+        Some(list) => list
+            .split(|byte| *byte == b',')
+            .map(|separator| parse_placeholder(separator, cli_arguments.escape).unwrap_or_default())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let mut inserted_separators: Vec<&[u8]> = separators.iter().map(Vec::as_slice).collect();
+    if let Some(JoinMode::String(join)) = &join {
+        inserted_separators.push(join);
+    }
+    validate_escape_output(cli_arguments.escape_output, &inserted_separators)
+        .map_err(|e| e.to_string())?;
+
+    let replacements: Vec<(Vec<u8>, Vec<u8>)> = cli_arguments
+        .replacements
+        .iter()
+        .map(|replacement| parse_replacement(replacement, cli_arguments.escape))
+        .collect::<Result<_, _>>()?;
+
+    let pad: Vec<u8> = match &cli_arguments.pad_char {
+        Some(pad_char) => parse_pad_char(pad_char, cli_arguments.escape)?,
+        None => b" ".to_vec(),
     };
 
     validate_align(
@@ -173,43 +673,429 @@ pub fn get_instructions() -> Result<Option<Instructions>, String> {
     if consuming.align {
         cli_arguments.align = Align::Left;
     }
+    validate_align_with(
+        cli_arguments.align_tabs,
+        cli_arguments.align,
+        join.is_some(),
+        cli_arguments.pad_char.is_some(),
+    )
+    .map_err(|e| e.to_string())?;
+    // Each column ends at a tab and the terminal's tabstops line them up, so no widths
+    // are measured and the output streams
+    let join: Option<JoinMode> = match cli_arguments.align_tabs {
+        true => {
+            cli_arguments.align = Align::Tabs;
+            Some(JoinMode::String(b"\t".to_vec()))
+        }
+        false => join,
+    };
+    validate_count_mode(cli_arguments.count_nonempty, cli_arguments.selection_mode)
+        .map_err(|e| e.to_string())?;
+    validate_count_unit(
+        cli_arguments.count_unit,
+        cli_arguments.count_nonempty,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_output_terminator(
+        cli_arguments.output_terminator.is_some(),
+        cli_arguments.crlf_output,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_output_append(cli_arguments.output_append, cli_arguments.output.is_some())
+        .map_err(|e| e.to_string())?;
+    validate_count_total(
+        cli_arguments.count_total,
+        cli_arguments.with_original.is_some(),
+        cli_arguments.number,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_histogram(
+        cli_arguments.histogram,
+        cli_arguments.count_total,
+        cli_arguments.with_original.is_some(),
+        cli_arguments.number,
+    )
+    .map_err(|e| e.to_string())?;
+    // The total and the histogram are both gathered from the per-record counts
+    cli_arguments.count |= cli_arguments.count_total || cli_arguments.histogram;
+    let histogram: Option<&'static str> = match cli_arguments.histogram {
+        false => None,
+        true => Some(
+            match (cli_arguments.count_unit, cli_arguments.selection_mode) {
+                (Some(CountUnit::Bytes), _) | (None, SelectionMode::Bytes) => "byte",
+                (Some(CountUnit::Chars), _) | (None, SelectionMode::Chars) => "char",
+                (None, SelectionMode::Capture) => "group",
+                _ => "field",
+            },
+        ),
+    };
+    validate_stats(
+        cli_arguments.stats,
+        cli_arguments.count,
+        cli_arguments.json,
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_complement_count(
+        cli_arguments.complement_count,
+        cli_arguments.selection_mode,
+        cli_arguments.align,
+        cli_arguments.count
+            || cli_arguments.stats
+            || cli_arguments.json
+            || cli_arguments.redact
+            || cli_arguments.transpose
+            || cli_arguments.shell_quote
+            || cli_arguments.index_pair_separator.is_some(),
+    )
+    .map_err(|e| e.to_string())?;
+    validate_redact(
+        cli_arguments.redact,
+        !placeholders.is_empty() || cli_arguments.invert_keep_positions,
+        cli_arguments.selection_mode,
+        cli_arguments.align,
+        cli_arguments.count
+            || cli_arguments.json
+            || cli_arguments.index_pair_separator.is_some()
+            || cli_arguments.sort
+            || cli_arguments.sort_numeric
+            || cli_arguments.sort_reverse
+            || cli_arguments.unique
+            || cli_arguments.reverse,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_offsets(
+        cli_arguments.offsets,
+        cli_arguments.selection_mode,
+        cli_arguments.csv,
+        cli_arguments.align,
+        cli_arguments.field_pattern.is_some()
+            || cli_arguments.json
+            || cli_arguments.stats
+            || cli_arguments.redact
+            || cli_arguments.transpose
+            || cli_arguments.index_pair_separator.is_some()
+            || cli_arguments.shell_quote
+            || cli_arguments.encoding.is_some()
+            || cli_arguments.decoding.is_some()
+            || join.is_some(),
+    )
+    .map_err(|e| e.to_string())?;
+    validate_index_pairs(
+        cli_arguments.index_pair_separator.is_some(),
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_only_delimited(cli_arguments.only_delimited, cli_arguments.selection_mode)
+        .map_err(|e| e.to_string())?;
+    validate_require(
+        cli_arguments.require.is_some(),
+        cli_arguments.selection_mode,
+        cli_arguments.transpose,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_json_input(
+        !cli_arguments.selections.is_empty() || header.is_some(),
+        cli_arguments.invert,
+        cli_arguments.count,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    let json_paths: Vec<Vec<String>> = match &cli_arguments.json_input {
+        Some(paths) => parse_json_paths(paths)?,
+        None => Vec::new(),
+    };
+    validate_percent_selections(
+        !cli_arguments.percent_selections.is_empty(),
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+        cli_arguments.transpose,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_pick(
+        cli_arguments.pick.is_some(),
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+        cli_arguments.transpose,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_anchors(
+        cli_arguments.from.is_some() || cli_arguments.to.is_some(),
+        cli_arguments.exclude_anchors,
+        !cli_arguments.selections.is_empty() || header.is_some(),
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+        cli_arguments.transpose,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_sort(
+        cli_arguments.sort || cli_arguments.sort_numeric || cli_arguments.sort_reverse,
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_max_records(
+        cli_arguments.max_records.is_some(),
+        cli_arguments.input_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_tail(cli_arguments.tail.is_some(), cli_arguments.input_mode)
+        .map_err(|e| e.to_string())?;
+    validate_transpose(
+        cli_arguments.transpose,
+        cli_arguments.input_mode,
+        cli_arguments.selection_mode,
+        cli_arguments.align,
+        cli_arguments.count || cli_arguments.json || cli_arguments.index_pair_separator.is_some(),
+        cli_arguments.shell_quote
+            || cli_arguments.sort
+            || cli_arguments.sort_numeric
+            || cli_arguments.sort_reverse
+            || cli_arguments.unique
+            || cli_arguments.reverse
+            || cli_arguments.field_pattern.is_some()
+            || cli_arguments.encoding.is_some()
+            || cli_arguments.decoding.is_some(),
+    )
+    .map_err(|e| e.to_string())?;
+    validate_header(
+        header.is_some(),
+        cli_arguments.selection_mode,
+        cli_arguments.input_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_max_fields(
+        cli_arguments.max_fields.is_some(),
+        cli_arguments.csv,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_separators(
+        cli_arguments.separators.is_some(),
+        cli_arguments.json,
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_replace(
+        !cli_arguments.replacements.is_empty(),
+        cli_arguments.json,
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_reverse(
+        cli_arguments.reverse,
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_unique(
+        cli_arguments.unique,
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_trim(cli_arguments.trim, cli_arguments.selection_mode).map_err(|e| e.to_string())?;
+    validate_codepoints(cli_arguments.codepoints, cli_arguments.selection_mode)
+        .map_err(|e| e.to_string())?;
+    validate_field_pattern(
+        cli_arguments.field_pattern.is_some(),
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_field_length(
+        cli_arguments.field_length.is_some(),
+        cli_arguments.field_length_basis.is_some(),
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_encoding(cli_arguments.encoding.is_some(), cli_arguments.align)
+        .map_err(|e| e.to_string())?;
+    validate_decoding(
+        cli_arguments.decoding.is_some(),
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_json(cli_arguments.json, join.is_some(), cli_arguments.align)
+        .map_err(|e| e.to_string())?;
+    validate_with_original(
+        cli_arguments.with_original.is_some(),
+        cli_arguments.original_separator.is_some(),
+        cli_arguments.json,
+        cli_arguments.align,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_shell_quote(
+        cli_arguments.shell_quote,
+        cli_arguments.json,
+        cli_arguments.align,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_csv(
+        cli_arguments.csv,
+        cli_arguments.ignore_case,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_keep_trailing_empty(
+        cli_arguments.keep_trailing_empty,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_squash_delimiters(
+        cli_arguments.squash_delimiters,
+        cli_arguments.selection_mode,
+        cli_arguments.csv,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_dedup_key(cli_arguments.dedup_key, cli_arguments.selection_mode)
+        .map_err(|e| e.to_string())?;
+    validate_assert_sorted(
+        cli_arguments.assert_sorted,
+        cli_arguments.numeric,
+        cli_arguments.unordered,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
+    validate_assert_uniform(
+        cli_arguments.assert_uniform,
+        cli_arguments.unordered,
+        cli_arguments.selection_mode,
+    )
+    .map_err(|e| e.to_string())?;
     validate_no_consuming(consuming).map_err(|e| e.to_string())?;
+    validate_delimiters(
+        cli_arguments.delimiters.len(),
+        cli_arguments.fixed,
+        cli_arguments.csv,
+    )
+    .map_err(|e| e.to_string())?;
+    let delimiter: Option<Delimiter> = combine_delimiters(cli_arguments.delimiters);
 
     let regex_engine: Option<RegexEngine> = match cli_arguments.selection_mode {
-        SelectionMode::Bytes | SelectionMode::Chars => None,
+        SelectionMode::Bytes | SelectionMode::Chars | SelectionMode::Json => None,
+        SelectionMode::Fields if cli_arguments.csv => {
+            // CSV splits on a literal delimiter, defaulting to a comma
+            let literal = match delimiter {
+                None => ",".to_string(),
+                Some(Delimiter::Literal(value)) => value,
+                Some(Delimiter::Regex(_)) => {
+                    return Err("--csv needs a literal delimiter, not a regex".to_string());
+                }
+            };
+            if literal.is_empty() {
+                return Err("empty string is not a valid delimiter".to_string());
+            }
+            if literal.contains('"') {
+                return Err("--csv delimiter cannot contain a double quote".to_string());
+            }
+            Some(RegexEngine::Literal(literal.into_bytes()))
+        }
         SelectionMode::Fields => {
-            let delimiter = cli_arguments.delimiter.unwrap_or_else(|| {
+            let delimiter = delimiter.unwrap_or_else(|| {
                 eprintln!("delimiter is required in fields mode (use -d or --delimiter)");
                 std::process::exit(2)
             });
-            let delimiter_pattern = match delimiter {
-                Delimiter::Literal(value) => {
-                    if value.is_empty() {
-                        eprintln!("empty string is not a valid delimiter");
-                        std::process::exit(2)
-                    }
-                    regex::escape(&value)
-                }
-                Delimiter::Regex(value) => {
-                    if value.is_empty() {
-                        eprintln!("empty string is not a valid delimiter");
-                        std::process::exit(2)
-                    }
-                    value
-                }
+            Some(delimiter_engine(
+                delimiter,
+                cli_arguments.fixed,
+                cli_arguments.ignore_case,
+            )?)
+        }
+        SelectionMode::Capture => {
+            let pattern = match cli_arguments.capture {
+                Some(pattern) => pattern,
+                None => return Err("capture mode requires a pattern".to_string()),
             };
+            // Accept /.../ for consistency with delimiters
+            let pattern = match parse_delimiter_token(&pattern) {
+                Delimiter::Literal(value) | Delimiter::Regex(value) => value,
+            };
+            if pattern.is_empty() {
+                return Err("empty string is not a valid capture pattern".to_string());
+            }
+            Some(compile_regex(&pattern)?)
+        }
+    };
 
-            let simple_regex = SimpleRegex::new(&delimiter_pattern);
+    let require: Option<BytesRegex> = match &cli_arguments.require {
+        Some(pattern) => {
+            Some(BytesRegex::new(pattern).map_err(|error| format!("invalid require: {error}"))?)
+        }
+        None => None,
+    };
 
-            match simple_regex {
-                Ok(regex) => Some(RegexEngine::Simple(regex)),
-                Err(_) => {
-                    let fancy_regex = FancyRegex::new(&delimiter_pattern)
-                        .map_err(|error| format!("failed to compile regex: {error}"))?;
-                    Some(RegexEngine::Fancy(fancy_regex))
-                }
+    let pick: Option<BytesRegex> = match &cli_arguments.pick {
+        Some(pattern) => {
+            Some(BytesRegex::new(pattern).map_err(|error| format!("invalid pick: {error}"))?)
+        }
+        None => None,
+    };
+
+    let compile_anchor = |pattern: &Option<String>, flag: &str| match pattern {
+        Some(pattern) => BytesRegex::new(pattern)
+            .map(Some)
+            .map_err(|error| format!("invalid {flag}: {error}")),
+        None => Ok(None),
+    };
+    let anchors: Option<FieldAnchors> = match (&cli_arguments.from, &cli_arguments.to) {
+        (None, None) => None,
+        (from, to) => Some(FieldAnchors {
+            from: compile_anchor(from, "from")?,
+            to: compile_anchor(to, "to")?,
+            exclusive: cli_arguments.exclude_anchors,
+        }),
+    };
+
+    let mut record_separator_max_len: Option<usize> = None;
+    let record_separator: Option<BytesRegex> = match cli_arguments.record_separator {
+        Some(pattern) => {
+            if cli_arguments.input_mode == InputMode::WholeString {
+                return Err(
+                    "--input-record-separator cannot be combined with --whole-string".to_string(),
+                );
+            }
+            let pattern = match parse_delimiter_token(&pattern) {
+                Delimiter::Literal(value) | Delimiter::Regex(value) => value,
+            };
+            let separator = BytesRegex::new(&pattern)
+                .map_err(|error| format!("invalid input-record-separator: {error}"))?;
+            if separator.is_match(b"") {
+                return Err(format!(
+                    "invalid input-record-separator: '{pattern}' can match an empty string"
+                ));
+            }
+            // Parsed as the bytes regex is, so patterns that aren't valid UTF-8 still measure
+            record_separator_max_len = regex_syntax::ParserBuilder::new()
+                .utf8(false)
+                .build()
+                .parse(&pattern)
+                .ok()
+                .and_then(|hir| hir.properties().maximum_len());
+            Some(separator)
+        }
+        None => None,
+    };
+
+    let field_pattern: Option<RegexEngine> = match cli_arguments.field_pattern {
+        Some(pattern) => {
+            let pattern = match parse_delimiter_token(&pattern) {
+                Delimiter::Literal(value) | Delimiter::Regex(value) => value,
+            };
+            if pattern.is_empty() {
+                return Err("empty string is not a valid field pattern".to_string());
             }
+            Some(compile_regex(&pattern)?)
         }
+        None => None,
     };
 
     // TODO: Parse arguments and build Instructions
@@ -230,47 +1116,140 @@ pub fn get_instructions() -> Result<Option<Instructions>, String> {
         join: join.clone(),
         input_mode: cli_arguments.input_mode,
         input: cli_arguments.input,
+        input_encoding: cli_arguments.input_encoding.unwrap_or(InputEncoding::Utf8),
+        keep_bom: cli_arguments.keep_bom,
+        strip_cr: cli_arguments.strip_cr,
+        record_separator,
+        record_separator_max_len,
+        comment_prefixes: cli_arguments.comment_prefixes.clone(),
+        // Line-buffered output is no use if the reader is still holding records back
+        batch_bytes: match cli_arguments.line_buffered {
+            true => cli_arguments.batch_bytes.or(Some(0)),
+            false => cli_arguments.batch_bytes,
+        },
+        read_buffer: cli_arguments.read_buffer,
+        // Redrawing a line in place only makes sense on a terminal
+        progress: cli_arguments.progress && std::io::stderr().is_terminal(),
         selections: cli_arguments.selections.clone(),
         skip_empty: cli_arguments.skip_empty,
+        squash_delimiters: cli_arguments.squash_delimiters,
+        trim: cli_arguments.trim,
         invert: cli_arguments.invert,
-        placeholder: placeholder.clone(),
+        placeholders: placeholders.clone(),
         strict_bounds: cli_arguments.strict_bounds,
         strict_range_order: cli_arguments.strict_range_order,
         strict_utf8: cli_arguments.strict_utf8,
+        csv: cli_arguments.csv,
+        max_fields: cli_arguments.max_fields,
+        keep_trailing_empty: cli_arguments.keep_trailing_empty,
+        max_records: cli_arguments.max_records,
+        tail: cli_arguments.tail,
+        max_record_bytes: cli_arguments.max_record_bytes,
+        header: header.clone(),
     };
 
     let transform_instructions = TransformInstructions {
         input_mode: cli_arguments.input_mode,
         selection_mode: cli_arguments.selection_mode,
+        codepoints: cli_arguments.codepoints,
         selections: cli_arguments.selections.clone(),
+        percent_selections: cli_arguments.percent_selections.clone(),
+        json_paths,
         invert: cli_arguments.invert,
+        redact: cli_arguments.redact,
+        offsets: cli_arguments.offsets,
         skip_empty: cli_arguments.skip_empty,
-        placeholder: placeholder,
+        squash_delimiters: cli_arguments.squash_delimiters,
+        trim: cli_arguments.trim,
+        only_delimited: cli_arguments.only_delimited,
+        require,
+        strict_require: cli_arguments.strict_require,
+        pick,
+        anchors,
+        unique: cli_arguments.unique,
+        // --sort-numeric and --sort-reverse imply --sort, as with sort -n and sort -r
+        sort: (cli_arguments.sort || cli_arguments.sort_numeric || cli_arguments.sort_reverse)
+            .then_some(FieldSort {
+                numeric: cli_arguments.sort_numeric,
+                reverse: cli_arguments.sort_reverse,
+            }),
+        reverse: cli_arguments.reverse,
+        separators,
+        replacements,
+        comment_prefixes: cli_arguments.comment_prefixes,
+        csv: cli_arguments.csv,
+        max_fields: cli_arguments.max_fields,
+        keep_trailing_empty: cli_arguments.keep_trailing_empty,
+        header,
+        field_pattern,
+        field_length: cli_arguments.field_length,
+        field_length_basis: cli_arguments
+            .field_length_basis
+            .unwrap_or(LengthBasis::Display),
+        dedup_key: cli_arguments.dedup_key,
+        assert_sorted: cli_arguments.assert_sorted,
+        assert_uniform: cli_arguments.assert_uniform,
+        placeholders,
         strict_return: cli_arguments.strict_return,
         strict_bounds: cli_arguments.strict_bounds,
         strict_range_order: cli_arguments.strict_range_order,
         strict_utf8: cli_arguments.strict_utf8,
         count: cli_arguments.count,
-        join: join,
-        regex_engine: regex_engine,
+        count_nonempty: cli_arguments.count_nonempty,
+        count_unit: cli_arguments.count_unit,
+        stats: cli_arguments.stats,
+        complement_count: cli_arguments.complement_count,
+        index_pair_separator: cli_arguments.index_pair_separator,
+        json: cli_arguments.json,
+        transpose: cli_arguments.transpose,
+        shell_quote: cli_arguments.shell_quote,
+        with_original: cli_arguments.with_original,
+        original_separator,
+        encoding: cli_arguments.encoding,
+        decoding: cli_arguments.decoding,
+        join,
+        regex_engine,
         align: cli_arguments.align,
+        pad,
     };
 
     let stdout_is_terminal = cli_arguments.output.is_none() && std::io::stdout().is_terminal();
 
     let output_instructions = OutputInstructions {
         output: cli_arguments.output,
+        output_append: cli_arguments.output_append,
+        trim_newline: cli_arguments.trim_newline,
         input_mode: cli_arguments.input_mode,
         selections: cli_arguments.selections,
         strict_bounds: cli_arguments.strict_bounds,
         strict_return: cli_arguments.strict_return,
+        strict_empty: cli_arguments.strict_empty,
         count: cli_arguments.count,
+        count_total: cli_arguments.count_total,
+        histogram,
+        escape_output: cli_arguments.escape_output,
+        crlf_output: cli_arguments.crlf_output,
+        output_terminator: cli_arguments.output_terminator,
+        fail_on_empty_output: cli_arguments.fail_on_empty_output,
+        number: cli_arguments.number,
+        unordered: cli_arguments.unordered,
+        line_buffered: cli_arguments.line_buffered,
+        numeric: cli_arguments.numeric,
         stdout_is_terminal,
     };
 
-    Ok(Some(Instructions {
+    let instructions = Instructions {
         input_instructions,
         transform_instructions,
         output_instructions,
-    }))
+        jobs: cli_arguments.jobs,
+        reorder_window: cli_arguments.reorder_window,
+    };
+
+    if cli_arguments.explain {
+        print_explanation(&instructions, delimiter_inferred);
+        return Ok(None);
+    }
+
+    Ok(Some(instructions))
 }
@@ -0,0 +1,926 @@
+use std::fmt::Write;
+
+/// What a flag takes as its value, which decides what the shell offers after it.
+#[derive(Clone, Copy)]
+enum Value {
+    None,
+    Text,
+    File,
+    Choices(&'static [&'static str]),
+}
+
+struct Flag {
+    long: &'static str,
+    short: Option<char>,
+    value: Value,
+    help: &'static str,
+}
+
+const fn flag(long: &'static str, short: Option<char>, value: Value, help: &'static str) -> Flag {
+    Flag {
+        long,
+        short,
+        value,
+        help,
+    }
+}
+
+/// --join also takes any string or hex, but these are the ones worth offering.
+const JOIN_KEYWORDS: &[&str] = &[
+    "auto",
+    "after-previous",
+    "before-next",
+    "first",
+    "last",
+    "space",
+    "none",
+];
+
+/// Every flag the completion scripts offer, in --help order.
+const FLAGS: &[Flag] = &[
+    flag("help", Some('h'), Value::None, "Print help text"),
+    flag("version", Some('v'), Value::None, "Print version number"),
+    flag(
+        "explain",
+        None,
+        Value::None,
+        "Print what the flags add up to, without reading input",
+    ),
+    flag(
+        "input",
+        Some('i'),
+        Value::File,
+        "Provide an input file (repeatable)",
+    ),
+    flag("output", Some('o'), Value::File, "Write output to a file"),
+    flag(
+        "output-append",
+        None,
+        Value::None,
+        "Append to the --output file instead of replacing it",
+    ),
+    flag(
+        "append",
+        None,
+        Value::None,
+        "Append to the --output file instead of replacing it",
+    ),
+    flag(
+        "delimiter",
+        Some('d'),
+        Value::Text,
+        "Specify the delimiter to use (repeat to split on any of them)",
+    ),
+    flag(
+        "tabs",
+        Some('t'),
+        Value::None,
+        "Split on tabs, the same as -d with a literal tab",
+    ),
+    flag(
+        "delimiter-file",
+        None,
+        Value::File,
+        "Read the delimiter from a file",
+    ),
+    flag(
+        "spec",
+        None,
+        Value::Text,
+        "Give the delimiter, selections and join in one string",
+    ),
+    flag(
+        "selections-file",
+        None,
+        Value::File,
+        "Read selections from a file (# comments, repeatable)",
+    ),
+    flag(
+        "fixed",
+        Some('F'),
+        Value::None,
+        "Treat the delimiter as a literal string, not a regex",
+    ),
+    flag(
+        "ignore-case",
+        Some('I'),
+        Value::None,
+        "Match the delimiter case-insensitively",
+    ),
+    flag(
+        "csv",
+        None,
+        Value::None,
+        "Split fields as CSV, honouring quotes (delimiter defaults to ,)",
+    ),
+    flag(
+        "join",
+        Some('j'),
+        Value::Choices(JOIN_KEYWORDS),
+        "Join each selection with string or hex or delimiter",
+    ),
+    flag(
+        "separators",
+        None,
+        Value::Text,
+        "Comma-separated joins for each output position, then --join",
+    ),
+    flag(
+        "replace",
+        None,
+        Value::Text,
+        "Swap a kept delimiter FROM for TO (repeatable, ignored with --join)",
+    ),
+    flag(
+        "placeholder",
+        Some('p'),
+        Value::Text,
+        "Inserts placeholder for invalid selections",
+    ),
+    flag(
+        "null-on-missing",
+        None,
+        Value::None,
+        "Inserts a NUL byte for invalid selections",
+    ),
+    flag(
+        "escape",
+        None,
+        Value::None,
+        "Decode \\t, \\n, \\xNN etc. in join and placeholder (default)",
+    ),
+    flag(
+        "no-escape",
+        None,
+        Value::None,
+        "Keep backslashes in join and placeholder literal",
+    ),
+    flag(
+        "escape-output",
+        None,
+        Value::None,
+        "Write control characters in the output as \\t, \\xNN etc.",
+    ),
+    flag(
+        "per-line",
+        None,
+        Value::None,
+        "Processes the input line by line (default)",
+    ),
+    flag(
+        "whole-string",
+        Some('w'),
+        Value::None,
+        "Processes the input as a single string, rather than each line separately",
+    ),
+    flag(
+        "zero-terminated",
+        Some('z'),
+        Value::None,
+        "Processes the input as zero-terminated strings",
+    ),
+    flag(
+        "input-record-separator",
+        None,
+        Value::Text,
+        "Split the input into records on a regex",
+    ),
+    flag(
+        "fields",
+        Some('f'),
+        Value::None,
+        "Select fields split by delimiter (default)",
+    ),
+    flag(
+        "bytes",
+        Some('b'),
+        Value::None,
+        "Select bytes from the input",
+    ),
+    flag(
+        "characters",
+        Some('c'),
+        Value::None,
+        "Select characters from the input",
+    ),
+    flag(
+        "codepoints",
+        None,
+        Value::None,
+        "In chars mode, count code points, not graphemes",
+    ),
+    flag(
+        "capture",
+        None,
+        Value::Text,
+        "Select capture groups from each match of a regex",
+    ),
+    flag(
+        "json-input",
+        None,
+        Value::Text,
+        "Select values by dotted path from each JSON record",
+    ),
+    flag(
+        "field-pattern",
+        None,
+        Value::Text,
+        "Keep only the first capture of each selected field",
+    ),
+    flag(
+        "field-length",
+        None,
+        Value::Text,
+        "Keep only selected fields whose length is within the range",
+    ),
+    flag(
+        "field-length-basis",
+        None,
+        Value::Choices(&["bytes", "chars", "display"]),
+        "How --field-length measures fields (bytes|chars|display)",
+    ),
+    flag(
+        "encode",
+        None,
+        Value::None,
+        "Encode each field (base64|base64url|hex, default base64)",
+    ),
+    flag(
+        "decode",
+        None,
+        Value::None,
+        "Decode each selected field (base64|base64url|hex, default base64)",
+    ),
+    flag(
+        "json",
+        None,
+        Value::None,
+        "Output each record as a JSON array of strings",
+    ),
+    flag(
+        "transpose",
+        None,
+        Value::None,
+        "Swap the rows and columns of a table (needs --whole-string)",
+    ),
+    flag(
+        "shell-quote",
+        None,
+        Value::None,
+        "Single-quote each field so it is safe to paste into a shell",
+    ),
+    flag(
+        "with-original",
+        None,
+        Value::None,
+        "Output the original record too (prefix|suffix, default prefix)",
+    ),
+    flag(
+        "original-separator",
+        None,
+        Value::Text,
+        "Separator between the original record and output (default tab)",
+    ),
+    flag(
+        "pad-char",
+        None,
+        Value::Text,
+        "Character used to pad aligned fields (default space)",
+    ),
+    flag(
+        "align",
+        Some('a'),
+        Value::Choices(&["left", "right", "center", "squash", "none"]),
+        "Align output (left|right|center|squash|none)",
+    ),
+    flag(
+        "align-with",
+        None,
+        Value::Choices(&["spaces", "tabs"]),
+        "Align with spaces (default) or a tab between columns",
+    ),
+    flag(
+        "field-dedup-key",
+        None,
+        Value::Text,
+        "Only output the first record for each value of field N",
+    ),
+    flag(
+        "count",
+        None,
+        Value::None,
+        "Return the number of results after splitting (all|nonempty)",
+    ),
+    flag(
+        "count-total",
+        None,
+        Value::None,
+        "Return one total of the counts across all records",
+    ),
+    flag(
+        "histogram",
+        None,
+        Value::None,
+        "Return how many records had each count (implies --count)",
+    ),
+    flag(
+        "count-unit",
+        None,
+        Value::Text,
+        "Count fields, bytes or chars, whatever the mode (implies --count)",
+    ),
+    flag(
+        "stats",
+        None,
+        Value::None,
+        "Return the field count, then each field's byte length, tab-separated",
+    ),
+    flag(
+        "complement-count",
+        None,
+        Value::None,
+        "Return how many fields the selections left out of each record",
+    ),
+    flag(
+        "emit-field-index-pairs",
+        None,
+        Value::None,
+        "Prefix each field with its index and SEP (default =)",
+    ),
+    flag(
+        "assert-sorted",
+        None,
+        Value::Text,
+        "Emit error if records are not sorted by field N",
+    ),
+    flag(
+        "numeric",
+        None,
+        Value::None,
+        "Compare --assert-sorted keys as numbers",
+    ),
+    flag(
+        "assert-uniform",
+        None,
+        Value::None,
+        "Emit error if records have different numbers of fields",
+    ),
+    flag(
+        "number",
+        Some('n'),
+        Value::None,
+        "Prefix each output record with its record number",
+    ),
+    flag(
+        "unordered",
+        None,
+        Value::None,
+        "Write records as soon as they are ready, in any order",
+    ),
+    flag(
+        "ordered",
+        None,
+        Value::None,
+        "Write records in input order (default)",
+    ),
+    flag(
+        "jobs",
+        Some('J'),
+        Value::Choices(&["auto"]),
+        "Number of worker threads (default auto)",
+    ),
+    flag(
+        "read-buffer",
+        None,
+        Value::Text,
+        "Bytes read from the input at a time (at least 1024)",
+    ),
+    flag(
+        "reorder-window",
+        None,
+        Value::Text,
+        "Most batches read ahead of the writer",
+    ),
+    flag(
+        "line-buffered",
+        None,
+        Value::None,
+        "Flush output after every record",
+    ),
+    flag(
+        "progress",
+        None,
+        Value::None,
+        "Show how much of the input files is read, on stderr",
+    ),
+    flag(
+        "batch-bytes",
+        None,
+        Value::Text,
+        "Bytes read before handing records to workers",
+    ),
+    flag(
+        "head",
+        None,
+        Value::Text,
+        "Stop reading input after N records",
+    ),
+    flag(
+        "max-records",
+        None,
+        Value::Text,
+        "Stop reading input after N records",
+    ),
+    flag("tail", None, Value::Text, "Process only the last N records"),
+    flag(
+        "max-record-bytes",
+        None,
+        Value::Text,
+        "Error on any record longer than N bytes",
+    ),
+    flag(
+        "header",
+        None,
+        Value::Text,
+        "Select columns by name from the first record, e.g. name,email",
+    ),
+    flag(
+        "crlf-output",
+        None,
+        Value::None,
+        "Terminate each output line with CRLF",
+    ),
+    flag(
+        "trim-newline",
+        None,
+        Value::None,
+        "Leave the terminator off the last record",
+    ),
+    flag(
+        "output-terminator",
+        None,
+        Value::Choices(&["newline", "nul", "none"]),
+        "Terminate output records with newline, nul, none or hex",
+    ),
+    flag(
+        "field-terminator",
+        None,
+        Value::Choices(&["nul", "none"]),
+        "Join the selected fields with nul, none or hex instead",
+    ),
+    flag(
+        "print0-fields",
+        None,
+        Value::None,
+        "Join the selected fields with NUL, for xargs -0",
+    ),
+    flag("invert", None, Value::None, "Inverts the chosen selection"),
+    flag(
+        "slice-syntax",
+        None,
+        Value::None,
+        "Read selections as end-exclusive slices",
+    ),
+    flag(
+        "invert-keep-positions",
+        None,
+        Value::None,
+        "Same as --redact, but blanks the fields when there's no --placeholder",
+    ),
+    flag(
+        "pick",
+        None,
+        Value::Text,
+        "Keep only fields matching REGEX, before selecting (--invert drops them)",
+    ),
+    flag(
+        "from",
+        None,
+        Value::Text,
+        "Select the fields from one matching --from to the next matching --to",
+    ),
+    flag(
+        "to",
+        None,
+        Value::Text,
+        "Select the fields from one matching --from to the next matching --to",
+    ),
+    flag(
+        "exclude-anchors",
+        None,
+        Value::None,
+        "Leave out the fields matching --from and --to",
+    ),
+    flag(
+        "offsets",
+        None,
+        Value::None,
+        "Return each selected field's byte offset and length as start:len",
+    ),
+    flag(
+        "redact",
+        None,
+        Value::None,
+        "Replace the selected fields with --placeholder, keeping the rest",
+    ),
+    flag(
+        "skip-empty",
+        Some('e'),
+        Value::None,
+        "Skips empty fields when indexing or counting",
+    ),
+    flag(
+        "no-skip-empty",
+        Some('E'),
+        Value::None,
+        "Does not skip empty fields when indexing or counting",
+    ),
+    flag(
+        "squash-delimiters",
+        None,
+        Value::None,
+        "Treat a run of delimiters as one, keeping empty edge fields",
+    ),
+    flag(
+        "keep-trailing-empty",
+        None,
+        Value::None,
+        "In whole-string mode, keep the empty field after a trailing delimiter",
+    ),
+    flag(
+        "max-fields",
+        None,
+        Value::Text,
+        "Split into at most N fields, the last keeping the rest",
+    ),
+    flag(
+        "split-once",
+        None,
+        Value::None,
+        "Split at the first delimiter only, as --max-fields=2",
+    ),
+    flag(
+        "trim",
+        None,
+        Value::None,
+        "Strip whitespace from both ends of each field",
+    ),
+    flag(
+        "trim-left",
+        None,
+        Value::None,
+        "Strip whitespace from the start of each field",
+    ),
+    flag(
+        "trim-right",
+        None,
+        Value::None,
+        "Strip whitespace from the end of each field",
+    ),
+    flag(
+        "only-delimited",
+        Some('s'),
+        Value::None,
+        "Skips records that contain no delimiter",
+    ),
+    flag(
+        "require",
+        None,
+        Value::Text,
+        "Skips records that don't match REGEX (errors with --strict)",
+    ),
+    flag(
+        "unique",
+        Some('u'),
+        Value::None,
+        "Skips fields already output earlier in the same record",
+    ),
+    flag(
+        "reverse",
+        Some('r'),
+        Value::None,
+        "Output the selected fields last to first",
+    ),
+    flag(
+        "sort",
+        None,
+        Value::None,
+        "Sort the selected fields within each record",
+    ),
+    flag(
+        "sort-numeric",
+        None,
+        Value::None,
+        "Sort by each field's leading number, then by bytes",
+    ),
+    flag(
+        "sort-reverse",
+        None,
+        Value::None,
+        "Sort in descending order",
+    ),
+    flag(
+        "comment",
+        None,
+        Value::Text,
+        "Skips records starting with PREFIX (repeatable)",
+    ),
+    flag(
+        "fail-on-empty-output",
+        None,
+        Value::None,
+        "Emit error if input produced no output at all",
+    ),
+    flag(
+        "strict",
+        None,
+        Value::None,
+        "Shorthand for all strict features",
+    ),
+    flag(
+        "no-strict",
+        None,
+        Value::None,
+        "Does not enforce strict features",
+    ),
+    flag(
+        "strict-bounds",
+        None,
+        Value::None,
+        "Emit error if range is out of bounds",
+    ),
+    flag(
+        "no-strict-bounds",
+        None,
+        Value::None,
+        "Does not emit error if range is out of bounds",
+    ),
+    flag(
+        "strict-return",
+        None,
+        Value::None,
+        "Emit error if there is no result",
+    ),
+    flag(
+        "no-strict-return",
+        None,
+        Value::None,
+        "Does not emit error if there is no result",
+    ),
+    flag(
+        "strict-empty",
+        None,
+        Value::None,
+        "Emit error if the input has no records",
+    ),
+    flag(
+        "no-strict-empty",
+        None,
+        Value::None,
+        "Does not emit error if the input has no records",
+    ),
+    flag(
+        "strict-range-order",
+        None,
+        Value::None,
+        "Emit error if start of a range is greater than the end",
+    ),
+    flag(
+        "no-strict-range-order",
+        None,
+        Value::None,
+        "Does not emit error if start of a range is greater than the end",
+    ),
+    flag(
+        "encoding",
+        None,
+        Value::Choices(&["utf8", "utf16le", "utf16be"]),
+        "Decode input from utf8 (default), utf16le or utf16be",
+    ),
+    flag(
+        "strip-bom",
+        None,
+        Value::None,
+        "Drop a UTF-8 BOM from the start of each input",
+    ),
+    flag(
+        "keep-bom",
+        None,
+        Value::None,
+        "Keep a UTF-8 BOM at the start of each input",
+    ),
+    flag(
+        "strip-cr",
+        None,
+        Value::None,
+        "Drop a trailing \\r from each record, whatever the terminator",
+    ),
+    flag(
+        "strict-utf8",
+        None,
+        Value::None,
+        "Emit error on invalid UTF-8 sequences",
+    ),
+    flag(
+        "no-strict-utf8",
+        None,
+        Value::None,
+        "Does not emit error on invalid UTF-8 sequences",
+    ),
+    flag(
+        "strict-require",
+        None,
+        Value::None,
+        "Emit error on records that don't match --require",
+    ),
+    flag(
+        "no-strict-require",
+        None,
+        Value::None,
+        "Skip records that don't match --require (default)",
+    ),
+];
+
+/// Prints the completion script for `splitby completions <shell>`. This runs before
+/// normal parsing, so the shell name can't be read as a delimiter or selection.
+pub fn print_completions(arguments: &[String]) -> Result<(), String> {
+    let script = match arguments {
+        [shell] => match shell.as_str() {
+            "bash" => bash_script(),
+            "zsh" => zsh_script(),
+            "fish" => fish_script(),
+            "powershell" => powershell_script(),
+            _ => {
+                return Err(format!(
+                    "completions error: unknown shell '{shell}', expected bash, zsh, fish or powershell"
+                ));
+            }
+        },
+        _ => {
+            return Err(
+                "completions error: expected one shell: bash, zsh, fish or powershell".to_string(),
+            );
+        }
+    };
+    print!("{script}");
+    Ok(())
+}
+
+/// Each name a flag can be given as, short first.
+fn flag_names(flag: &Flag) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(2);
+    if let Some(short) = flag.short {
+        names.push(format!("-{short}"));
+    }
+    names.push(format!("--{}", flag.long));
+    names
+}
+
+fn bash_script() -> String {
+    let mut script = String::new();
+    script.push_str("_splitby() {\n");
+    script.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    script.push_str("    local prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+    script.push_str("    # Bash splits --flag=value around the =\n");
+    script.push_str("    if [[ \"$cur\" == \"=\" ]]; then\n");
+    script.push_str("        cur=\"\"\n");
+    script.push_str("    elif [[ \"$prev\" == \"=\" ]]; then\n");
+    script.push_str("        prev=\"${COMP_WORDS[COMP_CWORD-2]}\"\n");
+    script.push_str("    fi\n");
+    script.push_str("    case \"$prev\" in\n");
+    for flag in FLAGS {
+        let action = match flag.value {
+            Value::None => continue,
+            Value::Text => "return".to_string(),
+            Value::File => "COMPREPLY=($(compgen -f -- \"$cur\")); return".to_string(),
+            Value::Choices(choices) => format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return",
+                choices.join(" ")
+            ),
+        };
+        let _ = writeln!(
+            script,
+            "        {}) {action} ;;",
+            flag_names(flag).join("|")
+        );
+    }
+    script.push_str("    esac\n");
+    script.push_str("    if [[ \"$cur\" == -* ]]; then\n");
+    let all_names: Vec<String> = FLAGS.iter().flat_map(flag_names).collect();
+    let _ = writeln!(
+        script,
+        "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+        all_names.join(" ")
+    );
+    script.push_str("    else\n");
+    script.push_str("        COMPREPLY=($(compgen -f -- \"$cur\"))\n");
+    script.push_str("    fi\n");
+    script.push_str("}\n");
+    script.push_str("complete -o default -F _splitby splitby\n");
+    script
+}
+
+fn zsh_script() -> String {
+    // Descriptions sit in '[...]' inside a single-quoted word
+    let escape = |help: &str| {
+        help.replace('\'', "'\\''")
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+            .replace(':', "\\:")
+    };
+    let mut script = String::new();
+    script.push_str("#compdef splitby\n\n");
+    script.push_str("_arguments -s \\\n");
+    for flag in FLAGS {
+        let takes_value = !matches!(flag.value, Value::None);
+        let equals = if takes_value { "=" } else { "" };
+        // Each spec is left open for the description, with a short and long pair brace-expanded
+        let names = match flag.short {
+            Some(short) => format!(
+                "'(-{short} --{long})'{{-{short},--{long}{equals}}}'",
+                long = flag.long
+            ),
+            None => format!("'--{}{equals}", flag.long),
+        };
+        let action = match flag.value {
+            Value::None => String::new(),
+            Value::Text => ":value: ".to_string(),
+            Value::File => ":file:_files".to_string(),
+            Value::Choices(choices) => format!(":value:({})", choices.join(" ")),
+        };
+        let _ = writeln!(script, "    {names}[{}]{action}' \\", escape(flag.help));
+    }
+    script.push_str("    '*:selection or file:_files'\n");
+    script
+}
+
+fn fish_script() -> String {
+    let escape = |text: &str| text.replace('\\', "\\\\").replace('\'', "\\'");
+    let mut script = String::new();
+    for flag in FLAGS {
+        script.push_str("complete -c splitby");
+        if let Some(short) = flag.short {
+            let _ = write!(script, " -s {short}");
+        }
+        let _ = write!(script, " -l {}", flag.long);
+        match flag.value {
+            Value::None => {}
+            Value::Text => script.push_str(" -x"),
+            Value::File => script.push_str(" -r -F"),
+            Value::Choices(choices) => {
+                let _ = write!(script, " -x -a '{}'", choices.join(" "));
+            }
+        }
+        let _ = writeln!(script, " -d '{}'", escape(flag.help));
+    }
+    script
+}
+
+fn powershell_script() -> String {
+    let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+    let mut script = String::new();
+    script.push_str("Register-ArgumentCompleter -Native -CommandName splitby -ScriptBlock {\n");
+    script.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n");
+    script.push_str("    $word = [string]$wordToComplete\n");
+    let all_names: Vec<String> = FLAGS
+        .iter()
+        .flat_map(flag_names)
+        .map(|name| quote(&name))
+        .collect();
+    let _ = writeln!(script, "    $flags = @({})", all_names.join(", "));
+    // Ordinal, since -j and -J are different flags
+    script.push_str(
+        "    $values = [System.Collections.Generic.Dictionary[string, string[]]]::new([System.StringComparer]::Ordinal)\n",
+    );
+    for flag in FLAGS {
+        let Value::Choices(choices) = flag.value else {
+            continue;
+        };
+        let choices: Vec<String> = choices.iter().map(|choice| quote(choice)).collect();
+        for name in flag_names(flag) {
+            let _ = writeln!(script, "    $values['{name}'] = @({})", choices.join(", "));
+        }
+    }
+    script.push_str(
+        r#"    $elements = @($commandAst.CommandElements | ForEach-Object { $_.ToString() })
+    $previous = if ($word) { $elements[-2] } else { $elements[-1] }
+    $prefix = ''
+    if ($word -match '^(--[^=]+)=(.*)$') {
+        $previous = $Matches[1]
+        $prefix = $Matches[1] + '='
+    }
+    if ($values.ContainsKey($previous)) {
+        $candidates = $values[$previous] | ForEach-Object { $prefix + $_ }
+    } elseif ($word.StartsWith('-')) {
+        $candidates = $flags
+    } else {
+        return
+    }
+    $candidates | Where-Object { $_.StartsWith($word) } | ForEach-Object {
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }
+}
+"#,
+    );
+    script
+}
@@ -0,0 +1,163 @@
+use crate::types::*;
+
+fn input_mode_name(input_mode: InputMode) -> &'static str {
+    match input_mode {
+        InputMode::PerLine => "per-line",
+        InputMode::WholeString => "whole-string",
+        InputMode::ZeroTerminated => "zero-terminated",
+    }
+}
+
+fn selection_mode_name(selection_mode: SelectionMode) -> &'static str {
+    match selection_mode {
+        SelectionMode::Fields => "fields",
+        SelectionMode::Bytes => "bytes",
+        SelectionMode::Chars => "chars",
+        SelectionMode::Capture => "capture",
+        SelectionMode::Json => "json",
+    }
+}
+
+/// Bytes as a quoted string, with control characters escaped so tabs and the like show.
+fn quoted(bytes: &[u8]) -> String {
+    format!("\"{}\"", String::from_utf8_lossy(bytes).escape_debug())
+}
+
+/// A regex as written, but with any control characters escaped. Backslashes are left alone.
+fn escape_controls(text: &str) -> String {
+    text.chars()
+        .map(|character| match character.is_control() {
+            true => character.escape_debug().to_string(),
+            false => character.to_string(),
+        })
+        .collect()
+}
+
+fn describe_engine(engine: &RegexEngine) -> String {
+    match engine {
+        RegexEngine::Byte(byte) => format!("{} (literal byte)", quoted(&[*byte])),
+        RegexEngine::Literal(literal) => format!("{} (literal)", quoted(literal)),
+        RegexEngine::Simple(regex) => format!("/{}/ (regex)", escape_controls(regex.as_str())),
+        RegexEngine::Fancy(regex) => format!(
+            "/{}/ (regex with lookaround)",
+            escape_controls(regex.as_str())
+        ),
+    }
+}
+
+fn describe_join(join: Option<&JoinMode>) -> String {
+    match join {
+        None => "default (the delimiter between the fields)".to_string(),
+        Some(JoinMode::String(join)) => quoted(join),
+        Some(JoinMode::Auto) => "auto".to_string(),
+        Some(JoinMode::AfterPrevious) => "after-previous".to_string(),
+        Some(JoinMode::BeforeNext) => "before-next".to_string(),
+        Some(JoinMode::First) => "first".to_string(),
+        Some(JoinMode::Last) => "last".to_string(),
+        Some(JoinMode::Space) => "space".to_string(),
+        Some(JoinMode::None) => "none".to_string(),
+    }
+}
+
+/// Selections as they'd be written on the command line, e.g. `1-3`, `-1-1:-2` or `p25-p75`.
+fn describe_selections(
+    selections: &[(i32, i32, i32)],
+    percent_selections: &[PercentSelection],
+) -> String {
+    if selections.is_empty() {
+        return "all".to_string();
+    }
+    let described: Vec<String> = selections
+        .iter()
+        .enumerate()
+        .map(|(slot, &(start, end, step))| {
+            if let Some(percent) = percent_selections.iter().find(|p| p.slot == slot) {
+                return match percent.start == percent.end {
+                    true => format!("p{}", percent.start),
+                    false => format!("p{}-p{}", percent.start, percent.end),
+                };
+            }
+            match (start == end, step) {
+                (true, _) => start.to_string(),
+                (false, 1) => format!("{start}-{end}"),
+                (false, step) => format!("{start}-{end}:{step}"),
+            }
+        })
+        .collect();
+    described.join(" ")
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    match enabled {
+        true => "on",
+        false => "off",
+    }
+}
+
+/// --explain: prints what the parsed flags add up to, on stderr so it can't be mistaken
+/// for output. Only the choices people most often get wrong are shown.
+pub fn print_explanation(instructions: &Instructions, delimiter_inferred: bool) {
+    let input = &instructions.input_instructions;
+    let transform = &instructions.transform_instructions;
+    let output = &instructions.output_instructions;
+
+    eprintln!("input mode: {}", input_mode_name(transform.input_mode));
+    eprintln!(
+        "selection mode: {}",
+        selection_mode_name(transform.selection_mode)
+    );
+    if let Some(engine) = &transform.regex_engine {
+        let source = match delimiter_inferred {
+            true => ", taken from the first argument",
+            false => "",
+        };
+        let label = match transform.selection_mode {
+            SelectionMode::Capture => "pattern",
+            _ => "delimiter",
+        };
+        eprintln!("{label}: {}{source}", describe_engine(engine));
+    }
+    let inverted = match transform.invert {
+        true => " (inverted)",
+        false => "",
+    };
+    match transform.selection_mode {
+        SelectionMode::Json => {
+            let paths: Vec<String> = transform
+                .json_paths
+                .iter()
+                .map(|path| path.join("."))
+                .collect();
+            eprintln!("paths: {}", paths.join(" "));
+        }
+        _ => eprintln!(
+            "selections: {}{inverted}",
+            describe_selections(&transform.selections, &transform.percent_selections)
+        ),
+    }
+    eprintln!("join: {}", describe_join(transform.join.as_ref()));
+    eprintln!(
+        "strict: bounds {}, return {}, range order {}, utf8 {}, require {}, empty {}",
+        on_off(transform.strict_bounds),
+        on_off(transform.strict_return),
+        on_off(transform.strict_range_order),
+        on_off(transform.strict_utf8),
+        on_off(transform.strict_require),
+        on_off(output.strict_empty),
+    );
+    match input.input.is_empty() {
+        true => eprintln!("input: stdin"),
+        false => {
+            let files: Vec<String> = input
+                .input
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            eprintln!("input: {}", files.join(", "));
+        }
+    }
+    match &output.output {
+        Some(path) => eprintln!("output: {}", path.display()),
+        None => eprintln!("output: stdout"),
+    }
+}
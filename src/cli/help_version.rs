@@ -1,44 +1,243 @@
 pub fn print_help() {
-    println!("Usage: splitby [options] <delimiter> <selections>");
+    println!("Usage: splitby [options] <delimiter> <selections> [files...]");
     println!("Options:");
     println!("  -h, --help        Print help text");
     println!("  -v, --version     Print version number");
-    println!("  -i, --input=<FILE>              Provide an input file");
+    println!(
+        "  --explain                       Print what the flags add up to, without reading input"
+    );
+    println!("  -i, --input=<FILE>              Provide an input file (repeatable)");
     println!("  -o, --output=<FILE>             Write output to a file");
-    println!("  -d, --delimiter=<REGEX>         Specify the delimiter to use");
+    println!(
+        "  --output-append, --append       Append to the --output file instead of replacing it"
+    );
+    println!(
+        "  -d, --delimiter=<REGEX>         Specify the delimiter to use (repeat to split on any of them)"
+    );
+    println!("  -t, --tabs                      Split on tabs, the same as -d with a literal tab");
+    println!("  --delimiter-file=<FILE>         Read the delimiter from a file");
+    println!(
+        "  --spec=<SPEC>                   Give the delimiter, selections and join in one string"
+    );
+    println!(
+        "  --selections-file=<FILE>        Read selections from a file (# comments, repeatable)"
+    );
+    println!(
+        "  -F, --fixed                     Treat the delimiter as a literal string, not a regex"
+    );
+    println!("  -I, --ignore-case               Match the delimiter case-insensitively");
+    println!(
+        "  --csv                           Split fields as CSV, honouring quotes (delimiter defaults to ,)"
+    );
     println!(
         "  -j, --join=<STRING|HEX|KEYWORD> Join each selection with string or hex or delimiter"
     );
+    println!(
+        "  --separators=<LIST>             Comma-separated joins for each output position, then --join"
+    );
+    println!(
+        "  --replace=<FROM=TO>             Swap a kept delimiter FROM for TO (repeatable, ignored with --join)"
+    );
     println!("  -p, --placeholder=<STRING|HEX>  Inserts placeholder for invalid selections");
+    println!("  --null-on-missing               Inserts a NUL byte for invalid selections");
+    println!(
+        "  --escape                        Decode \\t, \\n, \\xNN etc. in join and placeholder (default)"
+    );
+    println!("  --no-escape                     Keep backslashes in join and placeholder literal");
+    println!(
+        "  --escape-output                 Write control characters in the output as \\t, \\xNN etc."
+    );
     println!("  --per-line                      Processes the input line by line (default)");
     println!(
         "  -w, --whole-string              Processes the input as a single string, rather than each line separately"
     );
     println!("  -z, --zero-terminated           Processes the input as zero-terminated strings");
+    println!("  --input-record-separator=<REGEX> Split the input into records on a regex");
     println!("  -f, --fields                    Select fields split by delimiter (default)");
     println!("  -b, --bytes                     Select bytes from the input");
     println!("  -c, --characters                Select characters from the input");
-    println!("  -a, --align=<MODE>              Align output (left|right|squash|none)");
-    println!("  --count                         Return the number of results after splitting");
+    println!("  --codepoints                    In chars mode, count code points, not graphemes");
+    println!("  --capture=<REGEX>               Select capture groups from each match of a regex");
+    println!(
+        "  --json-input=<PATHS>            Select values by dotted path from each JSON record"
+    );
+    println!(
+        "  --field-pattern=<REGEX>         Keep only the first capture of each selected field"
+    );
+    println!(
+        "  --field-length=<MIN-MAX>        Keep only selected fields whose length is within the range"
+    );
+    println!(
+        "  --field-length-basis=<BASIS>    How --field-length measures fields (bytes|chars|display)"
+    );
+    println!(
+        "  --encode[=ENCODING]             Encode each field (base64|base64url|hex, default base64)"
+    );
+    println!(
+        "  --decode[=ENCODING]             Decode each selected field (base64|base64url|hex, default base64)"
+    );
+    println!("  --json                          Output each record as a JSON array of strings");
+    println!(
+        "  --transpose                     Swap the rows and columns of a table (needs --whole-string)"
+    );
+    println!(
+        "  --shell-quote                   Single-quote each field so it is safe to paste into a shell"
+    );
+    println!(
+        "  --with-original[=PLACEMENT]     Output the original record too (prefix|suffix, default prefix)"
+    );
+    println!(
+        "  --original-separator=<STRING>   Separator between the original record and output (default tab)"
+    );
+    println!(
+        "  --pad-char=<CHAR|HEX>           Character used to pad aligned fields (default space)"
+    );
+    println!("  -a, --align=<MODE>              Align output (left|right|center|squash|none)");
+    println!(
+        "  --align-with=<PADDING>          Align with spaces (default) or a tab between columns"
+    );
+    println!(
+        "  --field-dedup-key=<N>           Only output the first record for each value of field N"
+    );
+    println!(
+        "  --count[=MODE]                  Return the number of results after splitting (all|nonempty)"
+    );
+    println!("  --count-total                   Return one total of the counts across all records");
+    println!(
+        "  --histogram                     Return how many records had each count (implies --count)"
+    );
+    println!(
+        "  --count-unit=<UNIT>             Count fields, bytes or chars, whatever the mode (implies --count)"
+    );
+    println!(
+        "  --stats                         Return the field count, then each field's byte length, tab-separated"
+    );
+    println!(
+        "  --complement-count              Return how many fields the selections left out of each record"
+    );
+    println!(
+        "  --emit-field-index-pairs[=SEP]  Prefix each field with its index and SEP (default =)"
+    );
+    println!("  --assert-sorted=<N>             Emit error if records are not sorted by field N");
+    println!("  --numeric                       Compare --assert-sorted keys as numbers");
+    println!(
+        "  --assert-uniform                Emit error if records have different numbers of fields"
+    );
+    println!("  -n, --number                    Prefix each output record with its record number");
+    println!(
+        "  --unordered                     Write records as soon as they are ready, in any order"
+    );
+    println!("  --ordered                       Write records in input order (default)");
+    println!("  -J, --jobs=<N|auto>             Number of worker threads (default auto)");
+    println!(
+        "  --reorder-window=<N>            Most batches read ahead of the writer (default 1024)"
+    );
+    println!("  --line-buffered                 Flush output after every record");
+    println!(
+        "  --progress                      Show how much of the input files is read, on stderr"
+    );
+    println!("  --batch-bytes=<N>               Bytes read before handing records to workers");
+    println!(
+        "  --read-buffer=<N>               Bytes read from the input at a time (1024 up to 1 GiB)"
+    );
+    println!("  --head=<N>, --max-records=<N>   Stop reading input after N records");
+    println!("  --tail=<N>                      Process only the last N records");
+    println!("  --max-record-bytes=<N>          Error on any record longer than N bytes");
+    println!(
+        "  --header=<LIST>                 Select columns by name from the first record, e.g. name,email"
+    );
+    println!("  --crlf-output                   Terminate each output line with CRLF");
+    println!("  --trim-newline                  Leave the terminator off the last record");
+    println!(
+        "  --output-terminator=<TERM>      Terminate output records with newline, nul, none or hex"
+    );
+    println!(
+        "  --field-terminator=<TERM>       Join the selected fields with nul, none or hex instead"
+    );
+    println!("  --print0-fields                 Join the selected fields with NUL, for xargs -0");
     println!("  --invert                        Inverts the chosen selection");
+    println!(
+        "  --slice-syntax                  Read selections as end-exclusive slices, e.g. 1:3 for fields 1 and 2"
+    );
+    println!(
+        "  --invert-keep-positions         Same as --redact, but blanks the fields when there's no --placeholder"
+    );
+    println!(
+        "  --pick=<REGEX>                  Keep only fields matching REGEX, before selecting (--invert drops them)"
+    );
+    println!(
+        "  --from=<REGEX>, --to=<REGEX>    Select the fields from one matching --from to the next matching --to"
+    );
+    println!("  --exclude-anchors               Leave out the fields matching --from and --to");
+    println!(
+        "  --offsets                       Return each selected field's byte offset and length as start:len"
+    );
+    println!(
+        "  --redact                        Replace the selected fields with --placeholder, keeping the rest"
+    );
     println!("  -e, --skip-empty                Skips empty fields when indexing or counting");
     println!(
         "  -E, --no-skip-empty             Does not skip empty fields when indexing or counting"
     );
+    println!(
+        "  --squash-delimiters             Treat a run of delimiters as one, keeping empty edge fields"
+    );
+    println!(
+        "  --keep-trailing-empty           In whole-string mode, keep the empty field after a trailing delimiter"
+    );
+    println!(
+        "  --max-fields=<N>                Split into at most N fields, the last keeping the rest"
+    );
+    println!(
+        "  --split-once                    Split at the first delimiter only, as --max-fields=2"
+    );
+    println!("  --trim                          Strip whitespace from both ends of each field");
+    println!("  --trim-left, --trim-right       Strip whitespace from one end of each field");
+    println!("  -s, --only-delimited            Skips records that contain no delimiter");
+    println!(
+        "  --require=<REGEX>               Skips records that don't match REGEX (errors with --strict)"
+    );
+    println!(
+        "  -u, --unique                    Skips fields already output earlier in the same record"
+    );
+    println!("  -r, --reverse                   Output the selected fields last to first");
+    println!("  --sort                          Sort the selected fields within each record");
+    println!(
+        "  --sort-numeric                  Sort by each field's leading number, then by bytes"
+    );
+    println!("  --sort-reverse                  Sort in descending order");
+    println!("  --comment=<PREFIX>              Skips records starting with PREFIX (repeatable)");
+    println!("  --fail-on-empty-output          Emit error if input produced no output at all");
     println!("  --strict                        Shorthand for all strict features");
     println!("  --no-strict                     Does not enforce strict features");
     println!("  --strict-bounds                 Emit error if range is out of bounds");
     println!("  --no-strict-bounds              Does not emit error if range is out of bounds");
     println!("  --strict-return                 Emit error if there is no result");
     println!("  --no-strict-return              Does not emit error if there is no result");
+    println!("  --strict-empty                  Emit error (exit 3) if the input has no records");
+    println!("  --no-strict-empty               Does not emit error if the input has no records");
     println!(
         "  --strict-range-order            Emit error if start of a range is greater than the end"
     );
     println!(
         "  --no-strict-range-order         Does not emit error if start of a range is greater than the end"
     );
+    println!(
+        "  --encoding=<ENCODING>           Decode input from utf8 (default), utf16le or utf16be"
+    );
+    println!("  --strip-bom                     Drop a UTF-8 BOM from the start of each input");
+    println!("  --keep-bom                      Keep a UTF-8 BOM at the start of each input");
+    println!(
+        "  --strip-cr                      Drop a trailing \\r from each record, whatever the terminator"
+    );
     println!("  --strict-utf8                   Emit error on invalid UTF-8 sequences");
     println!("  --no-strict-utf8                Does not emit error on invalid UTF-8 sequences");
+    println!("  --strict-require                Emit error on records that don't match --require");
+    println!("  --no-strict-require             Skip records that don't match --require (default)");
+    println!("Exit status:");
+    println!(
+        "  0 success, 1 error in the input, 2 usage or I/O error, 3 empty input (--strict-empty)"
+    );
 }
 
 pub fn print_version() {
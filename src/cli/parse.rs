@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use regex::Regex as SimpleRegex;
 
@@ -7,7 +7,11 @@ use crate::cli::types::*;
 use crate::cli::utilities::*;
 use crate::types::InputMode;
 use crate::types::SelectionMode;
-use crate::types::{Align, JoinMode};
+use crate::types::{
+    Align, CountUnit, Delimiter, Encoding, HeaderSelection, InputEncoding, JoinMode, LengthBasis,
+    OriginalPlacement, Trim,
+};
+use crate::utilities::display_width;
 
 pub enum ParseResult {
     FlagParsed,
@@ -21,7 +25,7 @@ pub fn parse_flags(
     raw_instructions: &mut CLIArguments,
 ) -> Result<ParseResult, String> {
     if consuming.input {
-        raw_instructions.input = Some(PathBuf::from(arg));
+        raw_instructions.input.push(PathBuf::from(arg));
         consuming.input = false;
         return Ok(ParseResult::FlagParsed);
     }
@@ -31,7 +35,7 @@ pub fn parse_flags(
         return Ok(ParseResult::FlagParsed);
     }
     if consuming.delim {
-        raw_instructions.delimiter = Some(parse_delimiter_token(arg));
+        raw_instructions.delimiters.push(parse_delimiter_token(arg));
         consuming.delim = false;
         return Ok(ParseResult::FlagParsed);
     }
@@ -40,8 +44,182 @@ pub fn parse_flags(
         consuming.join = false;
         return Ok(ParseResult::FlagParsed);
     }
+    if consuming.capture {
+        raw_instructions.capture = Some(arg.to_string());
+        raw_instructions.selection_mode = SelectionMode::Capture;
+        consuming.capture = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.json_input {
+        raw_instructions.json_input = Some(arg.to_string());
+        raw_instructions.selection_mode = SelectionMode::Json;
+        consuming.json_input = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.record_separator {
+        raw_instructions.record_separator = Some(arg.to_string());
+        consuming.record_separator = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.require {
+        raw_instructions.require = Some(arg.to_string());
+        consuming.require = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.pick {
+        raw_instructions.pick = Some(arg.to_string());
+        consuming.pick = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.from {
+        raw_instructions.from = Some(arg.to_string());
+        consuming.from = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.to {
+        raw_instructions.to = Some(arg.to_string());
+        consuming.to = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.comment {
+        raw_instructions
+            .comment_prefixes
+            .push(parse_comment_prefix(arg)?);
+        consuming.comment = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.field_pattern {
+        raw_instructions.field_pattern = Some(arg.to_string());
+        consuming.field_pattern = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.field_length {
+        raw_instructions.field_length = Some(parse_field_length(arg)?);
+        consuming.field_length = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.field_length_basis {
+        raw_instructions.field_length_basis = Some(parse_length_basis(arg)?);
+        consuming.field_length_basis = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.count_unit {
+        raw_instructions.count_unit = Some(parse_count_unit(arg)?);
+        consuming.count_unit = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.assert_sorted {
+        raw_instructions.assert_sorted = Some(parse_key_field(arg, "assert-sorted")?);
+        consuming.assert_sorted = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.selections_file {
+        raw_instructions.selections_files.push(PathBuf::from(arg));
+        consuming.selections_file = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.delimiter_file {
+        raw_instructions.delimiter_file = Some(PathBuf::from(arg));
+        consuming.delimiter_file = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.header {
+        raw_instructions.header = Some(arg.to_string());
+        consuming.header = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.max_record_bytes {
+        raw_instructions.max_record_bytes = Some(parse_max_record_bytes(arg)?);
+        consuming.max_record_bytes = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.input_encoding {
+        raw_instructions.input_encoding = Some(parse_input_encoding(arg)?);
+        consuming.input_encoding = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.output_terminator {
+        raw_instructions.output_terminator = Some(parse_output_terminator(arg)?);
+        consuming.output_terminator = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.field_terminator {
+        raw_instructions.field_terminator = Some(parse_field_terminator(arg)?);
+        consuming.field_terminator = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.max_records {
+        raw_instructions.max_records = Some(parse_max_records(arg)?);
+        consuming.max_records = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.tail {
+        raw_instructions.tail = Some(parse_tail(arg)?);
+        consuming.tail = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.max_fields {
+        raw_instructions.max_fields = Some(parse_max_fields(arg)?);
+        consuming.max_fields = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.separators {
+        raw_instructions.separators = Some(arg.as_bytes().to_vec());
+        consuming.separators = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.replace {
+        raw_instructions.replacements.push(arg.as_bytes().to_vec());
+        consuming.replace = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.pad_char {
+        raw_instructions.pad_char = Some(arg.as_bytes().to_vec());
+        consuming.pad_char = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.align_with {
+        raw_instructions.align_tabs = parse_align_with(arg)?;
+        consuming.align_with = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.batch_bytes {
+        raw_instructions.batch_bytes = Some(parse_batch_bytes(arg)?);
+        consuming.batch_bytes = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.jobs {
+        raw_instructions.jobs = Some(parse_jobs(arg)?);
+        consuming.jobs = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.spec {
+        raw_instructions.spec = Some(arg.to_string());
+        consuming.spec = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.read_buffer {
+        raw_instructions.read_buffer = Some(parse_read_buffer(arg)?);
+        consuming.read_buffer = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.reorder_window {
+        raw_instructions.reorder_window = Some(parse_reorder_window(arg)?);
+        consuming.reorder_window = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.dedup_key {
+        raw_instructions.dedup_key = Some(parse_key_field(arg, "field-dedup-key")?);
+        consuming.dedup_key = false;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if consuming.original_separator {
+        raw_instructions.original_separator = Some(arg.as_bytes().to_vec());
+        consuming.original_separator = false;
+        return Ok(ParseResult::FlagParsed);
+    }
     if consuming.placeholder {
-        raw_instructions.placeholder = Some(arg.as_bytes().to_vec());
+        raw_instructions.placeholders.push(arg.as_bytes().to_vec());
         consuming.placeholder = false;
         return Ok(ParseResult::FlagParsed);
     }
@@ -49,13 +227,8 @@ pub fn parse_flags(
         // Our valid align possibilities:
         // - Normal align flags -> set the align
         // - anything else -> assume we're not consuming and set to default
-        if let Ok(Some(align_result)) = parse_align(&arg, true) {
-            match align_result {
-                Align::Left => raw_instructions.align = Align::Left,
-                Align::Right => raw_instructions.align = Align::Right,
-                Align::Squash => raw_instructions.align = Align::Squash,
-                Align::None => raw_instructions.align = Align::None,
-            }
+        if let Ok(Some(align_result)) = parse_align(arg, true) {
+            raw_instructions.align = align_result;
             consuming.align = false;
             return Ok(ParseResult::FlagParsed);
         }
@@ -64,19 +237,65 @@ pub fn parse_flags(
         consuming.align = false;
     }
     // Handle consuming flags
-    if arg.starts_with("--input") && arg != "--input" {
+    if arg.starts_with("--input-record-separator") && arg != "--input-record-separator" {
+        if !arg.starts_with("--input-record-separator=") {
+            return Err(format!("invalid input-record-separator flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.record_separator = Some(trim_quotes(value));
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--require") && arg != "--require" {
+        if !arg.starts_with("--require=") {
+            return Err(format!("invalid require flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.require = Some(trim_quotes(value));
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--pick") && arg != "--pick" {
+        if !arg.starts_with("--pick=") {
+            return Err(format!("invalid pick flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.pick = Some(trim_quotes(value));
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--from") && arg != "--from" {
+        if !arg.starts_with("--from=") {
+            return Err(format!("invalid from flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.from = Some(trim_quotes(value));
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--to") && arg != "--to" {
+        if !arg.starts_with("--to=") {
+            return Err(format!("invalid to flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.to = Some(trim_quotes(value));
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--input") && arg != "--input" && arg != "--input-record-separator" {
         if !arg.starts_with("--input=") {
             return Err(format!("invalid input flag: '{arg}'"));
         }
         let value = arg.split("=").nth(1);
         if let Some(value) = value {
-            raw_instructions.input = Some(PathBuf::from(trim_quotes(value)));
+            raw_instructions
+                .input
+                .push(PathBuf::from(trim_quotes(value)));
         } else {
-            return Err(format!("empty input value"));
+            return Err("empty input value".to_string());
         }
         return Ok(ParseResult::FlagParsed);
     }
-    if arg.starts_with("--output") && arg != "--output" {
+    if arg.starts_with("--output")
+        && arg != "--output"
+        && arg != "--output-append"
+        && !arg.starts_with("--output-terminator")
+    {
         if !arg.starts_with("--output=") {
             return Err(format!("invalid output flag: '{arg}'"));
         }
@@ -84,19 +303,47 @@ pub fn parse_flags(
         if let Some(value) = value {
             raw_instructions.output = Some(PathBuf::from(trim_quotes(value)));
         } else {
-            return Err(format!("empty output value"));
+            return Err("empty output value".to_string());
         }
         return Ok(ParseResult::FlagParsed);
     }
-    if arg.starts_with("--delimiter") && arg != "--delimiter" {
+    if arg.starts_with("--selections-file") && arg != "--selections-file" {
+        if !arg.starts_with("--selections-file=") {
+            return Err(format!("invalid selections-file flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions
+            .selections_files
+            .push(PathBuf::from(trim_quotes(value)));
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--delimiter-file") && arg != "--delimiter-file" {
+        if !arg.starts_with("--delimiter-file=") {
+            return Err(format!("invalid delimiter-file flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.delimiter_file = Some(PathBuf::from(trim_quotes(value)));
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--spec") && arg != "--spec" {
+        if !arg.starts_with("--spec=") {
+            return Err(format!("invalid spec flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.spec = Some(trim_quotes(value));
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--delimiter") && arg != "--delimiter" && arg != "--delimiter-file" {
         if !arg.starts_with("--delimiter=") {
             return Err(format!("invalid delimiter flag: '{arg}'"));
         }
         let value = arg.split("=").nth(1);
         if let Some(value) = value {
-            raw_instructions.delimiter = Some(parse_delimiter_token(value));
+            raw_instructions
+                .delimiters
+                .push(parse_delimiter_token(value));
         } else {
-            raw_instructions.delimiter = Some(parse_delimiter_token(""));
+            raw_instructions.delimiters.push(parse_delimiter_token(""));
         }
         return Ok(ParseResult::FlagParsed);
     }
@@ -112,25 +359,266 @@ pub fn parse_flags(
         }
         return Ok(ParseResult::FlagParsed);
     }
+    if arg.starts_with("--capture") && arg != "--capture" {
+        if !arg.starts_with("--capture=") {
+            return Err(format!("invalid capture flag: '{arg}'"));
+        }
+        // Patterns often contain '=', so only split on the first one
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.capture = Some(trim_quotes(value));
+        raw_instructions.selection_mode = SelectionMode::Capture;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--json-input") && arg != "--json-input" {
+        if !arg.starts_with("--json-input=") {
+            return Err(format!("invalid json-input flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.json_input = Some(trim_quotes(value));
+        raw_instructions.selection_mode = SelectionMode::Json;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--comment") && arg != "--comment" {
+        if !arg.starts_with("--comment=") {
+            return Err(format!("invalid comment flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions
+            .comment_prefixes
+            .push(parse_comment_prefix(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--field-pattern") && arg != "--field-pattern" {
+        if !arg.starts_with("--field-pattern=") {
+            return Err(format!("invalid field-pattern flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.field_pattern = Some(trim_quotes(value));
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--field-length-basis") && arg != "--field-length-basis" {
+        if !arg.starts_with("--field-length-basis=") {
+            return Err(format!("invalid field-length-basis flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.field_length_basis = Some(parse_length_basis(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--field-length") && arg != "--field-length" && arg != "--field-length-basis"
+    {
+        if !arg.starts_with("--field-length=") {
+            return Err(format!("invalid field-length flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.field_length = Some(parse_field_length(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--assert-sorted") && arg != "--assert-sorted" {
+        if !arg.starts_with("--assert-sorted=") {
+            return Err(format!("invalid assert-sorted flag: '{arg}'"));
+        }
+        let value = arg.split("=").nth(1).unwrap_or("");
+        raw_instructions.assert_sorted =
+            Some(parse_key_field(&trim_quotes(value), "assert-sorted")?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--encode") && arg != "--encode" {
+        if !arg.starts_with("--encode=") {
+            return Err(format!("invalid encode flag: '{arg}'"));
+        }
+        let value = arg.split("=").nth(1).unwrap_or("");
+        raw_instructions.encoding = Some(parse_encoding(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--decode") && arg != "--decode" {
+        if !arg.starts_with("--decode=") {
+            return Err(format!("invalid decode flag: '{arg}'"));
+        }
+        let value = arg.split("=").nth(1).unwrap_or("");
+        raw_instructions.decoding = Some(parse_encoding(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--header") && arg != "--header" {
+        if !arg.starts_with("--header=") {
+            return Err(format!("invalid header flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.header = Some(trim_quotes(value));
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--encoding") && arg != "--encoding" {
+        if !arg.starts_with("--encoding=") {
+            return Err(format!("invalid encoding flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.input_encoding = Some(parse_input_encoding(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--output-terminator") && arg != "--output-terminator" {
+        if !arg.starts_with("--output-terminator=") {
+            return Err(format!("invalid output-terminator flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.output_terminator = Some(parse_output_terminator(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--field-terminator") && arg != "--field-terminator" {
+        if !arg.starts_with("--field-terminator=") {
+            return Err(format!("invalid field-terminator flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.field_terminator = Some(parse_field_terminator(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--max-record-bytes") && arg != "--max-record-bytes" {
+        if !arg.starts_with("--max-record-bytes=") {
+            return Err(format!("invalid max-record-bytes flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.max_record_bytes = Some(parse_max_record_bytes(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--max-records") && arg != "--max-records" {
+        if !arg.starts_with("--max-records=") {
+            return Err(format!("invalid max-records flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.max_records = Some(parse_max_records(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    // --head is --max-records by another name. Checked by its = so --header isn't caught
+    if let Some(value) = arg.strip_prefix("--head=") {
+        raw_instructions.max_records = Some(parse_max_records(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--tail") && arg != "--tail" {
+        if !arg.starts_with("--tail=") {
+            return Err(format!("invalid tail flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.tail = Some(parse_tail(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--max-fields") && arg != "--max-fields" {
+        if !arg.starts_with("--max-fields=") {
+            return Err(format!("invalid max-fields flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.max_fields = Some(parse_max_fields(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--separators") && arg != "--separators" {
+        if !arg.starts_with("--separators=") {
+            return Err(format!("invalid separators flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.separators = Some(trim_quotes(value).as_bytes().to_vec());
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--replace") && arg != "--replace" {
+        if !arg.starts_with("--replace=") {
+            return Err(format!("invalid replace flag: '{arg}'"));
+        }
+        // The value is FROM=TO, so only split off the flag at the first '='
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions
+            .replacements
+            .push(trim_quotes(value).as_bytes().to_vec());
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--pad-char") && arg != "--pad-char" {
+        if !arg.starts_with("--pad-char=") {
+            return Err(format!("invalid pad-char flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.pad_char = Some(trim_quotes(value).as_bytes().to_vec());
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--batch-bytes") && arg != "--batch-bytes" {
+        if !arg.starts_with("--batch-bytes=") {
+            return Err(format!("invalid batch-bytes flag: '{arg}'"));
+        }
+        let value = arg.split("=").nth(1).unwrap_or("");
+        raw_instructions.batch_bytes = Some(parse_batch_bytes(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--jobs") && arg != "--jobs" {
+        if !arg.starts_with("--jobs=") {
+            return Err(format!("invalid jobs flag: '{arg}'"));
+        }
+        let value = arg.split("=").nth(1).unwrap_or("");
+        raw_instructions.jobs = Some(parse_jobs(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--read-buffer") && arg != "--read-buffer" {
+        if !arg.starts_with("--read-buffer=") {
+            return Err(format!("invalid read-buffer flag: '{arg}'"));
+        }
+        let value = arg.split("=").nth(1).unwrap_or("");
+        raw_instructions.read_buffer = Some(parse_read_buffer(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--reorder-window") && arg != "--reorder-window" {
+        if !arg.starts_with("--reorder-window=") {
+            return Err(format!("invalid reorder-window flag: '{arg}'"));
+        }
+        let value = arg.split("=").nth(1).unwrap_or("");
+        raw_instructions.reorder_window = Some(parse_reorder_window(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--field-dedup-key") && arg != "--field-dedup-key" {
+        if !arg.starts_with("--field-dedup-key=") {
+            return Err(format!("invalid field-dedup-key flag: '{arg}'"));
+        }
+        let value = arg.split("=").nth(1).unwrap_or("");
+        raw_instructions.dedup_key = Some(parse_key_field(&trim_quotes(value), "field-dedup-key")?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--with-original") && arg != "--with-original" {
+        if !arg.starts_with("--with-original=") {
+            return Err(format!("invalid with-original flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.with_original = Some(parse_original_placement(&trim_quotes(value))?);
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--original-separator") && arg != "--original-separator" {
+        if !arg.starts_with("--original-separator=") {
+            return Err(format!("invalid original-separator flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.original_separator = Some(trim_quotes(value).as_bytes().to_vec());
+        return Ok(ParseResult::FlagParsed);
+    }
     if arg.starts_with("--placeholder") && arg != "--placeholder" {
         if !arg.starts_with("--placeholder=") {
             return Err(format!("invalid placeholder flag: '{arg}'"));
         }
         let value = arg.split("=").nth(1);
         if let Some(value) = value {
-            raw_instructions.placeholder = Some(trim_quotes(value).as_bytes().to_vec());
+            raw_instructions
+                .placeholders
+                .push(trim_quotes(value).as_bytes().to_vec());
         } else {
-            raw_instructions.placeholder = Some("".as_bytes().to_vec());
+            raw_instructions.placeholders.push("".as_bytes().to_vec());
         }
         return Ok(ParseResult::FlagParsed);
     }
-    if arg.starts_with("--align") && arg != "--align" {
+    if arg.starts_with("--align-with") && arg != "--align-with" {
+        if !arg.starts_with("--align-with=") {
+            return Err(format!("invalid align-with flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.align_tabs = parse_align_with(&trim_quotes(value))?;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--align") && arg != "--align" && arg != "--align-with" {
         if !arg.starts_with("--align=") {
             return Err(format!("invalid align flag: '{arg}'"));
         }
         let value = match arg.split("=").nth(1) {
             Some(val) => val,
-            None => return Err(format!("empty align value")),
+            None => return Err("empty align value".to_string()),
         };
 
         match parse_align(&trim_quotes(value), false) {
@@ -141,10 +629,56 @@ pub fn parse_flags(
         return Ok(ParseResult::FlagParsed);
     }
 
+    if arg.starts_with("--emit-field-index-pairs") && arg != "--emit-field-index-pairs" {
+        if !arg.starts_with("--emit-field-index-pairs=") {
+            return Err(format!("invalid emit-field-index-pairs flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.index_pair_separator = Some(trim_quotes(value).as_bytes().to_vec());
+        return Ok(ParseResult::FlagParsed);
+    }
+
+    if arg.starts_with("--count-unit") && arg != "--count-unit" {
+        if !arg.starts_with("--count-unit=") {
+            return Err(format!("invalid count-unit flag: '{arg}'"));
+        }
+        let value = arg.split_once("=").map(|(_, value)| value).unwrap_or("");
+        raw_instructions.count_unit = Some(parse_count_unit(&trim_quotes(value))?);
+        raw_instructions.count = true;
+        return Ok(ParseResult::FlagParsed);
+    }
+    if arg.starts_with("--count")
+        && arg != "--count"
+        && arg != "--count-total"
+        && arg != "--count-unit"
+    {
+        if !arg.starts_with("--count=") {
+            return Err(format!("invalid count flag: '{arg}'"));
+        }
+        let value = match arg.split("=").nth(1) {
+            Some(val) => val,
+            None => return Err("empty count value".to_string()),
+        };
+
+        match trim_quotes(value).to_lowercase().as_str() {
+            "all" => raw_instructions.count_nonempty = false,
+            "nonempty" => raw_instructions.count_nonempty = true,
+            _ => {
+                return Err(format!(
+                    "invalid count mode: '{value}', valid modes are: all, nonempty"
+                ));
+            }
+        }
+        raw_instructions.count = true;
+        return Ok(ParseResult::FlagParsed);
+    }
+
     if arg.starts_with("-d") && arg != "-d" {
         // Support -d, -d',' and -d","
         let delim_value = &arg[2..]; // characters after -d
-        raw_instructions.delimiter = Some(parse_delimiter_token(delim_value));
+        raw_instructions
+            .delimiters
+            .push(parse_delimiter_token(delim_value));
         return Ok(ParseResult::FlagParsed);
     }
 
@@ -152,123 +686,512 @@ pub fn parse_flags(
     match arg {
         "-v" | "--version" => {
             print_version();
-            return Ok(ParseResult::Finished);
+            Ok(ParseResult::Finished)
         }
         "-h" | "--help" => {
             print_help();
-            return Ok(ParseResult::Finished);
+            Ok(ParseResult::Finished)
         }
         "--per-line" => {
             raw_instructions.input_mode = InputMode::PerLine;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--whole-string" | "-w" => {
             raw_instructions.input_mode = InputMode::WholeString;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--zero-terminated" | "-z" => {
             raw_instructions.input_mode = InputMode::ZeroTerminated;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--bytes" | "-b" => {
             raw_instructions.selection_mode = SelectionMode::Bytes;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--characters" | "-c" => {
             raw_instructions.selection_mode = SelectionMode::Chars;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--codepoints" => {
+            raw_instructions.codepoints = true;
+            Ok(ParseResult::FlagParsed)
         }
         "--fields" | "-f" => {
             raw_instructions.selection_mode = SelectionMode::Fields;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--capture" => {
+            consuming.capture = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--json-input" => {
+            consuming.json_input = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--input-record-separator" => {
+            consuming.record_separator = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--require" => {
+            consuming.require = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--pick" => {
+            consuming.pick = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--from" => {
+            consuming.from = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--to" => {
+            consuming.to = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--exclude-anchors" => {
+            raw_instructions.exclude_anchors = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--comment" => {
+            consuming.comment = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--field-pattern" => {
+            consuming.field_pattern = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--field-length" => {
+            consuming.field_length = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--field-length-basis" => {
+            consuming.field_length_basis = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        // --count-unit implies --count, as --sort-numeric implies --sort
+        "--count-unit" => {
+            consuming.count_unit = true;
+            raw_instructions.count = true;
+            Ok(ParseResult::FlagParsed)
         }
         "--input" | "-i" => {
             consuming.input = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--output" | "-o" => {
             consuming.output = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
+        }
+        // Already read ahead by get_instructions, as it changes how selections are parsed
+        "--slice-syntax" => Ok(ParseResult::FlagParsed),
+        "--output-append" | "--append" => {
+            raw_instructions.output_append = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--trim-newline" => {
+            raw_instructions.trim_newline = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--fixed" | "-F" => {
+            raw_instructions.fixed = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--transpose" => {
+            raw_instructions.transpose = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--json" => {
+            raw_instructions.json = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--with-original" => {
+            raw_instructions.with_original = Some(OriginalPlacement::Prefix);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--original-separator" => {
+            consuming.original_separator = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--shell-quote" => {
+            raw_instructions.shell_quote = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--ignore-case" | "-I" => {
+            raw_instructions.ignore_case = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--csv" => {
+            raw_instructions.csv = true;
+            Ok(ParseResult::FlagParsed)
         }
         "--delimiter" | "-d" => {
             consuming.delim = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--tabs" | "-t" => {
+            raw_instructions
+                .delimiters
+                .push(Delimiter::Literal("\t".to_string()));
+            Ok(ParseResult::FlagParsed)
         }
         "--join" | "-j" => {
             consuming.join = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--placeholder" | "-p" => {
             consuming.placeholder = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--align" | "-a" => {
             consuming.align = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--skip-empty" | "-e" => {
             raw_instructions.skip_empty = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--no-skip-empty" | "-E" => {
             raw_instructions.skip_empty = false;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--squash-delimiters" => {
+            raw_instructions.squash_delimiters = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--keep-trailing-empty" => {
+            raw_instructions.keep_trailing_empty = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--trim" => {
+            raw_instructions.trim = Trim::Both;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--trim-left" => {
+            raw_instructions.trim = match raw_instructions.trim {
+                Trim::Right | Trim::Both => Trim::Both,
+                Trim::None | Trim::Left => Trim::Left,
+            };
+            Ok(ParseResult::FlagParsed)
+        }
+        "--trim-right" => {
+            raw_instructions.trim = match raw_instructions.trim {
+                Trim::Left | Trim::Both => Trim::Both,
+                Trim::None | Trim::Right => Trim::Right,
+            };
+            Ok(ParseResult::FlagParsed)
+        }
+        "--reverse" | "-r" => {
+            raw_instructions.reverse = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--sort" => {
+            raw_instructions.sort = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--sort-numeric" => {
+            raw_instructions.sort_numeric = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--sort-reverse" => {
+            raw_instructions.sort_reverse = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--unique" | "-u" => {
+            raw_instructions.unique = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--only-delimited" | "-s" => {
+            raw_instructions.only_delimited = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--encode" => {
+            raw_instructions.encoding = Some(Encoding::Base64);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--decode" => {
+            raw_instructions.decoding = Some(Encoding::Base64);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--selections-file" => {
+            consuming.selections_file = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--delimiter-file" => {
+            consuming.delimiter_file = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--spec" => {
+            consuming.spec = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--header" => {
+            consuming.header = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--encoding" => {
+            consuming.input_encoding = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--output-terminator" => {
+            consuming.output_terminator = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--field-terminator" => {
+            consuming.field_terminator = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--print0-fields" => {
+            raw_instructions.field_terminator = Some(b"\0".to_vec());
+            Ok(ParseResult::FlagParsed)
+        }
+        "--max-record-bytes" => {
+            consuming.max_record_bytes = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--max-records" | "--head" => {
+            consuming.max_records = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--tail" => {
+            consuming.tail = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--max-fields" => {
+            consuming.max_fields = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--split-once" => {
+            raw_instructions.max_fields = Some(2);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--separators" => {
+            consuming.separators = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--replace" => {
+            consuming.replace = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--pad-char" => {
+            consuming.pad_char = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--align-with" => {
+            consuming.align_with = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--batch-bytes" => {
+            consuming.batch_bytes = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--line-buffered" => {
+            raw_instructions.line_buffered = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--progress" => {
+            raw_instructions.progress = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--jobs" | "-J" => {
+            consuming.jobs = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--reorder-window" => {
+            consuming.reorder_window = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--read-buffer" => {
+            consuming.read_buffer = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--assert-sorted" => {
+            consuming.assert_sorted = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--assert-uniform" => {
+            raw_instructions.assert_uniform = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--numeric" => {
+            raw_instructions.numeric = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--field-dedup-key" => {
+            consuming.dedup_key = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--count-total" => {
+            raw_instructions.count_total = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--histogram" => {
+            raw_instructions.histogram = true;
+            Ok(ParseResult::FlagParsed)
         }
         "--count" => {
             raw_instructions.count = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--stats" => {
+            raw_instructions.stats = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--complement-count" => {
+            raw_instructions.complement_count = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--emit-field-index-pairs" => {
+            raw_instructions.index_pair_separator = Some(b"=".to_vec());
+            Ok(ParseResult::FlagParsed)
+        }
+        "--escape" => {
+            raw_instructions.escape = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--escape-output" => {
+            raw_instructions.escape_output = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--no-escape" => {
+            raw_instructions.escape = false;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--fail-on-empty-output" => {
+            raw_instructions.fail_on_empty_output = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--explain" => {
+            raw_instructions.explain = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--number" | "-n" => {
+            raw_instructions.number = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--unordered" => {
+            raw_instructions.unordered = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--ordered" => {
+            raw_instructions.unordered = false;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--crlf-output" => {
+            raw_instructions.crlf_output = true;
+            Ok(ParseResult::FlagParsed)
         }
         "--invert" => {
             raw_instructions.invert = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--offsets" => {
+            raw_instructions.offsets = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        // An alias of --redact that blanks the fields when there's no --placeholder
+        "--invert-keep-positions" => {
+            raw_instructions.redact = true;
+            raw_instructions.invert_keep_positions = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--redact" => {
+            raw_instructions.redact = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--null-on-missing" => {
+            raw_instructions.null_on_missing = true;
+            Ok(ParseResult::FlagParsed)
         }
         "--strict" => {
             raw_instructions.strict_bounds = true;
             raw_instructions.strict_range_order = true;
             raw_instructions.strict_return = true;
+            raw_instructions.strict_empty = true;
             raw_instructions.strict_utf8 = true;
-            return Ok(ParseResult::FlagParsed);
+            raw_instructions.strict_require = true;
+            Ok(ParseResult::FlagParsed)
         }
         "--no-strict" => {
             raw_instructions.strict_bounds = false;
             raw_instructions.strict_range_order = false;
             raw_instructions.strict_return = false;
+            raw_instructions.strict_empty = false;
             raw_instructions.strict_utf8 = false;
-            return Ok(ParseResult::FlagParsed);
+            raw_instructions.strict_require = false;
+            Ok(ParseResult::FlagParsed)
         }
         "--strict-bounds" => {
             raw_instructions.strict_bounds = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--no-strict-bounds" => {
             raw_instructions.strict_bounds = false;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--strict-return" => {
             raw_instructions.strict_return = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--no-strict-return" => {
             raw_instructions.strict_return = false;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--strict-empty" => {
+            raw_instructions.strict_empty = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--no-strict-empty" => {
+            raw_instructions.strict_empty = false;
+            Ok(ParseResult::FlagParsed)
         }
         "--strict-range-order" => {
             raw_instructions.strict_range_order = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--no-strict-range-order" => {
             raw_instructions.strict_range_order = false;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--strict-utf8" => {
             raw_instructions.strict_utf8 = true;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
         }
         "--no-strict-utf8" => {
             raw_instructions.strict_utf8 = false;
-            return Ok(ParseResult::FlagParsed);
+            Ok(ParseResult::FlagParsed)
+        }
+        "--strict-require" => {
+            raw_instructions.strict_require = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--no-strict-require" => {
+            raw_instructions.strict_require = false;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--strip-bom" => {
+            raw_instructions.keep_bom = false;
+            Ok(ParseResult::FlagParsed)
+        }
+        "--keep-bom" => {
+            raw_instructions.keep_bom = true;
+            Ok(ParseResult::FlagParsed)
         }
-        _ => return Ok(ParseResult::FlagNotParsed),
+        "--strip-cr" => {
+            raw_instructions.strip_cr = true;
+            Ok(ParseResult::FlagParsed)
+        }
+        _ => Ok(ParseResult::FlagNotParsed),
+    }
+}
+
+/// Parses --align-with, returning whether columns are separated with tabs.
+pub fn parse_align_with(arg: &str) -> Result<bool, String> {
+    match arg.to_lowercase().as_str() {
+        "spaces" => Ok(false),
+        "tabs" => Ok(true),
+        _ => Err(format!(
+            "invalid align-with value: '{arg}', must be spaces or tabs"
+        )),
     }
 }
 
@@ -277,20 +1200,225 @@ pub fn parse_align(arg: &str, allow_any: bool) -> Result<Option<Align>, String>
         "left" => Ok(Some(Align::Left)),
         "right" => Ok(Some(Align::Right)),
         "squash" => Ok(Some(Align::Squash)),
+        "center" | "centre" => Ok(Some(Align::Center)),
         "none" => Ok(Some(Align::None)),
         _ => {
             if allow_any {
                 Ok(None)
             } else {
                 Err(format!(
-                    "invalid align mode: '{arg}', valid modes are: left, right, squash, none"
+                    "invalid align mode: '{arg}', valid modes are: left, right, center, squash, none"
                 ))
             }
         }
     }
 }
 
-pub fn parse_join(arg: &[u8]) -> Option<JoinMode> {
+pub fn parse_input_encoding(arg: &str) -> Result<InputEncoding, String> {
+    match arg.to_lowercase().as_str() {
+        "utf8" | "utf-8" => Ok(InputEncoding::Utf8),
+        "utf16le" | "utf-16le" => Ok(InputEncoding::Utf16Le),
+        "utf16be" | "utf-16be" => Ok(InputEncoding::Utf16Be),
+        _ => Err(format!(
+            "invalid encoding: '{arg}', must be utf8, utf16le or utf16be"
+        )),
+    }
+}
+
+pub fn parse_encoding(arg: &str) -> Result<Encoding, String> {
+    match arg.to_lowercase().as_str() {
+        "base64" => Ok(Encoding::Base64),
+        "base64url" => Ok(Encoding::Base64Url),
+        "hex" => Ok(Encoding::Hex),
+        _ => Err(format!(
+            "invalid encoding: '{arg}', valid encodings are: base64, base64url, hex"
+        )),
+    }
+}
+
+/// Parses a --field-length range: "MIN-MAX", "MIN-", "-MAX" or an exact "N".
+pub fn parse_field_length(arg: &str) -> Result<(usize, usize), String> {
+    let invalid =
+        || format!("invalid field-length: '{arg}', expected a range like 3-10, 3-, -10 or 5");
+    let parse_bound = |bound: &str, default: usize| match bound {
+        "" => Ok(default),
+        bound => bound.parse::<usize>().map_err(|_| invalid()),
+    };
+
+    let (min, max) = match arg.split_once('-') {
+        Some((min, max)) => (parse_bound(min, 0)?, parse_bound(max, usize::MAX)?),
+        None if arg.is_empty() => return Err(invalid()),
+        None => {
+            let exact = parse_bound(arg, 0)?;
+            (exact, exact)
+        }
+    };
+    if min > max {
+        return Err(format!(
+            "invalid field-length: '{arg}', minimum is greater than maximum"
+        ));
+    }
+    Ok((min, max))
+}
+
+pub fn parse_original_placement(arg: &str) -> Result<OriginalPlacement, String> {
+    match arg.to_lowercase().as_str() {
+        "prefix" => Ok(OriginalPlacement::Prefix),
+        "suffix" => Ok(OriginalPlacement::Suffix),
+        _ => Err(format!(
+            "invalid with-original placement: '{arg}', valid placements are: prefix, suffix"
+        )),
+    }
+}
+
+pub fn parse_length_basis(arg: &str) -> Result<LengthBasis, String> {
+    match arg.to_lowercase().as_str() {
+        "bytes" => Ok(LengthBasis::Bytes),
+        "chars" => Ok(LengthBasis::Chars),
+        "display" => Ok(LengthBasis::Display),
+        _ => Err(format!(
+            "invalid field-length-basis: '{arg}', valid bases are: bytes, chars, display"
+        )),
+    }
+}
+
+pub fn parse_count_unit(arg: &str) -> Result<CountUnit, String> {
+    match arg.to_lowercase().as_str() {
+        "fields" => Ok(CountUnit::Fields),
+        "bytes" => Ok(CountUnit::Bytes),
+        "chars" => Ok(CountUnit::Chars),
+        _ => Err(format!(
+            "invalid count-unit: '{arg}', valid units are: fields, bytes, chars"
+        )),
+    }
+}
+
+/// Decodes a --pad-char value, which must be one byte or one single-width character.
+pub fn parse_pad_char(arg: &[u8], escape: bool) -> Result<Vec<u8>, String> {
+    let pad = parse_placeholder(arg, escape).unwrap_or_default();
+    let is_single_character = match std::str::from_utf8(&pad) {
+        Ok(text) => text.chars().count() == 1 && display_width(&pad) == 1,
+        Err(_) => pad.len() == 1,
+    };
+    if !is_single_character {
+        return Err(format!(
+            "invalid pad-char: '{}', must be a single character",
+            String::from_utf8_lossy(arg)
+        ));
+    }
+    Ok(pad)
+}
+
+pub fn parse_comment_prefix(arg: &str) -> Result<Vec<u8>, String> {
+    if arg.is_empty() {
+        return Err("empty string is not a valid comment prefix".to_string());
+    }
+    Ok(arg.as_bytes().to_vec())
+}
+
+pub fn parse_output_terminator(arg: &str) -> Result<Vec<u8>, String> {
+    match arg.to_lowercase().as_str() {
+        "newline" => Ok(b"\n".to_vec()),
+        "nul" => Ok(b"\0".to_vec()),
+        "none" => Ok(Vec::new()),
+        _ => parse_hex(arg.as_bytes()).ok_or_else(|| {
+            format!("invalid output-terminator: '{arg}', must be newline, nul, none or hex (e.g. 0x0d0a)")
+        }),
+    }
+}
+
+pub fn parse_field_terminator(arg: &str) -> Result<Vec<u8>, String> {
+    match arg.to_lowercase().as_str() {
+        "nul" => Ok(b"\0".to_vec()),
+        "none" => Ok(Vec::new()),
+        _ => parse_hex(arg.as_bytes()).ok_or_else(|| {
+            format!("invalid field-terminator: '{arg}', must be nul, none or hex (e.g. 0x00)")
+        }),
+    }
+}
+
+pub fn parse_max_record_bytes(arg: &str) -> Result<usize, String> {
+    match arg.parse::<usize>() {
+        Ok(max) if max > 0 => Ok(max),
+        _ => Err(format!(
+            "invalid max-record-bytes: '{arg}', must be a positive number"
+        )),
+    }
+}
+
+pub fn parse_max_records(arg: &str) -> Result<usize, String> {
+    arg.parse::<usize>()
+        .map_err(|_| format!("invalid max-records: '{arg}', must be a non-negative number"))
+}
+
+pub fn parse_tail(arg: &str) -> Result<usize, String> {
+    arg.parse::<usize>()
+        .map_err(|_| format!("invalid tail: '{arg}', must be a non-negative number"))
+}
+
+/// Parses a --max-fields value. 0 and 1 both leave the record unsplit.
+pub fn parse_max_fields(arg: &str) -> Result<usize, String> {
+    arg.parse::<usize>()
+        .map_err(|_| format!("invalid max-fields: '{arg}', must be a non-negative number"))
+}
+
+/// Parses a --batch-bytes value. 0 sends every record to the workers as soon as it is read.
+pub fn parse_batch_bytes(arg: &str) -> Result<usize, String> {
+    arg.parse::<usize>()
+        .map_err(|_| format!("invalid batch-bytes: '{arg}', must be a non-negative number"))
+}
+
+/// Parses a --jobs value. "auto" is returned as 0, which sizes the workers to the available cores.
+pub fn parse_jobs(arg: &str) -> Result<usize, String> {
+    if arg.eq_ignore_ascii_case("auto") {
+        return Ok(0);
+    }
+    match arg.parse::<i64>() {
+        Ok(jobs) if jobs < 0 => Err(format!(
+            "invalid jobs: '{arg}', must be a positive number, or 0 or auto for all cores"
+        )),
+        Ok(jobs) => usize::try_from(jobs).map_err(|_| format!("invalid jobs: '{arg}'")),
+        Err(_) => Err(format!("invalid jobs: '{arg}'")),
+    }
+}
+
+/// The smallest --read-buffer, below which each read costs more than it brings in.
+const MIN_READ_BUFFER: usize = 1024;
+/// The largest --read-buffer, as each source allocates it up front.
+const MAX_READ_BUFFER: usize = 1024 * 1024 * 1024;
+
+/// Parses a --read-buffer value, the bytes each source is read in.
+pub fn parse_read_buffer(arg: &str) -> Result<usize, String> {
+    match arg.parse::<usize>() {
+        Ok(size) if (MIN_READ_BUFFER..=MAX_READ_BUFFER).contains(&size) => Ok(size),
+        _ => Err(format!(
+            "invalid read-buffer: '{arg}', must be between {MIN_READ_BUFFER} and {MAX_READ_BUFFER} bytes"
+        )),
+    }
+}
+
+/// Parses a --reorder-window value, the most batches that can be read ahead of the writer.
+pub fn parse_reorder_window(arg: &str) -> Result<usize, String> {
+    match arg.parse::<usize>() {
+        Ok(window) if window > 0 => Ok(window),
+        _ => Err(format!(
+            "invalid reorder-window: '{arg}', must be a positive number"
+        )),
+    }
+}
+
+/// Parses the field index for flags like --field-dedup-key, which name one key field.
+pub fn parse_key_field(arg: &str, flag_name: &str) -> Result<i32, String> {
+    match arg.parse::<i32>() {
+        Ok(0) => Err(format!(
+            "invalid {flag_name}: fields are 1-based, 0 is an invalid index"
+        )),
+        Ok(key) => Ok(key),
+        Err(_) => Err(format!("invalid {flag_name}: '{arg}'")),
+    }
+}
+
+pub fn parse_join(arg: &[u8], escape: bool) -> Option<JoinMode> {
     match arg {
         b"auto" => Some(JoinMode::Auto),
         b"after-previous" => Some(JoinMode::AfterPrevious),
@@ -302,21 +1430,86 @@ pub fn parse_join(arg: &[u8]) -> Option<JoinMode> {
         // Regular string join or hex
         _ => {
             // Try parsing as hex first
-            match parse_hex(&arg) {
+            match parse_hex(arg) {
                 Some(hex_bytes) => Some(JoinMode::String(hex_bytes)),
+                None if escape => Some(JoinMode::String(parse_escapes(arg))),
                 None => Some(JoinMode::String(arg.to_vec())),
             }
         }
     }
 }
 
-pub fn parse_placeholder(arg: &[u8]) -> Option<Vec<u8>> {
-    match parse_hex(&arg) {
+/// Parses a --replace pair, FROM=TO. Each side is a string or hex like a placeholder, so an
+/// `=` delimiter is written `0x3d`.
+pub fn parse_replacement(arg: &[u8], escape: bool) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let invalid = || {
+        format!(
+            "invalid replace: '{}' must be FROM=TO",
+            String::from_utf8_lossy(arg)
+        )
+    };
+    let split = arg
+        .iter()
+        .position(|byte| *byte == b'=')
+        .ok_or_else(invalid)?;
+    let from = parse_placeholder(&arg[..split], escape).unwrap_or_default();
+    let to = parse_placeholder(&arg[split + 1..], escape).unwrap_or_default();
+    if from.is_empty() {
+        return Err(invalid());
+    }
+    Ok((from, to))
+}
+
+pub fn parse_placeholder(arg: &[u8], escape: bool) -> Option<Vec<u8>> {
+    match parse_hex(arg) {
         Some(hex_bytes) => Some(hex_bytes),
+        None if escape => Some(parse_escapes(arg)),
         None => Some(arg.to_vec()),
     }
 }
 
+/// Decode C-style escapes: \t \n \r \0 \\ and \xNN.
+/// Unknown or malformed escapes are kept as written, so `\q` stays `\q`.
+pub fn parse_escapes(value: &[u8]) -> Vec<u8> {
+    let mut output: Vec<u8> = Vec::with_capacity(value.len());
+    let mut index = 0;
+    while index < value.len() {
+        if value[index] != b'\\' || index + 1 >= value.len() {
+            output.push(value[index]);
+            index += 1;
+            continue;
+        }
+        let decoded = match value[index + 1] {
+            b't' => Some(b'\t'),
+            b'n' => Some(b'\n'),
+            b'r' => Some(b'\r'),
+            b'0' => Some(b'\0'),
+            b'\\' => Some(b'\\'),
+            _ => None,
+        };
+        if let Some(byte) = decoded {
+            output.push(byte);
+            index += 2;
+            continue;
+        }
+        // from_str_radix would also take a sign, so both digits are checked, or \x+f decodes
+        if value[index + 1] == b'x'
+            && let Some(hex_pair) = value.get(index + 2..index + 4)
+            && hex_pair.iter().all(u8::is_ascii_hexdigit)
+            && let Some(byte) = std::str::from_utf8(hex_pair)
+                .ok()
+                .and_then(|hex_pair| u8::from_str_radix(hex_pair, 16).ok())
+        {
+            output.push(byte);
+            index += 4;
+            continue;
+        }
+        output.push(value[index]);
+        index += 1;
+    }
+    output
+}
+
 pub fn parse_hex(hex_str: &[u8]) -> Option<Vec<u8>> {
     if !hex_str.starts_with(b"0x") && !hex_str.starts_with(b"0X") {
         return None;
@@ -327,7 +1520,7 @@ pub fn parse_hex(hex_str: &[u8]) -> Option<Vec<u8>> {
         return None;
     }
 
-    if hex_digits.len() % 2 != 0 {
+    if !hex_digits.len().is_multiple_of(2) {
         return None; // Odd number of hex digits
     }
 
@@ -343,14 +1536,246 @@ pub fn parse_hex(hex_str: &[u8]) -> Option<Vec<u8>> {
     Some(bytes)
 }
 
+/// Parses the comma-separated --header list. Anything that reads as a selection stays one,
+/// the rest are column names for the reader to find in the header record.
+pub fn parse_header_selections(
+    header: &str,
+    selection_regex: &SimpleRegex,
+) -> Result<Vec<HeaderSelection>, String> {
+    header
+        .split(',')
+        .map(|token| match token {
+            "" => Err(format!(
+                "invalid header: '{header}' has an empty column name"
+            )),
+            token if selection_regex.is_match(token) => {
+                parse_selection_token(token, selection_regex).map(HeaderSelection::Index)
+            }
+            token => Ok(HeaderSelection::Name(token.to_string())),
+        })
+        .collect()
+}
+
+/// Parses the comma-separated --json-input list into dotted paths, so `user.name,items.0`
+/// is two paths of two segments each.
+pub fn parse_json_paths(paths: &str) -> Result<Vec<Vec<String>>, String> {
+    paths
+        .split(',')
+        .map(|path| {
+            let segments: Vec<String> = path.split('.').map(str::to_string).collect();
+            match segments.iter().any(String::is_empty) {
+                true => Err(format!(
+                    "invalid json-input: '{paths}' has an empty path segment"
+                )),
+                false => Ok(segments),
+            }
+        })
+        .collect()
+}
+
+/// Reads a --selections-file. Selections are separated by whitespace, newlines or commas,
+/// and `#` comments out the rest of a line. Errors give the file and line of a bad token.
+pub fn parse_selections_file(
+    path: &Path,
+    selection_regex: &SimpleRegex,
+) -> Result<Vec<(i32, i32, i32)>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+    let mut selections: Vec<(i32, i32, i32)> = Vec::new();
+
+    for (line_index, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let tokens = line
+            .split(|character: char| character == ',' || character.is_whitespace())
+            .filter(|token| !token.is_empty());
+        for token in tokens {
+            let selection = match selection_regex.is_match(token) {
+                true => parse_selection_token(token, selection_regex),
+                false => Err(format!("invalid selection: '{token}'")),
+            };
+            let selection = selection
+                .map_err(|error| format!("{}:{}: {error}", path.display(), line_index + 1))?;
+            selections.push(selection);
+        }
+    }
+
+    Ok(selections)
+}
+
+/// Reads a --delimiter-file. The whole file is the delimiter, less one trailing newline,
+/// so it can hold characters that are awkward to quote, `/regex/` syntax included.
+pub fn read_delimiter_file(path: &Path) -> Result<String, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+    let delimiter = match contents.strip_suffix('\n') {
+        Some(line) => line.strip_suffix('\r').unwrap_or(line),
+        None => &contents,
+    };
+    if delimiter.is_empty() {
+        return Err(format!("{}: delimiter file is empty", path.display()));
+    }
+    Ok(delimiter.to_string())
+}
+
+/// Splits a --spec string into its whitespace-separated words. Single quotes keep everything
+/// up to the closing quote as written, and double quotes do too except for `\"` and `\\`.
+/// Backslashes outside quotes are kept, so a `/regex/` needs no extra escaping.
+fn split_spec_words(spec: &str) -> Result<Vec<String>, String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut word: Option<String> = None;
+    let mut characters = spec.chars();
+
+    while let Some(character) = characters.next() {
+        match character {
+            character if character.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match characters.next() {
+                        Some('\'') => break,
+                        Some(character) => word.push(character),
+                        None => return Err("invalid spec: unterminated ' quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match characters.next() {
+                        Some('"') => break,
+                        Some('\\') => match characters.next() {
+                            Some(escaped @ ('"' | '\\')) => word.push(escaped),
+                            Some(other) => {
+                                word.push('\\');
+                                word.push(other);
+                            }
+                            None => return Err("invalid spec: unterminated \" quote".to_string()),
+                        },
+                        Some(character) => word.push(character),
+                        None => return Err("invalid spec: unterminated \" quote".to_string()),
+                    }
+                }
+            }
+            character => word.get_or_insert_with(String::new).push(character),
+        }
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+/// Parses a --spec string such as `d=/,/ sel=1,3-4 join=|` into the values it gives. Each
+/// word is KEY=VALUE, and a key can only be given once.
+pub fn parse_spec(spec: &str) -> Result<Spec, String> {
+    let mut parsed = Spec::default();
+
+    for word in split_spec_words(spec)? {
+        let Some((key, value)) = word.split_once('=') else {
+            return Err(format!("invalid spec: '{word}', must be KEY=VALUE"));
+        };
+        let slot = match key {
+            "d" => &mut parsed.delimiter,
+            "sel" => &mut parsed.selections,
+            "join" => &mut parsed.join,
+            "placeholder" => &mut parsed.placeholder,
+            _ => {
+                return Err(format!(
+                    "invalid spec: unknown key '{key}', must be d, sel, join or placeholder"
+                ));
+            }
+        };
+        if slot.is_some() {
+            return Err(format!("invalid spec: '{key}' is given more than once"));
+        }
+        *slot = Some(value.to_string());
+    }
+
+    Ok(parsed)
+}
+
+/// A `pN` percent, or None if the text isn't one.
+fn percent_value(text: &str) -> Option<&str> {
+    let digits = text.strip_prefix(['p', 'P'])?;
+    match !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        true => Some(digits),
+        false => None,
+    }
+}
+
+/// A `p50` or `p25-p75` token as its start and end percents, or None if it isn't one.
+pub fn parse_percent_token(token: &str) -> Option<Result<(u32, u32), String>> {
+    let trimmed = token.trim();
+    let (start, end) = match trimmed.split_once('-') {
+        Some((start, end)) => (percent_value(start)?, percent_value(end)?),
+        None => (percent_value(trimmed)?, percent_value(trimmed)?),
+    };
+    let parse = |digits: &str| match digits.parse::<u32>() {
+        Ok(percent) if percent <= 100 => Ok(percent),
+        _ => Err(format!(
+            "invalid selection: '{token}', percents must be between 0 and 100"
+        )),
+    };
+    let range = parse(start).and_then(|start| Ok((start, parse(end)?)));
+    Some(range.and_then(|(start, end)| match start <= end {
+        true => Ok((start, end)),
+        false => Err(format!(
+            "invalid selection: '{token}', the start percent is greater than the end"
+        )),
+    }))
+}
+
+/// A --slice-syntax slice, whose end is exclusive, as the inclusive range used everywhere else.
+/// A missing start is the first field and a missing end runs to the last. An end of -1 leaves
+/// out the last field, as in Python. Ends on opposite sides are resolved per record, where a
+/// slice that comes out backwards selects nothing.
+fn parse_slice(token: &str, captures: &regex::Captures) -> Result<(i32, i32, i32), String> {
+    let number = |name: &str| {
+        captures
+            .name(name)
+            .map(|value| value.as_str().parse::<i32>())
+            .transpose()
+            .map_err(|_| format!("invalid selection: '{token}'"))
+    };
+    let start = number("slice_start")?.unwrap_or(1);
+    let step = number("step")?.unwrap_or(1);
+    if start == 0 {
+        return Err(format!(
+            "invalid selection: '{token}', fields are numbered from 1"
+        ));
+    }
+    if step <= 0 {
+        return Err(format!(
+            "invalid selection: '{token}', slice steps must be positive"
+        ));
+    }
+    let end = match number("slice_end")? {
+        None => -1,
+        Some(0) => {
+            return Err(format!(
+                "invalid selection: '{token}', fields are numbered from 1"
+            ));
+        }
+        Some(end) => end - 1,
+    };
+    // Only a slice with both ends on the same side can be known to be empty before reading
+    if end == 0 || (start.signum() == end.signum() && end < start) {
+        return Err(format!("invalid selection: '{token}' selects no fields"));
+    }
+
+    Ok((start, end, step))
+}
+
 pub fn parse_selection_token(
     token: &str,
     selection_regex: &SimpleRegex,
-) -> Result<(i32, i32), String> {
+) -> Result<(i32, i32, i32), String> {
     let trimmed = token.trim();
     let captures = selection_regex
         .captures(trimmed)
         .ok_or_else(|| format!("invalid selection: '{token}'"))?;
+    if captures.name("slice").is_some() {
+        return parse_slice(token, &captures);
+    }
     let start_match = captures
         .name("start")
         .ok_or_else(|| format!("invalid selection: '{token}'"))?;
@@ -377,5 +1802,16 @@ pub fn parse_selection_token(
             .map_err(|_| format!("invalid selection: '{token}'")),
     }?;
 
-    Ok((start, end))
+    let step = match captures.name("step") {
+        Some(step_match) => step_match
+            .as_str()
+            .parse::<i32>()
+            .map_err(|_| format!("invalid selection: '{token}'"))?,
+        None => 1,
+    };
+    if step == 0 {
+        return Err(format!("invalid selection: '{token}', step cannot be 0"));
+    }
+
+    Ok((start, end, step))
 }
@@ -9,23 +9,148 @@ pub struct Consuming {
     pub join: bool,
     pub placeholder: bool,
     pub align: bool,
+    pub capture: bool,
+    pub json_input: bool,
+    pub field_pattern: bool,
+    pub field_length: bool,
+    pub field_length_basis: bool,
+    pub count_unit: bool,
+    pub record_separator: bool,
+    pub require: bool,
+    pub pick: bool,
+    pub from: bool,
+    pub to: bool,
+    pub comment: bool,
+    pub dedup_key: bool,
+    pub assert_sorted: bool,
+    pub jobs: bool,
+    pub reorder_window: bool,
+    pub batch_bytes: bool,
+    pub read_buffer: bool,
+    pub pad_char: bool,
+    pub align_with: bool,
+    pub separators: bool,
+    pub replace: bool,
+    pub max_fields: bool,
+    pub max_records: bool,
+    pub tail: bool,
+    pub output_terminator: bool,
+    pub field_terminator: bool,
+    pub input_encoding: bool,
+    pub max_record_bytes: bool,
+    pub header: bool,
+    pub selections_file: bool,
+    pub delimiter_file: bool,
+    pub spec: bool,
+    pub original_separator: bool,
+}
+
+/// The parts of a --spec string, each as it would be given to its own flag.
+#[derive(Default)]
+pub struct Spec {
+    pub delimiter: Option<String>,
+    pub selections: Option<String>,
+    pub join: Option<String>,
+    pub placeholder: Option<String>,
 }
 
 pub struct CLIArguments {
     pub output: Option<PathBuf>,
-    pub input: Option<PathBuf>,
+    pub output_append: bool,
+    pub trim_newline: bool,
+    pub input: Vec<PathBuf>,
+    pub selections_files: Vec<PathBuf>,
+    pub delimiter_file: Option<PathBuf>,
+    pub spec: Option<String>, // --spec as given, read once the arguments are parsed
     pub join: Option<Vec<u8>>, // This is a string because we want to do validation AFTER parsing
+    pub field_terminator: Option<Vec<u8>>, // Replaces the join between selected fields
     pub align: Align,
-    pub delimiter: Option<Delimiter>,
-    pub placeholder: Option<Vec<u8>>,
+    pub delimiters: Vec<Delimiter>, // Several are matched as an alternation
+    pub fixed: bool,
+    pub ignore_case: bool,
+    pub csv: bool,
+    pub capture: Option<String>,
+    pub json_input: Option<String>,
+    pub field_pattern: Option<String>,
+    pub field_length: Option<(usize, usize)>,
+    pub field_length_basis: Option<LengthBasis>,
+    pub record_separator: Option<String>,
+    pub require: Option<String>,
+    pub pick: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub exclude_anchors: bool,
+    pub comment_prefixes: Vec<Vec<u8>>,
+    pub placeholders: Vec<Vec<u8>>, // every --placeholder, in the order given
+    pub pad_char: Option<Vec<u8>>,
+    pub align_tabs: bool, // --align-with=tabs
+    pub separators: Option<Vec<u8>>,
+    pub replacements: Vec<Vec<u8>>, // Each --replace as given, FROM=TO
+    pub max_fields: Option<usize>,
+    pub max_records: Option<usize>,
+    pub tail: Option<usize>,
+    pub input_encoding: Option<InputEncoding>,
+    pub max_record_bytes: Option<usize>,
+    pub header: Option<String>,
+    pub escape: bool,
     pub input_mode: InputMode,
     pub selection_mode: SelectionMode,
+    pub codepoints: bool,
     pub count: bool,
+    pub count_nonempty: bool,
+    pub count_unit: Option<CountUnit>,
+    pub count_total: bool,
+    pub histogram: bool,
+    pub escape_output: bool,
+    pub stats: bool,
+    pub complement_count: bool,
+    pub index_pair_separator: Option<Vec<u8>>,
+    pub json: bool,
+    pub transpose: bool,
+    pub shell_quote: bool,
+    pub with_original: Option<OriginalPlacement>,
+    pub original_separator: Option<Vec<u8>>,
+    pub encoding: Option<Encoding>,
+    pub decoding: Option<Encoding>,
+    pub crlf_output: bool,
+    pub output_terminator: Option<Vec<u8>>,
+    pub fail_on_empty_output: bool,
+    pub number: bool,
+    pub explain: bool,
+    pub unordered: bool,
     pub invert: bool,
+    pub invert_keep_positions: bool, // --redact was given as this alias, so needs no placeholder
+    pub redact: bool,
+    pub null_on_missing: bool,
+    pub offsets: bool,
     pub skip_empty: bool,
+    pub squash_delimiters: bool,
+    pub keep_trailing_empty: bool,
+    pub trim: Trim,
+    pub only_delimited: bool,
+    pub unique: bool,
+    pub sort: bool,
+    pub sort_numeric: bool,
+    pub sort_reverse: bool,
+    pub reverse: bool,
+    pub dedup_key: Option<i32>,
+    pub jobs: Option<usize>,
+    pub reorder_window: Option<usize>,
+    pub batch_bytes: Option<usize>,
+    pub read_buffer: Option<usize>,
+    pub line_buffered: bool,
+    pub progress: bool,
+    pub assert_sorted: Option<i32>,
+    pub numeric: bool,
+    pub assert_uniform: bool,
     pub strict_utf8: bool,
+    pub keep_bom: bool,
+    pub strip_cr: bool,
     pub strict_return: bool,
+    pub strict_empty: bool,
     pub strict_bounds: bool,
     pub strict_range_order: bool,
-    pub selections: Vec<(i32, i32)>,
+    pub strict_require: bool,
+    pub selections: Vec<(i32, i32, i32)>, // (start, end, step)
+    pub percent_selections: Vec<PercentSelection>,
 }
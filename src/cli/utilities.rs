@@ -1,12 +1,12 @@
 use crate::types::Delimiter;
 
 pub fn trim_quotes(value: &str) -> String {
-    if value.starts_with("\"") && value.ends_with("\"") {
-        return value[1..value.len() - 1].to_string();
-    } else if value.starts_with("\'") && value.ends_with("\'") {
+    if (value.starts_with("\"") && value.ends_with("\""))
+        || (value.starts_with("\'") && value.ends_with("\'"))
+    {
         return value[1..value.len() - 1].to_string();
     }
-    return value.to_string();
+    value.to_string()
 }
 
 pub fn parse_delimiter_token(value: &str) -> Delimiter {
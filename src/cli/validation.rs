@@ -1,6 +1,6 @@
 use crate::{
     cli::types::Consuming,
-    types::{Align, InputMode, SelectionMode},
+    types::{Align, CountUnit, InputMode, SelectionMode, Trim},
 };
 
 pub fn validate_align(
@@ -12,7 +12,7 @@ pub fn validate_align(
         return Ok(());
     }
 
-    if input_mode != InputMode::PerLine {
+    if input_mode != InputMode::PerLine && align != Align::Squash {
         return Err("--align is only supported in per-line mode".to_string());
     }
 
@@ -23,14 +23,55 @@ pub fn validate_align(
     Ok(())
 }
 
+/// Tabs take the place of both the padding and the join, so only plain left alignment fits.
+pub fn validate_align_with(
+    align_tabs: bool,
+    align: Align,
+    join: bool,
+    pad_char: bool,
+) -> Result<(), String> {
+    if !align_tabs {
+        return Ok(());
+    }
+
+    if align != Align::Left {
+        return Err("--align-with=tabs needs --align=left".to_string());
+    }
+    if join {
+        return Err("--align-with=tabs cannot be combined with --join".to_string());
+    }
+    if pad_char {
+        return Err("--align-with=tabs cannot be combined with --pad-char".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_delimiters(count: usize, fixed: bool, csv: bool) -> Result<(), String> {
+    if count < 2 {
+        return Ok(());
+    }
+
+    // Several literals could mean either any of them or all of them in a row
+    if fixed {
+        return Err(
+            "--fixed takes a single delimiter; drop it to split on any of several".to_string(),
+        );
+    }
+
+    if csv {
+        return Err("--csv takes a single delimiter".to_string());
+    }
+
+    Ok(())
+}
+
 pub fn validate_join_mode(join_str: &[u8], selection_mode: SelectionMode) -> Result<(), String> {
-    if join_str.starts_with(b"@") {
-        if selection_mode != SelectionMode::Fields {
-            return Err(
-                "join flags (@auto, @after-previous, etc.) are only supported in fields mode"
-                    .to_string(),
-            );
-        }
+    if join_str.starts_with(b"@") && selection_mode != SelectionMode::Fields {
+        return Err(
+            "join flags (@auto, @after-previous, etc.) are only supported in fields mode"
+                .to_string(),
+        );
     }
 
     if !join_str.starts_with(b"@") && selection_mode == SelectionMode::Bytes {
@@ -40,6 +81,795 @@ pub fn validate_join_mode(join_str: &[u8], selection_mode: SelectionMode) -> Res
     Ok(())
 }
 
+pub fn validate_count_mode(
+    count_nonempty: bool,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if count_nonempty && selection_mode != SelectionMode::Fields {
+        return Err("--count=nonempty is only supported in fields mode".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_count_unit(
+    count_unit: Option<CountUnit>,
+    count_nonempty: bool,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    let Some(count_unit) = count_unit else {
+        return Ok(());
+    };
+
+    if selection_mode == SelectionMode::Capture {
+        return Err("--count-unit is not supported in capture mode".to_string());
+    }
+    // Only fields mode has a delimiter to split the record on
+    if count_unit == CountUnit::Fields && selection_mode != SelectionMode::Fields {
+        return Err("--count-unit=fields is only supported in fields mode".to_string());
+    }
+    if count_unit != CountUnit::Fields && count_nonempty {
+        return Err("--count=nonempty counts fields, so needs --count-unit=fields".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_output_terminator(
+    output_terminator: bool,
+    crlf_output: bool,
+) -> Result<(), String> {
+    if output_terminator && crlf_output {
+        return Err(
+            "--output-terminator cannot be combined with --crlf-output, use --output-terminator=0x0d0a"
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+pub fn validate_output_append(output_append: bool, output: bool) -> Result<(), String> {
+    if output_append && !output {
+        return Err("--output-append needs --output to append to".to_string());
+    }
+
+    Ok(())
+}
+
+/// The join and --separators are written between fields by splitby rather than taken from the
+/// record, so --escape-output would turn a tab or NUL meant to split the output into `\t` or `\0`.
+pub fn validate_escape_output(escape_output: bool, separators: &[&[u8]]) -> Result<(), String> {
+    let changed_by_escaping = |separator: &[u8]| match std::str::from_utf8(separator) {
+        Ok(text) => text
+            .chars()
+            .any(|character| character.is_control() || character == '\\'),
+        Err(_) => true,
+    };
+    if escape_output
+        && separators
+            .iter()
+            .any(|separator| changed_by_escaping(separator))
+    {
+        return Err(
+            "--escape-output would escape the --join, --field-terminator or --separators it inserts, so they can't hold control characters or backslashes"
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+pub fn validate_field_terminator(field_terminator: bool, join: bool) -> Result<(), String> {
+    if field_terminator && join {
+        return Err(
+            "--field-terminator and --print0-fields replace the join, so cannot be combined with --join"
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+pub fn validate_count_total(
+    count_total: bool,
+    with_original: bool,
+    number: bool,
+) -> Result<(), String> {
+    if !count_total {
+        return Ok(());
+    }
+
+    if with_original {
+        return Err("--count-total cannot be combined with --with-original".to_string());
+    }
+
+    if number {
+        return Err("--count-total cannot be combined with --number".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_histogram(
+    histogram: bool,
+    count_total: bool,
+    with_original: bool,
+    number: bool,
+) -> Result<(), String> {
+    if !histogram {
+        return Ok(());
+    }
+
+    if count_total {
+        return Err("--histogram cannot be combined with --count-total".to_string());
+    }
+
+    if with_original {
+        return Err("--histogram cannot be combined with --with-original".to_string());
+    }
+
+    if number {
+        return Err("--histogram cannot be combined with --number".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_stats(
+    stats: bool,
+    count: bool,
+    json: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if !stats {
+        return Ok(());
+    }
+
+    if selection_mode != SelectionMode::Fields {
+        return Err("--stats is only supported in fields mode".to_string());
+    }
+    if count {
+        return Err("--stats cannot be combined with --count".to_string());
+    }
+    if json {
+        return Err("--stats cannot be combined with --json".to_string());
+    }
+    if align != Align::None {
+        return Err("--stats cannot be used with --align".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_complement_count(
+    complement_count: bool,
+    selection_mode: SelectionMode,
+    align: Align,
+    replaces_output: bool,
+) -> Result<(), String> {
+    if !complement_count {
+        return Ok(());
+    }
+
+    if selection_mode != SelectionMode::Fields {
+        return Err("--complement-count is only supported in fields mode".to_string());
+    }
+    if align != Align::None {
+        return Err("--complement-count cannot be used with --align".to_string());
+    }
+    if replaces_output {
+        return Err(
+            "--complement-count cannot be combined with --count, --stats, --json, --redact, --invert-keep-positions, --transpose, --shell-quote or --emit-field-index-pairs"
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+pub fn validate_null_on_missing(null_on_missing: bool, placeholder: bool) -> Result<(), String> {
+    if null_on_missing && placeholder {
+        return Err("--null-on-missing cannot be combined with --placeholder".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_redact(
+    redact: bool,
+    placeholder: bool,
+    selection_mode: SelectionMode,
+    align: Align,
+    reorders_fields: bool,
+) -> Result<(), String> {
+    if !redact {
+        return Ok(());
+    }
+
+    if selection_mode != SelectionMode::Fields {
+        return Err("--redact is only supported in fields mode".to_string());
+    }
+    if !placeholder {
+        return Err("--redact needs --placeholder to replace the selected fields with".to_string());
+    }
+    if align != Align::None {
+        return Err("--redact cannot be used with --align".to_string());
+    }
+    if reorders_fields {
+        return Err(
+            "--redact cannot be combined with --count, --json, --emit-field-index-pairs, --sort, --unique or --reverse"
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+pub fn validate_offsets(
+    offsets: bool,
+    selection_mode: SelectionMode,
+    csv: bool,
+    align: Align,
+    reshapes_fields: bool,
+) -> Result<(), String> {
+    if !offsets {
+        return Ok(());
+    }
+
+    if selection_mode != SelectionMode::Fields {
+        return Err("--offsets is only supported in fields mode".to_string());
+    }
+    // Unquoted CSV values are no longer the bytes they were read from
+    if csv {
+        return Err("--offsets cannot be used with --csv".to_string());
+    }
+    if align != Align::None {
+        return Err("--offsets cannot be used with --align".to_string());
+    }
+    if reshapes_fields {
+        return Err(
+            "--offsets cannot be combined with --field-pattern, --json, --stats, --redact, --invert-keep-positions, --transpose, --emit-field-index-pairs, --shell-quote, --encode, --decode or --join"
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+pub fn validate_index_pairs(
+    index_pairs: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if !index_pairs {
+        return Ok(());
+    }
+
+    if selection_mode != SelectionMode::Fields {
+        return Err("--emit-field-index-pairs is only supported in fields mode".to_string());
+    }
+
+    if align != Align::None {
+        return Err("--emit-field-index-pairs cannot be used with --align".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_only_delimited(
+    only_delimited: bool,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if only_delimited && selection_mode != SelectionMode::Fields {
+        return Err("--only-delimited is only supported in fields mode".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_require(
+    require: bool,
+    selection_mode: SelectionMode,
+    transpose: bool,
+) -> Result<(), String> {
+    if require && selection_mode != SelectionMode::Fields {
+        return Err("--require is only supported in fields mode".to_string());
+    }
+    if require && transpose {
+        return Err("--require cannot be combined with --transpose".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_percent_selections(
+    percent_selections: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+    transpose: bool,
+) -> Result<(), String> {
+    if percent_selections && selection_mode != SelectionMode::Fields {
+        return Err("percent selections are only supported in fields mode".to_string());
+    }
+    if percent_selections && align != Align::None {
+        return Err("percent selections cannot be combined with --align".to_string());
+    }
+    if percent_selections && transpose {
+        return Err("percent selections cannot be combined with --transpose".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_pick(
+    pick: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+    transpose: bool,
+) -> Result<(), String> {
+    if pick && selection_mode != SelectionMode::Fields {
+        return Err("--pick is only supported in fields mode".to_string());
+    }
+    if pick && align != Align::None {
+        return Err("--pick cannot be combined with --align".to_string());
+    }
+    if pick && transpose {
+        return Err("--pick cannot be combined with --transpose".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_anchors(
+    anchors: bool,
+    exclude_anchors: bool,
+    has_selections: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+    transpose: bool,
+) -> Result<(), String> {
+    if exclude_anchors && !anchors {
+        return Err("--exclude-anchors requires --from or --to".to_string());
+    }
+    if !anchors {
+        return Ok(());
+    }
+
+    if selection_mode != SelectionMode::Fields {
+        return Err("--from and --to are only supported in fields mode".to_string());
+    }
+    if has_selections {
+        return Err("--from and --to select the fields themselves, so they cannot be combined with selections or --header".to_string());
+    }
+    if align != Align::None {
+        return Err("--from and --to cannot be combined with --align".to_string());
+    }
+    if transpose {
+        return Err("--from and --to cannot be combined with --transpose".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_squash_delimiters(
+    squash_delimiters: bool,
+    selection_mode: SelectionMode,
+    csv: bool,
+) -> Result<(), String> {
+    if squash_delimiters && selection_mode != SelectionMode::Fields {
+        return Err("--squash-delimiters is only supported in fields mode".to_string());
+    }
+    // A quoted "" is a value, not two delimiters in a row
+    if squash_delimiters && csv {
+        return Err("--squash-delimiters cannot be combined with --csv".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_keep_trailing_empty(
+    keep_trailing_empty: bool,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if keep_trailing_empty && selection_mode != SelectionMode::Fields {
+        return Err("--keep-trailing-empty is only supported in fields mode".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_csv(
+    csv: bool,
+    ignore_case: bool,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if csv && selection_mode != SelectionMode::Fields {
+        return Err("--csv is only supported in fields mode".to_string());
+    }
+    if csv && ignore_case {
+        return Err("--csv cannot be combined with --ignore-case".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_sort(
+    sort: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if sort && selection_mode != SelectionMode::Fields {
+        return Err("--sort is only supported in fields mode".to_string());
+    }
+    if sort && !matches!(align, Align::None | Align::Squash) {
+        return Err("--sort can only be combined with --align=squash".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_max_records(max_records: bool, input_mode: InputMode) -> Result<(), String> {
+    if max_records && input_mode == InputMode::WholeString {
+        return Err("--max-records cannot be combined with --whole-string".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_tail(tail: bool, input_mode: InputMode) -> Result<(), String> {
+    if tail && input_mode == InputMode::WholeString {
+        return Err("--tail cannot be combined with --whole-string".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_transpose(
+    transpose: bool,
+    input_mode: InputMode,
+    selection_mode: SelectionMode,
+    align: Align,
+    reshapes_output: bool,
+    rewrites_fields: bool,
+) -> Result<(), String> {
+    if !transpose {
+        return Ok(());
+    }
+
+    if input_mode != InputMode::WholeString || selection_mode != SelectionMode::Fields {
+        return Err("--transpose needs --whole-string and fields mode".to_string());
+    }
+    if align != Align::None {
+        return Err("--transpose cannot be used with --align".to_string());
+    }
+    if reshapes_output {
+        return Err(
+            "--transpose cannot be combined with --count, --json or --emit-field-index-pairs"
+                .to_string(),
+        );
+    }
+    if rewrites_fields {
+        return Err(
+            "--transpose cannot be combined with --shell-quote, --sort, --unique, --reverse, --field-pattern, --encode or --decode"
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+pub fn validate_header(
+    header: bool,
+    selection_mode: SelectionMode,
+    input_mode: InputMode,
+) -> Result<(), String> {
+    if header && selection_mode != SelectionMode::Fields {
+        return Err("--header can only be used in fields mode".to_string());
+    }
+    if header && input_mode == InputMode::WholeString {
+        return Err("--header cannot be combined with --whole-string".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_max_fields(
+    max_fields: bool,
+    csv: bool,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if max_fields && selection_mode != SelectionMode::Fields {
+        return Err("--max-fields is only supported in fields mode".to_string());
+    }
+    if max_fields && csv {
+        return Err("--max-fields cannot be combined with --csv".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_separators(
+    separators: bool,
+    json: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if separators && selection_mode != SelectionMode::Fields {
+        return Err("--separators is only supported in fields mode".to_string());
+    }
+    if separators && json {
+        return Err("--separators cannot be combined with --json".to_string());
+    }
+    if separators && !matches!(align, Align::None | Align::Squash) {
+        return Err("--separators can only be combined with --align=squash".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_replace(
+    replace: bool,
+    json: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if replace && selection_mode != SelectionMode::Fields {
+        return Err("--replace is only supported in fields mode".to_string());
+    }
+    if replace && json {
+        return Err("--replace cannot be combined with --json".to_string());
+    }
+    // Aligned columns are padded to the widths of the delimiters as they were
+    if replace && !matches!(align, Align::None | Align::Squash) {
+        return Err("--replace can only be combined with --align=squash".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_reverse(
+    reverse: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if reverse && selection_mode != SelectionMode::Fields {
+        return Err("--reverse is only supported in fields mode".to_string());
+    }
+    if reverse && !matches!(align, Align::None | Align::Squash) {
+        return Err("--reverse can only be combined with --align=squash".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_unique(
+    unique: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if unique && selection_mode != SelectionMode::Fields {
+        return Err("--unique is only supported in fields mode".to_string());
+    }
+    if unique && !matches!(align, Align::None | Align::Squash) {
+        return Err("--unique can only be combined with --align=squash".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_trim(trim: Trim, selection_mode: SelectionMode) -> Result<(), String> {
+    if trim != Trim::None && !matches!(selection_mode, SelectionMode::Fields | SelectionMode::Chars)
+    {
+        return Err("--trim is only supported in fields and chars modes".to_string());
+    }
+
+    Ok(())
+}
+
+/// JSON mode selects by path, so index selections and the flags that work on them don't apply.
+pub fn validate_json_input(
+    selections: bool,
+    invert: bool,
+    count: bool,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if selection_mode != SelectionMode::Json {
+        return Ok(());
+    }
+
+    if selections {
+        return Err(
+            "--json-input selects by path, so index selections are not allowed".to_string(),
+        );
+    }
+    if invert {
+        return Err("--invert is not supported with --json-input".to_string());
+    }
+    if count {
+        return Err("--count is not supported with --json-input".to_string());
+    }
+
+    Ok(())
+}
+
+/// Selections are 1-based, so 0 can never select anything. Capture mode is the exception,
+/// where group 0 is the whole match.
+pub fn validate_selections(
+    selections: &[(i32, i32, i32)],
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if selection_mode != SelectionMode::Capture
+        && selections
+            .iter()
+            .any(|(start, end, _)| *start == 0 || *end == 0)
+    {
+        return Err("selections are 1-based, 0 is an invalid index".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_codepoints(codepoints: bool, selection_mode: SelectionMode) -> Result<(), String> {
+    if codepoints && selection_mode != SelectionMode::Chars {
+        return Err("--codepoints is only supported in chars mode".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_field_pattern(
+    field_pattern: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if field_pattern && selection_mode != SelectionMode::Fields {
+        return Err("--field-pattern is only supported in fields mode".to_string());
+    }
+    if field_pattern && align != Align::None {
+        return Err("--field-pattern cannot be combined with --align".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_encoding(encoding: bool, align: Align) -> Result<(), String> {
+    if encoding && align != Align::None {
+        return Err("--encode cannot be combined with --align".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_field_length(
+    field_length: bool,
+    field_length_basis: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if field_length_basis && !field_length {
+        return Err("--field-length-basis requires --field-length".to_string());
+    }
+    if field_length && selection_mode != SelectionMode::Fields {
+        return Err("--field-length is only supported in fields mode".to_string());
+    }
+    if field_length && align != Align::None {
+        return Err("--field-length cannot be combined with --align".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_decoding(
+    decoding: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if decoding && selection_mode != SelectionMode::Fields {
+        return Err("--decode is only supported in fields mode".to_string());
+    }
+    if decoding && align != Align::None {
+        return Err("--decode cannot be combined with --align".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_json(json: bool, join: bool, align: Align) -> Result<(), String> {
+    if json && join {
+        return Err("--json cannot be combined with --join".to_string());
+    }
+    if json && align != Align::None {
+        return Err("--json cannot be combined with --align".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_with_original(
+    with_original: bool,
+    original_separator: bool,
+    json: bool,
+    align: Align,
+) -> Result<(), String> {
+    if original_separator && !with_original {
+        return Err("--original-separator requires --with-original".to_string());
+    }
+    if with_original && json {
+        return Err("--with-original cannot be combined with --json".to_string());
+    }
+    if with_original && !matches!(align, Align::None | Align::Squash) {
+        return Err("--with-original can only be combined with --align=squash".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_shell_quote(
+    shell_quote: bool,
+    json: bool,
+    align: Align,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if shell_quote && selection_mode != SelectionMode::Fields {
+        return Err("--shell-quote is only supported in fields mode".to_string());
+    }
+    if shell_quote && json {
+        return Err("--shell-quote cannot be combined with --json".to_string());
+    }
+    if shell_quote && !matches!(align, Align::None | Align::Squash) {
+        return Err("--shell-quote can only be combined with --align=squash".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_dedup_key(
+    dedup_key: Option<i32>,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if dedup_key.is_some() && selection_mode != SelectionMode::Fields {
+        return Err("--field-dedup-key is only supported in fields mode".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_assert_sorted(
+    assert_sorted: Option<i32>,
+    numeric: bool,
+    unordered: bool,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if numeric && assert_sorted.is_none() {
+        return Err("--numeric requires --assert-sorted".to_string());
+    }
+    if assert_sorted.is_some() && selection_mode != SelectionMode::Fields {
+        return Err("--assert-sorted is only supported in fields mode".to_string());
+    }
+    if assert_sorted.is_some() && unordered {
+        return Err("--assert-sorted cannot be combined with --unordered".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_assert_uniform(
+    assert_uniform: bool,
+    unordered: bool,
+    selection_mode: SelectionMode,
+) -> Result<(), String> {
+    if assert_uniform && selection_mode != SelectionMode::Fields {
+        return Err("--assert-uniform is only supported in fields mode".to_string());
+    }
+    if assert_uniform && unordered {
+        return Err("--assert-uniform cannot be combined with --unordered".to_string());
+    }
+
+    Ok(())
+}
+
 pub fn validate_no_consuming(consuming: Consuming) -> Result<(), String> {
     if consuming.input {
         return Err("input set but no input file given".to_string());
@@ -53,6 +883,108 @@ pub fn validate_no_consuming(consuming: Consuming) -> Result<(), String> {
     if consuming.join {
         return Err("join set but no join string given".to_string());
     }
+    if consuming.capture {
+        return Err("capture set but no capture pattern given".to_string());
+    }
+    if consuming.json_input {
+        return Err("json-input set but no paths given".to_string());
+    }
+    if consuming.record_separator {
+        return Err("input-record-separator set but no separator given".to_string());
+    }
+    if consuming.comment {
+        return Err("comment set but no comment prefix given".to_string());
+    }
+    if consuming.field_pattern {
+        return Err("field-pattern set but no pattern given".to_string());
+    }
+    if consuming.original_separator {
+        return Err("original-separator set but no separator given".to_string());
+    }
+    if consuming.field_length {
+        return Err("field-length set but no range given".to_string());
+    }
+    if consuming.field_length_basis {
+        return Err("field-length-basis set but no basis given".to_string());
+    }
+    if consuming.count_unit {
+        return Err("count-unit set but no unit given".to_string());
+    }
+    if consuming.assert_sorted {
+        return Err("assert-sorted set but no key field given".to_string());
+    }
+    if consuming.pad_char {
+        return Err("pad-char set but no pad character given".to_string());
+    }
+    if consuming.align_with {
+        return Err("align-with set but no spaces or tabs given".to_string());
+    }
+    if consuming.selections_file {
+        return Err("selections-file set but no file given".to_string());
+    }
+    if consuming.delimiter_file {
+        return Err("delimiter-file set but no file given".to_string());
+    }
+    if consuming.spec {
+        return Err("spec set but no spec given".to_string());
+    }
+    if consuming.header {
+        return Err("header set but no columns given".to_string());
+    }
+    if consuming.input_encoding {
+        return Err("encoding set but no encoding given".to_string());
+    }
+    if consuming.output_terminator {
+        return Err("output-terminator set but no terminator given".to_string());
+    }
+    if consuming.field_terminator {
+        return Err("field-terminator set but no terminator given".to_string());
+    }
+    if consuming.max_record_bytes {
+        return Err("max-record-bytes set but no byte count given".to_string());
+    }
+    if consuming.max_records {
+        return Err("max-records set but no record count given".to_string());
+    }
+    if consuming.require {
+        return Err("require set but no regex given".to_string());
+    }
+    if consuming.pick {
+        return Err("pick set but no regex given".to_string());
+    }
+    if consuming.from {
+        return Err("from set but no regex given".to_string());
+    }
+    if consuming.to {
+        return Err("to set but no regex given".to_string());
+    }
+    if consuming.tail {
+        return Err("tail set but no record count given".to_string());
+    }
+    if consuming.max_fields {
+        return Err("max-fields set but no field count given".to_string());
+    }
+    if consuming.separators {
+        return Err("separators set but no separator list given".to_string());
+    }
+    if consuming.replace {
+        return Err("replace set but no FROM=TO given".to_string());
+    }
+    if consuming.batch_bytes {
+        return Err("batch-bytes set but no byte count given".to_string());
+    }
+    if consuming.jobs {
+        return Err("jobs set but no job count given".to_string());
+    }
+    if consuming.read_buffer {
+        return Err("read-buffer set but no buffer size given".to_string());
+    }
+    if consuming.reorder_window {
+        return Err("reorder-window set but no batch count given".to_string());
+    }
+    if consuming.dedup_key {
+        return Err("field-dedup-key set but no key field given".to_string());
+    }
     if consuming.placeholder {
         return Err("placeholder set but no placeholder string given".to_string());
     }
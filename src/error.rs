@@ -0,0 +1,125 @@
+use std::fmt;
+
+/// Where the record an error came from sits in the input, counting from 1. Newline-terminated
+/// input counts lines, and NUL-terminated input counts records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Line(usize),
+    Record(usize),
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Position::Line(number) => write!(formatter, "line {number}"),
+            Position::Record(number) => write!(formatter, "record {number}"),
+        }
+    }
+}
+
+/// Why a run stopped. Each kind carries the message shown to the user, and decides the
+/// exit status, so callers never have to match on the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SplitError {
+    /// Invalid flags or config, found before any input is read.
+    Usage(String),
+    /// A file couldn't be opened or created, or reading or writing failed.
+    Io(String),
+    /// A selection that can't be applied, like index 0 or a range out of order.
+    BadSelection(String),
+    /// A selection fell outside the record under --strict-bounds.
+    StrictBounds(String),
+    /// A record produced no output under --strict-return.
+    StrictReturn(String),
+    /// Input wasn't valid UTF-8 (or UTF-16) under --strict-utf8.
+    InvalidUtf8(String),
+    /// A regex failed while matching.
+    Regex(String),
+    /// The input broke some other rule, such as an --assert-* check or a malformed field.
+    Data(String),
+    /// The input had no records at all under --strict-empty.
+    EmptyInput(String),
+    /// The output was closed before everything was written, as with `splitby ... | head`.
+    /// The binary stops quietly with success, the way other Unix tools do.
+    BrokenPipe(String),
+    /// One of the others, raised by the record at this position. The position is only
+    /// added to the message when it's displayed, as in `line 2: strict-bounds error: ...`.
+    InRecord(Position, Box<SplitError>),
+}
+
+impl SplitError {
+    /// The exit status for this error. Success is 0, errors in the input itself are 1,
+    /// usage and I/O errors are 2, and an empty input under --strict-empty is 3, so a script
+    /// can tell "nothing to read" apart from "something was wrong with it". A broken pipe is
+    /// an I/O error here, though the binary exits with success on it.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SplitError::InRecord(_, error) => error.exit_code(),
+            SplitError::Usage(_) | SplitError::Io(_) | SplitError::BrokenPipe(_) => 2,
+            SplitError::EmptyInput(_) => 3,
+            _ => 1,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            SplitError::Usage(message)
+            | SplitError::Io(message)
+            | SplitError::BadSelection(message)
+            | SplitError::StrictBounds(message)
+            | SplitError::StrictReturn(message)
+            | SplitError::InvalidUtf8(message)
+            | SplitError::Regex(message)
+            | SplitError::Data(message)
+            | SplitError::EmptyInput(message)
+            | SplitError::BrokenPipe(message) => message,
+            SplitError::InRecord(_, error) => error.message(),
+        }
+    }
+
+    /// The record the error came from, if it came from one.
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            SplitError::InRecord(position, _) => Some(*position),
+            _ => None,
+        }
+    }
+
+    /// The error without its position, to match on what went wrong.
+    pub fn kind(&self) -> &SplitError {
+        match self {
+            SplitError::InRecord(_, error) => error.kind(),
+            _ => self,
+        }
+    }
+
+    /// Rewrites the message, keeping the kind and any position.
+    pub fn map_message(self, map: impl FnOnce(String) -> String) -> Self {
+        match self {
+            SplitError::Usage(message) => SplitError::Usage(map(message)),
+            SplitError::Io(message) => SplitError::Io(map(message)),
+            SplitError::BadSelection(message) => SplitError::BadSelection(map(message)),
+            SplitError::StrictBounds(message) => SplitError::StrictBounds(map(message)),
+            SplitError::StrictReturn(message) => SplitError::StrictReturn(map(message)),
+            SplitError::InvalidUtf8(message) => SplitError::InvalidUtf8(map(message)),
+            SplitError::Regex(message) => SplitError::Regex(map(message)),
+            SplitError::Data(message) => SplitError::Data(map(message)),
+            SplitError::EmptyInput(message) => SplitError::EmptyInput(map(message)),
+            SplitError::BrokenPipe(message) => SplitError::BrokenPipe(map(message)),
+            SplitError::InRecord(position, error) => {
+                SplitError::InRecord(position, Box::new(error.map_message(map)))
+            }
+        }
+    }
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SplitError::InRecord(position, error) => write!(formatter, "{position}: {error}"),
+            _ => write!(formatter, "{}", self.message()),
+        }
+    }
+}
+
+impl std::error::Error for SplitError {}
@@ -1,24 +1,116 @@
 mod get_largest_field_widths;
+mod progress;
+mod utf16_reader;
 
+use crate::error::SplitError;
+use crate::input::get_largest_field_widths::{add_field_widths, get_largest_field_widths};
+use crate::input::progress::{Progress, ProgressReader};
+use crate::input::utf16_reader::Utf16Reader;
+use crate::transform::transform_utilities::{
+    Field, field_text, is_comment, split_record_fields, squash_delimiter_runs, trim_field,
+};
 use crate::types::*;
 
 use crossbeam::channel;
+use regex::bytes::Regex as BytesRegex;
 use std::{
+    collections::VecDeque,
     fs::File,
-    io::{self, BufRead, BufReader},
+    io::{self, BufRead, BufReader, Read},
+    path::PathBuf,
+    rc::Rc,
 };
 
+/// How far an --input-record-separator buffer may run past --max-record-bytes before giving up,
+/// leaving room for a separator that has only been partly read.
+const SEPARATOR_SLACK: usize = 64 * 1024;
+
+fn record_too_long(index: usize, max_record_bytes: usize) -> SplitError {
+    SplitError::Data(format!(
+        "max-record-bytes error: record {} is longer than {max_record_bytes} bytes",
+        index + 1
+    ))
+}
+
+/// The UTF-16 reader reports strict decoding failures as invalid data; anything else is
+/// the read itself failing.
+fn read_error(error: io::Error) -> SplitError {
+    match error.kind() {
+        io::ErrorKind::InvalidData => SplitError::InvalidUtf8(format!("{error}")),
+        _ => SplitError::Io(format!("{error}")),
+    }
+}
+
+/// Like `read_until`, but stops once `buffer` holds more than `limit` bytes so an
+/// unterminated record can't grow without bound.
+fn read_until_limited(
+    reader: &mut Box<dyn BufRead + '_>,
+    terminator: u8,
+    buffer: &mut Vec<u8>,
+    limit: usize,
+) -> io::Result<usize> {
+    let mut bytes_read = 0usize;
+    while buffer.len() <= limit {
+        let chunk = match reader.fill_buf() {
+            Ok(chunk) => chunk,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        if chunk.is_empty() {
+            break;
+        }
+        let (used, found) = match memchr::memchr(terminator, chunk) {
+            Some(position) => (position + 1, true),
+            None => (chunk.len(), false),
+        };
+        buffer.extend_from_slice(&chunk[..used]);
+        reader.consume(used);
+        bytes_read += used;
+        if found {
+            break;
+        }
+    }
+    Ok(bytes_read)
+}
+
+/// Record buffers the writer has finished with, handed back a batch at a time so the
+/// channel is touched once per batch rather than once per record.
+struct BufferPool {
+    receiver: channel::Receiver<Vec<Vec<u8>>>,
+    spare: Vec<Vec<u8>>,
+}
+
+impl BufferPool {
+    /// An empty buffer, reused when one is spare and freshly made otherwise.
+    fn take(&mut self) -> Vec<u8> {
+        if self.spare.is_empty()
+            && let Ok(buffers) = self.receiver.try_recv()
+        {
+            self.spare = buffers;
+        }
+        self.spare.pop().unwrap_or_default()
+    }
+}
+
 fn read_record(
-    reader: &mut Box<dyn BufRead>,
+    reader: &mut Box<dyn BufRead + '_>,
     buffer: &mut Vec<u8>,
     index: &mut usize,
     terminator: u8,
-) -> Result<Option<Record>, String> {
-    let bytes_read = reader.read_until(terminator, buffer).map_err(|error| {
+    max_record_bytes: Option<usize>,
+    buffer_pool: &mut BufferPool,
+) -> Result<Option<Record>, SplitError> {
+    // One byte over the limit is enough to know, and leaves room for a \r before the \n
+    let bytes_read = match max_record_bytes {
+        Some(max) => read_until_limited(reader, terminator, buffer, max.saturating_add(1)),
+        None => reader.read_until(terminator, buffer),
+    };
+    let bytes_read = bytes_read.map_err(|error| {
+        let error = read_error(error);
         if terminator == b'\0' {
-            format!("error while reading: {error}")
+            error.map_message(|message| format!("error while reading: {message}"))
         } else {
-            format!("{error}")
+            error
         }
     })?;
     if bytes_read == 0 {
@@ -28,14 +120,21 @@ fn read_record(
     let has_terminator = buffer.last() == Some(&terminator);
     if has_terminator {
         buffer.pop();
-        if &terminator == &b'\n' && buffer.last() == Some(&b'\r') {
+        if terminator == b'\n' && buffer.last() == Some(&b'\r') {
             buffer.pop();
         }
     }
+    if let Some(max) = max_record_bytes
+        && buffer.len() > max
+    {
+        return Err(record_too_long(*index, max));
+    }
 
-    let record_bytes = std::mem::take(buffer);
+    // The next record reads into a buffer the writer has finished with, if there is one
+    let record_bytes = std::mem::replace(buffer, buffer_pool.take());
     let record = Record {
         index: *index,
+        sequence: *index,
         bytes: record_bytes,
         has_terminator,
         field_widths: None,
@@ -45,43 +144,394 @@ fn read_record(
     Ok(Some(record))
 }
 
-pub fn read_input(
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Drops a UTF-8 BOM from the start of a source. Reading stops as soon as the bytes can't
+/// be a BOM, so a short first line from an interactive stdin isn't held up.
+fn skip_utf8_bom<'a>(
+    mut source: Box<dyn BufRead + 'a>,
+) -> Result<Box<dyn BufRead + 'a>, SplitError> {
+    let mut prefix: Vec<u8> = Vec::with_capacity(UTF8_BOM.len());
+    while prefix.len() < UTF8_BOM.len() && UTF8_BOM.starts_with(&prefix) {
+        let available = source.fill_buf().map_err(read_error)?;
+        if available.is_empty() {
+            break;
+        }
+        let length = available.len().min(UTF8_BOM.len() - prefix.len());
+        prefix.extend_from_slice(&available[..length]);
+        source.consume(length);
+    }
+
+    if prefix == UTF8_BOM || prefix.is_empty() {
+        return Ok(source);
+    }
+    // Not a BOM, so put back what was read
+    Ok(Box::new(io::Cursor::new(prefix).chain(source)))
+}
+
+/// Opens one input source, with `None` or `-` meaning stdin.
+/// UTF-16 sources are transcoded here, so everything after reads UTF-8.
+fn open_input(
+    path: Option<&PathBuf>,
     input_instructions: &InputInstructions,
-    record_sender: channel::Sender<Vec<Record>>,
-) -> Result<(), String> {
-    let batch_byte_quota = std::env::var("SPLITBY_BATCH_QUOTA")
-        .ok()
-        .and_then(|value| value.parse::<usize>().ok())
-        .filter(|value| *value > 0)
-        .unwrap_or(128 * 1024);
-
-    let mut reader: Box<dyn BufRead> = match input_instructions.input.as_ref() {
-        Some(path) => {
-            let file = File::open(path)
-                .map_err(|error| format!("failed to open {}: {error}", path.display()))?;
-            Box::new(BufReader::new(file))
-        }
-        None => {
+    progress: Option<&Rc<Progress>>,
+) -> Result<Box<dyn BufRead>, SplitError> {
+    let read_buffer = input_instructions.read_buffer;
+    let source: Box<dyn BufRead> = match path {
+        Some(path) if path.as_os_str() != "-" => {
+            let file = File::open(path).map_err(|error| {
+                SplitError::Io(format!("failed to open {}: {error}", path.display()))
+            })?;
+            let file: Box<dyn Read> = match progress {
+                Some(progress) => Box::new(ProgressReader {
+                    inner: file,
+                    progress: Rc::clone(progress),
+                }),
+                None => Box::new(file),
+            };
+            match read_buffer {
+                Some(capacity) => Box::new(BufReader::with_capacity(capacity, file)),
+                None => Box::new(BufReader::new(file)),
+            }
+        }
+        _ => {
             let stdin = io::stdin();
-            Box::new(stdin.lock())
+            // Reads at least as large as stdin's own buffer go straight past it
+            match read_buffer {
+                Some(capacity) => Box::new(BufReader::with_capacity(capacity, stdin.lock())),
+                None => Box::new(stdin.lock()),
+            }
+        }
+    };
+    let strict = input_instructions.strict_utf8;
+    // The transcoded UTF-8 is buffered again, at the same size as the source
+    let transcoded = |source: Box<dyn BufRead>, big_endian: bool| -> Box<dyn BufRead> {
+        let decoder = Utf16Reader::new(source, big_endian, strict);
+        match read_buffer {
+            Some(capacity) => Box::new(BufReader::with_capacity(capacity, decoder)),
+            None => Box::new(BufReader::new(decoder)),
+        }
+    };
+    match input_instructions.input_encoding {
+        InputEncoding::Utf8 if input_instructions.keep_bom => Ok(source),
+        InputEncoding::Utf8 => skip_utf8_bom(source),
+        InputEncoding::Utf16Le => Ok(transcoded(source, false)),
+        InputEncoding::Utf16Be => Ok(transcoded(source, true)),
+    }
+}
+
+/// Reads every record from a reader the library caller holds, the way the command reads a
+/// single input: a UTF-8 BOM is dropped and a last record without a terminator keeps none.
+pub fn read_reader_records(
+    reader: impl Read,
+    input_mode: InputMode,
+    mut on_record: impl FnMut(Record) -> Result<(), SplitError>,
+) -> Result<(), SplitError> {
+    let mut reader = skip_utf8_bom(Box::new(BufReader::new(reader)))?;
+    let mut index: usize = 0;
+    let terminator = match input_mode {
+        InputMode::PerLine => b'\n',
+        InputMode::ZeroTerminated => b'\0',
+        InputMode::WholeString => {
+            let mut buffer: Vec<u8> = Vec::new();
+            reader.read_to_end(&mut buffer).map_err(read_error)?;
+            if buffer.is_empty() {
+                return Ok(());
+            }
+            return on_record(Record {
+                index,
+                sequence: index,
+                bytes: buffer,
+                has_terminator: false,
+                field_widths: None,
+                join_widths: None,
+            });
+        }
+    };
+    // Nothing hands buffers back here, so every record gets a fresh one
+    let mut buffer_pool = BufferPool {
+        receiver: channel::never(),
+        spare: Vec::new(),
+    };
+    let mut buffer: Vec<u8> = Vec::new();
+    while let Some(record) = read_record(
+        &mut reader,
+        &mut buffer,
+        &mut index,
+        terminator,
+        None,
+        &mut buffer_pool,
+    )? {
+        on_record(record)?;
+    }
+    Ok(())
+}
+
+/// Reads the next record ending at a match of `separator`. `buffer` carries bytes read past
+/// the end of the previous record. The final record at EOF has no terminator.
+/// Each chunk is searched once: a separator not found yet must run into the new bytes, so the
+/// search resumes at most `max_separator_len` back from the end. An unbounded separator has no such limit,
+/// so the whole buffer is searched again.
+fn read_separated_record(
+    reader: &mut Box<dyn BufRead>,
+    buffer: &mut Vec<u8>,
+    index: &mut usize,
+    separator: &BytesRegex,
+    max_separator_len: Option<usize>,
+    max_record_bytes: Option<usize>,
+) -> Result<Option<Record>, SplitError> {
+    let mut reached_eof = false;
+    let mut search_from: usize = 0;
+    loop {
+        // Searched in place rather than on a slice, so anchors and \b still see what's before
+        let found = separator
+            .find_at(buffer, search_from)
+            .map(|found| found.range());
+
+        if let Some(max) = max_record_bytes {
+            let record_length = match &found {
+                Some(found) => found.start,
+                None if reached_eof => buffer.len(),
+                None => buffer.len().saturating_sub(SEPARATOR_SLACK),
+            };
+            if record_length > max {
+                return Err(record_too_long(*index, max));
+            }
+        }
+
+        // A match touching the end of the buffer might still grow, so wait for more input
+        if let Some(found) = &found
+            && (found.end < buffer.len() || reached_eof)
+        {
+            let remainder = buffer.split_off(found.end);
+            buffer.truncate(found.start);
+            let record_bytes = std::mem::replace(buffer, remainder);
+            let record = Record {
+                index: *index,
+                sequence: *index,
+                bytes: record_bytes,
+                has_terminator: true,
+                field_widths: None,
+                join_widths: None,
+            };
+            *index += 1;
+            return Ok(Some(record));
+        }
+
+        if reached_eof {
+            if buffer.is_empty() {
+                return Ok(None);
+            }
+            let record = Record {
+                index: *index,
+                sequence: *index,
+                bytes: std::mem::take(buffer),
+                has_terminator: false,
+                field_widths: None,
+                join_widths: None,
+            };
+            *index += 1;
+            return Ok(Some(record));
+        }
+
+        // A separator still to be completed must end in the bytes about to be read, so it
+        // starts within its longest length of the end. A match touching the end can still lose
+        // to an earlier one that only completes with more input, so it's searched from too
+        let could_still_start = match max_separator_len {
+            Some(max_len) => (buffer.len() + 1).saturating_sub(max_len),
+            None => 0,
+        };
+        search_from = match found {
+            Some(found) => found.start.min(could_still_start),
+            None => could_still_start,
+        };
+        let chunk = reader.fill_buf().map_err(read_error)?;
+        if chunk.is_empty() {
+            reached_eof = true;
+        } else {
+            let chunk_len = chunk.len();
+            buffer.extend_from_slice(chunk);
+            reader.consume(chunk_len);
+        }
+    }
+}
+
+/// Reads every record from one source, or until `max_records` have been read in total.
+/// A partial record at the end of a file is complete at EOF, so records never span files.
+fn read_source_records(
+    reader: &mut Box<dyn BufRead>,
+    terminator: u8,
+    input_instructions: &InputInstructions,
+    is_last_source: bool,
+    index: &mut usize,
+    buffer_pool: &mut BufferPool,
+    mut on_record: impl FnMut(Record) -> Result<(), SplitError>,
+) -> Result<(), SplitError> {
+    let max_record_bytes = input_instructions.max_record_bytes;
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut next_record =
+        |buffer: &mut Vec<u8>, index: &mut usize| match &input_instructions.record_separator {
+            Some(separator) => read_separated_record(
+                reader,
+                buffer,
+                index,
+                separator,
+                input_instructions.record_separator_max_len,
+                max_record_bytes,
+            ),
+            None => read_record(
+                reader,
+                buffer,
+                index,
+                terminator,
+                max_record_bytes,
+                buffer_pool,
+            ),
+        };
+    let max_records = input_instructions.max_records;
+    while max_records.is_none_or(|max_records| *index < max_records)
+        && let Some(mut record) = next_record(&mut buffer, index)?
+    {
+        if input_instructions.strip_cr && record.bytes.last() == Some(&b'\r') {
+            record.bytes.pop();
+        }
+        // Only the very last record of the stream keeps a missing terminator missing
+        if !is_last_source {
+            record.has_terminator = true;
         }
+        on_record(record)?;
+    }
+    Ok(())
+}
+
+/// Resolves the --header names against the header record, the first that isn't a comment.
+/// Names index the fields the way selections do, so after --skip-empty and --trim.
+fn resolve_header(
+    record: &Record,
+    input_instructions: &InputInstructions,
+) -> Result<(), SplitError> {
+    let Some(header) = &input_instructions.header else {
+        return Ok(());
+    };
+    if header.resolved.get().is_some()
+        || is_comment(&record.bytes, &input_instructions.comment_prefixes)
+    {
+        return Ok(());
+    }
+    let engine = input_instructions
+        .regex_engine
+        .as_ref()
+        .ok_or_else(|| SplitError::Data("internal error: missing regex engine".to_string()))?;
+
+    let text = field_text(&record.bytes, engine, input_instructions.strict_utf8)?;
+    let mut csv_values: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut fields: Vec<Field> = split_record_fields(
+        &text,
+        engine,
+        input_instructions.input_mode,
+        input_instructions.csv,
+        input_instructions.max_fields,
+        input_instructions.keep_trailing_empty,
+        &mut csv_values,
+    )?;
+    if input_instructions.squash_delimiters {
+        squash_delimiter_runs(&mut fields);
+    }
+    if input_instructions.skip_empty {
+        fields.retain(|field| !trim_field(field.text, input_instructions.trim).is_empty());
+    }
+    let columns: Vec<&[u8]> = fields
+        .iter()
+        .map(|field| trim_field(field.text, input_instructions.trim))
+        .collect();
+
+    let mut selections = input_instructions.selections.clone();
+    for token in &header.tokens {
+        let selection = match token {
+            HeaderSelection::Index(selection) => *selection,
+            HeaderSelection::Name(name) => {
+                let position = columns
+                    .iter()
+                    .position(|column| *column == name.as_bytes())
+                    .ok_or_else(|| {
+                        let names: Vec<String> = columns
+                            .iter()
+                            .map(|column| String::from_utf8_lossy(column).into_owned())
+                            .collect();
+                        SplitError::Data(format!(
+                            "header error: no column named '{name}', header has: {}",
+                            names.join(", ")
+                        ))
+                    })?;
+                let index = position as i32 + 1;
+                (index, index, 1)
+            }
+        };
+        selections.push(selection);
+    }
+    let _ = header.resolved.set(selections);
+    Ok(())
+}
+
+/// The selections align measures, which --header only knows once the header is read.
+fn align_selections(input_instructions: &InputInstructions) -> &[(i32, i32, i32)] {
+    match &input_instructions.header {
+        Some(header) => header.resolved.get().map_or(&[], |selections| selections),
+        None => &input_instructions.selections,
+    }
+}
+
+pub fn read_input(
+    input_instructions: &InputInstructions,
+    record_sender: channel::Sender<Vec<Record>>,
+    buffer_pool: channel::Receiver<Vec<Vec<u8>>>,
+    reorder_window: channel::Sender<()>,
+) -> Result<(), SplitError> {
+    let mut buffer_pool = BufferPool {
+        receiver: buffer_pool,
+        spare: Vec::new(),
+    };
+    let batch_byte_quota = input_instructions.batch_bytes.unwrap_or_else(|| {
+        std::env::var("SPLITBY_BATCH_QUOTA")
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(128 * 1024)
+    });
+
+    // Files are read one after another, with the index carrying on across them
+    let sources: Vec<Option<&PathBuf>> = if input_instructions.input.is_empty() {
+        vec![None]
+    } else {
+        input_instructions.input.iter().map(Some).collect()
     };
     let mut index: usize = 0;
+    // Once --max-records is reached no more sources are opened, and returning drops the
+    // sender so the workers and output wind down without the rest of the input
+    let reached_max_records = |index: usize| {
+        input_instructions
+            .max_records
+            .is_some_and(|max| index >= max)
+    };
     let mut batch: Vec<Record> = Vec::new();
     let mut batch_bytes: usize = 0;
 
     let flush_batch = |record_sender: &channel::Sender<Vec<Record>>,
                        batch: &mut Vec<Record>,
                        batch_bytes: &mut usize|
-     -> Result<(), String> {
+     -> Result<(), SplitError> {
         if batch.is_empty() {
             return Ok(());
         }
         let pending_batch = std::mem::take(batch);
         *batch_bytes = 0;
+        // Waits while the window is full. It only closes once the writer has stopped
+        if reorder_window.send(()).is_err() {
+            return Ok(());
+        }
         record_sender
             .send(pending_batch)
-            .map_err(|error| format!("{error}"))?;
+            .map_err(|error| SplitError::Data(format!("{error}")))?;
         Ok(())
     };
 
@@ -90,7 +540,7 @@ pub fn read_input(
                                batch_bytes: &mut usize,
                                batch_byte_quota: usize,
                                record_sender: &channel::Sender<Vec<Record>>|
-     -> Result<(), String> {
+     -> Result<(), SplitError> {
         *batch_bytes = batch_bytes.saturating_add(record.bytes.len());
         batch.push(record);
 
@@ -100,25 +550,158 @@ pub fn read_input(
         Ok(())
     };
 
-    // Handle align mode: read all records, scan widths, then stream
-    if !matches!(input_instructions.align, Align::None)
-        && input_instructions.input_mode == InputMode::PerLine
-    {
+    let terminator = match input_instructions.input_mode {
+        InputMode::ZeroTerminated => b'\0',
+        _ => b'\n',
+    };
+    // Align mode needs every record's widths before the first is sent. Squash and tabs need
+    // no widths, so they stream like unaligned output
+    let needs_field_widths = !matches!(
+        input_instructions.align,
+        Align::None | Align::Squash | Align::Tabs
+    ) && input_instructions.input_mode == InputMode::PerLine;
+    // Regular files can be read twice: once to scan the widths, then again to stream
+    let sources_rereadable = !input_instructions.input.is_empty()
+        && input_instructions.input.iter().all(|path| {
+            path.as_os_str() != "-" && std::fs::metadata(path).is_ok_and(|meta| meta.is_file())
+        });
+    let mut field_widths: Option<(Vec<usize>, Vec<usize>)> = None;
+    // Rereadable files are read twice for align, so progress counts both passes. It's
+    // dropped on return, which wipes the line from stderr
+    let progress: Option<Rc<Progress>> = match input_instructions.progress {
+        true => {
+            let passes =
+                match needs_field_widths && sources_rereadable && input_instructions.tail.is_none()
+                {
+                    true => 2,
+                    false => 1,
+                };
+            Progress::for_files(&input_instructions.input, passes)
+        }
+        false => None,
+    };
+
+    // --tail only knows which records are last at the end, so it keeps a ring of them and
+    // sends them renumbered from 0, leaving the output's ordering as it expects
+    if let Some(tail) = input_instructions.tail {
+        let mut tail_records: VecDeque<Record> = VecDeque::new();
+        for (source_index, source) in sources.iter().enumerate() {
+            if reached_max_records(index) {
+                break;
+            }
+            let mut reader = open_input(*source, input_instructions, progress.as_ref())?;
+            let is_last_source = source_index == sources.len() - 1;
+            read_source_records(
+                &mut reader,
+                terminator,
+                input_instructions,
+                is_last_source,
+                &mut index,
+                &mut buffer_pool,
+                |record| {
+                    // The header is the first record, so it's looked up before it's dropped
+                    resolve_header(&record, input_instructions)?;
+                    if tail_records.len() == tail {
+                        tail_records.pop_front();
+                    }
+                    if tail > 0 {
+                        tail_records.push_back(record);
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+
+        let mut tail_records: Vec<Record> = tail_records.into();
+        for (sequence, record) in tail_records.iter_mut().enumerate() {
+            record.sequence = sequence;
+        }
+        if needs_field_widths {
+            let (max_widths, max_join_widths) = get_largest_field_widths(
+                &tail_records,
+                align_selections(input_instructions),
+                input_instructions,
+            )?;
+            for record in &mut tail_records {
+                record.field_widths = Some(max_widths.clone());
+                record.join_widths = Some(max_join_widths.clone());
+            }
+        }
+        for record in tail_records {
+            add_record_to_batch(
+                record,
+                &mut batch,
+                &mut batch_bytes,
+                batch_byte_quota,
+                &record_sender,
+            )?;
+        }
+        flush_batch(&record_sender, &mut batch, &mut batch_bytes)?;
+        return Ok(());
+    }
+
+    if needs_field_widths && sources_rereadable {
+        let mut max_widths: Vec<usize> = Vec::new();
+        let mut max_join_widths: Vec<usize> = Vec::new();
+        for (source_index, source) in sources.iter().enumerate() {
+            if reached_max_records(index) {
+                break;
+            }
+            let mut reader = open_input(*source, input_instructions, progress.as_ref())?;
+            let is_last_source = source_index == sources.len() - 1;
+            read_source_records(
+                &mut reader,
+                b'\n',
+                input_instructions,
+                is_last_source,
+                &mut index,
+                &mut buffer_pool,
+                |record| {
+                    resolve_header(&record, input_instructions)?;
+                    add_field_widths(
+                        &record,
+                        align_selections(input_instructions),
+                        input_instructions,
+                        &mut max_widths,
+                        &mut max_join_widths,
+                    )
+                },
+            )?;
+        }
+        field_widths = Some((max_widths, max_join_widths));
+        index = 0;
+    } else if needs_field_widths {
+        // Stdin and pipes can only be read once: read all records, scan widths, then stream
         let mut all_records: Vec<Record> = Vec::new();
-        let mut buffer: Vec<u8> = Vec::new();
 
         // Read all records into memory
-        loop {
-            match read_record(&mut reader, &mut buffer, &mut index, b'\n')? {
-                Some(record) => all_records.push(record),
-                None => break,
+        for (source_index, source) in sources.iter().enumerate() {
+            if reached_max_records(index) {
+                break;
             }
+            let mut reader = open_input(*source, input_instructions, progress.as_ref())?;
+            let is_last_source = source_index == sources.len() - 1;
+            read_source_records(
+                &mut reader,
+                b'\n',
+                input_instructions,
+                is_last_source,
+                &mut index,
+                &mut buffer_pool,
+                |record| {
+                    resolve_header(&record, input_instructions)?;
+                    all_records.push(record);
+                    Ok(())
+                },
+            )?;
         }
 
         // Scan field widths
-        use crate::input::get_largest_field_widths::get_largest_field_widths;
-        let (max_widths, max_join_widths) =
-            get_largest_field_widths(&all_records, input_instructions)?;
+        let (max_widths, max_join_widths) = get_largest_field_widths(
+            &all_records,
+            align_selections(input_instructions),
+            input_instructions,
+        )?;
 
         // Attach widths to each record
         for record in &mut all_records {
@@ -143,54 +726,71 @@ pub fn read_input(
 
     // Normal streaming behavior
     match input_instructions.input_mode {
-        InputMode::PerLine => {
-            let mut buffer: Vec<u8> = Vec::new();
-            loop {
-                match read_record(&mut reader, &mut buffer, &mut index, b'\n')? {
-                    Some(record) => {
+        InputMode::PerLine | InputMode::ZeroTerminated => {
+            for (source_index, source) in sources.iter().enumerate() {
+                if reached_max_records(index) {
+                    break;
+                }
+                let mut reader = open_input(*source, input_instructions, progress.as_ref())?;
+                let is_last_source = source_index == sources.len() - 1;
+                read_source_records(
+                    &mut reader,
+                    terminator,
+                    input_instructions,
+                    is_last_source,
+                    &mut index,
+                    &mut buffer_pool,
+                    |mut record| {
+                        resolve_header(&record, input_instructions)?;
+                        if let Some((max_widths, max_join_widths)) = &field_widths {
+                            record.field_widths = Some(max_widths.clone());
+                            record.join_widths = Some(max_join_widths.clone());
+                        }
                         add_record_to_batch(
                             record,
                             &mut batch,
                             &mut batch_bytes,
                             batch_byte_quota,
                             &record_sender,
-                        )?;
-                    }
-                    None => {
-                        flush_batch(&record_sender, &mut batch, &mut batch_bytes)?;
-                        return Ok(());
-                    }
-                }
+                        )
+                    },
+                )?;
             }
+            flush_batch(&record_sender, &mut batch, &mut batch_bytes)?;
+            Ok(())
         }
-        InputMode::ZeroTerminated => {
+        InputMode::WholeString => {
+            // Whole-string mode joins every file into the one string
             let mut buffer: Vec<u8> = Vec::new();
-            loop {
-                match read_record(&mut reader, &mut buffer, &mut index, b'\0')? {
-                    Some(record) => {
-                        add_record_to_batch(
-                            record,
-                            &mut batch,
-                            &mut batch_bytes,
-                            batch_byte_quota,
-                            &record_sender,
-                        )?;
+            for source in &sources {
+                let mut reader = open_input(*source, input_instructions, progress.as_ref())?;
+                match input_instructions.max_record_bytes {
+                    // Reading one byte past the limit is enough to know the input is too long
+                    Some(max) => {
+                        let remaining = max.saturating_add(1).saturating_sub(buffer.len());
+                        Read::take(&mut reader, remaining as u64)
+                            .read_to_end(&mut buffer)
+                            .map_err(read_error)?;
+                        if buffer.len() > max {
+                            return Err(record_too_long(index, max));
+                        }
                     }
                     None => {
-                        flush_batch(&record_sender, &mut batch, &mut batch_bytes)?;
-                        return Ok(());
+                        reader.read_to_end(&mut buffer).map_err(read_error)?;
                     }
                 }
             }
-        }
-        InputMode::WholeString => {
-            let mut buffer: Vec<u8> = Vec::new();
-            reader
-                .read_to_end(&mut buffer)
-                .map_err(|error| format!("{error}"))?;
+            // The whole input is the one record, so its \r is before any final newline
+            if input_instructions.strip_cr {
+                let newline = usize::from(buffer.ends_with(b"\n"));
+                if buffer[..buffer.len() - newline].ends_with(b"\r") {
+                    buffer.remove(buffer.len() - newline - 1);
+                }
+            }
 
             batch.push(Record {
                 index,
+                sequence: index,
                 bytes: buffer,
                 has_terminator: false,
                 field_widths: None,
@@ -0,0 +1,163 @@
+use std::cell::{Cell, RefCell};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// How often the line is redrawn, so a fast read doesn't spend its time writing to stderr.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The --progress line on stderr: bytes read from the input files against their combined
+/// size. It is redrawn in place after a carriage return and wiped when the last reader is
+/// dropped, so nothing is left on the terminal once reading is done.
+pub struct Progress {
+    total: u64,
+    read: Cell<u64>,
+    last_drawn: Cell<Instant>,
+    drawn_width: Cell<usize>, // length of the line on screen, to wipe it
+    output: RefCell<Box<dyn Write>>, // stderr outside the tests
+    redraw_interval: Duration,
+}
+
+impl Progress {
+    /// Progress over `passes` reads of every input, or None if any input has no known size.
+    pub fn for_files(input: &[PathBuf], passes: u64) -> Option<Rc<Progress>> {
+        if input.is_empty() {
+            return None;
+        }
+        let mut total: u64 = 0;
+        for path in input {
+            if path.as_os_str() == "-" {
+                return None;
+            }
+            let metadata = std::fs::metadata(path).ok().filter(|meta| meta.is_file())?;
+            total = total.saturating_add(metadata.len());
+        }
+        match total.saturating_mul(passes) {
+            0 => None,
+            total => Some(Rc::new(Progress::new(
+                total,
+                Box::new(io::stderr()),
+                REDRAW_INTERVAL,
+            ))),
+        }
+    }
+
+    fn new(total: u64, output: Box<dyn Write>, redraw_interval: Duration) -> Progress {
+        Progress {
+            total,
+            read: Cell::new(0),
+            // Waiting one interval before the first draw keeps small inputs quiet
+            last_drawn: Cell::new(Instant::now()),
+            drawn_width: Cell::new(0),
+            output: RefCell::new(output),
+            redraw_interval,
+        }
+    }
+
+    fn advance(&self, bytes: usize) {
+        self.read.set(self.read.get().saturating_add(bytes as u64));
+        if self.last_drawn.get().elapsed() < self.redraw_interval {
+            return;
+        }
+        self.last_drawn.set(Instant::now());
+
+        // A file that grew while being read can go past its size, so hold at 100%
+        let read = self.read.get().min(self.total);
+        let line = format!(
+            "splitby: {:>3}% ({read} of {} bytes)",
+            read * 100 / self.total,
+            self.total
+        );
+        let padding = self.drawn_width.get().saturating_sub(line.len());
+        self.drawn_width.set(line.len());
+        let mut output = self.output.borrow_mut();
+        let _ = write!(output, "\r{line}{:padding$}", "");
+        let _ = output.flush();
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        let width = self.drawn_width.get();
+        if width > 0 {
+            let output = self.output.get_mut();
+            let _ = write!(output, "\r{:width$}\r", "");
+            let _ = output.flush();
+        }
+    }
+}
+
+/// Counts the bytes read from one input file towards the shared progress.
+pub struct ProgressReader<R: Read> {
+    pub inner: R,
+    pub progress: Rc<Progress>,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.advance(read);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects what the progress line writes, still readable once the progress is dropped.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn no_progress_without_sized_files() {
+        let empty_file =
+            std::env::temp_dir().join(format!("splitby-{}-progress-empty", std::process::id()));
+        std::fs::write(&empty_file, b"").unwrap();
+        let zero_total = Progress::for_files(std::slice::from_ref(&empty_file), 1);
+        let _ = std::fs::remove_file(&empty_file);
+
+        assert!(Progress::for_files(&[], 1).is_none(), "no input is stdin");
+        assert!(Progress::for_files(&[PathBuf::from("-")], 1).is_none());
+        assert!(zero_total.is_none(), "nothing to read");
+    }
+
+    #[test]
+    fn draws_the_line_then_wipes_it() {
+        let output = SharedOutput::default();
+        let progress = Rc::new(Progress::new(10, Box::new(output.clone()), Duration::ZERO));
+        let mut reader = ProgressReader {
+            inner: &b"0123456789"[..],
+            progress: Rc::clone(&progress),
+        };
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+
+        let drawn = String::from_utf8(output.0.borrow().clone()).unwrap();
+        assert!(
+            drawn.ends_with("\rsplitby: 100% (10 of 10 bytes)"),
+            "{drawn:?}"
+        );
+
+        drop(reader);
+        drop(progress);
+        let line = "splitby: 100% (10 of 10 bytes)";
+        let wiped = String::from_utf8(output.0.borrow().clone()).unwrap();
+        assert!(
+            wiped.ends_with(&format!("\r{}\r", " ".repeat(line.len()))),
+            "{wiped:?}"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::io::{self, Read};
+
+/// Transcodes a UTF-16 stream to UTF-8 as it is read, so records split as usual afterwards.
+/// A leading byte order mark picks the byte order and is dropped. Invalid sequences become
+/// U+FFFD, or an error when `strict` is set.
+pub struct Utf16Reader<R: Read> {
+    inner: R,
+    big_endian: bool,
+    strict: bool,
+    checked_bom: bool,
+    reached_eof: bool,
+    pending: Vec<u8>, // input bytes not yet decoded, such as half a code unit
+    decoded: Vec<u8>, // UTF-8 not yet handed out
+    decoded_position: usize,
+}
+
+impl<R: Read> Utf16Reader<R> {
+    pub fn new(inner: R, big_endian: bool, strict: bool) -> Self {
+        Utf16Reader {
+            inner,
+            big_endian,
+            strict,
+            checked_bom: false,
+            reached_eof: false,
+            pending: Vec::new(),
+            decoded: Vec::new(),
+            decoded_position: 0,
+        }
+    }
+
+    fn fill_decoded(&mut self) -> io::Result<()> {
+        self.decoded.clear();
+        self.decoded_position = 0;
+
+        let mut chunk = [0u8; 8192];
+        let bytes_read = loop {
+            match self.inner.read(&mut chunk) {
+                Ok(bytes_read) => break bytes_read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        };
+        self.pending.extend_from_slice(&chunk[..bytes_read]);
+        self.reached_eof = bytes_read == 0;
+
+        if !self.checked_bom && (self.pending.len() >= 2 || self.reached_eof) {
+            let bom_big_endian = match self.pending.get(..2) {
+                Some([0xFF, 0xFE]) => Some(false),
+                Some([0xFE, 0xFF]) => Some(true),
+                _ => None,
+            };
+            if let Some(big_endian) = bom_big_endian {
+                self.big_endian = big_endian;
+                self.pending.drain(..2);
+            }
+            self.checked_bom = true;
+        }
+        if !self.checked_bom {
+            return Ok(());
+        }
+
+        let mut units: Vec<u16> = self
+            .pending
+            .chunks_exact(2)
+            .map(|pair| match self.big_endian {
+                true => u16::from_be_bytes([pair[0], pair[1]]),
+                false => u16::from_le_bytes([pair[0], pair[1]]),
+            })
+            .collect();
+        // A high surrogate at the end of the chunk waits for its pair
+        if !self.reached_eof
+            && units
+                .last()
+                .is_some_and(|unit| (0xD800..0xDC00).contains(unit))
+        {
+            units.pop();
+        }
+        self.pending.drain(..units.len() * 2);
+
+        for character in char::decode_utf16(units) {
+            let character = match character {
+                Ok(character) => character,
+                Err(error) if self.strict => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "invalid UTF-16 input: unpaired surrogate {:#06x}",
+                            error.unpaired_surrogate()
+                        ),
+                    ));
+                }
+                Err(_) => char::REPLACEMENT_CHARACTER,
+            };
+            let mut encoded = [0u8; 4];
+            self.decoded
+                .extend_from_slice(character.encode_utf8(&mut encoded).as_bytes());
+        }
+
+        if self.reached_eof && !self.pending.is_empty() {
+            if self.strict {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid UTF-16 input: odd number of bytes",
+                ));
+            }
+            self.pending.clear();
+            self.decoded
+                .extend_from_slice(char::REPLACEMENT_CHARACTER.to_string().as_bytes());
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Utf16Reader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        while self.decoded_position >= self.decoded.len() {
+            if self.reached_eof {
+                return Ok(0);
+            }
+            self.fill_decoded()?;
+        }
+        let available = &self.decoded[self.decoded_position..];
+        let length = available.len().min(buffer.len());
+        buffer[..length].copy_from_slice(&available[..length]);
+        self.decoded_position += length;
+        Ok(length)
+    }
+}
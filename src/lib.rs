@@ -0,0 +1,114 @@
+//! Split text by a regex delimiter and select parts of the result.
+//!
+//! [`Splitter`] runs the same split and select core as the `splitby` command, on bytes
+//! or a reader held by the caller:
+//!
+//! ```
+//! use splitby::{Config, Delimiter, Splitter};
+//!
+//! let splitter = Splitter::new(Config {
+//!     delimiter: Some(Delimiter::Literal(",".to_string())),
+//!     selections: vec!["1".to_string(), "3".to_string()],
+//!     ..Config::default()
+//! })?;
+//! assert_eq!(splitter.split(b"a,b,c\nd,e,f\n")?, b"a,c\nd,f\n");
+//! # Ok::<(), splitby::SplitError>(())
+//! ```
+
+mod cli;
+mod error;
+mod input;
+mod output;
+mod splitter;
+mod transform;
+mod types;
+mod utilities;
+
+pub use error::{Position, SplitError};
+pub use splitter::{Config, Splitter};
+pub use types::{Delimiter, InputMode, SelectionMode};
+
+use input::read_input;
+use output::get_results;
+use transform::process_records;
+use types::*;
+
+use crossbeam::channel;
+use std::{cmp::max, sync::Arc};
+
+use crate::cli::get_instructions;
+
+/// Batches the reader can get ahead of the writer without --reorder-window.
+const DEFAULT_REORDER_WINDOW: usize = 1024;
+
+/// Runs the command-line tool on the process arguments. The `splitby` binary prints the
+/// error and exits with [`SplitError::exit_code`].
+pub fn run() -> Result<(), SplitError> {
+    let instructions = match get_instructions().map_err(SplitError::Usage)? {
+        Some(instructions) => instructions,
+        None => return Ok(()),
+    };
+
+    let jobs = instructions.jobs;
+    let reorder_window = instructions
+        .reorder_window
+        .unwrap_or(DEFAULT_REORDER_WINDOW);
+    let input_instructions = instructions.input_instructions;
+    let transform_instructions = Arc::new(instructions.transform_instructions);
+    let output_instructions = instructions.output_instructions;
+
+    let (input_sender, input_receiver) = channel::bounded::<Vec<Record>>(1024);
+    let (output_sender, output_receiver) = channel::bounded::<ResultChunk>(1024);
+    // Written output buffers go back to the reader, a batch at a time, to hold new records
+    let (buffer_pool_sender, buffer_pool_receiver) = channel::bounded::<Vec<Vec<u8>>>(16);
+    // The reader takes a slot for each batch and the writer frees it once the batch is written,
+    // so a slow batch stops the reading rather than leaving later ones to pile up behind it
+    let (window_sender, window_receiver) = channel::bounded::<()>(reorder_window);
+
+    // Setting up our Reader worker
+    let input_handle = std::thread::spawn(move || {
+        read_input(
+            &input_instructions,
+            input_sender,
+            buffer_pool_receiver,
+            window_sender,
+        )
+    });
+
+    // Working out how much memory we need. --jobs is an exact worker count, otherwise
+    // we leave a core free for the reader and writer threads
+    let worker_count = match jobs {
+        Some(jobs) if jobs > 0 => jobs,
+        None if std::env::var("SPLITBY_SINGLE_CORE").is_ok() => 1,
+        _ => {
+            let cores = std::thread::available_parallelism()
+                .map(|count| count.get())
+                .unwrap_or(1);
+            max(cores - 1, 1)
+        }
+    };
+
+    // Setting up our main processing workers
+    for _worker_index in 0..worker_count {
+        let worker_instructions = Arc::clone(&transform_instructions);
+        let worker_receiver = input_receiver.clone();
+        let worker_sender = output_sender.clone();
+        std::thread::spawn(move || {
+            let _ = process_records(worker_instructions, worker_receiver, worker_sender)
+                .map_err(|error| eprintln!("{error}"));
+        });
+    }
+    drop(output_sender);
+    drop(input_receiver);
+
+    let results_status = get_results(
+        output_instructions,
+        output_receiver,
+        buffer_pool_sender,
+        window_receiver,
+    );
+
+    // A failed writer leaves the reader to stop on its own, so the writer's error comes first
+    results_status?;
+    input_handle.join().unwrap()
+}
@@ -1,25 +1,290 @@
 use crossbeam::channel;
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashSet},
     io::{self, Write},
 };
 
+use crate::error::{Position, SplitError};
 use crate::types::*;
 
+/// Writes bytes with control characters and invalid UTF-8 escaped, for --escape-output.
+/// Uses the escapes --escape decodes: \t \n \r \0 \\ and \xNN for everything else.
+fn push_escaped(output_buffer: &mut Vec<u8>, bytes: &[u8]) {
+    let push_hex = |output_buffer: &mut Vec<u8>, byte: u8| {
+        output_buffer.extend_from_slice(format!("\\x{byte:02x}").as_bytes());
+    };
+    for chunk in bytes.utf8_chunks() {
+        for character in chunk.valid().chars() {
+            match character {
+                '\t' => output_buffer.extend_from_slice(b"\\t"),
+                '\n' => output_buffer.extend_from_slice(b"\\n"),
+                '\r' => output_buffer.extend_from_slice(b"\\r"),
+                '\0' => output_buffer.extend_from_slice(b"\\0"),
+                '\\' => output_buffer.extend_from_slice(b"\\\\"),
+                _ if character.is_control() => {
+                    let mut encoded = [0u8; 4];
+                    for &byte in character.encode_utf8(&mut encoded).as_bytes() {
+                        push_hex(output_buffer, byte);
+                    }
+                }
+                _ => {
+                    let mut encoded = [0u8; 4];
+                    output_buffer.extend_from_slice(character.encode_utf8(&mut encoded).as_bytes());
+                }
+            }
+        }
+        for &byte in chunk.invalid() {
+            push_hex(output_buffer, byte);
+        }
+    }
+}
+
+/// The per-record counts gathered for --count-total and --histogram, which each write a
+/// summary at the end instead of a count per record.
+#[derive(Default)]
+struct CountTally {
+    total: usize,
+    histogram: BTreeMap<usize, u64>,
+}
+
+/// Appends a record and its terminator to the output buffer, unless it was suppressed
+/// or repeats an earlier dedup key. Returns whether the record had any content of its own.
+/// `record_number` is written as a tab-separated prefix when --number is set. With
+/// `count_tally`, the record is a count that is tallied instead of written.
+/// `escape_output` escapes the record's own bytes, but never its terminator.
+fn push_output_record(
+    output_buffer: &mut Vec<u8>,
+    output_record: &OutputRecord,
+    record_terminator: Option<&[u8]>,
+    seen_keys: &mut HashSet<Vec<u8>>,
+    record_number: Option<usize>,
+    count_tally: Option<&mut CountTally>,
+    escape_output: bool,
+) -> Result<bool, SplitError> {
+    if output_record.suppressed {
+        return Ok(false);
+    }
+    if let Some(key) = &output_record.dedup_key
+        && !seen_keys.insert(key.clone())
+    {
+        return Ok(false);
+    }
+    if let Some(tally) = count_tally {
+        let count = output_record.count.ok_or_else(|| {
+            SplitError::Data("internal error: count-total record is not a count".to_string())
+        })?;
+        tally.total += count;
+        *tally.histogram.entry(count).or_default() += 1;
+        return Ok(true);
+    }
+    if let Some(record_number) = record_number {
+        output_buffer.extend_from_slice(record_number.to_string().as_bytes());
+        output_buffer.push(b'\t');
+    }
+    match escape_output {
+        true => push_escaped(output_buffer, &output_record.bytes),
+        false => output_buffer.extend_from_slice(&output_record.bytes),
+    }
+    if let Some(terminator_bytes) = record_terminator
+        && output_record.has_terminator
+    {
+        output_buffer.extend_from_slice(terminator_bytes);
+    }
+    Ok(!output_record.bytes.is_empty())
+}
+
+/// For --trim-newline: how many bytes at the end of the buffer are the terminator of the record
+/// just pushed. They're held back from writing until another record follows them.
+fn held_terminator_length(
+    output_buffer: &[u8],
+    output_record: &OutputRecord,
+    record_terminator: Option<&[u8]>,
+) -> usize {
+    match record_terminator {
+        Some(terminator) if output_record.has_terminator => terminator.len(),
+        Some(_) => 0,
+        // Whole-string mode has no terminator of its own, so it's the input's final newline
+        None if output_buffer.ends_with(b"\r\n") => 2,
+        None if output_buffer.ends_with(b"\n") => 1,
+        None => 0,
+    }
+}
+
+/// Buffers bigger than this are freed rather than kept around for the reader.
+const MAX_RECYCLED_CAPACITY: usize = 64 * 1024;
+
+/// Hands a batch's written record buffers back to the reader to reuse. If the pool is full,
+/// or the reader has finished, they are simply dropped.
+fn recycle_buffers(outputs: Vec<OutputRecord>, buffer_pool: &channel::Sender<Vec<Vec<u8>>>) {
+    if buffer_pool.is_full() {
+        return;
+    }
+    let buffers: Vec<Vec<u8>> = outputs
+        .into_iter()
+        .map(|output_record| output_record.bytes)
+        .filter(|bytes| bytes.capacity() > 0 && bytes.capacity() <= MAX_RECYCLED_CAPACITY)
+        .map(|mut bytes| {
+            bytes.clear();
+            bytes
+        })
+        .collect();
+    let _ = buffer_pool.try_send(buffers);
+}
+
+/// Marks an error with the position of the record it came from.
+fn record_error(input_mode: InputMode, index: usize, error: SplitError) -> SplitError {
+    let position = match input_mode {
+        InputMode::WholeString => return error,
+        InputMode::PerLine => Position::Line(index + 1),
+        InputMode::ZeroTerminated => Position::Record(index + 1),
+    };
+    SplitError::InRecord(position, Box::new(error))
+}
+
+/// Errors if the record's --assert-sorted key sorts before the previous record's key.
+fn check_sorted(
+    previous_key: &mut Option<Vec<u8>>,
+    output_record: &OutputRecord,
+    numeric: bool,
+) -> Result<(), SplitError> {
+    let Some(key) = &output_record.sort_key else {
+        return Ok(());
+    };
+    if output_record.suppressed {
+        return Ok(());
+    }
+    let parse_number = |key: &[u8]| -> Result<f64, SplitError> {
+        let text = String::from_utf8_lossy(key);
+        text.trim().parse::<f64>().map_err(|_| {
+            SplitError::Data(format!("assert-sorted error: key '{text}' is not a number"))
+        })
+    };
+
+    if let Some(previous) = previous_key.as_ref() {
+        let out_of_order = if numeric {
+            parse_number(key)? < parse_number(previous)?
+        } else {
+            key < previous
+        };
+        if out_of_order {
+            return Err(SplitError::Data(format!(
+                "assert-sorted error: key '{}' sorts before previous key '{}'",
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(previous)
+            )));
+        }
+    } else if numeric {
+        parse_number(key)?;
+    }
+    *previous_key = Some(key.clone());
+    Ok(())
+}
+
+/// Tracks --assert-uniform: the first record's field count, and the first record to differ.
+#[derive(Default)]
+struct UniformCheck {
+    expected: Option<usize>,
+    first_mismatch: Option<(usize, usize)>,
+}
+
+impl UniformCheck {
+    fn check(&mut self, output_record: &OutputRecord, index: usize) {
+        let Some(field_count) = output_record.field_count else {
+            return;
+        };
+        if output_record.suppressed || self.first_mismatch.is_some() {
+            return;
+        }
+        match self.expected {
+            None => self.expected = Some(field_count),
+            Some(expected) if expected != field_count => {
+                self.first_mismatch = Some((index, field_count));
+            }
+            Some(_) => {}
+        }
+    }
+
+    fn result(&self, input_mode: InputMode) -> Result<(), SplitError> {
+        match (self.expected, self.first_mismatch) {
+            (Some(expected), Some((index, field_count))) => Err(record_error(
+                input_mode,
+                index,
+                SplitError::Data(format!(
+                    "assert-uniform error: record has {field_count} fields, expected {expected} like the first record"
+                )),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How an input with no records ends. The first of these that applies decides it:
+/// --strict-empty fails with its own exit status, --count answers 0 so the other strict flags
+/// have nothing to fail, then --strict-return and --strict-bounds fail as any record would.
+fn empty_input_status(output_instructions: &OutputInstructions) -> Result<(), SplitError> {
+    if output_instructions.strict_empty {
+        return Err(SplitError::EmptyInput(
+            "strict-empty error: no input received".to_string(),
+        ));
+    }
+    if output_instructions.count {
+        return Ok(());
+    }
+    if output_instructions.strict_return {
+        return Err(SplitError::StrictReturn(
+            "strict-return error: no input received".to_string(),
+        ));
+    }
+    if output_instructions.strict_bounds
+        && let Some(&(raw_start, _, _)) = output_instructions.selections.first()
+    {
+        return Err(SplitError::StrictBounds(format!(
+            "strict-bounds error: index ({raw_start}) out of bounds, must be between 1 and 0"
+        )));
+    }
+    Ok(())
+}
+
+/// A closed pipe, as with `splitby ... | head`, means the reader has all it wants, so it gets
+/// its own kind for the caller to stop quietly on rather than report.
+fn write_error(error: io::Error) -> SplitError {
+    match error.kind() {
+        io::ErrorKind::BrokenPipe => SplitError::BrokenPipe(error.to_string()),
+        _ => SplitError::Io(error.to_string()),
+    }
+}
+
 pub fn get_results(
     output_instructions: OutputInstructions,
     result_receiver: channel::Receiver<ResultChunk>,
-) -> Result<(), String> {
-    let record_terminator: Option<u8> = match output_instructions.input_mode {
-        InputMode::PerLine => Some(b'\n'),
-        InputMode::ZeroTerminated => Some(b'\0'),
+    buffer_pool: channel::Sender<Vec<Vec<u8>>>,
+    reorder_window: channel::Receiver<()>,
+) -> Result<(), SplitError> {
+    let line_terminator: &[u8] = match output_instructions.crlf_output {
+        true => b"\r\n",
+        false => b"\n",
+    };
+    let record_terminator: Option<&[u8]> = match output_instructions.input_mode {
         InputMode::WholeString => None,
+        _ if output_instructions.output_terminator.is_some() => {
+            output_instructions.output_terminator.as_deref()
+        }
+        InputMode::PerLine => Some(line_terminator),
+        InputMode::ZeroTerminated => Some(b"\0"),
     };
 
     let mut writer: Box<dyn Write> = match &output_instructions.output {
         Some(path) => {
-            let file = std::fs::File::create(path)
-                .map_err(|error| format!("failed to create {}: {}", path.display(), error))?;
+            let file = match output_instructions.output_append {
+                true => std::fs::OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .open(path),
+                false => std::fs::File::create(path),
+            }
+            .map_err(|error| {
+                SplitError::Io(format!("failed to create {}: {}", path.display(), error))
+            })?;
             Box::new(io::BufWriter::new(file))
         }
         None => {
@@ -28,69 +293,149 @@ pub fn get_results(
         }
     };
 
-    let output_flush_threshold = std::env::var("SPLITBY_OUTPUT_FLUSH")
-        .ok()
-        .and_then(|value| value.parse::<usize>().ok())
-        .filter(|value| *value > 0)
-        .unwrap_or(64 * 1024);
+    // Line-buffered output flushes as soon as there is anything to write
+    let output_flush_threshold = match output_instructions.line_buffered {
+        true => 1,
+        false => std::env::var("SPLITBY_OUTPUT_FLUSH")
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(64 * 1024),
+    };
+    let line_buffered = output_instructions.line_buffered;
     let mut next_index: usize = 0;
-    let mut pending: BTreeMap<usize, Vec<OutputRecord>> = BTreeMap::new();
+    let mut wrote_content = false;
+    let tally_counts = output_instructions.count_total || output_instructions.histogram.is_some();
+    let mut count_tally = CountTally::default();
+    let mut seen_keys: HashSet<Vec<u8>> = HashSet::new();
+    let mut previous_sort_key: Option<Vec<u8>> = None;
+    let mut uniform_check = UniformCheck::default();
+    // Keyed by sequence, each batch with the input index of its first record
+    let mut pending: BTreeMap<usize, (usize, Vec<OutputRecord>)> = BTreeMap::new();
     let mut output_buffer: Vec<u8> = Vec::with_capacity(output_flush_threshold * 2);
+    let trim_newline = output_instructions.trim_newline;
+    // With --trim-newline, the last record's terminator stays in the buffer until we know
+    // whether another record follows it
+    let mut held_terminator: usize = 0;
+    // Whether any bytes have reached the writer, as the buffer alone forgets earlier flushes
+    let mut flushed_any = false;
 
-    let flush_output =
-        |writer: &mut Box<dyn Write>, output_buffer: &mut Vec<u8>| -> Result<(), String> {
-            if output_buffer.is_empty() {
-                return Ok(());
-            }
-            writer
-                .write_all(output_buffer)
-                .map_err(|error| error.to_string())?;
-            output_buffer.clear();
-            Ok(())
-        };
+    let flush_output = |writer: &mut Box<dyn Write>,
+                        output_buffer: &mut Vec<u8>,
+                        held_terminator: usize|
+     -> Result<bool, SplitError> {
+        let ready = output_buffer.len() - held_terminator;
+        if ready == 0 {
+            return Ok(false);
+        }
+        writer
+            .write_all(&output_buffer[..ready])
+            .map_err(write_error)?;
+        output_buffer.drain(..ready);
+        if line_buffered {
+            writer.flush().map_err(write_error)?;
+        }
+        Ok(true)
+    };
 
     while let Ok(result) = result_receiver.recv() {
         match result {
             ResultChunk::Err { index, error } => {
-                let index = index + 1;
-                match output_instructions.input_mode {
-                    InputMode::WholeString => return Err(error),
-                    InputMode::PerLine => return Err(format!("line {index}: {error}")),
-                    InputMode::ZeroTerminated => {
-                        return Err(format!("record {index}: {error}"));
+                return Err(record_error(output_instructions.input_mode, index, error));
+            }
+            ResultChunk::Ok {
+                start_line,
+                outputs,
+                ..
+            } if output_instructions.unordered => {
+                // No reorder buffer: next_index just counts the records written so far
+                for (offset, output_record) in outputs.iter().enumerate() {
+                    let buffer_length = output_buffer.len();
+                    wrote_content |= push_output_record(
+                        &mut output_buffer,
+                        output_record,
+                        record_terminator,
+                        &mut seen_keys,
+                        output_instructions
+                            .number
+                            .then_some(start_line + offset + 1),
+                        tally_counts.then_some(&mut count_tally),
+                        output_instructions.escape_output,
+                    )?;
+                    if trim_newline && output_buffer.len() > buffer_length {
+                        held_terminator = held_terminator_length(
+                            &output_buffer,
+                            output_record,
+                            record_terminator,
+                        );
+                    }
+
+                    if output_buffer.len() >= output_flush_threshold {
+                        flushed_any |=
+                            flush_output(&mut writer, &mut output_buffer, held_terminator)?;
                     }
                 }
+                next_index += outputs.len();
+                recycle_buffers(outputs, &buffer_pool);
+                let _ = reorder_window.try_recv();
+                continue;
             }
             ResultChunk::Ok {
                 start_index,
+                start_line,
                 outputs,
             } => {
-                pending.insert(start_index, outputs);
+                pending.insert(start_index, (start_line, outputs));
             }
         }
 
         while let Some(&pending_index) = pending.keys().next() {
             if pending_index == next_index {
-                if let Some(outputs) = pending.remove(&next_index) {
+                if let Some((start_line, outputs)) = pending.remove(&next_index) {
                     let base_index = next_index;
                     let mut offset = 0usize;
 
                     while offset < outputs.len() {
-                        let output_record = &outputs[offset];
-                        output_buffer.extend_from_slice(&output_record.bytes);
-                        if let Some(terminator_byte) = record_terminator {
-                            if output_record.has_terminator {
-                                output_buffer.push(terminator_byte);
-                            }
+                        check_sorted(
+                            &mut previous_sort_key,
+                            &outputs[offset],
+                            output_instructions.numeric,
+                        )
+                        .map_err(|error| {
+                            record_error(output_instructions.input_mode, start_line + offset, error)
+                        })?;
+                        uniform_check.check(&outputs[offset], start_line + offset);
+                        let buffer_length = output_buffer.len();
+                        wrote_content |= push_output_record(
+                            &mut output_buffer,
+                            &outputs[offset],
+                            record_terminator,
+                            &mut seen_keys,
+                            output_instructions
+                                .number
+                                .then_some(start_line + offset + 1),
+                            tally_counts.then_some(&mut count_tally),
+                            output_instructions.escape_output,
+                        )?;
+                        if trim_newline && output_buffer.len() > buffer_length {
+                            held_terminator = held_terminator_length(
+                                &output_buffer,
+                                &outputs[offset],
+                                record_terminator,
+                            );
                         }
 
                         if output_buffer.len() >= output_flush_threshold {
-                            flush_output(&mut writer, &mut output_buffer)?;
+                            flushed_any |=
+                                flush_output(&mut writer, &mut output_buffer, held_terminator)?;
                         }
 
                         next_index = base_index + offset + 1;
                         offset += 1;
                     }
+                    recycle_buffers(outputs, &buffer_pool);
+                    // Frees the batch's slot, letting the reader send another
+                    let _ = reorder_window.try_recv();
                 }
             } else {
                 break;
@@ -98,13 +443,29 @@ pub fn get_results(
         }
     }
 
-    while let Some(outputs) = pending.remove(&next_index) {
-        for output_record in outputs {
-            output_buffer.extend_from_slice(&output_record.bytes);
-            if let Some(terminator_byte) = record_terminator {
-                if output_record.has_terminator {
-                    output_buffer.push(terminator_byte);
-                }
+    while let Some((start_line, outputs)) = pending.remove(&next_index) {
+        for (offset, output_record) in outputs.into_iter().enumerate() {
+            let line = start_line + offset;
+            check_sorted(
+                &mut previous_sort_key,
+                &output_record,
+                output_instructions.numeric,
+            )
+            .map_err(|error| record_error(output_instructions.input_mode, line, error))?;
+            uniform_check.check(&output_record, line);
+            let buffer_length = output_buffer.len();
+            wrote_content |= push_output_record(
+                &mut output_buffer,
+                &output_record,
+                record_terminator,
+                &mut seen_keys,
+                output_instructions.number.then_some(line + 1),
+                tally_counts.then_some(&mut count_tally),
+                output_instructions.escape_output,
+            )?;
+            if trim_newline && output_buffer.len() > buffer_length {
+                held_terminator =
+                    held_terminator_length(&output_buffer, &output_record, record_terminator);
             }
 
             next_index += 1;
@@ -113,37 +474,75 @@ pub fn get_results(
 
     if !pending.is_empty() {
         let first_missing = next_index;
-        return Err(format!(
+        return Err(SplitError::Data(format!(
             "result stream ended early: missing record {first_missing}"
-        ));
+        )));
     }
 
     if next_index == 0 {
-        if output_instructions.count {
-            writer.write_all(b"0").map_err(|error| error.to_string())?;
+        empty_input_status(&output_instructions)?;
+        // A histogram of no records has no rows
+        if output_instructions.count && output_instructions.histogram.is_none() {
+            writer.write_all(b"0").map_err(write_error)?;
         }
-        if output_instructions.strict_return {
-            return Err("strict-return error: no input received".to_string());
+    }
+
+    if output_instructions.count_total && next_index > 0 {
+        output_buffer.extend_from_slice(count_tally.total.to_string().as_bytes());
+        held_terminator = 0;
+        if let Some(terminator) = record_terminator {
+            output_buffer.extend_from_slice(terminator);
+            held_terminator = terminator.len();
         }
-        if output_instructions.strict_bounds && !output_instructions.selections.is_empty() {
-            let (raw_start, _) = output_instructions.selections[0];
-            return Err(format!(
-                "strict-bounds error: index ({}) out of bounds, must be between 1 and {}",
-                raw_start, 0
-            ));
+    }
+
+    // One row per count, smallest first, e.g. `3 fields: 12 lines`
+    if let Some(unit) = output_instructions.histogram {
+        let record_noun = match output_instructions.input_mode {
+            InputMode::PerLine => "line",
+            _ => "record",
+        };
+        let plural = |amount: u64| if amount == 1 { "" } else { "s" };
+        for (&count, &records) in &count_tally.histogram {
+            let row = format!(
+                "{count} {unit}{}: {records} {record_noun}{}",
+                plural(count as u64),
+                plural(records)
+            );
+            output_buffer.extend_from_slice(row.as_bytes());
+            held_terminator = 0;
+            if let Some(terminator) = record_terminator {
+                output_buffer.extend_from_slice(terminator);
+                held_terminator = terminator.len();
+            }
         }
     }
 
-    // Whole-string mode: ensure terminal output ends with a newline if it has content
-    if output_instructions.stdout_is_terminal
+    if output_instructions.fail_on_empty_output && next_index > 0 && !wrote_content {
+        return Err(SplitError::Data(
+            "fail-on-empty-output error: input produced no output".to_string(),
+        ));
+    }
+
+    // Whole-string mode: an explicit --output-terminator always ends the output, otherwise
+    // ensure terminal output ends with a newline if it has content
+    if trim_newline {
+        output_buffer.truncate(output_buffer.len() - held_terminator);
+    } else if output_instructions.input_mode == InputMode::WholeString
+        && let Some(terminator) = &output_instructions.output_terminator
+    {
+        if flushed_any || !output_buffer.is_empty() {
+            output_buffer.extend_from_slice(terminator);
+        }
+    } else if output_instructions.stdout_is_terminal
         && output_instructions.input_mode == InputMode::WholeString
         && !output_buffer.is_empty()
         && output_buffer.last() != Some(&b'\n')
     {
-        output_buffer.push(b'\n');
+        output_buffer.extend_from_slice(line_terminator);
     }
 
-    flush_output(&mut writer, &mut output_buffer)?;
-    writer.flush().map_err(|error| error.to_string())?;
-    Ok(())
+    flush_output(&mut writer, &mut output_buffer, 0)?;
+    writer.flush().map_err(write_error)?;
+    uniform_check.result(output_instructions.input_mode)
 }
@@ -0,0 +1,158 @@
+use std::io::Read;
+
+use crate::cli::{
+    delimiter_engine, parse_join, parse_selection_token, selection_regex, validate_join_mode,
+    validate_selections,
+};
+use crate::error::{Position, SplitError};
+use crate::input::read_reader_records;
+use crate::transform::transform_record;
+use crate::types::*;
+
+/// What a [`Splitter`] splits on and selects. Fields follow the command-line flags of the
+/// same names, and the defaults match the command's.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// Fields (the default), bytes or chars. Capture mode is only available on the command line.
+    pub selection_mode: SelectionMode,
+    /// Per-line (the default), NUL-terminated, or the whole input as one record.
+    pub input_mode: InputMode,
+    /// Required in fields mode. `Regex` is a pattern, `Literal` is matched as written.
+    pub delimiter: Option<Delimiter>,
+    /// Treat a `Regex` delimiter as literal text too, as `--fixed` does.
+    pub fixed: bool,
+    pub ignore_case: bool,
+    /// Selection tokens as on the command line, e.g. `2`, `-1`, `1-3` or `1-10:2`.
+    /// Commas and spaces may separate several in one string. Empty selects everything.
+    pub selections: Vec<String>,
+    pub invert: bool,
+    pub skip_empty: bool,
+    /// A string, hex value or keyword such as `space`, as `--join` takes.
+    pub join: Option<String>,
+    pub placeholder: Option<Vec<u8>>,
+    pub count: bool,
+    pub strict_bounds: bool,
+    pub strict_return: bool,
+    pub strict_range_order: bool,
+    pub strict_utf8: bool,
+}
+
+/// Splits input into records, and each record into the selected fields, bytes or chars,
+/// the same way the `splitby` command does.
+pub struct Splitter {
+    transform_instructions: TransformInstructions,
+}
+
+impl Splitter {
+    pub fn new(config: Config) -> Result<Self, SplitError> {
+        let selection_regex = selection_regex();
+        let mut selections: Vec<(i32, i32, i32)> = Vec::new();
+        for token in config
+            .selections
+            .iter()
+            .flat_map(|selections| selections.split([',', ' ']))
+            .filter(|token| !token.trim().is_empty())
+        {
+            let selection = match selection_regex.is_match(token.trim()) {
+                true => parse_selection_token(token, &selection_regex),
+                false => Err(format!("invalid selection: '{token}'")),
+            };
+            selections.push(selection.map_err(SplitError::Usage)?);
+        }
+        validate_selections(&selections, config.selection_mode).map_err(SplitError::Usage)?;
+
+        let join: Option<JoinMode> = match &config.join {
+            Some(join) => {
+                validate_join_mode(join.as_bytes(), config.selection_mode)
+                    .map_err(SplitError::Usage)?;
+                parse_join(join.as_bytes(), true)
+            }
+            None => None,
+        };
+
+        let regex_engine: Option<RegexEngine> = match config.selection_mode {
+            SelectionMode::Bytes | SelectionMode::Chars => None,
+            SelectionMode::Fields => {
+                let delimiter = config.delimiter.ok_or_else(|| {
+                    SplitError::Usage("delimiter is required in fields mode".to_string())
+                })?;
+                Some(
+                    delimiter_engine(delimiter, config.fixed, config.ignore_case)
+                        .map_err(SplitError::Usage)?,
+                )
+            }
+            SelectionMode::Capture => {
+                return Err(SplitError::Usage(
+                    "capture mode is not supported by Splitter".to_string(),
+                ));
+            }
+            SelectionMode::Json => {
+                return Err(SplitError::Usage(
+                    "json mode is not supported by Splitter".to_string(),
+                ));
+            }
+        };
+
+        Ok(Splitter {
+            transform_instructions: TransformInstructions {
+                input_mode: config.input_mode,
+                selection_mode: config.selection_mode,
+                selections,
+                invert: config.invert,
+                skip_empty: config.skip_empty,
+                join,
+                placeholders: config.placeholder.iter().cloned().collect(),
+                count: config.count,
+                strict_bounds: config.strict_bounds,
+                strict_return: config.strict_return,
+                strict_range_order: config.strict_range_order,
+                strict_utf8: config.strict_utf8,
+                regex_engine,
+                pad: b" ".to_vec(),
+                ..TransformInstructions::default()
+            },
+        })
+    }
+
+    /// Splits the input and returns the output the command would write for it. Each
+    /// record keeps its terminator, so a last line without one is output without one.
+    pub fn split(&self, input: &[u8]) -> Result<Vec<u8>, SplitError> {
+        self.split_reader(input)
+    }
+
+    /// Reads records from the reader as [`Splitter::split`] splits them, so only the
+    /// output is held in memory.
+    pub fn split_reader<R: Read>(&self, reader: R) -> Result<Vec<u8>, SplitError> {
+        let terminator: Option<u8> = match self.transform_instructions.input_mode {
+            InputMode::PerLine => Some(b'\n'),
+            InputMode::ZeroTerminated => Some(b'\0'),
+            InputMode::WholeString => None,
+        };
+
+        let mut output: Vec<u8> = Vec::new();
+        let mut records = 0usize;
+        read_reader_records(reader, self.transform_instructions.input_mode, |record| {
+            records += 1;
+            let has_terminator = record.has_terminator;
+            let processed =
+                transform_record(&self.transform_instructions, record).map_err(|error| {
+                    SplitError::InRecord(Position::Record(records), Box::new(error))
+                })?;
+            if let Some(processed) = processed {
+                output.extend_from_slice(&processed.into_bytes());
+                if let Some(terminator) = terminator
+                    && has_terminator
+                {
+                    output.push(terminator);
+                }
+            }
+            Ok(())
+        })?;
+
+        // As with the command, counting nothing still gives an answer
+        if records == 0 && self.transform_instructions.count {
+            output.extend_from_slice(b"0");
+        }
+        Ok(output)
+    }
+}
@@ -2,20 +2,66 @@ use crossbeam::channel;
 use std::sync::Arc;
 
 mod process_bytes;
+mod process_captures;
 mod process_chars;
 mod process_fields;
+mod process_json;
+mod process_transpose;
 pub mod transform_utilities;
 
 use self::process_bytes::process_bytes;
+use self::process_captures::process_captures;
 use self::process_chars::process_chars;
-use self::process_fields::process_fields;
+use self::process_fields::{get_field_count, get_key_field, process_fields};
+use self::process_json::process_json;
+use self::process_transpose::process_transpose;
+use self::transform_utilities::is_comment;
+use crate::error::SplitError;
 use crate::types::*;
 
+/// Runs one record through the processor for its selection mode. `None` means the record
+/// was dropped, as --only-delimited does.
+pub fn transform_record(
+    transform_instructions: &TransformInstructions,
+    record: Record,
+) -> Result<Option<Processed>, SplitError> {
+    let engine = || {
+        transform_instructions
+            .regex_engine
+            .as_ref()
+            .ok_or_else(|| SplitError::Data("internal error: missing regex engine".to_string()))
+    };
+    let processed = match transform_instructions.selection_mode {
+        SelectionMode::Bytes => process_bytes(transform_instructions, record).map(Some)?,
+        SelectionMode::Chars => process_chars(transform_instructions, record).map(Some)?,
+        SelectionMode::Fields if transform_instructions.transpose => {
+            process_transpose(transform_instructions, engine()?, record)
+                .map(|bytes| Some(Processed::Bytes(bytes)))?
+        }
+        SelectionMode::Fields => process_fields(transform_instructions, engine()?, record)?,
+        SelectionMode::Capture => {
+            process_captures(transform_instructions, engine()?, record).map(Some)?
+        }
+        SelectionMode::Json => process_json(transform_instructions, record)
+            .map(|bytes| Some(Processed::Bytes(bytes)))?,
+    };
+
+    if transform_instructions.strict_return
+        && let Some(Processed::Bytes(bytes)) = &processed
+        && bytes.is_empty()
+    {
+        return Err(SplitError::StrictReturn(
+            "strict-return error: empty field".to_string(),
+        ));
+    }
+    Ok(processed)
+}
+
 pub fn process_records(
-    transform_instructions: Arc<TransformInstructions>,
+    mut transform_instructions: Arc<TransformInstructions>,
     record_receiver: channel::Receiver<Vec<Record>>,
     result_sender: channel::Sender<ResultChunk>,
-) -> Result<(), String> {
+) -> Result<(), SplitError> {
     loop {
         let record_batch = match record_receiver.recv() {
             Ok(record_batch) => record_batch,
@@ -26,38 +72,136 @@ pub fn process_records(
             continue;
         }
 
-        let batch_start_index = record_batch[0].index;
+        // The reader resolves --header before sending the header record, so once it is
+        // resolved this worker swaps in the real selections for the rest of its records
+        if let Some(header) = &transform_instructions.header
+            && let Some(selections) = header.resolved.get()
+        {
+            let mut resolved_instructions = (*transform_instructions).clone();
+            resolved_instructions.selections = selections.clone();
+            resolved_instructions.header = None;
+            transform_instructions = Arc::new(resolved_instructions);
+        }
+
+        let batch_start_index = record_batch[0].sequence;
+        let batch_start_line = record_batch[0].index;
         let mut batch_outputs: Vec<OutputRecord> = Vec::with_capacity(record_batch.len());
 
         for record in record_batch {
             let record_index = record.index;
             let has_terminator = record.has_terminator;
+            let original: Option<Vec<u8>> = transform_instructions
+                .with_original
+                .map(|_| record.bytes.clone());
 
-            let processed_result: Result<Vec<u8>, String> =
-                match transform_instructions.selection_mode {
-                    SelectionMode::Bytes => process_bytes(&transform_instructions, record),
-                    SelectionMode::Chars => process_chars(&transform_instructions, record),
-                    SelectionMode::Fields => {
-                        let engine = transform_instructions
-                            .regex_engine
-                            .as_ref()
-                            .ok_or_else(|| "internal error: missing regex engine".to_string())?;
-                        process_fields(&transform_instructions, engine, record)
-                    }
-                };
+            // Comments stay in the results as suppressed records, so ordering remains contiguous
+            if is_comment(&record.bytes, &transform_instructions.comment_prefixes) {
+                batch_outputs.push(OutputRecord {
+                    bytes: Vec::new(),
+                    has_terminator: false,
+                    suppressed: true,
+                    dedup_key: None,
+                    sort_key: None,
+                    field_count: None,
+                    count: None,
+                });
+                continue;
+            }
 
-            match processed_result {
-                Ok(bytes) => {
-                    if transform_instructions.strict_return && bytes.is_empty() {
-                        let _ = result_sender.send(ResultChunk::Err {
-                            index: record_index,
-                            error: "strict-return error: empty field".to_string(),
-                        });
-                        return Ok(());
+            let key_field = |key_index: Option<i32>| -> Result<Option<Vec<u8>>, SplitError> {
+                match (key_index, transform_instructions.regex_engine.as_ref()) {
+                    (Some(key_index), Some(engine)) => {
+                        get_key_field(&transform_instructions, engine, &record.bytes, key_index)
+                            .map(Some)
+                    }
+                    _ => Ok(None),
+                }
+            };
+            let field_count = || -> Result<Option<usize>, SplitError> {
+                match (
+                    transform_instructions.assert_uniform,
+                    transform_instructions.regex_engine.as_ref(),
+                ) {
+                    (true, Some(engine)) => {
+                        get_field_count(&transform_instructions, engine, &record.bytes).map(Some)
                     }
+                    _ => Ok(None),
+                }
+            };
+            let keys = key_field(transform_instructions.dedup_key)
+                .and_then(|dedup_key| {
+                    key_field(transform_instructions.assert_sorted)
+                        .map(|sort_key| (dedup_key, sort_key))
+                })
+                .and_then(|(dedup_key, sort_key)| {
+                    field_count().map(|field_count| (dedup_key, sort_key, field_count))
+                });
+            let (dedup_key, sort_key, field_count) = match keys {
+                Ok(keys) => keys,
+                Err(error) => {
+                    let _ = result_sender.send(ResultChunk::Err {
+                        index: record_index,
+                        error,
+                    });
+                    return Ok(());
+                }
+            };
+
+            match transform_record(&transform_instructions, record) {
+                Ok(None) => {
+                    batch_outputs.push(OutputRecord {
+                        bytes: Vec::new(),
+                        has_terminator: false,
+                        suppressed: true,
+                        dedup_key,
+                        sort_key,
+                        field_count,
+                        count: None,
+                    });
+                }
+                Ok(Some(processed)) => {
+                    let count = match processed {
+                        Processed::Count(count) => Some(count),
+                        Processed::Bytes(_) => None,
+                    };
+                    let bytes = processed.into_bytes();
+                    let bytes = if transform_instructions.json && !transform_instructions.count {
+                        let mut array = Vec::with_capacity(bytes.len() + 2);
+                        array.push(b'[');
+                        array.extend_from_slice(&bytes);
+                        array.push(b']');
+                        array
+                    } else {
+                        bytes
+                    };
+                    let bytes = match (transform_instructions.with_original, original) {
+                        (Some(placement), Some(original)) => {
+                            let separator = &transform_instructions.original_separator;
+                            let (first, second) = match placement {
+                                OriginalPlacement::Prefix => {
+                                    (original.as_slice(), bytes.as_slice())
+                                }
+                                OriginalPlacement::Suffix => {
+                                    (bytes.as_slice(), original.as_slice())
+                                }
+                            };
+                            let mut joined =
+                                Vec::with_capacity(first.len() + separator.len() + second.len());
+                            joined.extend_from_slice(first);
+                            joined.extend_from_slice(separator);
+                            joined.extend_from_slice(second);
+                            joined
+                        }
+                        _ => bytes,
+                    };
                     batch_outputs.push(OutputRecord {
                         bytes,
                         has_terminator,
+                        suppressed: false,
+                        dedup_key,
+                        sort_key,
+                        field_count,
+                        count,
                     });
                 }
                 Err(error) => {
@@ -73,8 +217,9 @@ pub fn process_records(
         result_sender
             .send(ResultChunk::Ok {
                 start_index: batch_start_index,
+                start_line: batch_start_line,
                 outputs: batch_outputs,
             })
-            .map_err(|error| error.to_string())?;
+            .map_err(|error| SplitError::Data(error.to_string()))?;
     }
 }
@@ -1,38 +1,49 @@
+use crate::error::SplitError;
 use crate::transform::transform_utilities::*;
 use crate::types::*;
 
 pub fn process_bytes(
     transform_instructions: &TransformInstructions,
     record: Record,
-) -> Result<Vec<u8>, String> {
+) -> Result<Processed, SplitError> {
     let bytes = &record.bytes;
     let byte_length = bytes.len();
 
     if transform_instructions.count {
-        return Ok(byte_length.to_string().into_bytes());
+        let count = match transform_instructions.count_unit {
+            Some(CountUnit::Chars) => count_record_units(
+                bytes,
+                CountUnit::Chars,
+                false,
+                transform_instructions.strict_utf8,
+            )?,
+            _ => byte_length,
+        };
+        return Ok(Processed::Count(count));
     }
 
     if byte_length == 0 {
         if transform_instructions.strict_return {
-            return Err("strict-return error: empty record".to_string());
+            return Err(SplitError::StrictReturn(
+                "strict-return error: empty record".to_string(),
+            ));
         }
         if transform_instructions.strict_bounds && !transform_instructions.selections.is_empty() {
-            return Err("strict-bounds error: empty record".to_string());
+            return Err(SplitError::StrictBounds(
+                "strict-bounds error: empty record".to_string(),
+            ));
         }
-        return Ok(Vec::new());
+        return Ok(Processed::Bytes(Vec::new()));
     }
 
     // Initial normalisation pass
-    let normalised_selections: Vec<(usize, usize)> = match normalise_selections(
+    let normalised_selections: Vec<(usize, usize)> = normalise_selections(
         &transform_instructions.selections,
         byte_length,
-        transform_instructions.placeholder.is_some(),
+        !transform_instructions.placeholders.is_empty(),
         transform_instructions.strict_bounds,
         transform_instructions.strict_range_order,
-    ) {
-        Ok(result) => result,
-        Err(error) => return Err(error),
-    };
+    )?;
 
     // Invert if applicable
     let selections = if transform_instructions.selections.is_empty() {
@@ -43,23 +54,70 @@ pub fn process_bytes(
         invert_selections(normalised_selections, byte_length)
     };
 
+    // Bytes only take a join from a field terminator, which goes between selections
+    let field_join: Option<&[u8]> = match &transform_instructions.join {
+        Some(JoinMode::String(join)) if !transform_instructions.json => Some(join),
+        _ => None,
+    };
+
     // Make our real output
     let mut output: Vec<u8> = Vec::with_capacity(byte_length);
-    for selection in selections {
+    let mut strict_return_passed: bool = false;
+    let mut missing: usize = 0;
+    for (index, selection) in selections.iter().enumerate() {
+        // JSON output gets one string per selection, as does a field terminator
+        let mut piece: Vec<u8> = Vec::new();
+        let target = if transform_instructions.json || field_join.is_some() {
+            &mut piece
+        } else {
+            &mut output
+        };
         for i in selection.0..=selection.1 {
-            if i < byte_length {
-                output.push(bytes[i])
-            } else {
-                if let Some(placeholder) = &transform_instructions.placeholder {
-                    output.extend_from_slice(&placeholder);
-                }
+            if let Some(&byte) = bytes.get(i) {
+                target.push(byte);
+                strict_return_passed = true;
+            } else if let Some(placeholder) =
+                nth_placeholder(&transform_instructions.placeholders, missing)
+            {
+                target.extend_from_slice(placeholder);
+                strict_return_passed |= !placeholder.is_empty();
+                missing += 1;
+            }
+        }
+        if transform_instructions.json {
+            if index > 0 {
+                output.push(b',');
+            }
+            let mut encoded_piece: Vec<u8> = Vec::with_capacity(piece.len());
+            push_encoded(&mut encoded_piece, &piece, transform_instructions.encoding);
+            push_json_string(&mut output, &encoded_piece);
+        } else if let Some(join) = field_join {
+            if index > 0 {
+                output.extend_from_slice(join);
             }
+            push_encoded(&mut output, &piece, transform_instructions.encoding);
         }
     }
 
-    if transform_instructions.strict_return && output.is_empty() {
-        Err("strict-return error: no valid output".to_string())
+    // Otherwise there are no field boundaries here, so the whole output is encoded
+    if transform_instructions.encoding.is_some()
+        && !transform_instructions.json
+        && field_join.is_none()
+    {
+        let mut encoded_output: Vec<u8> = Vec::with_capacity(output.len() * 2);
+        push_encoded(
+            &mut encoded_output,
+            &output,
+            transform_instructions.encoding,
+        );
+        output = encoded_output;
+    }
+
+    if transform_instructions.strict_return && !strict_return_passed {
+        Err(SplitError::StrictReturn(
+            "strict-return error: no valid output".to_string(),
+        ))
     } else {
-        Ok(output)
+        Ok(Processed::Bytes(output))
     }
 }
@@ -0,0 +1,159 @@
+use std::borrow::Cow;
+
+use crate::error::SplitError;
+use crate::transform::transform_utilities::*;
+use crate::types::*;
+
+/// Selections are capture group numbers, so 0 is the whole match rather than an invalid index.
+/// Shift non-negative indices up by one so normalisation can treat group 0 as field 1.
+fn shift_group_index(raw_index: i32) -> i32 {
+    if raw_index >= 0 {
+        raw_index + 1
+    } else {
+        raw_index
+    }
+}
+
+pub fn process_captures(
+    transform_instructions: &TransformInstructions,
+    engine: &RegexEngine,
+    record: Record,
+) -> Result<Processed, SplitError> {
+    let text: Cow<str> = bytes_to_cow_string(&record.bytes, transform_instructions.strict_utf8)?;
+
+    // Group 0 is the whole match, None marks a group that did not participate
+    let groups: Vec<Option<(usize, &[u8])>> = match engine {
+        RegexEngine::Simple(regex) => match regex.captures(text.as_bytes()) {
+            Some(captures) => captures
+                .iter()
+                .map(|group| group.map(|group| (group.start(), group.as_bytes())))
+                .collect(),
+            None => Vec::new(),
+        },
+        RegexEngine::Fancy(regex) => match regex.captures(&text) {
+            Ok(Some(captures)) => captures
+                .iter()
+                .map(|group| group.map(|group| (group.start(), group.as_str().as_bytes())))
+                .collect(),
+            Ok(None) => Vec::new(),
+            Err(error) => {
+                return Err(SplitError::Regex(format!(
+                    "regex matching error: {}",
+                    error
+                )));
+            }
+        },
+        RegexEngine::Literal(_) | RegexEngine::Byte(_) => {
+            return Err(SplitError::Data(
+                "internal error: capture mode needs a regex engine".to_string(),
+            ));
+        }
+    };
+
+    if transform_instructions.count {
+        let count = groups.len().saturating_sub(1);
+        return Ok(Processed::Count(count));
+    }
+
+    if groups.is_empty() {
+        return Ok(Processed::Bytes(Vec::new()));
+    }
+
+    let shifted_selections: Vec<(i32, i32, i32)> = transform_instructions
+        .selections
+        .iter()
+        .map(|&(start, end, step)| (shift_group_index(start), shift_group_index(end), step))
+        .collect();
+
+    let normalised_selections: Vec<(usize, usize)> = normalise_selections(
+        &shifted_selections,
+        groups.len(),
+        !transform_instructions.placeholders.is_empty(),
+        transform_instructions.strict_bounds,
+        transform_instructions.strict_range_order,
+    )?;
+
+    // With no selections we output the whole match, and invert never brings group 0 back in
+    let selections = if transform_instructions.selections.is_empty() {
+        vec![(0, 0)]
+    } else if !transform_instructions.invert {
+        normalised_selections
+    } else {
+        invert_selections(normalised_selections, groups.len())
+            .into_iter()
+            .filter(|&(_, end)| end >= 1)
+            .map(|(start, end)| (start.max(1), end))
+            .collect()
+    };
+
+    // Captures have no delimiters of their own, so join modes fall back to their defaults
+    let fields: Vec<Field> = groups
+        .iter()
+        .map(|group| Field {
+            start: group.map_or(0, |(start, _)| start),
+            text: group.map_or(b"", |(_, text)| text),
+            delimiter: b"",
+        })
+        .collect();
+
+    let mut output: Vec<u8> = Vec::with_capacity(text.len());
+    let mut strict_return_passed: bool = false;
+    let mut missing: usize = 0;
+
+    for (selection_index, selection) in selections.iter().enumerate() {
+        for group_index in selection.0..=selection.1 {
+            let mut piece: Vec<u8> = Vec::new();
+            let target = if transform_instructions.json {
+                &mut piece
+            } else {
+                &mut output
+            };
+            match groups.get(group_index) {
+                Some(Some((_, group_text))) => {
+                    if !group_text.is_empty() {
+                        push_encoded(target, group_text, transform_instructions.encoding);
+                        strict_return_passed = true;
+                    }
+                }
+                Some(None) | None => {
+                    if let Some(placeholder) =
+                        nth_placeholder(&transform_instructions.placeholders, missing)
+                    {
+                        target.extend_from_slice(placeholder);
+                        strict_return_passed = true;
+                        missing += 1;
+                    }
+                }
+            }
+
+            let is_last = selection_index == selections.len() - 1 && group_index == selection.1;
+            if transform_instructions.json {
+                push_json_string(&mut output, &piece);
+                if !is_last {
+                    output.push(b',');
+                }
+            } else if !is_last {
+                let join = choose_join_bytes(
+                    group_index,
+                    selection_index,
+                    &selections,
+                    &fields,
+                    transform_instructions.join.as_ref(),
+                    b"",
+                    b"",
+                    !transform_instructions.placeholders.is_empty(),
+                    transform_instructions.invert,
+                );
+                output.extend_from_slice(join);
+            }
+        }
+    }
+
+    if transform_instructions.strict_return && !strict_return_passed {
+        Err(SplitError::StrictReturn(
+            "strict-return error: no valid output".to_string(),
+        ))
+    } else {
+        Ok(Processed::Bytes(output))
+    }
+}
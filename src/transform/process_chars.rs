@@ -1,47 +1,58 @@
 use std::borrow::Cow;
 use unicode_segmentation::UnicodeSegmentation;
 
+use crate::error::SplitError;
 use crate::transform::transform_utilities::*;
 use crate::types::*;
 
 pub fn process_chars(
     transform_instructions: &TransformInstructions,
     record: Record,
-) -> Result<Vec<u8>, String> {
-    let text: Cow<str> =
-        match bytes_to_cow_string(&record.bytes, transform_instructions.strict_utf8) {
-            Ok(string) => string,
-            Err(e) => return Err(e),
-        };
+) -> Result<Processed, SplitError> {
+    let text: Cow<str> = bytes_to_cow_string(&record.bytes, transform_instructions.strict_utf8)?;
 
-    let graphemes: Vec<&str> = text.graphemes(true).collect();
-    let grapheme_count = graphemes.len();
+    // With --codepoints a combining mark is a character of its own, as in `cut -c`
+    let graphemes: Vec<&str> = match transform_instructions.codepoints {
+        true => text
+            .char_indices()
+            .map(|(start, character)| &text[start..start + character.len_utf8()])
+            .collect(),
+        false => text.graphemes(true).collect(),
+    };
 
     if transform_instructions.count {
-        return Ok(grapheme_count.to_string().into_bytes());
+        let count = match transform_instructions.count_unit {
+            Some(CountUnit::Bytes) => record.bytes.len(),
+            _ => graphemes.len(),
+        };
+        return Ok(Processed::Count(count));
     }
 
+    let graphemes: &[&str] = trim_graphemes(&graphemes, transform_instructions.trim);
+    let grapheme_count = graphemes.len();
+
     if grapheme_count == 0 {
         if transform_instructions.strict_return {
-            return Err("strict-return error: empty record".to_string());
+            return Err(SplitError::StrictReturn(
+                "strict-return error: empty record".to_string(),
+            ));
         }
         if transform_instructions.strict_bounds && !transform_instructions.selections.is_empty() {
-            return Err("strict-bounds error: empty record".to_string());
+            return Err(SplitError::StrictBounds(
+                "strict-bounds error: empty record".to_string(),
+            ));
         }
-        return Ok(Vec::new());
+        return Ok(Processed::Bytes(Vec::new()));
     }
 
     // Initial normalisation pass
-    let normalised_selections: Vec<(usize, usize)> = match normalise_selections(
+    let normalised_selections: Vec<(usize, usize)> = normalise_selections(
         &transform_instructions.selections,
         grapheme_count,
-        transform_instructions.placeholder.is_some(),
+        !transform_instructions.placeholders.is_empty(),
         transform_instructions.strict_bounds,
         transform_instructions.strict_range_order,
-    ) {
-        Ok(result) => result,
-        Err(error) => return Err(error),
-    };
+    )?;
 
     // Invert if applicable
     let selections = if transform_instructions.selections.is_empty() {
@@ -54,29 +65,59 @@ pub fn process_chars(
 
     // Make our real output
     let mut output: Vec<u8> = Vec::with_capacity(grapheme_count);
+    let mut strict_return_passed: bool = false;
+    let mut missing: usize = 0;
     for (index, selection) in selections.iter().enumerate() {
+        // JSON output gets one string per selection
+        let mut piece: Vec<u8> = Vec::new();
+        let target = if transform_instructions.json {
+            &mut piece
+        } else {
+            &mut output
+        };
         for i in selection.0..=selection.1 {
-            if i < grapheme_count {
-                output.extend_from_slice(graphemes[i].as_bytes());
-            } else if let Some(placeholder) = &transform_instructions.placeholder {
-                output.extend_from_slice(&placeholder);
+            if let Some(grapheme) = graphemes.get(i) {
+                target.extend_from_slice(grapheme.as_bytes());
+                strict_return_passed = true;
+            } else if let Some(placeholder) =
+                nth_placeholder(&transform_instructions.placeholders, missing)
+            {
+                target.extend_from_slice(placeholder);
+                strict_return_passed |= !placeholder.is_empty();
+                missing += 1;
+            }
+            if !(index == selections.len() - 1 && i == selection.1)
+                && let Some(JoinMode::String(join_bytes)) = &transform_instructions.join
+            {
+                target.extend_from_slice(join_bytes);
             }
-            if !(index == selections.len() - 1 && i == selection.1) {
-                match &transform_instructions.join {
-                    Some(JoinMode::String(join_bytes)) => {
-                        output.extend_from_slice(join_bytes);
-                    }
-                    Some(JoinMode::None) | _ => {
-                        // do nothing
-                    }
-                }
+        }
+        if transform_instructions.json {
+            if index > 0 {
+                output.push(b',');
             }
+            let mut encoded_piece: Vec<u8> = Vec::with_capacity(piece.len());
+            push_encoded(&mut encoded_piece, &piece, transform_instructions.encoding);
+            push_json_string(&mut output, &encoded_piece);
         }
     }
 
-    if transform_instructions.strict_return && output.is_empty() {
-        Err("strict-return error: no valid output".to_string())
+    // Outside JSON there are no field boundaries here, so the whole output is encoded
+    if transform_instructions.encoding.is_some() && !transform_instructions.json {
+        let mut encoded_output: Vec<u8> = Vec::with_capacity(output.len() * 2);
+        push_encoded(
+            &mut encoded_output,
+            &output,
+            transform_instructions.encoding,
+        );
+        output = encoded_output;
+    }
+
+    if transform_instructions.strict_return && !strict_return_passed {
+        Err(SplitError::StrictReturn(
+            "strict-return error: no valid output".to_string(),
+        ))
     } else {
-        Ok(output)
+        Ok(Processed::Bytes(output))
     }
 }
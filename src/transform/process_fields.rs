@@ -1,100 +1,420 @@
 use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 
+use regex::bytes::Regex as BytesRegex;
+
+use crate::error::SplitError;
 use crate::transform::transform_utilities::*;
 use crate::types::*;
 use crate::utilities::display_width;
 
+/// --redact: every field is kept in place with its delimiter, but selected fields are
+/// swapped for the placeholder.
+fn redact_fields(fields: &[Field], selections: &[(usize, usize)], placeholder: &[u8]) -> Vec<u8> {
+    let mut selected = vec![false; fields.len()];
+    for &(start, end) in selections {
+        for is_selected in selected.iter_mut().take(end + 1).skip(start) {
+            *is_selected = true;
+        }
+    }
+
+    let mut output: Vec<u8> = Vec::new();
+    for (field, is_selected) in fields.iter().zip(selected) {
+        match is_selected {
+            true => output.extend_from_slice(placeholder),
+            false => output.extend_from_slice(field.text),
+        }
+        output.extend_from_slice(field.delimiter);
+    }
+    output
+}
+
+/// --from/--to: the span of fields between the anchors, or `None` when one isn't found.
+/// The --to search starts after the --from field, so the same pattern can mark both ends.
+fn anchor_span(fields: &[Field], anchors: &FieldAnchors) -> Option<Vec<(usize, usize)>> {
+    let find = |regex: &BytesRegex, from: usize| {
+        (from..fields.len()).find(|&field_index| regex.is_match(fields[field_index].text))
+    };
+    let start = match &anchors.from {
+        Some(from) => find(from, 0)?,
+        None => 0,
+    };
+    let (start, end) = match &anchors.to {
+        Some(to) => (
+            start,
+            find(to, start + usize::from(anchors.from.is_some()))?,
+        ),
+        None => (start, fields.len() - 1),
+    };
+    let (start, end) = match anchors.exclusive {
+        true => (
+            start + usize::from(anchors.from.is_some()),
+            end.checked_sub(usize::from(anchors.to.is_some()))?,
+        ),
+        false => (start, end),
+    };
+    match start <= end {
+        true => Some(vec![(start, end)]),
+        false => Some(Vec::new()),
+    }
+}
+
+/// --offsets: each selected field as `start:length` in bytes from the start of the record,
+/// tab-separated. Fields past the end of the record are left out.
+fn field_offsets(fields: &[Field], selections: &[(usize, usize)]) -> Vec<u8> {
+    let mut output: Vec<u8> = Vec::new();
+    let selected_fields = selections
+        .iter()
+        .flat_map(|&(start, end)| start..=end)
+        .filter_map(|field_index| fields.get(field_index));
+    for field in selected_fields {
+        if !output.is_empty() {
+            output.push(b'\t');
+        }
+        output.extend_from_slice(format!("{}:{}", field.start, field.text.len()).as_bytes());
+    }
+    output
+}
+
+/// With no selections and nothing that reshapes fields, the output is the record as read.
+/// Whole-string mode is left out, as it drops a trailing delimiter.
+/// Every field is named here, so a new one won't build until it's either allowed through or
+/// required to be off. Only options that can't change an unselected record are allowed.
+fn is_pass_through(transform_instructions: &TransformInstructions) -> bool {
+    let TransformInstructions {
+        // Allowed: these only matter once something is selected, checked or transformed
+        selection_mode: _,
+        codepoints: _,
+        percent_selections: _,
+        json_paths: _,
+        invert: _,
+        comment_prefixes: _,
+        keep_trailing_empty: _,
+        field_length_basis: _,
+        dedup_key: _,
+        assert_sorted: _,
+        assert_uniform: _,
+        placeholders: _,
+        strict_bounds: _,
+        strict_range_order: _,
+        strict_utf8: _,
+        strict_require: _,
+        count_nonempty: _,
+        count_unit: _,
+        with_original: _,
+        original_separator: _,
+        regex_engine: _,
+        pad: _,
+        // Everything else has to be off
+        input_mode,
+        selections,
+        header,
+        join,
+        align,
+        skip_empty,
+        squash_delimiters,
+        trim,
+        only_delimited,
+        csv,
+        max_fields,
+        redact,
+        offsets,
+        unique,
+        sort,
+        reverse,
+        separators,
+        replacements,
+        field_pattern,
+        field_length,
+        count,
+        stats,
+        complement_count,
+        index_pair_separator,
+        json,
+        shell_quote,
+        encoding,
+        decoding,
+        transpose,
+        strict_return,
+        require,
+        pick,
+        anchors,
+    } = transform_instructions;
+
+    *input_mode != InputMode::WholeString
+        && selections.is_empty()
+        && header.is_none()
+        && join.is_none()
+        && *align == Align::None
+        && *trim == Trim::None
+        && max_fields.is_none()
+        && sort.is_none()
+        && separators.is_empty()
+        && replacements.is_empty()
+        && field_pattern.is_none()
+        && field_length.is_none()
+        && index_pair_separator.is_none()
+        && encoding.is_none()
+        && decoding.is_none()
+        && require.is_none()
+        && pick.is_none()
+        && anchors.is_none()
+        && !*skip_empty
+        && !*squash_delimiters
+        && !*only_delimited
+        && !*csv
+        && !*redact
+        && !*offsets
+        && !*unique
+        && !*reverse
+        && !*count
+        && !*stats
+        && !*complement_count
+        && !*json
+        && !*shell_quote
+        && !*transpose
+        && !*strict_return
+}
+
+/// Returns `None` when the record should be suppressed entirely.
 pub fn process_fields(
     transform_instructions: &TransformInstructions,
     engine: &RegexEngine,
     record: Record,
-) -> Result<Vec<u8>, String> {
-    let text: Cow<str> =
-        match bytes_to_cow_string(&record.bytes, transform_instructions.strict_utf8) {
-            Ok(string) => string,
-            Err(e) => return Err(e),
+) -> Result<Option<Processed>, SplitError> {
+    if let Some(require) = &transform_instructions.require
+        && !require.is_match(&record.bytes)
+    {
+        return match transform_instructions.strict_require {
+            true => Err(SplitError::Data(format!(
+                "require error: record does not match '{}'",
+                require.as_str()
+            ))),
+            false => Ok(None),
         };
+    }
 
-    let delimiter_len = match engine {
-        RegexEngine::Simple(regex) => regex.as_str().len(),
-        RegexEngine::Fancy(_) => 1,
-    };
-    let estimated_field_count = estimate_field_count(record.bytes.len(), delimiter_len);
-    let mut fields: Vec<Field> = Vec::with_capacity(estimated_field_count);
-    let mut cursor = 0usize;
-
-    match engine {
-        RegexEngine::Simple(engine) => {
-            for delimiter in engine.find_iter(&text) {
-                fields.push(Field {
-                    text: text[cursor..delimiter.start()].as_bytes(),
-                    delimiter: text[delimiter.start()..delimiter.end()].as_bytes(),
-                });
-                cursor = delimiter.end();
-            }
-        }
-        RegexEngine::Fancy(engine) => {
-            for delimiter_result in engine.find_iter(&text) {
-                match delimiter_result {
-                    Ok(delimiter) => {
-                        fields.push(Field {
-                            text: text[cursor..delimiter.start()].as_bytes(),
-                            delimiter: text[delimiter.start()..delimiter.end()].as_bytes(),
-                        });
-                        cursor = delimiter.end();
-                    }
-                    Err(error) => {
-                        return Err(format!("regex matching error: {}", error));
-                    }
-                }
-            }
-        }
+    // Bytes and chars are counted over the whole record, so it needn't be split
+    if transform_instructions.count
+        && let Some(count_unit @ (CountUnit::Bytes | CountUnit::Chars)) =
+            transform_instructions.count_unit
+    {
+        let count = count_record_units(
+            &record.bytes,
+            count_unit,
+            false,
+            transform_instructions.strict_utf8,
+        )?;
+        return Ok(Some(Processed::Count(count)));
     }
 
-    // Don't add an empty field at the end for whole-string
-    let final_text = text[cursor..text.len()].as_bytes();
-    if !final_text.is_empty() || transform_instructions.input_mode != InputMode::WholeString {
-        fields.push(Field {
-            text: text[cursor..text.len()].as_bytes(),
-            delimiter: b"",
-        });
+    let text: Cow<[u8]> = field_text(&record.bytes, engine, transform_instructions.strict_utf8)?;
+    if is_pass_through(transform_instructions) && matches!(text, Cow::Borrowed(_)) {
+        return Ok(Some(Processed::Bytes(record.bytes)));
     }
 
-    if transform_instructions.skip_empty {
-        fields = fields
-            .into_iter()
-            .filter(|field| !field.text.is_empty())
-            .collect();
+    // Replacement characters would shift every offset after them
+    if transform_instructions.offsets && matches!(text, Cow::Owned(_)) {
+        return Err(SplitError::InvalidUtf8(
+            "offsets error: record is not valid UTF-8".to_string(),
+        ));
+    }
+
+    let mut csv_values: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut fields: Vec<Field> = split_record_fields(
+        &text,
+        engine,
+        transform_instructions.input_mode,
+        transform_instructions.csv,
+        transform_instructions.max_fields,
+        transform_instructions.keep_trailing_empty,
+        &mut csv_values,
+    )?;
+
+    if transform_instructions.only_delimited
+        && fields.iter().all(|field| field.delimiter.is_empty())
+    {
+        return Ok(None);
+    }
+
+    if transform_instructions.squash_delimiters {
+        squash_delimiter_runs(&mut fields);
     }
 
+    // Squash collapses runs of delimiters by dropping the empty fields between them.
+    // --skip-empty looks at the field as --trim will leave it, so blank fields go too
+    let squash = transform_instructions.align == Align::Squash;
+    if transform_instructions.skip_empty || squash {
+        let trim = match transform_instructions.skip_empty {
+            true => transform_instructions.trim,
+            false => Trim::None,
+        };
+        fields.retain(|field| !trim_field(field.text, trim).is_empty());
+    }
+
+    // Picked before selecting, so positions count only the fields that are left
+    if let Some(pick) = &transform_instructions.pick {
+        fields.retain(|field| pick.is_match(field.text) != transform_instructions.invert);
+    }
+    // --invert has been spent on the pick
+    let invert = transform_instructions.invert && transform_instructions.pick.is_none();
+
     if transform_instructions.count {
-        let count = fields.len();
-        return Ok(count.to_string().into_bytes());
+        let count = if transform_instructions.count_nonempty {
+            fields.iter().filter(|field| !field.text.is_empty()).count()
+        } else {
+            fields.len()
+        };
+        return Ok(Some(Processed::Count(count)));
+    }
+
+    if transform_instructions.stats {
+        let mut stats = fields.len().to_string().into_bytes();
+        for field in &fields {
+            stats.push(b'\t');
+            stats.extend_from_slice(field.text.len().to_string().as_bytes());
+        }
+        return Ok(Some(Processed::Bytes(stats)));
+    }
+
+    // Trimmed after counting, so --count still sees the fields as given
+    if transform_instructions.trim != Trim::None {
+        for field in &mut fields {
+            let trimmed = trim_field(field.text, transform_instructions.trim);
+            if matches!(transform_instructions.trim, Trim::Left | Trim::Both) {
+                field.start += field.text.len() - trim_field(field.text, Trim::Left).len();
+            }
+            field.text = trimmed;
+        }
     }
 
     if fields.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    let normalised_selections: Vec<(usize, usize)> = match normalise_selections(
-        &transform_instructions.selections,
-        fields.len(),
-        transform_instructions.placeholder.is_some(),
-        transform_instructions.strict_bounds,
-        transform_instructions.strict_range_order,
-    ) {
-        Ok(result) => result,
-        Err(error) => return Err(error),
+        return Ok(Some(Processed::Bytes(
+            match transform_instructions.complement_count {
+                true => b"0".to_vec(),
+                false => Vec::new(),
+            },
+        )));
+    }
+
+    let normalised_selections: Vec<(usize, usize)> = match &transform_instructions.anchors {
+        Some(anchors) => match anchor_span(&fields, anchors) {
+            Some(span) => span,
+            None if transform_instructions.strict_bounds => {
+                return Err(SplitError::StrictBounds(
+                    "strict-bounds error: anchor field not found".to_string(),
+                ));
+            }
+            None => Vec::new(),
+        },
+        None => normalise_selections(
+            &resolve_percent_selections(
+                &transform_instructions.selections,
+                &transform_instructions.percent_selections,
+                fields.len(),
+            ),
+            fields.len(),
+            !transform_instructions.placeholders.is_empty(),
+            transform_instructions.strict_bounds,
+            transform_instructions.strict_range_order,
+        )?,
     };
 
-    let selections = if transform_instructions.selections.is_empty() {
+    let selections = if transform_instructions.selections.is_empty()
+        && transform_instructions.anchors.is_none()
+    {
         vec![(0, fields.len().saturating_sub(1))]
-    } else if !transform_instructions.invert {
+    } else if !invert {
         normalised_selections
     } else {
         invert_selections(normalised_selections, fields.len())
     };
 
+    // What's left out is the complement of the selections, so a field picked twice is still
+    // one field, and placeholder positions past the end are no field at all
+    if transform_instructions.complement_count {
+        let left_out: usize = invert_selections(selections, fields.len())
+            .iter()
+            .map(|(start, end)| end - start + 1)
+            .sum();
+        return Ok(Some(Processed::Bytes(left_out.to_string().into_bytes())));
+    }
+
+    if transform_instructions.redact {
+        return Ok(Some(Processed::Bytes(redact_fields(
+            &fields,
+            &selections,
+            single_placeholder(&transform_instructions.placeholders).unwrap_or(b""),
+        ))));
+    }
+
+    let selections = match &transform_instructions.field_pattern {
+        Some(pattern) => filter_selected_fields(
+            &mut fields,
+            selections,
+            single_placeholder(&transform_instructions.placeholders),
+            |text| first_capture(pattern, text),
+        )?,
+        None => selections,
+    };
+
+    let selections = match transform_instructions.field_length {
+        Some((min, max)) => filter_selected_fields(
+            &mut fields,
+            selections,
+            single_placeholder(&transform_instructions.placeholders),
+            |text| {
+                let length = field_length(text, transform_instructions.field_length_basis);
+                Ok((min..=max).contains(&length).then_some(text))
+            },
+        )?,
+        None => selections,
+    };
+
+    let selections = match transform_instructions.unique {
+        true => unique_selections(
+            &fields,
+            selections,
+            single_placeholder(&transform_instructions.placeholders),
+        ),
+        false => selections,
+    };
+
+    let selections = match transform_instructions.sort {
+        Some(sort) => sort_selections(
+            &fields,
+            selections,
+            single_placeholder(&transform_instructions.placeholders),
+            sort,
+        ),
+        None => selections,
+    };
+
+    // Each field gets its own selection, so ranges are reversed as well
+    let selections: Vec<(usize, usize)> = match transform_instructions.reverse {
+        true => selections
+            .iter()
+            .flat_map(|(start, end)| *start..=*end)
+            .rev()
+            .map(|field_index| (field_index, field_index))
+            .collect(),
+        false => selections,
+    };
+
+    if selections.is_empty() {
+        if transform_instructions.strict_return {
+            return Err(SplitError::StrictReturn(
+                "strict-return error: no valid output".to_string(),
+            ));
+        }
+        return Ok(Some(Processed::Bytes(Vec::new())));
+    }
+
+    if transform_instructions.offsets {
+        return Ok(Some(Processed::Bytes(field_offsets(&fields, &selections))));
+    }
+
     let estimated_output_size = estimate_output_size(record.bytes.len(), selections.len());
     let mut output: Vec<u8> = Vec::with_capacity(estimated_output_size);
     let mut strict_return_passed: bool = false;
@@ -112,31 +432,66 @@ pub fn process_fields(
         .map(|field| field.delimiter)
         .unwrap_or(b"");
 
-    let align_active = transform_instructions.align != Align::None;
+    let align_active = !matches!(
+        transform_instructions.align,
+        Align::None | Align::Squash | Align::Tabs
+    );
+    let join_mode = match &transform_instructions.join {
+        None if squash => Some(&JoinMode::Space),
+        join => join.as_ref(),
+    };
     let mut field_position: usize = 0;
+    let mut missing: usize = 0;
 
     for (selection_index, selection) in selections.iter().enumerate() {
         for field_index in selection.0..=selection.1 {
             // Skip if there's no data in this field
             let has_data = field_index < fields.len()
-                || (transform_instructions.placeholder.is_some() && !transform_instructions.invert);
+                || (!transform_instructions.placeholders.is_empty() && !invert);
 
             if !has_data {
                 continue;
             }
 
-            let push_text = |output: &mut Vec<u8>, strict_return_passed: &mut bool| {
-                if field_index < fields.len() {
-                    if !fields[field_index].text.is_empty() {
-                        output.extend_from_slice(fields[field_index].text);
-                        *strict_return_passed = true;
-                    }
-                } else if let Some(placeholder) = &transform_instructions.placeholder {
-                    output.extend_from_slice(placeholder);
-                    *strict_return_passed = true;
+            let placeholder = match field_index < fields.len() {
+                true => None,
+                false => {
+                    missing += 1;
+                    nth_placeholder(&transform_instructions.placeholders, missing - 1)
                 }
             };
 
+            let push_text =
+                |output: &mut Vec<u8>, strict_return_passed: &mut bool| -> Result<(), SplitError> {
+                    if let Some(separator) = &transform_instructions.index_pair_separator {
+                        output.extend_from_slice((field_index + 1).to_string().as_bytes());
+                        output.extend_from_slice(separator);
+                    }
+                    let text_start = output.len();
+                    if field_index < fields.len() {
+                        if !fields[field_index].text.is_empty() {
+                            let decoded: Vec<u8>;
+                            let text = match transform_instructions.decoding {
+                                Some(decoding) => {
+                                    decoded = decode_bytes(fields[field_index].text, decoding)?;
+                                    &decoded
+                                }
+                                None => fields[field_index].text,
+                            };
+                            push_encoded(output, text, transform_instructions.encoding);
+                            *strict_return_passed = true;
+                        }
+                    } else if let Some(placeholder) = placeholder {
+                        output.extend_from_slice(placeholder);
+                        *strict_return_passed = true;
+                    }
+                    if transform_instructions.shell_quote {
+                        let text = output.split_off(text_start);
+                        push_shell_quoted(output, &text);
+                    }
+                    Ok(())
+                };
+
             let is_last = selection_index == selections.len() - 1 && field_index == selection.1;
 
             if align_active {
@@ -152,7 +507,7 @@ pub fn process_fields(
                 };
                 let current_field_width = if field_index < fields.len() {
                     display_width(fields[field_index].text)
-                } else if let Some(placeholder) = &transform_instructions.placeholder {
+                } else if let Some(placeholder) = placeholder {
                     display_width(placeholder)
                 } else {
                     0
@@ -161,29 +516,33 @@ pub fn process_fields(
 
                 let push_padding = |output: &mut Vec<u8>, n: usize| {
                     for _ in 0..n {
-                        output.push(b' ');
+                        output.extend_from_slice(&transform_instructions.pad);
                     }
                 };
 
-                if transform_instructions.align == Align::Right {
-                    push_padding(&mut output, padding_needed);
-                }
-                push_text(&mut output, &mut strict_return_passed);
+                // Center splits the padding, with any odd column going after the text
+                let padding_before = match transform_instructions.align {
+                    Align::Right => padding_needed,
+                    Align::Center => padding_needed / 2,
+                    _ => 0,
+                };
+                push_padding(&mut output, padding_before);
+                push_text(&mut output, &mut strict_return_passed)?;
 
                 if !is_last {
-                    if transform_instructions.align == Align::Left {
-                        push_padding(&mut output, padding_needed);
+                    if matches!(transform_instructions.align, Align::Left | Align::Center) {
+                        push_padding(&mut output, padding_needed - padding_before);
                     }
                     let join = choose_join_bytes(
                         field_index,
                         selection_index,
                         &selections,
                         &fields,
-                        transform_instructions.join.as_ref(),
+                        join_mode,
                         first_delimiter,
                         last_delimiter,
-                        transform_instructions.placeholder.is_some(),
-                        transform_instructions.invert,
+                        !transform_instructions.placeholders.is_empty(),
+                        invert,
                     );
                     output.extend_from_slice(join);
                     let join_width = display_width(join);
@@ -195,34 +554,306 @@ pub fn process_fields(
                             push_padding(&mut output, max_join_width - join_width);
                         }
                     }
-                    if transform_instructions.align == Align::Squash {
-                        push_padding(&mut output, padding_needed);
-                    }
                 }
                 field_position += 1;
+            } else if transform_instructions.json {
+                let mut piece: Vec<u8> = Vec::new();
+                push_text(&mut piece, &mut strict_return_passed)?;
+                push_json_string(&mut output, &piece);
+                if !is_last {
+                    output.push(b',');
+                }
             } else {
-                push_text(&mut output, &mut strict_return_passed);
+                push_text(&mut output, &mut strict_return_passed)?;
                 if !is_last {
-                    let join = choose_join_bytes(
-                        field_index,
-                        selection_index,
-                        &selections,
-                        &fields,
-                        transform_instructions.join.as_ref(),
-                        first_delimiter,
-                        last_delimiter,
-                        transform_instructions.placeholder.is_some(),
-                        transform_instructions.invert,
-                    );
+                    let reversed_join = match (transform_instructions.reverse, join_mode) {
+                        (true, None | Some(JoinMode::Auto)) => selections
+                            .get(selection_index + 1)
+                            .and_then(|next| reversed_delimiter(&fields, field_index, next.0)),
+                        _ => None,
+                    };
+                    let join = match transform_instructions.separators.get(field_position) {
+                        Some(separator) => Some(separator.as_slice()),
+                        None => reversed_join,
+                    };
+                    let join = join.unwrap_or_else(|| {
+                        choose_join_bytes(
+                            field_index,
+                            selection_index,
+                            &selections,
+                            &fields,
+                            join_mode,
+                            first_delimiter,
+                            last_delimiter,
+                            !transform_instructions.placeholders.is_empty(),
+                            invert,
+                        )
+                    });
+                    // Only delimiters kept from the input are remapped, never a join given instead
+                    let join = match (
+                        join_mode,
+                        transform_instructions.separators.get(field_position),
+                    ) {
+                        (None, None) => {
+                            replace_delimiter(join, &transform_instructions.replacements)
+                        }
+                        _ => join,
+                    };
                     output.extend_from_slice(join);
                 }
+                field_position += 1;
             }
         }
     }
 
     if transform_instructions.strict_return && !strict_return_passed {
-        Err("strict-return error: no valid output".to_string())
+        Err(SplitError::StrictReturn(
+            "strict-return error: no valid output".to_string(),
+        ))
     } else {
-        Ok(output)
+        Ok(Some(Processed::Bytes(output)))
+    }
+}
+
+/// Returns the first capture group of `pattern` in `text`, or the whole match if it has none.
+fn first_capture<'t>(
+    pattern: &RegexEngine,
+    text: &'t [u8],
+) -> Result<Option<&'t [u8]>, SplitError> {
+    match pattern {
+        RegexEngine::Simple(regex) => Ok(regex.captures(text).and_then(|captures| {
+            captures
+                .get(1)
+                .or_else(|| captures.get(0))
+                .map(|group| group.as_bytes())
+        })),
+        RegexEngine::Fancy(regex) => {
+            // fancy-regex only matches strings, so a field that isn't valid UTF-8 never matches
+            let Ok(text) = std::str::from_utf8(text) else {
+                return Ok(None);
+            };
+            match regex.captures(text) {
+                Ok(captures) => Ok(captures.and_then(|captures| {
+                    captures
+                        .get(1)
+                        .or_else(|| captures.get(0))
+                        .map(|group| group.as_str().as_bytes())
+                })),
+                Err(error) => Err(SplitError::Regex(format!(
+                    "regex matching error: {}",
+                    error
+                ))),
+            }
+        }
+        RegexEngine::Literal(_) | RegexEngine::Byte(_) => Err(SplitError::Data(
+            "internal error: field pattern needs a regex engine".to_string(),
+        )),
+    }
+}
+
+/// Replaces each selected field with what `filter` returns for it, as used by --field-pattern
+/// and --field-length. Fields it rejects take the placeholder, or are dropped by splitting
+/// them out of the selections.
+fn filter_selected_fields<'a>(
+    fields: &mut [Field<'a>],
+    selections: Vec<(usize, usize)>,
+    placeholder: Option<&'a [u8]>,
+    mut filter: impl FnMut(&'a [u8]) -> Result<Option<&'a [u8]>, SplitError>,
+) -> Result<Vec<(usize, usize)>, SplitError> {
+    let mut kept_selections: Vec<(usize, usize)> = Vec::with_capacity(selections.len());
+    // A field can be selected more than once, so remember whether each one was kept
+    let mut extracted: Vec<Option<bool>> = vec![None; fields.len()];
+
+    for (start, end) in selections {
+        let mut run_start: Option<usize> = None;
+        for field_index in start..=end {
+            let keep = match (fields.get_mut(field_index), extracted.get_mut(field_index)) {
+                (Some(_), Some(Some(kept))) => *kept,
+                (Some(field), Some(slot)) => {
+                    let keep = match filter(field.text)? {
+                        Some(text) => {
+                            field.text = text;
+                            true
+                        }
+                        None => match placeholder {
+                            Some(placeholder) => {
+                                field.text = placeholder;
+                                true
+                            }
+                            None => false,
+                        },
+                    };
+                    *slot = Some(keep);
+                    keep
+                }
+                // Out-of-range fields are left for the placeholder handling further on
+                _ => true,
+            };
+
+            match (keep, run_start) {
+                (true, None) => run_start = Some(field_index),
+                (false, Some(run)) => {
+                    kept_selections.push((run, field_index - 1));
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(run) = run_start {
+            kept_selections.push((run, end));
+        }
+    }
+
+    Ok(kept_selections)
+}
+
+/// Splits fields out of the selections when the same bytes were already selected earlier
+/// in the record, for --unique. Out-of-range fields count as the placeholder.
+fn unique_selections(
+    fields: &[Field],
+    selections: Vec<(usize, usize)>,
+    placeholder: Option<&[u8]>,
+) -> Vec<(usize, usize)> {
+    let mut kept_selections: Vec<(usize, usize)> = Vec::with_capacity(selections.len());
+    let mut seen: HashSet<&[u8]> = HashSet::new();
+
+    for (start, end) in selections {
+        let mut run_start: Option<usize> = None;
+        for field_index in start..=end {
+            let text = fields
+                .get(field_index)
+                .map(|field| field.text)
+                .or(placeholder);
+            let keep = match text {
+                Some(text) => seen.insert(text),
+                None => true,
+            };
+
+            match (keep, run_start) {
+                (true, None) => run_start = Some(field_index),
+                (false, Some(run)) => {
+                    kept_selections.push((run, field_index - 1));
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(run) = run_start {
+            kept_selections.push((run, end));
+        }
+    }
+
+    kept_selections
+}
+
+/// With --reverse, neighbours are joined by the delimiter that followed the earlier of the two
+/// in the record, which is the one originally between them when they were adjacent.
+fn reversed_delimiter<'a>(
+    fields: &[Field<'a>],
+    field_index: usize,
+    next_index: usize,
+) -> Option<&'a [u8]> {
+    let delimiter = fields.get(field_index.min(next_index))?.delimiter;
+    (!delimiter.is_empty()).then_some(delimiter)
+}
+
+/// Reorders the selected fields for --sort, giving each its own selection. Out-of-range
+/// fields sort as the placeholder, or are left out when there isn't one.
+fn sort_selections(
+    fields: &[Field],
+    selections: Vec<(usize, usize)>,
+    placeholder: Option<&[u8]>,
+    sort: FieldSort,
+) -> Vec<(usize, usize)> {
+    let mut selected: Vec<(usize, &[u8])> = selections
+        .iter()
+        .flat_map(|(start, end)| *start..=*end)
+        .filter_map(|field_index| {
+            let text = fields
+                .get(field_index)
+                .map(|field| field.text)
+                .or(placeholder)?;
+            Some((field_index, text))
+        })
+        .collect();
+
+    selected.sort_by(|(_, left), (_, right)| {
+        let ordering = match sort.numeric {
+            true => match (leading_number(left), leading_number(right)) {
+                (Some(left_number), Some(right_number)) => left_number
+                    .total_cmp(&right_number)
+                    .then_with(|| left.cmp(right)),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => left.cmp(right),
+            },
+            false => left.cmp(right),
+        };
+        match sort.reverse {
+            true => ordering.reverse(),
+            false => ordering,
+        }
+    });
+
+    selected
+        .into_iter()
+        .map(|(field_index, _)| (field_index, field_index))
+        .collect()
+}
+
+/// Splits a record the same way process_fields does, for the per-record keys and counts.
+fn split_key_fields<'a>(
+    transform_instructions: &TransformInstructions,
+    engine: &'a RegexEngine,
+    text: &'a [u8],
+    csv_values: &'a mut Vec<(usize, Vec<u8>)>,
+) -> Result<Vec<Field<'a>>, SplitError> {
+    let mut fields: Vec<Field> = split_record_fields(
+        text,
+        engine,
+        transform_instructions.input_mode,
+        transform_instructions.csv,
+        transform_instructions.max_fields,
+        transform_instructions.keep_trailing_empty,
+        csv_values,
+    )?;
+    if transform_instructions.squash_delimiters {
+        squash_delimiter_runs(&mut fields);
+    }
+    if transform_instructions.skip_empty {
+        fields.retain(|field| !field.text.is_empty());
     }
+    Ok(fields)
+}
+
+/// Returns the number of fields in a record, used by --assert-uniform.
+pub fn get_field_count(
+    transform_instructions: &TransformInstructions,
+    engine: &RegexEngine,
+    bytes: &[u8],
+) -> Result<usize, SplitError> {
+    let text: Cow<[u8]> = field_text(bytes, engine, transform_instructions.strict_utf8)?;
+    let mut csv_values: Vec<(usize, Vec<u8>)> = Vec::new();
+    Ok(split_key_fields(transform_instructions, engine, &text, &mut csv_values)?.len())
+}
+
+/// Returns the text of a single field, used as the --field-dedup-key.
+/// A field that doesn't exist gives an empty key.
+pub fn get_key_field(
+    transform_instructions: &TransformInstructions,
+    engine: &RegexEngine,
+    bytes: &[u8],
+    raw_index: i32,
+) -> Result<Vec<u8>, SplitError> {
+    let text: Cow<[u8]> = field_text(bytes, engine, transform_instructions.strict_utf8)?;
+    let mut csv_values: Vec<(usize, Vec<u8>)> = Vec::new();
+    let fields = split_key_fields(transform_instructions, engine, &text, &mut csv_values)?;
+
+    let index = resolve_index(raw_index, fields.len())?;
+    let key = usize::try_from(index)
+        .ok()
+        .and_then(|index| fields.get(index))
+        .map(|field| field.text.to_vec())
+        .unwrap_or_default();
+    Ok(key)
 }
@@ -0,0 +1,80 @@
+use serde_json::Value;
+
+use crate::error::SplitError;
+use crate::transform::transform_utilities::*;
+use crate::types::*;
+
+/// Walks a dotted path down from `root`. Object keys are taken as written, and a segment
+/// indexes an array when it is a number. Anything else is missing.
+fn lookup<'a>(root: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(root, |value, segment| match value {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+pub fn process_json(
+    transform_instructions: &TransformInstructions,
+    record: Record,
+) -> Result<Vec<u8>, SplitError> {
+    // A blank line has nothing to select from, so every path is missing rather than an error
+    let root: Option<Value> = match record.bytes.trim_ascii().is_empty() {
+        true => None,
+        false => Some(
+            serde_json::from_slice(&record.bytes)
+                .map_err(|error| SplitError::Data(format!("invalid JSON: {error}")))?,
+        ),
+    };
+
+    // Values have no delimiters between them, so the default join is a space as in capture mode
+    let join: &[u8] = match &transform_instructions.join {
+        Some(JoinMode::String(join_bytes)) => join_bytes,
+        _ => b" ",
+    };
+
+    let mut output: Vec<u8> = Vec::with_capacity(record.bytes.len());
+    let mut strict_return_passed: bool = false;
+    let mut missing: usize = 0;
+
+    for (path_index, path) in transform_instructions.json_paths.iter().enumerate() {
+        let mut piece: Vec<u8> = Vec::new();
+        match root.as_ref().and_then(|root| lookup(root, path)) {
+            // Strings come out raw, everything else as compact JSON
+            Some(Value::String(text)) => piece.extend_from_slice(text.as_bytes()),
+            Some(value) => piece.extend_from_slice(value.to_string().as_bytes()),
+            None => {
+                if let Some(placeholder) =
+                    nth_placeholder(&transform_instructions.placeholders, missing)
+                {
+                    piece.extend_from_slice(placeholder);
+                    strict_return_passed = true;
+                    missing += 1;
+                }
+            }
+        }
+        if !piece.is_empty() {
+            strict_return_passed = true;
+        }
+
+        if transform_instructions.json {
+            push_json_string(&mut output, &piece);
+            if path_index + 1 < transform_instructions.json_paths.len() {
+                output.push(b',');
+            }
+        } else {
+            push_encoded(&mut output, &piece, transform_instructions.encoding);
+            if path_index + 1 < transform_instructions.json_paths.len() {
+                output.extend_from_slice(join);
+            }
+        }
+    }
+
+    if transform_instructions.strict_return && !strict_return_passed {
+        Err(SplitError::StrictReturn(
+            "strict-return error: no valid output".to_string(),
+        ))
+    } else {
+        Ok(output)
+    }
+}
@@ -0,0 +1,99 @@
+use std::borrow::Cow;
+
+use crate::error::SplitError;
+use crate::transform::transform_utilities::*;
+use crate::types::*;
+
+/// Reads the whole-string record as a table, one row per line, and emits its columns as rows.
+/// Selections pick columns, and short rows are padded with the placeholder or left empty.
+pub fn process_transpose(
+    transform_instructions: &TransformInstructions,
+    engine: &RegexEngine,
+    record: Record,
+) -> Result<Vec<u8>, SplitError> {
+    let text: Cow<str> = bytes_to_cow_string(&record.bytes, transform_instructions.strict_utf8)?;
+
+    let mut rows: Vec<Vec<Vec<u8>>> = Vec::new();
+    let mut first_delimiter: Option<Vec<u8>> = None;
+    let mut last_delimiter: Option<Vec<u8>> = None;
+    for line in text.lines() {
+        // Each line splits like a per-line record, so a trailing delimiter leaves an empty field
+        let mut csv_values: Vec<(usize, Vec<u8>)> = Vec::new();
+        let mut fields: Vec<Field> = split_record_fields(
+            line.as_bytes(),
+            engine,
+            InputMode::PerLine,
+            transform_instructions.csv,
+            transform_instructions.max_fields,
+            false,
+            &mut csv_values,
+        )?;
+
+        if let Some(field) = fields.iter().find(|field| !field.delimiter.is_empty()) {
+            first_delimiter.get_or_insert_with(|| field.delimiter.to_vec());
+        }
+        if let Some(field) = fields.iter().rfind(|field| !field.delimiter.is_empty()) {
+            last_delimiter = Some(field.delimiter.to_vec());
+        }
+
+        if transform_instructions.squash_delimiters {
+            squash_delimiter_runs(&mut fields);
+        }
+        if transform_instructions.skip_empty {
+            fields.retain(|field| !trim_field(field.text, transform_instructions.trim).is_empty());
+        }
+        rows.push(
+            fields
+                .iter()
+                .map(|field| trim_field(field.text, transform_instructions.trim).to_vec())
+                .collect(),
+        );
+    }
+
+    let column_count = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    if column_count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let normalised_selections: Vec<(usize, usize)> = normalise_selections(
+        &transform_instructions.selections,
+        column_count,
+        !transform_instructions.placeholders.is_empty(),
+        transform_instructions.strict_bounds,
+        transform_instructions.strict_range_order,
+    )?;
+
+    let selections = if transform_instructions.selections.is_empty() {
+        vec![(0, column_count - 1)]
+    } else if !transform_instructions.invert {
+        normalised_selections
+    } else {
+        invert_selections(normalised_selections, column_count)
+    };
+
+    // Cells in a row are joined like fields, by default with the table's own delimiter
+    let join: &[u8] = match &transform_instructions.join {
+        Some(JoinMode::String(join)) => join,
+        Some(JoinMode::Space) => b" ",
+        Some(JoinMode::None) => b"",
+        Some(JoinMode::Last) => last_delimiter.as_deref().unwrap_or(b""),
+        _ => first_delimiter.as_deref().unwrap_or(b""),
+    };
+    let pad: &[u8] = single_placeholder(&transform_instructions.placeholders).unwrap_or(b"");
+
+    let mut output: Vec<u8> = Vec::with_capacity(record.bytes.len() + rows.len());
+    let columns = selections.iter().flat_map(|&(start, end)| start..=end);
+    for (output_index, column) in columns.enumerate() {
+        if output_index > 0 {
+            output.push(b'\n');
+        }
+        for (row_index, row) in rows.iter().enumerate() {
+            if row_index > 0 {
+                output.extend_from_slice(join);
+            }
+            output.extend_from_slice(row.get(column).map_or(pad, |cell| cell.as_slice()));
+        }
+    }
+
+    Ok(output)
+}
@@ -1,25 +1,64 @@
 use std::borrow::Cow;
 
-use crate::types::JoinMode;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::SplitError;
+use crate::types::{
+    CountUnit, Encoding, InputMode, JoinMode, LengthBasis, PercentSelection, RegexEngine, Trim,
+};
+use crate::utilities::display_width;
 
 /// From Bytes to Cow string
-pub fn bytes_to_cow_string<'a>(bytes: &'a [u8], strict_utf8: bool) -> Result<Cow<'a, str>, String> {
+pub fn bytes_to_cow_string<'a>(
+    bytes: &'a [u8],
+    strict_utf8: bool,
+) -> Result<Cow<'a, str>, SplitError> {
     match std::str::from_utf8(bytes) {
         Ok(string) => Ok(Cow::Borrowed(string)),
         Err(_) => match strict_utf8 {
             false => Ok(Cow::Owned(String::from_utf8_lossy(bytes).into_owned())),
-            true => Err("strict-utf8 error: input is not valid UTF-8".to_string()),
+            true => Err(SplitError::InvalidUtf8(
+                "strict-utf8 error: input is not valid UTF-8".to_string(),
+            )),
         },
     }
 }
 
+/// The record bytes that fields are split from. A byte regex or literal splits the record as
+/// it is, so stray bytes come through untouched. fancy-regex only matches strings, so for it
+/// invalid UTF-8 is still replaced. Under --strict-utf8 it's an error either way.
+pub fn field_text<'a>(
+    bytes: &'a [u8],
+    engine: &RegexEngine,
+    strict_utf8: bool,
+) -> Result<Cow<'a, [u8]>, SplitError> {
+    if !strict_utf8 && !matches!(engine, RegexEngine::Fancy(_)) {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    Ok(match bytes_to_cow_string(bytes, strict_utf8)? {
+        Cow::Borrowed(text) => Cow::Borrowed(text.as_bytes()),
+        Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+    })
+}
+
+/// True if the record starts with one of the --comment prefixes, after any leading spaces or tabs.
+pub fn is_comment(bytes: &[u8], comment_prefixes: &[Vec<u8>]) -> bool {
+    let content_start = bytes
+        .iter()
+        .position(|byte| *byte != b' ' && *byte != b'\t')
+        .unwrap_or(bytes.len());
+    comment_prefixes
+        .iter()
+        .any(|prefix| bytes[content_start..].starts_with(prefix))
+}
+
 /// Rough capacity hint for field buffers.
 pub fn estimate_field_count(input_len: usize, delimiter_len: usize) -> usize {
     if input_len == 0 {
         return 1;
     }
     let estimated = input_len / 50.max(delimiter_len + 10);
-    estimated.max(1).min(10000)
+    estimated.clamp(1, 10000)
 }
 
 /// Rough capacity hint for output buffers.
@@ -30,22 +69,132 @@ pub fn estimate_output_size(input_len: usize, selection_count: usize) -> usize {
     (input_len * 2 / selection_count.max(1)).max(input_len / 4)
 }
 
-pub fn resolve_index(raw_index: i32, len: usize) -> Result<i32, String> {
+pub fn resolve_index(raw_index: i32, len: usize) -> Result<i32, SplitError> {
     if raw_index > 0 {
         Ok(raw_index - 1)
     } else {
         const MAX_SAFE_LEN: usize = i32::MAX as usize;
         if len > MAX_SAFE_LEN {
-            return Err(format!(
+            return Err(SplitError::Data(format!(
                 "input too large: {} fields exceeds maximum of {} fields. \
                 negative indices cannot be resolved for inputs this large",
                 len, MAX_SAFE_LEN
-            ));
+            )));
         }
         Ok(len as i32 + raw_index)
     }
 }
 
+/// The bytes or characters in a whole record, for --count-unit. Fields are counted by the
+/// caller, as they need splitting first.
+pub fn count_record_units(
+    bytes: &[u8],
+    count_unit: CountUnit,
+    codepoints: bool,
+    strict_utf8: bool,
+) -> Result<usize, SplitError> {
+    match count_unit {
+        CountUnit::Bytes => Ok(bytes.len()),
+        CountUnit::Chars => {
+            let text = bytes_to_cow_string(bytes, strict_utf8)?;
+            Ok(match codepoints {
+                true => text.chars().count(),
+                false => text.graphemes(true).count(),
+            })
+        }
+        CountUnit::Fields => Err(SplitError::Data(
+            "internal error: fields can't be counted before splitting".to_string(),
+        )),
+    }
+}
+
+/// --replace: the `to` of the first pair whose `from` is exactly this delimiter, otherwise the
+/// delimiter as it was.
+pub fn replace_delimiter<'a>(
+    delimiter: &'a [u8],
+    replacements: &'a [(Vec<u8>, Vec<u8>)],
+) -> &'a [u8] {
+    replacements
+        .iter()
+        .find(|(from, _)| from == delimiter)
+        .map_or(delimiter, |(_, to)| to)
+}
+
+/// The placeholder for the `missing`th selection past the end of a record, counting from 0.
+/// Each --placeholder is used in turn, and the last one repeats.
+pub fn nth_placeholder(placeholders: &[Vec<u8>], missing: usize) -> Option<&[u8]> {
+    placeholders
+        .get(missing)
+        .or(placeholders.last())
+        .map(Vec::as_slice)
+}
+
+/// The placeholder where one value stands for every field, as with --redact. It's the last
+/// --placeholder, so a later one overrides an earlier one.
+pub fn single_placeholder(placeholders: &[Vec<u8>]) -> Option<&[u8]> {
+    placeholders.last().map(Vec::as_slice)
+}
+
+/// Fills each percent selection's slot for a record of `length` fields. A percent is rounded
+/// half up to a field number and clamped to the record, so `p0` is the first field and `p100`
+/// the last.
+pub fn resolve_percent_selections<'a>(
+    selections: &'a [(i32, i32, i32)],
+    percent_selections: &[PercentSelection],
+    length: usize,
+) -> Cow<'a, [(i32, i32, i32)]> {
+    if percent_selections.is_empty() {
+        return Cow::Borrowed(selections);
+    }
+    let field = |percent: u32| {
+        let rounded = (length.saturating_mul(percent as usize) + 50) / 100;
+        rounded.clamp(1, length.clamp(1, i32::MAX as usize)) as i32
+    };
+    let mut resolved = selections.to_vec();
+    for percent in percent_selections {
+        resolved[percent.slot] = (field(percent.start), field(percent.end), 1);
+    }
+    Cow::Owned(resolved)
+}
+
+/// Errors if either end of a resolved selection falls outside the record.
+fn check_strict_bounds(
+    raw_start: i32,
+    raw_end: i32,
+    start: i32,
+    end: i32,
+    length: usize,
+) -> Result<(), SplitError> {
+    if length == 0 {
+        return Err(SplitError::StrictBounds(
+            "strict-bounds error: no valid fields to select".to_string(),
+        ));
+    }
+
+    let is_single_index = raw_start == raw_end;
+
+    if start < 0 || start >= length as i32 {
+        if is_single_index {
+            return Err(SplitError::StrictBounds(format!(
+                "strict-bounds error: index ({}) out of bounds, must be between 1 and {}",
+                raw_start, length
+            )));
+        } else {
+            return Err(SplitError::StrictBounds(format!(
+                "strict-bounds error: start index ({}) out of bounds, must be between 1 and {}",
+                raw_start, length
+            )));
+        }
+    }
+    if end < 0 || end >= length as i32 {
+        return Err(SplitError::StrictBounds(format!(
+            "strict-bounds error: end index ({}) out of bounds, must be between 1 and {}",
+            raw_end, length
+        )));
+    }
+    Ok(())
+}
+
 /// Parse and validate a selection range.
 pub fn normalise_selection(
     raw_start: i32,
@@ -54,9 +203,11 @@ pub fn normalise_selection(
     is_placeholder: bool,
     strict_bounds: bool,
     strict_range_order: bool,
-) -> Result<Option<(usize, usize)>, String> {
+) -> Result<Option<(usize, usize)>, SplitError> {
     if strict_bounds && (raw_start == 0 || raw_end == 0) {
-        return Err(format!("selections are 1-based, 0 is an invalid index"));
+        return Err(SplitError::BadSelection(
+            "selections are 1-based, 0 is an invalid index".to_string(),
+        ));
     }
 
     let start = resolve_index(raw_start, length)?;
@@ -65,10 +216,10 @@ pub fn normalise_selection(
     if start > end {
         match strict_range_order {
             true => {
-                return Err(format!(
+                return Err(SplitError::BadSelection(format!(
                     "strict-range-order error: end index ({}) is less than start index ({}) in selection {}-{}",
                     raw_end, raw_start, raw_start, raw_end
-                ));
+                )));
             }
             false => {
                 return Ok(None);
@@ -77,31 +228,7 @@ pub fn normalise_selection(
     }
 
     if strict_bounds {
-        if length == 0 {
-            return Err(format!("strict-bounds error: no valid fields to select"));
-        }
-
-        let is_single_index = raw_start == raw_end;
-
-        if start < 0 || start >= length as i32 {
-            if is_single_index {
-                return Err(format!(
-                    "strict-bounds error: index ({}) out of bounds, must be between 1 and {}",
-                    raw_start, length
-                ));
-            } else {
-                return Err(format!(
-                    "strict-bounds error: start index ({}) out of bounds, must be between 1 and {}",
-                    raw_start, length
-                ));
-            }
-        }
-        if end < 0 || end >= length as i32 {
-            return Err(format!(
-                "strict-bounds error: end index ({}) out of bounds, must be between 1 and {}",
-                raw_end, length
-            ));
-        }
+        check_strict_bounds(raw_start, raw_end, start, end, length)?;
         Ok(Some((start as usize, end as usize)))
     } else {
         if end < 0 {
@@ -123,15 +250,75 @@ pub fn normalise_selection(
     }
 }
 
+/// Parse and validate a stepped selection range, expanding it into single-index selections.
+/// A negative step walks from start down to end.
+pub fn expand_stepped_selection(
+    (raw_start, raw_end, step): (i32, i32, i32),
+    length: usize,
+    is_placeholder: bool,
+    strict_bounds: bool,
+    strict_range_order: bool,
+) -> Result<Vec<(usize, usize)>, SplitError> {
+    if strict_bounds && (raw_start == 0 || raw_end == 0) {
+        return Err(SplitError::BadSelection(
+            "selections are 1-based, 0 is an invalid index".to_string(),
+        ));
+    }
+
+    let start = resolve_index(raw_start, length)?;
+    let end = resolve_index(raw_end, length)?;
+
+    if (step > 0 && start > end) || (step < 0 && start < end) {
+        if strict_range_order {
+            let comparison = if step > 0 { "less" } else { "greater" };
+            return Err(SplitError::BadSelection(format!(
+                "strict-range-order error: end index ({}) is {} than start index ({}) in selection {}-{}:{}",
+                raw_end, comparison, raw_start, raw_start, raw_end, step
+            )));
+        }
+        return Ok(Vec::new());
+    }
+
+    if strict_bounds {
+        check_strict_bounds(raw_start, raw_end, start, end, length)?;
+    }
+    // A start before the first field is clamped to it, as unstepped ranges are, so the
+    // steps are counted from the first field rather than from outside the record
+    let start = match step > 0 {
+        true => start.max(0),
+        false => start,
+    };
+
+    let mut expanded: Vec<(usize, usize)> = Vec::new();
+    let mut index = start as i64;
+    while (step > 0 && index <= end as i64) || (step < 0 && index >= end as i64) {
+        if index >= 0 && (is_placeholder || index < length as i64) {
+            expanded.push((index as usize, index as usize));
+        }
+        index += step as i64;
+    }
+    Ok(expanded)
+}
+
 pub fn normalise_selections(
-    selections: &Vec<(i32, i32)>,
+    selections: &[(i32, i32, i32)],
     length: usize,
     is_placeholder: bool,
     is_strict_bounds: bool,
     is_strict_range_order: bool,
-) -> Result<Vec<(usize, usize)>, String> {
+) -> Result<Vec<(usize, usize)>, SplitError> {
     let mut normalised_selections: Vec<(usize, usize)> = Vec::with_capacity(selections.len());
-    for &(start, end) in selections {
+    for &(start, end, step) in selections {
+        if step != 1 {
+            normalised_selections.extend(expand_stepped_selection(
+                (start, end, step),
+                length,
+                is_placeholder,
+                is_strict_bounds,
+                is_strict_range_order,
+            )?);
+            continue;
+        }
         match normalise_selection(
             start,
             end,
@@ -153,6 +340,9 @@ pub fn normalise_selections(
 }
 
 /// Invert a list of selection ranges by sorting, merging, and building the complement.
+/// The complement is always taken against the record's actual `length`: a range running past
+/// the end inverts as if clamped to it, so on 3 fields `2-4` inverts like `2-3`, and one wholly
+/// outside the record removes nothing. Placeholder positions past the end are never brought back.
 pub fn invert_selections(
     mut normalised_selections: Vec<(usize, usize)>,
     length: usize,
@@ -164,21 +354,22 @@ pub fn invert_selections(
     // Merge
     let mut merged: Vec<(usize, usize)> = Vec::with_capacity(normalised_selections.len());
     for (start, end) in normalised_selections {
-        if let Some((_, last_end)) = merged.last_mut() {
-            if start <= *last_end {
-                *last_end = (*last_end).max(end);
-                continue;
-            }
+        if let Some((_, last_end)) = merged.last_mut()
+            && start <= *last_end
+        {
+            *last_end = (*last_end).max(end);
+            continue;
         }
         merged.push((start, end));
     }
 
-    // Build inverted list
+    // Build inverted list. Gaps stop at the end of the record, so a selection past the end
+    // can't leave out-of-range positions for the placeholder to fill
     let mut invert_pointer: usize = 0;
     let mut inverted: Vec<(usize, usize)> = Vec::with_capacity(merged.len());
     for (start, end) in &merged {
-        if *start > invert_pointer {
-            inverted.push((invert_pointer, start.saturating_sub(1)));
+        if *start > invert_pointer && invert_pointer < length {
+            inverted.push((invert_pointer, start.saturating_sub(1).min(length - 1)));
         }
         invert_pointer = end.saturating_add(1);
     }
@@ -189,10 +380,219 @@ pub fn invert_selections(
 }
 
 pub struct Field<'a> {
+    pub start: usize, // byte offset of the text within the record
     pub text: &'a [u8],
     pub delimiter: &'a [u8],
 }
 
+/// --squash-delimiters: drops the empty fields inside runs of delimiters, so each run is
+/// one boundary. The first and last fields stay even when empty, since a delimiter at
+/// either end of the record still marks an edge, which --skip-empty would drop.
+pub fn squash_delimiter_runs(fields: &mut Vec<Field>) {
+    let last = fields.len().saturating_sub(1);
+    let mut index = 0;
+    fields.retain(|field| {
+        let keep = index == 0 || index == last || !field.text.is_empty();
+        index += 1;
+        keep
+    });
+}
+
+/// Split text into fields on every delimiter match, or only the first `max_fields - 1`
+/// so the last field keeps the rest of the text. Whole-string mode doesn't add an empty
+/// field after a trailing delimiter, unless `keep_trailing_empty` is set.
+pub fn split_fields<'a>(
+    text_bytes: &'a [u8],
+    engine: &RegexEngine,
+    input_mode: InputMode,
+    max_fields: Option<usize>,
+    keep_trailing_empty: bool,
+) -> Result<Vec<Field<'a>>, SplitError> {
+    let max_delimiters = max_fields.map_or(usize::MAX, |max| max.saturating_sub(1));
+    let delimiter_len = match engine {
+        RegexEngine::Simple(regex) => regex.as_str().len(),
+        RegexEngine::Fancy(_) => 1,
+        RegexEngine::Literal(literal) => literal.len(),
+        RegexEngine::Byte(_) => 1,
+    };
+    let estimated_field_count = estimate_field_count(text_bytes.len(), delimiter_len);
+    let mut fields: Vec<Field> = Vec::with_capacity(estimated_field_count);
+    let mut cursor = 0usize;
+
+    match engine {
+        RegexEngine::Simple(engine) => {
+            for delimiter in engine.find_iter(text_bytes).take(max_delimiters) {
+                fields.push(Field {
+                    start: cursor,
+                    text: &text_bytes[cursor..delimiter.start()],
+                    delimiter: &text_bytes[delimiter.start()..delimiter.end()],
+                });
+                cursor = delimiter.end();
+            }
+        }
+        RegexEngine::Fancy(engine) => {
+            // field_text has already made the record valid UTF-8 for fancy-regex
+            let text = std::str::from_utf8(text_bytes).map_err(|_| {
+                SplitError::Data("internal error: fancy-regex needs UTF-8 text".to_string())
+            })?;
+            for delimiter_result in engine.find_iter(text).take(max_delimiters) {
+                match delimiter_result {
+                    Ok(delimiter) => {
+                        fields.push(Field {
+                            start: cursor,
+                            text: &text_bytes[cursor..delimiter.start()],
+                            delimiter: &text_bytes[delimiter.start()..delimiter.end()],
+                        });
+                        cursor = delimiter.end();
+                    }
+                    Err(error) => {
+                        return Err(SplitError::Regex(format!(
+                            "regex matching error: {}",
+                            error
+                        )));
+                    }
+                }
+            }
+        }
+        RegexEngine::Literal(literal) => {
+            for delimiter_start in
+                memchr::memmem::find_iter(text_bytes, literal).take(max_delimiters)
+            {
+                let delimiter_end = delimiter_start + literal.len();
+                fields.push(Field {
+                    start: cursor,
+                    text: &text_bytes[cursor..delimiter_start],
+                    delimiter: &text_bytes[delimiter_start..delimiter_end],
+                });
+                cursor = delimiter_end;
+            }
+        }
+        RegexEngine::Byte(byte) => {
+            for delimiter_start in memchr::memchr_iter(*byte, text_bytes).take(max_delimiters) {
+                fields.push(Field {
+                    start: cursor,
+                    text: &text_bytes[cursor..delimiter_start],
+                    delimiter: &text_bytes[delimiter_start..delimiter_start + 1],
+                });
+                cursor = delimiter_start + 1;
+            }
+        }
+    }
+
+    let final_text = &text_bytes[cursor..];
+    if !final_text.is_empty() || input_mode != InputMode::WholeString || keep_trailing_empty {
+        fields.push(Field {
+            start: cursor,
+            text: final_text,
+            delimiter: b"",
+        });
+    }
+
+    Ok(fields)
+}
+
+/// Split a CSV record on a literal delimiter, following RFC 4180 quoting.
+/// Quoted values are returned unquoted with `""` collapsed to `"`, each with the offset
+/// its raw text starts at. Spaces around a quoted value are dropped, and any other text
+/// after the closing quote is kept as-is.
+pub fn split_csv_values(
+    text: &[u8],
+    delimiter: &[u8],
+    input_mode: InputMode,
+) -> Result<Vec<(usize, Vec<u8>)>, SplitError> {
+    let skip_spaces = !delimiter.starts_with(b" ");
+    let mut values: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut cursor = 0usize;
+
+    loop {
+        let value_start = cursor;
+        let mut value: Vec<u8> = Vec::new();
+        let mut quote_start = cursor;
+        while skip_spaces && text.get(quote_start) == Some(&b' ') {
+            quote_start += 1;
+        }
+
+        if text.get(quote_start) == Some(&b'"') {
+            cursor = quote_start + 1;
+            loop {
+                match text.get(cursor) {
+                    Some(b'"') if text.get(cursor + 1) == Some(&b'"') => {
+                        value.push(b'"');
+                        cursor += 2;
+                    }
+                    Some(b'"') => {
+                        cursor += 1;
+                        break;
+                    }
+                    Some(&byte) => {
+                        value.push(byte);
+                        cursor += 1;
+                    }
+                    None => {
+                        return Err(SplitError::Data(match input_mode {
+                            InputMode::WholeString => "unterminated quoted field".to_string(),
+                            _ => "unterminated quoted field (use --whole-string for quoted fields spanning lines)".to_string(),
+                        }));
+                    }
+                }
+            }
+            while cursor < text.len() && !text[cursor..].starts_with(delimiter) {
+                if !(skip_spaces && text[cursor] == b' ') {
+                    value.push(text[cursor]);
+                }
+                cursor += 1;
+            }
+        } else {
+            let value_end = memchr::memmem::find(&text[cursor..], delimiter)
+                .map(|offset| cursor + offset)
+                .unwrap_or(text.len());
+            value.extend_from_slice(&text[cursor..value_end]);
+            cursor = value_end;
+        }
+
+        values.push((value_start, value));
+        if cursor >= text.len() {
+            break;
+        }
+        cursor += delimiter.len();
+    }
+
+    Ok(values)
+}
+
+/// Split a record into fields, parsing CSV quoting when `csv` is set.
+/// CSV values are unquoted into `csv_values`, which the returned fields borrow from.
+pub fn split_record_fields<'a>(
+    text: &'a [u8],
+    engine: &'a RegexEngine,
+    input_mode: InputMode,
+    csv: bool,
+    max_fields: Option<usize>,
+    keep_trailing_empty: bool,
+    csv_values: &'a mut Vec<(usize, Vec<u8>)>,
+) -> Result<Vec<Field<'a>>, SplitError> {
+    if !csv {
+        return split_fields(text, engine, input_mode, max_fields, keep_trailing_empty);
+    }
+    let RegexEngine::Literal(delimiter) = engine else {
+        return Err(SplitError::Data(
+            "internal error: csv mode needs a literal delimiter".to_string(),
+        ));
+    };
+
+    *csv_values = split_csv_values(text, delimiter, input_mode)?;
+    let last_index = csv_values.len().saturating_sub(1);
+    Ok(csv_values
+        .iter()
+        .enumerate()
+        .map(|(index, (start, value))| Field {
+            start: *start,
+            text: value,
+            delimiter: if index < last_index { delimiter } else { b"" },
+        })
+        .collect())
+}
+
 pub fn get_current_delimiter<'a>(field_index: usize, fields: &'a [Field<'a>]) -> &'a [u8] {
     if field_index < fields.len() {
         fields[field_index].delimiter
@@ -244,17 +644,18 @@ pub fn get_next_delimiter<'a>(
     };
 
     // For before-next, use delimiter before the next selected field
-    let next_delimiter = if let Some(next_selected_idx) = next_field_index
+
+    (if let Some(next_selected_idx) = next_field_index
         && next_selected_idx > 0
         && next_selected_idx <= fields.len()
     {
         fields[next_selected_idx - 1].delimiter
     } else {
         b""
-    };
-    next_delimiter
+    }) as _
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn choose_join_bytes<'a>(
     field_index: usize,
     selection_index: usize,
@@ -307,7 +708,19 @@ pub fn choose_join_bytes<'a>(
         }
         Some(JoinMode::Space) => b" ",
         Some(JoinMode::None) => b"",
-        None | Some(JoinMode::Auto) => {
+        // Auto is its own rule rather than whatever the default is: the delimiter that
+        // appeared after the previous field, then the first delimiter, then a space
+        Some(JoinMode::Auto) => {
+            let current_delimiter = get_current_delimiter(field_index, fields);
+            if !current_delimiter.is_empty() {
+                current_delimiter
+            } else if !first_delimiter.is_empty() {
+                first_delimiter
+            } else {
+                b" "
+            }
+        }
+        None => {
             let current_delimiter = get_current_delimiter(field_index, fields);
             if !current_delimiter.is_empty() {
                 current_delimiter
@@ -331,3 +744,230 @@ pub fn choose_join_bytes<'a>(
         }
     }
 }
+
+/// Appends `bytes` single-quoted for a POSIX shell, closing and reopening the quotes
+/// around each embedded `'`. An empty field becomes `''` so it still counts as an argument.
+pub fn push_shell_quoted(output: &mut Vec<u8>, bytes: &[u8]) {
+    output.push(b'\'');
+    for byte in bytes {
+        match byte {
+            b'\'' => output.extend_from_slice(b"'\\''"),
+            other => output.push(*other),
+        }
+    }
+    output.push(b'\'');
+}
+
+/// Appends `bytes` as a quoted JSON string. Invalid UTF-8 becomes U+FFFD.
+pub fn push_json_string(output: &mut Vec<u8>, bytes: &[u8]) {
+    output.push(b'"');
+    for character in String::from_utf8_lossy(bytes).chars() {
+        match character {
+            '"' => output.extend_from_slice(b"\\\""),
+            '\\' => output.extend_from_slice(b"\\\\"),
+            '\n' => output.extend_from_slice(b"\\n"),
+            '\r' => output.extend_from_slice(b"\\r"),
+            '\t' => output.extend_from_slice(b"\\t"),
+            '\u{08}' => output.extend_from_slice(b"\\b"),
+            '\u{0c}' => output.extend_from_slice(b"\\f"),
+            control if (control as u32) < 0x20 => {
+                output.extend_from_slice(format!("\\u{:04x}", control as u32).as_bytes());
+            }
+            other => {
+                let mut buffer = [0u8; 4];
+                output.extend_from_slice(other.encode_utf8(&mut buffer).as_bytes());
+            }
+        }
+    }
+    output.push(b'"');
+}
+
+/// Appends `bytes` to the output, encoded when --encode is set.
+pub fn push_encoded(output: &mut Vec<u8>, bytes: &[u8], encoding: Option<Encoding>) {
+    const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    const BASE64_URL: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    let alphabet = match encoding {
+        None => {
+            output.extend_from_slice(bytes);
+            return;
+        }
+        Some(Encoding::Hex) => {
+            for byte in bytes {
+                output.push(HEX[(byte >> 4) as usize]);
+                output.push(HEX[(byte & 0x0f) as usize]);
+            }
+            return;
+        }
+        Some(Encoding::Base64) => BASE64,
+        Some(Encoding::Base64Url) => BASE64_URL,
+    };
+
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for position in 0..4 {
+            if position <= chunk.len() {
+                output.push(alphabet[(group >> (18 - 6 * position) & 0x3f) as usize]);
+            } else {
+                output.push(b'=');
+            }
+        }
+    }
+}
+
+/// Decodes a --decode field. Base64 accepts either alphabet, with or without padding.
+pub fn decode_bytes(bytes: &[u8], encoding: Encoding) -> Result<Vec<u8>, SplitError> {
+    let invalid = || {
+        let name = match encoding {
+            Encoding::Base64 => "base64",
+            Encoding::Base64Url => "base64url",
+            Encoding::Hex => "hex",
+        };
+        SplitError::Data(format!(
+            "decode error: '{}' is not valid {name}",
+            String::from_utf8_lossy(bytes)
+        ))
+    };
+
+    if encoding == Encoding::Hex {
+        if !bytes.len().is_multiple_of(2) {
+            return Err(invalid());
+        }
+        return bytes
+            .chunks(2)
+            .map(|pair| {
+                // from_str_radix would also take a sign, as in "+f"
+                if !pair.iter().all(u8::is_ascii_hexdigit) {
+                    return Err(invalid());
+                }
+                std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(invalid)
+            })
+            .collect();
+    }
+
+    let unpadded = match bytes.iter().position(|byte| *byte == b'=') {
+        Some(padding_start) if bytes[padding_start..].iter().all(|byte| *byte == b'=') => {
+            &bytes[..padding_start]
+        }
+        Some(_) => return Err(invalid()),
+        None => bytes,
+    };
+    if unpadded.len() % 4 == 1 {
+        return Err(invalid());
+    }
+
+    let mut decoded: Vec<u8> = Vec::with_capacity(unpadded.len() * 3 / 4);
+    let mut group: u32 = 0;
+    let mut group_bits: u32 = 0;
+    for byte in unpadded {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err(invalid()),
+        };
+        group = (group << 6) | value as u32;
+        group_bits += 6;
+        if group_bits >= 8 {
+            group_bits -= 8;
+            decoded.push((group >> group_bits) as u8);
+            group &= (1 << group_bits) - 1;
+        }
+    }
+    Ok(decoded)
+}
+
+/// Measures a field for --field-length.
+pub fn field_length(text: &[u8], basis: LengthBasis) -> usize {
+    match basis {
+        LengthBasis::Bytes => text.len(),
+        LengthBasis::Chars => String::from_utf8_lossy(text).graphemes(true).count(),
+        LengthBasis::Display => display_width(text),
+    }
+}
+
+/// Strips Unicode whitespace from the ends of a field, as set by --trim.
+/// A field that isn't valid UTF-8 only has ASCII whitespace stripped.
+pub fn trim_field(text: &[u8], trim: Trim) -> &[u8] {
+    let Ok(string) = std::str::from_utf8(text) else {
+        return match trim {
+            Trim::None => text,
+            Trim::Left => text.trim_ascii_start(),
+            Trim::Right => text.trim_ascii_end(),
+            Trim::Both => text.trim_ascii(),
+        };
+    };
+    match trim {
+        Trim::None => string,
+        Trim::Left => string.trim_start(),
+        Trim::Right => string.trim_end(),
+        Trim::Both => string.trim(),
+    }
+    .as_bytes()
+}
+
+/// Drops whitespace graphemes from the ends of a chars-mode record, as set by --trim.
+pub fn trim_graphemes<'a, 'g>(graphemes: &'a [&'g str], trim: Trim) -> &'a [&'g str] {
+    let is_whitespace = |grapheme: &&str| grapheme.chars().all(char::is_whitespace);
+    let mut start = 0;
+    let mut end = graphemes.len();
+    if matches!(trim, Trim::Left | Trim::Both) {
+        start = graphemes
+            .iter()
+            .position(|grapheme| !is_whitespace(grapheme))
+            .unwrap_or(end);
+    }
+    if matches!(trim, Trim::Right | Trim::Both) {
+        end = graphemes
+            .iter()
+            .rposition(|grapheme| !is_whitespace(grapheme))
+            .map_or(start, |last| last + 1);
+    }
+    &graphemes[start..end.max(start)]
+}
+
+/// Parses the number at the start of a field, after any whitespace, for --sort-numeric.
+/// Commas are taken as thousands separators only between groups of three digits, after at
+/// most three leading ones, as in `1,234,567`; anything else ends the number.
+pub fn leading_number(text: &[u8]) -> Option<f64> {
+    let start = text.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    let text = &text[start..];
+    let mut end = usize::from(matches!(text.first(), Some(b'+' | b'-')));
+    let digits_start = end;
+    while text.get(end).is_some_and(u8::is_ascii_digit) {
+        end += 1;
+    }
+    let grouped = (1..=3).contains(&(end - digits_start));
+    while grouped
+        && text.get(end) == Some(&b',')
+        && text
+            .get(end + 1..end + 4)
+            .is_some_and(|group| group.iter().all(u8::is_ascii_digit))
+        && !text.get(end + 4).is_some_and(u8::is_ascii_digit)
+    {
+        end += 4;
+    }
+    if text.get(end) == Some(&b'.') && text.get(end + 1).is_some_and(u8::is_ascii_digit) {
+        end += 1;
+        while text.get(end).is_some_and(u8::is_ascii_digit) {
+            end += 1;
+        }
+    }
+    if end == digits_start {
+        return None;
+    }
+    std::str::from_utf8(&text[..end])
+        .ok()?
+        .replace(',', "")
+        .parse::<f64>()
+        .ok()
+}
@@ -1,19 +1,77 @@
 use fancy_regex::Regex as FancyRegex;
-use regex::Regex as SimpleRegex;
+use regex::bytes::Regex as BytesRegex;
 use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+use crate::error::SplitError;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
 pub enum InputMode {
+    #[default]
     PerLine,
     WholeString,
     ZeroTerminated,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
 pub enum SelectionMode {
+    #[default]
     Fields,
     Bytes,
     Chars,
+    Capture,
+    Json,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Encoding {
+    Base64,    // standard alphabet, padded
+    Base64Url, // URL-safe alphabet, padded
+    Hex,       // lowercase
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum InputEncoding {
+    Utf8,    // read as-is
+    Utf16Le, // transcoded to UTF-8 as it's read
+    Utf16Be, // transcoded to UTF-8 as it's read
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Default)]
+pub enum Trim {
+    #[default]
+    None,
+    Left,  // --trim-left
+    Right, // --trim-right
+    Both,  // --trim, or both one-sided flags
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct FieldSort {
+    pub numeric: bool, // --sort-numeric: compare leading numbers, then bytes
+    pub reverse: bool, // --sort-reverse
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum OriginalPlacement {
+    Prefix, // original record, separator, then the selection
+    Suffix, // selection, separator, then the original record
+}
+
+/// What --count-unit counts, in place of the selection mode's own unit.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CountUnit {
+    Fields, // fields split on the delimiter
+    Bytes,  // bytes in the record
+    Chars,  // graphemes in the record
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Default)]
+pub enum LengthBasis {
+    Bytes, // raw byte length
+    Chars, // grapheme count
+    #[default]
+    Display, // terminal display width
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -28,15 +86,36 @@ pub enum JoinMode {
     None,            // @none: no join (equivalent to "")
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+/// A `p50` or `p25-p75` selection. Its slot in the selections holds a stand-in until
+/// process_fields knows how many fields the record has.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct PercentSelection {
+    pub slot: usize, // index into the selections
+    pub start: u32,  // percent of the field count, 0 to 100
+    pub end: u32,
+}
+
+/// --from and --to: the span of fields between the first field matching `from` and the
+/// next one matching `to`. Either end left out runs to that end of the record.
+#[derive(Clone)]
+pub struct FieldAnchors {
+    pub from: Option<BytesRegex>,
+    pub to: Option<BytesRegex>,
+    pub exclusive: bool, // --exclude-anchors: the matching fields themselves are left out
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Default)]
 pub enum Align {
     Left,
     Right,
     Squash,
+    Center,
+    Tabs, // --align-with=tabs: a tab between columns, leaving widths to the tabstops
+    #[default]
     None,
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Delimiter {
     Literal(String),
     Regex(String),
@@ -44,8 +123,23 @@ pub enum Delimiter {
 
 #[derive(Clone)]
 pub enum RegexEngine {
-    Simple(SimpleRegex),
+    Simple(BytesRegex), // matched against the raw record, so stray bytes stay as they are
     Fancy(FancyRegex),
+    Literal(Vec<u8>), // --fixed: split on exact byte matches, no regex
+    Byte(u8),         // a single literal byte, split with memchr
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum HeaderSelection {
+    Name(String),           // a column looked up in the header record
+    Index((i32, i32, i32)), // an ordinary selection, used as given
+}
+
+/// The --header columns, resolved by the reader once it has read the header record.
+/// Records are only sent after that, so workers always find it resolved.
+pub struct HeaderSelections {
+    pub tokens: Vec<HeaderSelection>,
+    pub resolved: OnceLock<Vec<(i32, i32, i32)>>, // the selections in use, positional ones first
 }
 
 pub struct InputInstructions {
@@ -53,40 +147,126 @@ pub struct InputInstructions {
     pub align: Align,
     pub join: Option<JoinMode>,
     pub input_mode: InputMode,
-    pub input: Option<PathBuf>,
-    pub selections: Vec<(i32, i32)>,
+    pub input: Vec<PathBuf>, // read in order as one stream, stdin when empty
+    pub input_encoding: InputEncoding, // --encoding: how the input is decoded to UTF-8
+    pub keep_bom: bool,      // --keep-bom: leave a leading UTF-8 BOM in each source
+    pub strip_cr: bool,      // --strip-cr: drop a trailing \r from every record, in any mode
+    pub record_separator: Option<BytesRegex>, // --input-record-separator: split records on a regex
+    pub record_separator_max_len: Option<usize>, // longest separator match, None if unbounded
+    pub comment_prefixes: Vec<Vec<u8>>, // --comment: records starting with these are skipped
+    pub batch_bytes: Option<usize>, // --batch-bytes: reader batch size, 0 sends each record alone
+    pub read_buffer: Option<usize>, // --read-buffer: capacity of each source's read buffer
+    pub progress: bool,      // --progress: show how much of the input files is read, on a terminal
+    pub max_fields: Option<usize>, // --max-fields: stop splitting after this many fields
+    pub keep_trailing_empty: bool, // --keep-trailing-empty: whole-string mode keeps a last empty field
+    pub max_records: Option<usize>, // --max-records: stop reading after this many records
+    pub tail: Option<usize>,       // --tail: only process the last this many records
+    pub max_record_bytes: Option<usize>, // --max-record-bytes: error on any longer record
+    pub header: Option<Arc<HeaderSelections>>, // --header: select columns by name
+    pub selections: Vec<(i32, i32, i32)>, // (start, end, step)
     pub skip_empty: bool,
+    pub squash_delimiters: bool, // --squash-delimiters: a run of delimiters is one boundary
+    pub trim: Trim,              // --trim: strip whitespace from the ends of each field
     pub invert: bool,
-    pub placeholder: Option<Vec<u8>>,
+    pub placeholders: Vec<Vec<u8>>, // every --placeholder, taken in turn by missing selections
     pub strict_bounds: bool,
     pub strict_range_order: bool,
     pub strict_utf8: bool,
+    pub csv: bool, // --csv: split fields on the literal delimiter, honouring quotes
 }
 
+#[derive(Clone, Default)]
 pub struct TransformInstructions {
     pub input_mode: InputMode,
     pub selection_mode: SelectionMode,
-    pub selections: Vec<(i32, i32)>,
+    pub codepoints: bool, // --codepoints: chars mode indexes code points, not grapheme clusters
+    pub selections: Vec<(i32, i32, i32)>, // (start, end, step)
+    pub percent_selections: Vec<PercentSelection>, // p50, p25-p75: resolved per record
+    pub json_paths: Vec<Vec<String>>, // --json-input: dotted paths, one per selected value
     pub invert: bool,
+    pub redact: bool, // --redact: swap selected fields for the placeholder, keeping the rest
+    pub offsets: bool, // --offsets: emit where each selected field lies instead of its text
     pub skip_empty: bool,
-    pub placeholder: Option<Vec<u8>>,
+    pub squash_delimiters: bool, // --squash-delimiters: a run of delimiters is one boundary
+    pub trim: Trim,              // --trim: strip whitespace from the ends of each field
+    pub unique: bool,            // --unique: drop fields already emitted earlier in the same record
+    pub sort: Option<FieldSort>, // --sort: emit the selected fields in sorted order
+    pub reverse: bool,           // --reverse: emit the selected fields last to first
+    pub separators: Vec<Vec<u8>>, // --separators: the join after each output position, then --join
+    pub replacements: Vec<(Vec<u8>, Vec<u8>)>, // --replace: (from, to) for kept delimiters
+    pub only_delimited: bool,
+    pub require: Option<BytesRegex>, // --require: records that don't match are dropped
+    pub strict_require: bool,        // --strict-require: error on them instead
+    pub pick: Option<BytesRegex>,    // --pick: only fields matching this are kept
+    pub anchors: Option<FieldAnchors>, // --from/--to: select the fields between two matches
+    pub comment_prefixes: Vec<Vec<u8>>, // --comment: records starting with these are dropped
+    pub csv: bool, // --csv: split fields on the literal delimiter, honouring quotes
+    pub max_fields: Option<usize>, // --max-fields: stop splitting after this many fields
+    pub keep_trailing_empty: bool, // --keep-trailing-empty: whole-string mode keeps a last empty field
+    pub header: Option<Arc<HeaderSelections>>, // --header: selections swapped in once resolved
+    pub field_pattern: Option<RegexEngine>, // --field-pattern: keep only the first capture of each field
+    pub field_length: Option<(usize, usize)>, // --field-length: keep fields whose length is within min..=max
+    pub field_length_basis: LengthBasis, // --field-length-basis: how --field-length measures a field
+    pub dedup_key: Option<i32>,
+    pub assert_sorted: Option<i32>, // --assert-sorted: key field that must never decrease
+    pub assert_uniform: bool, // --assert-uniform: count each record's fields for the output check
+    pub placeholders: Vec<Vec<u8>>, // every --placeholder, taken in turn by missing selections
     pub strict_return: bool,
     pub strict_bounds: bool,
     pub strict_range_order: bool,
     pub strict_utf8: bool,
     pub count: bool,
+    pub count_nonempty: bool,
+    pub count_unit: Option<CountUnit>, // --count-unit: count this instead of the mode's unit
+    pub stats: bool, // --stats: emit the field count and each field's byte length instead
+    pub complement_count: bool, // --complement-count: emit how many fields were not selected
+    pub index_pair_separator: Option<Vec<u8>>, // --emit-field-index-pairs: prefix fields with "index<sep>"
+    pub json: bool,        // --json: emit each record as a JSON array of strings
+    pub transpose: bool,   // --transpose: emit the columns of a whole-string table as rows
+    pub shell_quote: bool, // --shell-quote: wrap each field in single quotes for the shell
+    pub with_original: Option<OriginalPlacement>, // --with-original: emit the source record alongside
+    pub original_separator: Vec<u8>, // --original-separator: between the record and the selection
+    pub encoding: Option<Encoding>,  // --encode: encode each field (or the whole byte/char output)
+    pub decoding: Option<Encoding>,  // --decode: decode each selected field before output
     pub join: Option<JoinMode>,
     pub regex_engine: Option<RegexEngine>,
     pub align: Align,
+    pub pad: Vec<u8>, // --pad-char: padding used by align, a space by default
 }
 
 pub struct OutputInstructions {
     pub count: bool,
+    /// Sum the per-record counts and write only the total at the end.
+    pub count_total: bool,
+    /// Tally the per-record counts and write how many records had each, naming the unit
+    /// counted, e.g. `3 fields: 12 lines`.
+    pub histogram: Option<&'static str>,
+    /// Escape control characters and invalid UTF-8 in each record, but not its terminator.
+    pub escape_output: bool,
     pub strict_return: bool,
     pub strict_bounds: bool,
+    pub strict_empty: bool, // --strict-empty: an input with no records is an error
     pub input_mode: InputMode,
-    pub selections: Vec<(i32, i32)>,
+    pub selections: Vec<(i32, i32, i32)>, // (start, end, step)
     pub output: Option<PathBuf>,
+    /// Append to the --output file rather than truncating it.
+    pub output_append: bool,
+    pub trim_newline: bool, // --trim-newline: leave off the last record's terminator
+    /// Write `\r\n` instead of `\n` as the per-line record terminator.
+    pub crlf_output: bool,
+    /// Write this after each record instead of the terminator the input mode implies.
+    /// In whole-string mode it ends the output instead of the terminal newline fixup.
+    pub output_terminator: Option<Vec<u8>>,
+    /// Error if input was received but every record produced empty output.
+    pub fail_on_empty_output: bool,
+    /// Prefix each output record with its 1-based record number and a tab.
+    pub number: bool,
+    /// Write batches as they arrive instead of restoring input order.
+    pub unordered: bool,
+    /// Flush output after every record instead of buffering.
+    pub line_buffered: bool,
+    /// Compare --assert-sorted keys as numbers rather than bytes.
+    pub numeric: bool,
     /// True when writing to stdout and stdout is a TTY (not piped/redirected). For use by output layer (e.g. trailing newline, color).
     pub stdout_is_terminal: bool,
 }
@@ -95,27 +275,60 @@ pub struct Instructions {
     pub input_instructions: InputInstructions,
     pub transform_instructions: TransformInstructions,
     pub output_instructions: OutputInstructions,
+    /// Number of transform workers from --jobs, 0 sizes them to the available cores.
+    /// None falls back to SPLITBY_SINGLE_CORE, then the available cores.
+    pub jobs: Option<usize>,
+    /// Most batches read but not yet written, from --reorder-window. None uses the default.
+    pub reorder_window: Option<usize>,
 }
 
 pub struct Record {
-    pub index: usize,
+    pub index: usize,    // position in the input, for messages and --number
+    pub sequence: usize, // position in the output order, the same as index except under --tail
     pub bytes: Vec<u8>,
     pub has_terminator: bool,
     pub field_widths: Option<Vec<usize>>,
     pub join_widths: Option<Vec<usize>>,
 }
 
+/// A record once transformed: the bytes to write, or under --count the number counted.
+pub enum Processed {
+    Bytes(Vec<u8>),
+    Count(usize),
+}
+
+impl Processed {
+    /// The bytes written for the record, with a count in decimal.
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Processed::Bytes(bytes) => bytes,
+            Processed::Count(count) => count.to_string().into_bytes(),
+        }
+    }
+}
+
 pub struct OutputRecord {
     pub bytes: Vec<u8>,
     pub has_terminator: bool,
+    /// Record was dropped by the transform, so neither bytes nor terminator are written.
+    pub suppressed: bool,
+    /// Key field for --field-dedup-key, records repeating an earlier key are dropped on output.
+    pub dedup_key: Option<Vec<u8>>,
+    /// Key field for --assert-sorted, checked against the previous record on output.
+    pub sort_key: Option<Vec<u8>>,
+    /// Number of fields for --assert-uniform, compared with the first record's on output.
+    pub field_count: Option<usize>,
+    /// The record's --count, which --count-total and --histogram tally rather than write.
+    pub count: Option<usize>,
 }
 pub enum ResultChunk {
     Ok {
-        start_index: usize,
+        start_index: usize, // sequence of the first record, for ordering
+        start_line: usize,  // input index of the first record, for messages and --number
         outputs: Vec<OutputRecord>,
     },
     Err {
         index: usize,
-        error: String,
+        error: SplitError,
     },
 }
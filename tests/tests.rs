@@ -99,6 +99,38 @@ fn run_hex_output_test(
     );
 }
 
+/// A file in the temp directory for tests that read or write one, deleted when dropped.
+/// Its name carries the process id, so separate test runs don't share it.
+struct TempFile {
+    path: std::path::PathBuf,
+}
+
+impl TempFile {
+    /// A file holding `contents`.
+    fn new(name: &str, contents: &[u8]) -> TempFile {
+        let file = TempFile::absent(name);
+        std::fs::write(&file.path, contents).expect("writing temp file should not fail");
+        file
+    }
+
+    /// A path with no file there yet, for splitby to create.
+    fn absent(name: &str) -> TempFile {
+        let path = std::env::temp_dir().join(format!("splitby-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        TempFile { path }
+    }
+
+    fn path(&self) -> &str {
+        self.path.to_str().expect("temp paths should be UTF-8")
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
 mod basic_usage {
     use super::*;
 
@@ -153,1377 +185,6792 @@ mod basic_usage {
     }
 }
 
-mod range_and_selection {
-    use super::*;
+mod library {
+    use splitby::{Config, Delimiter, InputMode, Position, SelectionMode, SplitError, Splitter};
+
+    fn comma_config(selections: &[&str]) -> Config {
+        Config {
+            delimiter: Some(Delimiter::Literal(",".to_string())),
+            selections: selections.iter().map(|token| token.to_string()).collect(),
+            ..Config::default()
+        }
+    }
 
     #[test]
-    fn negative_number() {
-        run_success_test(
-            "Negative number",
-            b"this is a test\n",
-            &["-d", " ", "-1"],
-            b"test\n",
-        );
+    fn selects_fields() {
+        let splitter = Splitter::new(comma_config(&["1", "3"])).unwrap();
+        assert_eq!(splitter.split(b"a,b,c\nd,e,f").unwrap(), b"a,c\nd,f");
     }
 
     #[test]
-    fn negative_split_by_comma() {
-        run_success_test(
-            "Negative split by comma",
-            b"apple,banana,plum,cherry\n",
-            &["-d", ",", "-2"],
-            b"plum\n",
-        );
+    fn regex_delimiter_and_join() {
+        let splitter = Splitter::new(Config {
+            delimiter: Some(Delimiter::Regex(r"\s+".to_string())),
+            selections: vec!["2-3".to_string()],
+            join: Some("space".to_string()),
+            ..Config::default()
+        })
+        .unwrap();
+        assert_eq!(splitter.split(b"a  b\tc\r\n").unwrap(), b"b c\n");
     }
 
     #[test]
-    fn split_by_space_empty_selection() {
-        run_success_test(
-            "Split by space, empty selection",
-            b"this is a test\n",
-            &["-d", " "],
-            b"this is a test\n",
-        );
+    fn chars_and_whole_string() {
+        let splitter = Splitter::new(Config {
+            selection_mode: SelectionMode::Chars,
+            input_mode: InputMode::WholeString,
+            selections: vec!["2,-1".to_string()],
+            ..Config::default()
+        })
+        .unwrap();
+        assert_eq!(splitter.split("xé\nz".as_bytes()).unwrap(), "éz".as_bytes());
     }
 
     #[test]
-    fn split_by_space_empty_selection_whole_string() {
-        run_success_test(
-            "Split by space, empty selection whole-string",
-            b"this is a test\n",
-            &["-w", "-d", " "],
-            b"this is a test\n",
-        );
+    fn reads_from_a_reader() {
+        let splitter = Splitter::new(comma_config(&["-1"])).unwrap();
+        let input: &[u8] = b"a,b\nc,d\n";
+        assert_eq!(splitter.split_reader(input).unwrap(), b"b\nd\n");
     }
 
     #[test]
-    fn range_selection() {
-        run_success_test(
-            "Range selection",
-            b"this is a test\n",
-            &["-d", " ", "1-2"],
-            b"this is\n",
-        );
+    fn drops_a_utf8_bom() {
+        let splitter = Splitter::new(comma_config(&["1"])).unwrap();
+        assert_eq!(splitter.split(b"\xEF\xBB\xBFa,b\n").unwrap(), b"a\n");
     }
 
     #[test]
-    fn negative_range_selection() {
-        run_success_test(
-            "Negative range selection",
-            b"this is a test\n",
-            &["-d", " ", "-3--1"],
-            b"is a test\n",
-        );
+    fn invalid_config() {
+        let missing_delimiter = Splitter::new(Config::default());
+        assert!(matches!(missing_delimiter, Err(SplitError::Usage(_))));
+        let bad_selection = Splitter::new(comma_config(&["x"]));
+        assert!(matches!(bad_selection, Err(SplitError::Usage(_))));
     }
 
     #[test]
-    fn positive_to_negative_range() {
-        run_success_test(
-            "Positive to negative range",
-            b"this is a test\n",
-            &["-d", " ", "2--1"],
-            b"is a test\n",
+    fn record_error_has_its_number() {
+        let splitter = Splitter::new(Config {
+            strict_bounds: true,
+            ..comma_config(&["3"])
+        })
+        .unwrap();
+        let error = splitter.split(b"a,b,c\nd,e\n").unwrap_err();
+        assert_eq!(error.position(), Some(Position::Record(2)));
+        assert!(matches!(error.kind(), SplitError::StrictBounds(_)));
+        assert!(
+            error
+                .to_string()
+                .starts_with("record 2: strict-bounds error:")
         );
+        assert!(error.message().starts_with("strict-bounds error:"));
     }
+}
+
+mod keep_trailing_empty {
+    use super::*;
 
     #[test]
-    fn negative_to_positive_range() {
+    fn whole_string_drops_trailing_empty_by_default() {
         run_success_test(
-            "Negative to positive range",
-            b"this is a test\n",
-            &["-d", " ", "-3-4"],
-            b"is a test\n",
+            "a,b, has two fields in whole-string mode",
+            b"a,b,",
+            &["-w", "-d", ",", "--count"],
+            b"2",
         );
     }
 
     #[test]
-    fn split_by_space_with_multiple_indexes() {
+    fn flag_keeps_trailing_empty() {
         run_success_test(
-            "Split by space with multiple indexes",
-            b"this is a test\n",
-            &["-d", " ", "1", "2", "3-4"],
-            b"this is a test\n",
+            "--keep-trailing-empty counts the trailing empty field",
+            b"a,b,",
+            &["-w", "-d", ",", "--keep-trailing-empty", "--count"],
+            b"3",
+        );
+        run_success_test(
+            "--keep-trailing-empty selects the trailing empty field",
+            b"a,b,",
+            &["-w", "-d", ",", "--keep-trailing-empty", "--join=|", "1-3"],
+            b"a|b|",
         );
     }
 
     #[test]
-    fn split_by_space_whole_string() {
+    fn per_line_already_keeps_it() {
         run_success_test(
-            "Split by space whole-string",
-            b"this is a test\n",
-            &["-w", "-d", " ", "1", "3-4"],
-            b"this a test\n",
+            "Per-line mode counts the trailing empty field either way",
+            b"a,b,\n",
+            &["-d", ",", "--count"],
+            b"3\n",
+        );
+        run_success_test(
+            "The flag changes nothing per line",
+            b"a,b,\n",
+            &["-d", ",", "--keep-trailing-empty", "--count"],
+            b"3\n",
         );
     }
 
     #[test]
-    fn whole_string_ends_with_newline_when_missing() {
-        // Input has no trailing newline. When stdout is a terminal we add newline; in tests (pipe) we don't.
-        run_success_test(
-            "Whole-string: no trailing newline when not a terminal",
-            b"foo,bar",
-            &["-w", "-d", ",", "1"],
-            b"foo",
-        );
+    fn rejects_other_modes() {
+        run_error_test("Bytes mode", b"abc", &["-b", "--keep-trailing-empty", "1"]);
     }
 }
 
-mod comma_separated_selection {
+mod squash_delimiters {
     use super::*;
 
     #[test]
-    fn basic() {
+    fn runs_of_delimiters_are_one_boundary() {
         run_success_test(
-            "Comma-separated selections: basic",
-            b"apple,banana,cherry,date\n",
-            &["-d", ",", "1,2,3"],
-            b"apple,banana,cherry\n",
+            "a,,b,,,c has three fields",
+            b"a,,b,,,c\n",
+            &["-d", ",", "--squash-delimiters", "1", "2", "3"],
+            b"a,b,c\n",
         );
     }
 
     #[test]
-    fn with_ranges() {
+    fn count_counts_squashed_fields() {
         run_success_test(
-            "Comma-separated selections: with ranges",
-            b"apple,banana,cherry,date,elderberry\n",
-            &["-d", ",", "1-2,4,5"],
-            b"apple,banana,date,elderberry\n",
+            "--count sees three fields",
+            b"a,,b,,,c\n",
+            &["-d", ",", "--squash-delimiters", "--count"],
+            b"3\n",
         );
     }
 
     #[test]
-    fn leading_comma() {
+    fn edge_delimiters_keep_one_empty_field() {
         run_success_test(
-            "Comma-separated selections: leading comma",
-            b"apple,banana,cherry\n",
-            &["-d", ",", ",1"],
-            b"apple\n",
+            "Leading and trailing runs each leave one empty field",
+            b",,a,,b,,\n",
+            &["-d", ",", "--squash-delimiters", "--join=|", "1-4"],
+            b"|a|b|\n",
+        );
+        run_success_test(
+            "--skip-empty drops them instead",
+            b",,a,,b,,\n",
+            &["-d", ",", "--skip-empty", "--join=|", "1-4"],
+            b"a|b\n",
         );
     }
 
     #[test]
-    fn trailing_comma() {
+    fn works_with_regex_delimiters() {
         run_success_test(
-            "Comma-separated selections: trailing comma",
-            b"apple,banana,cherry\n",
-            &["-d", ",", "1,"],
-            b"apple\n",
+            "Adjacent regex matches are squashed",
+            b"a; b;;c\n",
+            &["-d", "/[; ]/", "--squash-delimiters", "--join=,"],
+            b"a,b,c\n",
         );
     }
 
     #[test]
-    fn mixed_with_spaces() {
-        run_success_test(
-            "Comma-separated selections: mixed with spaces",
-            b"apple,banana,cherry,date\n",
-            &["-d", ",", "1,2", "3", "4"],
-            b"apple,banana,cherry,date\n",
-        );
+    fn rejects_other_modes_and_csv() {
+        run_error_test("Bytes mode", b"abc\n", &["-b", "--squash-delimiters", "1"]);
+        run_error_test("CSV", b"a,,b\n", &["--csv", "--squash-delimiters", "1"]);
     }
+}
+
+mod join_auto {
+    use super::*;
+
+    const INPUT: &[u8] = b"a;b,c|d\n";
+    const DELIMITER: &str = "/[;,|]/";
 
     #[test]
-    fn multiple_comma_strings() {
+    fn uses_the_delimiter_after_the_previous_field() {
         run_success_test(
-            "Comma-separated selections: multiple comma strings",
-            b"apple,banana,cherry,date\n",
-            &["-d", ",", "1,2", "3,4"],
-            b"apple,banana,cherry,date\n",
+            "auto keeps the delimiter that appeared between the fields",
+            INPUT,
+            &["-d", DELIMITER, "--join=auto", "2", "3"],
+            b"b,c\n",
+        );
+        run_success_test(
+            "first uses the record's first delimiter instead",
+            INPUT,
+            &["-d", DELIMITER, "--join=first", "2", "3"],
+            b"b;c\n",
+        );
+        run_success_test(
+            "space ignores the delimiters",
+            INPUT,
+            &["-d", DELIMITER, "--join=space", "2", "3"],
+            b"b c\n",
         );
     }
 
     #[test]
-    fn negative_indices() {
+    fn falls_back_to_the_first_delimiter() {
         run_success_test(
-            "Comma-separated selections: negative indices",
-            b"apple,banana,cherry,date\n",
-            &["-d", ",", "-3,-1"],
-            b"banana,date\n",
+            "auto falls back to the first delimiter after the last field",
+            INPUT,
+            &["-d", DELIMITER, "--join=auto", "4", "3"],
+            b"d;c\n",
+        );
+        run_success_test(
+            "the default looks ahead to the next selection instead",
+            INPUT,
+            &["-d", DELIMITER, "4", "3"],
+            b"d,c\n",
         );
     }
 
     #[test]
-    fn mixed_positive_and_negative() {
+    fn falls_back_to_space() {
         run_success_test(
-            "Comma-separated selections: mixed positive and negative",
-            b"apple,banana,cherry,date\n",
-            &["-d", ",", "1,-2"],
-            b"apple,cherry\n",
+            "auto falls back to a space when the record has no delimiter",
+            b"a\n",
+            &["-d", ",", "--join=auto", "--placeholder=x", "1", "2"],
+            b"a x\n",
         );
     }
+}
+
+mod raw_fields {
+    use super::*;
 
     #[test]
-    fn with_join() {
+    fn stray_byte_leaves_other_fields_alone() {
         run_success_test(
-            "Comma-separated selections: with join",
-            b"apple,banana,cherry\n",
-            &["-d", ",", "--join=|", "1", "2", "3"],
-            b"apple|banana|cherry\n",
+            "A stray 0xFF in an unselected field doesn't alter the selected one",
+            b"id,\xff\xfe,name\n",
+            &["-d", ",", "1", "3"],
+            b"id,name\n",
         );
     }
 
     #[test]
-    fn whole_string_mode() {
+    fn selected_field_keeps_its_bytes() {
         run_success_test(
-            "Comma-separated selections: whole-string mode",
-            b"apple,banana\ncherry,date\n",
-            &["-w", "-d", ",", "1,2"],
-            b"apple,banana\ncherry",
+            "An invalid field is output byte for byte",
+            b"a\xffb,c\n",
+            &["-d", "/,/", "1"],
+            b"a\xffb\n",
+        );
+        run_success_test(
+            "A byte regex matches around invalid bytes",
+            b"x\xff1y2z\n",
+            &["-d", "/\\d/", "2"],
+            b"y\n",
         );
     }
 
     #[test]
-    fn byte_mode() {
+    fn lookaround_delimiter_still_replaces() {
         run_success_test(
-            "Comma-separated selections: byte mode",
-            b"hello\n",
-            &["--bytes", "1", "3", "5"],
-            b"hlo\n",
+            "fancy-regex needs UTF-8, so the record is replaced first",
+            b"a\xff,b\n",
+            &["-d", "/(?<=\\S),/", "1"],
+            "a\u{FFFD}\n".as_bytes(),
         );
     }
+}
+
+mod nul_delimiter {
+    use super::*;
 
     #[test]
-    fn char_mode() {
+    fn splits_on_nul_in_per_line_mode() {
         run_success_test(
-            "Comma-separated selections: char mode",
-            b"hello\n",
-            &["--characters", "1", "3", "5"],
-            b"hlo\n",
+            "\\0 in a regex delimiter is a NUL byte",
+            b"a\0b\0c\nd\0e\n",
+            &["-d", "/\\0/", "2"],
+            b"b\ne\n",
+        );
+        run_success_test(
+            "\\x00 splits the same way",
+            b"a\0b\0c\n",
+            &["-d", "/\\x00/", "1", "3"],
+            b"a\0c\n",
         );
     }
 
     #[test]
-    fn with_invert() {
+    fn nul_among_other_delimiters() {
         run_success_test(
-            "Comma-separated selections: with invert",
-            b"a,b,c,d\n",
-            &["-d", ",", "--invert", "1,3"],
-            b"b,d\n",
+            "NUL can be one of several delimiters",
+            b"a\0b,c\n",
+            &["-d", "/\\0/", "-d", ",", "-j", "|", "1-3"],
+            b"a|b|c\n",
         );
     }
 
     #[test]
-    fn empty_parts_ignored() {
+    fn escaped_backslash_is_not_nul() {
         run_success_test(
-            "Comma-separated selections: empty parts ignored",
-            b"apple,banana,cherry\n",
-            &["-d", ",", ",,1,,"],
-            b"apple\n",
+            "\\\\0 is still a backslash followed by 0",
+            b"a\\0b\0c\n",
+            &["-d", "/\\\\0/", "2"],
+            b"b\0c\n",
         );
     }
+}
+
+mod tabs {
+    use super::*;
 
     #[test]
-    fn with_d_flag_letter_treated_as_selection_errors() {
-        run_error_test(
-            "Comma-separated selections: with -d flag, letter treated as selection (errors)",
-            b"apple,banana,cherry\n",
-            &["-d", ",", "a", "1"],
+    fn splits_on_tabs() {
+        run_success_test(
+            "-t splits a tab-separated line",
+            b"name\tage\tcity\n",
+            &["-t", "1", "3"],
+            b"name\tcity\n",
+        );
+        run_success_test(
+            "--tabs composes with --join",
+            b"name\tage\tcity\n",
+            &["--tabs", "--join=,", "1-3"],
+            b"name,age,city\n",
         );
     }
 
     #[test]
-    fn with_d_flag_mixed_letter_number_letter_part_errors() {
-        run_error_test(
-            "Comma-separated selections: with -d flag, mixed letter-number (letter part errors)",
-            b"apple,banana,cherry\n",
-            &["-d", ",", "1,a", "1"],
-        );
+    fn same_as_tab_delimiter() {
+        let input = b"a\t\tb c\td\n";
+        let output = |args: &[&str]| {
+            Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+                .args(args)
+                .write_stdin(input.as_slice())
+                .output()
+                .unwrap()
+                .stdout
+        };
+        let tabs = output(&["-t", "2-4"]);
+        assert_eq!(tabs, b"\tb c\td\n");
+        assert_eq!(tabs, output(&["-d", "\t", "2-4"]));
+        assert_eq!(tabs, output(&["-d", "/\\t/", "2-4"]));
     }
 }
 
-mod optional_delimiter {
+mod invert_keep_positions {
     use super::*;
 
     #[test]
-    fn comma_as_first_argument() {
+    fn blanks_chosen_fields_in_place() {
         run_success_test(
-            "Optional delimiter: comma as first argument",
-            b"apple,banana,cherry\n",
-            &[",", "1"],
-            b"apple\n",
+            "Plain --invert removes field 2",
+            b"a,b,c\n",
+            &["-d", ",", "--invert", "2"],
+            b"a,c\n",
         );
-    }
-
-    #[test]
-    fn comma_with_multiple_selections() {
         run_success_test(
-            "Optional delimiter: comma with multiple selections",
-            b"apple,banana,cherry\n",
-            &[",", "1", "3"],
-            b"apple,cherry\n",
+            "--invert-keep-positions leaves it empty",
+            b"a,b,c\n",
+            &["-d", ",", "--invert-keep-positions", "2"],
+            b"a,,c\n",
         );
     }
 
     #[test]
-    fn regex_pattern_as_first_argument() {
+    fn placeholder_fills_blanked_fields() {
         run_success_test(
-            "Optional delimiter: regex pattern as first argument",
-            b"this is a test\n",
-            &["/\\s+/", "1", "2"],
-            b"this is\n",
+            "The placeholder takes the place of the chosen fields",
+            b"a,b,c,d\n",
+            &[
+                "-d",
+                ",",
+                "--invert-keep-positions",
+                "--placeholder=-",
+                "2",
+                "4",
+            ],
+            b"a,-,c,-\n",
         );
     }
 
     #[test]
-    fn literal_pattern_as_first_argument() {
+    fn is_an_alias_of_redact() {
         run_success_test(
-            "Optional delimiter: literal pattern as first argument",
-            b"apple,banana;cherry\n",
-            &["[,;]", "1"],
-            b"apple,banana;cherry\n",
+            "Given alongside --redact, it's the same flag",
+            b"a,b\n",
+            &[
+                "-d",
+                ",",
+                "--invert-keep-positions",
+                "--redact",
+                "-p",
+                "x",
+                "1",
+            ],
+            b"x,b\n",
         );
     }
 
     #[test]
-    fn d_flag_takes_priority() {
+    fn rejects_bad_usage() {
         run_error_test(
-            "Optional delimiter: -d flag takes priority",
-            b"apple,banana,cherry\n",
-            &["-d", ",", ".", "1"],
+            "--invert-keep-positions in chars mode",
+            b"ab\n",
+            &["-c", "--invert-keep-positions", "1"],
         );
     }
+}
+
+mod anchors {
+    use super::*;
 
     #[test]
-    fn selection_takes_priority_over_delimiter() {
-        run_error_test(
-            "Optional delimiter: selection takes priority over delimiter",
-            b"apple,banana,cherry\n",
-            &["1", "2"],
+    fn selects_between_anchors() {
+        run_success_test(
+            "Fields from BEGIN to END, anchors included",
+            b"x,BEGIN,a,b,END,y\nBEGIN,c,END\n",
+            &["-d", ",", "--from=^BEGIN$", "--to=^END$"],
+            b"BEGIN,a,b,END\nBEGIN,c,END\n",
+        );
+        run_success_test(
+            "--exclude-anchors leaves the anchors out",
+            b"x,BEGIN,a,b,END,y\n",
+            &[
+                "-d",
+                ",",
+                "--from",
+                "^BEGIN$",
+                "--to",
+                "^END$",
+                "--exclude-anchors",
+            ],
+            b"a,b\n",
         );
     }
 
     #[test]
-    fn single_letter_as_delimiter() {
+    fn one_anchor_runs_to_the_end() {
         run_success_test(
-            "Optional delimiter: single letter as delimiter",
-            b"apple,banana,cherry\n",
-            &["a", "2"],
-            b"pple,b\n",
+            "--from alone runs to the last field",
+            b"x,BEGIN,a,b\n",
+            &["-d", ",", "--from=BEGIN"],
+            b"BEGIN,a,b\n",
+        );
+        run_success_test(
+            "--to alone runs from the first field",
+            b"x,y,END,z\n",
+            &["-d", ",", "--to=END", "--exclude-anchors"],
+            b"x,y\n",
         );
     }
 
     #[test]
-    fn regex_delimiter_with_d_flag() {
+    fn missing_anchor() {
         run_success_test(
-            "Optional delimiter: regex delimiter with -d flag",
-            b"apple,banana;cherry\n",
-            &["-d", "/[,;]/", "1", "3"],
-            b"apple,cherry\n",
+            "A record without the anchor is empty",
+            b"x,BEGIN,a\n",
+            &["-d", ",", "--from=BEGIN", "--to=END"],
+            b"\n",
+        );
+        run_error_test(
+            "--strict-bounds errors on a missing anchor",
+            b"x,BEGIN,a\n",
+            &["-d", ",", "--from=BEGIN", "--to=END", "--strict-bounds"],
         );
     }
 
     #[test]
-    fn literal_pipe_with_d_flag() {
+    fn rejects_bad_usage() {
+        run_error_test(
+            "Anchors with selections",
+            b"a,b\n",
+            &["-d", ",", "--from=a", "1"],
+        );
+        run_error_test(
+            "--exclude-anchors alone",
+            b"a,b\n",
+            &["-d", ",", "--exclude-anchors"],
+        );
+    }
+}
+
+mod pick {
+    use super::*;
+
+    #[test]
+    fn keeps_matching_fields() {
         run_success_test(
-            "Optional delimiter: literal pipe with -d flag",
-            b"apple|banana|cherry\n",
-            &["-d", "|", "1", "3"],
-            b"apple|cherry\n",
+            "Only fields containing a vowel are kept",
+            b"a,bb,e,xyz\n",
+            &["-d", ",", "--pick", "[aeiou]"],
+            b"a,e\n",
         );
     }
 
     #[test]
-    fn regex_delimiter_implicit() {
+    fn invert_drops_matching_fields() {
         run_success_test(
-            "Optional delimiter: regex delimiter implicit",
-            b"apple,banana;cherry\n",
-            &["/[,;]/", "1", "3"],
-            b"apple,cherry\n",
+            "--invert drops the picked fields",
+            b"a,bb,e,xyz\n",
+            &["-d", ",", "--pick=[aeiou]", "--invert"],
+            b"bb,xyz\n",
         );
     }
 
     #[test]
-    fn regex_delimiter_literal_slash() {
+    fn selections_apply_after_pick() {
         run_success_test(
-            "Optional delimiter: regex delimiter for literal slash",
-            b"apple/banana/cherry\n",
-            &["/\\//", "1", "3"],
-            b"apple/cherry\n",
+            "Positions count only the picked fields",
+            b"id,42,name,7\n",
+            &["-d", ",", "--pick=^\\d+$", "2"],
+            b"7\n",
+        );
+    }
+
+    #[test]
+    fn rejects_bad_usage() {
+        run_error_test("--pick in chars mode", b"abc\n", &["-c", "--pick=a", "1"]);
+        run_error_test(
+            "--pick with an invalid regex",
+            b"a,b\n",
+            &["-d", ",", "--pick=("],
         );
     }
 }
 
-mod edge_case {
+mod require {
     use super::*;
 
     #[test]
-    fn single_field_with_out_of_range_index() {
+    fn drops_records_that_do_not_match() {
         run_success_test(
-            "Single field with out-of-range index",
-            b"apple\n",
-            &["-d", " ", "2"],
-            b"\n",
+            "Malformed records are dropped",
+            b"1,a\nbad\n2,b\n",
+            &["-d", ",", "--require", "^\\d+,\\w+$", "2"],
+            b"a\nb\n",
         );
     }
 
     #[test]
-    fn single_delimiter_at_beginning() {
+    fn partial_match_is_enough() {
         run_success_test(
-            "Single delimiter at beginning",
-            b" apple\n",
-            &["-d", " ", "2"],
-            b"apple\n",
+            "An unanchored pattern can match anywhere",
+            b"a,1\nb,c\n",
+            &["-d", ",", "--require=\\d", "1"],
+            b"a\n",
         );
     }
 
     #[test]
-    fn single_delimiter_at_end() {
-        run_success_test(
-            "Single delimiter at end",
-            b"apple \n",
-            &["-d", " ", "1"],
-            b"apple\n",
+    fn strict_errors_with_the_record() {
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["-d", ",", "--require=^\\d+,", "--strict", "2"])
+            .write_stdin("1,a\nbad\n2,b\n")
+            .output()
+            .expect("running splitby should not fail");
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("line 2: require error"),
+            "stderr should name the record, got: {stderr}"
         );
     }
 
     #[test]
-    fn multiple_delimiters_with_spaces_and_commas() {
-        run_success_test(
-            "Multiple delimiters with spaces and commas",
-            b"apple, orange  banana, pear\n",
-            &["-d", "/[, ]+/", "1-3"],
-            b"apple, orange  banana\n",
+    fn strict_require_alone_errors() {
+        run_error_test(
+            "--strict-require errors on a malformed record",
+            b"1,a\nbad\n",
+            &["-d", ",", "--require=,", "--strict-require", "1"],
         );
     }
 
     #[test]
-    fn delimiter_appears_multiple_times() {
+    fn whole_record_passes_through() {
         run_success_test(
-            "Delimiter appears multiple times",
-            b"apple,,orange\n",
-            &["-d", ",", "3"],
-            b"orange\n",
+            "Matching records pass through whole with no selection",
+            b"1,a\nbad\n",
+            &["-d", ",", "--require=,"],
+            b"1,a\n",
         );
     }
 
     #[test]
-    fn delimiter_appears_multiple_times_with_range() {
-        run_success_test(
-            "Delimiter appears multiple times with range",
-            b"apple,,orange\n",
-            &["-d", ",", "1-3"],
-            b"apple,,orange\n",
+    fn rejects_other_modes() {
+        run_error_test(
+            "--require in bytes mode",
+            b"abc\n",
+            &["-b", "--require=a", "1"],
+        );
+        run_error_test(
+            "--require with an invalid regex",
+            b"a\n",
+            &["-d", ",", "--require=(", "1"],
         );
     }
 }
 
-mod join_and_trim {
+mod head_and_tail {
     use super::*;
 
     #[test]
-    fn can_join_selections() {
+    fn head_keeps_the_first_records() {
         run_success_test(
-            "Can join selections",
-            b"boo hoo foo\n",
-            &["-d", " ", "--join=,"],
-            b"boo,hoo,foo\n",
+            "--head 2 keeps the first two records",
+            b"1\n2\n3\n4\n5\n",
+            &["-d", ",", "--head", "2", "1"],
+            b"1\n2\n",
         );
     }
 
     #[test]
-    fn can_join_whole_string() {
+    fn tail_keeps_the_last_records() {
         run_success_test(
-            "Can join whole string",
-            b"boo hoo foo\n",
-            &["-w", "-d", " ", "--join=,"],
-            b"boo,hoo,foo\n",
+            "--tail 2 keeps the last two records",
+            b"1\n2\n3\n4\n5\n",
+            &["-d", ",", "--tail", "2", "1"],
+            b"4\n5\n",
         );
     }
 
     #[test]
-    fn doesnt_join_in_ranges() {
+    fn tail_keeps_input_line_numbers() {
         run_success_test(
-            "Doesn't join in ranges",
-            b"boo hoo foo\n",
-            &["-d", " ", "--join=,", "1", "2-3"],
-            b"boo,hoo,foo\n",
+            "--tail records keep their line numbers from the input",
+            b"a\nb\nc\nd\ne",
+            &["-d", ",", "--tail=2", "--number", "1"],
+            b"4\td\n5\te",
+        );
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["-d", ",", "--tail", "3", "--strict-bounds", "2"])
+            .write_stdin("a,b\na,b\na,b\na,b\na\na,b\n")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("line 5: strict-bounds error"),
+            "errors should name the input line, got: {stderr}"
         );
     }
 
     #[test]
-    fn join_space() {
+    fn tail_longer_than_input() {
         run_success_test(
-            "Join with space",
-            b"apple,banana,cherry\n",
-            &["-d", ",", "--join=space", "1", "2", "3"],
-            b"apple banana cherry\n",
+            "--tail longer than the input keeps everything",
+            b"1\n2\n",
+            &["-d", ",", "--tail", "5", "1"],
+            b"1\n2\n",
         );
     }
 
     #[test]
-    fn join_space_single_selection() {
+    fn tail_after_head() {
         run_success_test(
-            "Join with space (single selection, no join)",
-            b"apple,banana,cherry\n",
-            &["-d", ",", "--join=space", "1"],
-            b"apple\n",
+            "--tail takes the last of the records --head reads",
+            b"1\n2\n3\n4\n5\n",
+            &["-d", ",", "--head=4", "--tail=2", "1"],
+            b"3\n4\n",
         );
     }
 
     #[test]
-    fn join_first_mixed_delimiters() {
+    fn tail_still_reads_the_header() {
         run_success_test(
-            "Join with first (mixed delimiters, uses first)",
-            b"apple;banana,cherry\n",
-            &["-d", "/[,;]/", "--join=first", "1", "2", "3"],
-            b"apple;banana;cherry\n",
+            "--header is looked up before --tail drops it",
+            b"name,age\nann,30\nbob,40\n",
+            &["-d", ",", "--tail", "1", "--header=age"],
+            b"40\n",
         );
     }
 
     #[test]
-    fn join_last_mixed_delimiters() {
-        run_success_test(
-            "Join with last (mixed delimiters, uses last)",
-            b"apple;banana,cherry\n",
-            &["-d", "/[,;]/", "--join=last", "1", "2", "3"],
-            b"apple,banana,cherry\n",
+    fn tail_rejects_whole_string() {
+        run_error_test(
+            "--tail with --whole-string",
+            b"a\n",
+            &["-w", "-d", ",", "--tail", "1", "1"],
         );
     }
 
     #[test]
-    fn join_first_no_delimiters() {
-        run_success_test(
-            "Join with first (no delimiters, falls back to space)",
-            b"apple\n",
-            &["-d", ",", "--join=first", "1"],
-            b"apple\n",
+    fn tail_needs_a_number() {
+        run_error_test("--tail with no count", b"a\n", &["-d", ",", "1", "--tail"]);
+        run_error_test(
+            "--tail with a bad count",
+            b"a\n",
+            &["-d", ",", "--tail=x", "1"],
         );
     }
+}
+
+mod zero_selection {
+    use super::*;
 
     #[test]
-    fn join_last_no_delimiters() {
-        run_success_test(
-            "Join with last (no delimiters, falls back to space)",
-            b"apple\n",
-            &["-d", ",", "--join=last", "1"],
-            b"apple\n",
-        );
+    fn zero_errors_in_every_mode() {
+        for mode in ["--fields", "--bytes", "--characters"] {
+            run_error_test(
+                &format!("0 errors with {mode}"),
+                b"a,b\n",
+                &["-d", ",", mode, "0"],
+            );
+            run_error_test(
+                &format!("0 errors with {mode} and --no-strict"),
+                b"a,b\n",
+                &["-d", ",", mode, "--no-strict", "0"],
+            );
+            run_error_test(
+                &format!("0 errors with {mode} and --strict"),
+                b"a,b\n",
+                &["-d", ",", mode, "--strict", "0"],
+            );
+        }
     }
 
     #[test]
-    fn join_with_empty_fields() {
-        run_success_test(
-            "Join with empty fields (still finds delimiter)",
-            b",,,\n",
-            &["-d", ",", "--join=first", "1", "2", "3"],
-            b",,\n",
-        );
+    fn zero_errors_as_either_end_of_a_range() {
+        run_error_test("0 as the start", b"a,b\n", &["-d", ",", "0-2"]);
+        run_error_test("0 as the end", b"a,b\n", &["-d", ",", "1-0"]);
+        run_error_test("0 with a step", b"a,b\n", &["-d", ",", "0-2:1"]);
     }
 
     #[test]
-    fn join_space_whole_string() {
-        run_success_test(
-            "Join with space in whole-string mode",
-            b"apple,banana,cherry",
-            &["-w", "-d", ",", "--join=space", "1", "2", "3"],
-            b"apple banana cherry",
-        );
+    fn zero_errors_without_input() {
+        run_error_test("0 errors before reading anything", b"", &["-d", ",", "0"]);
     }
+}
 
-    #[test]
-    fn join_first_whole_string() {
-        run_success_test(
-            "Join with first in whole-string mode",
-            b"apple,banana,cherry",
-            &["-w", "-d", ",", "--join=first", "1", "2", "3"],
-            b"apple,banana,cherry",
+mod completions {
+    use super::*;
+
+    fn completions(shell: &str) -> String {
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["completions", shell])
+            .output()
+            .expect("running splitby should not fail");
+        assert!(
+            output.status.success(),
+            "completions {shell} should succeed"
         );
+        String::from_utf8(output.stdout).expect("scripts should be UTF-8")
     }
 
     #[test]
-    fn join_last_whole_string() {
-        run_success_test(
-            "Join with last in whole-string mode",
-            b"apple,banana,cherry",
-            &["-w", "-d", ",", "--join=last", "1", "2", "3"],
-            b"apple,banana,cherry",
-        );
+    fn every_shell_has_a_script() {
+        assert!(completions("bash").contains("complete -o default -F _splitby splitby"));
+        assert!(completions("zsh").starts_with("#compdef splitby"));
+        assert!(completions("fish").contains("complete -c splitby -s j -l join"));
+        assert!(completions("powershell").contains("Register-ArgumentCompleter"));
     }
 
     #[test]
-    fn join_after_previous_basic() {
-        run_success_test(
-            "Join with after-previous (basic)",
-            b"apple,banana,cherry\n",
-            &["-d", ",", "--join=after-previous", "1", "3"],
-            b"apple,cherry\n",
-        );
+    fn covers_every_flag_in_help() {
+        let help = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .arg("--help")
+            .output()
+            .expect("running splitby should not fail")
+            .stdout;
+        let help = String::from_utf8(help).expect("help should be UTF-8");
+        let script = completions("bash");
+        for word in help.split(|character: char| !(character.is_alphanumeric() || character == '-'))
+        {
+            if word.starts_with("--") && word.len() > 2 {
+                assert!(script.contains(word), "completions are missing {word}");
+            }
+        }
     }
 
     #[test]
-    fn join_after_previous_mixed_delimiters() {
-        run_success_test(
-            "Join with after-previous (mixed delimiters)",
-            b"apple,banana;cherry:date\n",
-            &["-d", "/[,;:]/", "--join=after-previous", "1", "3"],
-            b"apple,cherry\n",
+    fn offers_join_and_align_keywords() {
+        let script = completions("bash");
+        assert!(script.contains("-j|--join) COMPREPLY=($(compgen -W \"auto after-previous"));
+        assert!(
+            script
+                .contains("-a|--align) COMPREPLY=($(compgen -W \"left right center squash none\"")
         );
     }
 
     #[test]
-    fn join_after_previous_non_adjacent() {
-        run_success_test(
-            "Join with after-previous (non-adjacent fields)",
-            b"apple,banana,cherry,date\n",
-            &["-d", ",", "--join=after-previous", "1", "4"],
-            b"apple,date\n",
-        );
+    fn unknown_shell_errors() {
+        run_error_test("Unknown shell", b"", &["completions", "tcsh"]);
+        run_error_test("Missing shell", b"", &["completions"]);
     }
+}
+
+mod broken_pipe {
+    use splitby::SplitError;
+    use std::io::Write;
+    use std::process::Stdio;
 
     #[test]
-    fn join_after_previous_multiple_selections() {
-        run_success_test(
-            "Join with after-previous (multiple selections)",
-            b"apple,banana;cherry:date\n",
-            &["-d", "/[,;:]/", "--join=after-previous", "1", "3", "1"],
-            b"apple,cherry:apple\n",
-        );
+    fn library_callers_get_an_error() {
+        // Only the binary treats a closed pipe as success, so the library never exits for it
+        let error = SplitError::BrokenPipe("Broken pipe (os error 32)".to_string());
+        assert_eq!(error.exit_code(), 2);
+        assert_eq!(error.to_string(), "Broken pipe (os error 32)");
     }
 
     #[test]
-    fn join_after_previous_out_of_bounds() {
-        run_success_test(
-            "Join with after-previous (out-of-bounds field, no delimiter available)",
-            b"apple\n",
-            &["-d", ",", "--join=after-previous", "1", "5"],
-            b"apple\n",
+    fn closed_stdout_exits_cleanly() {
+        let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["-d", ",", "1"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        // Close the reading end before anything is written, as `| head` does once satisfied
+        drop(child.stdout.take());
+
+        let mut input: Vec<u8> = Vec::new();
+        for line in 0..200_000 {
+            input.extend_from_slice(format!("{line},x\n").as_bytes());
+        }
+        let mut stdin = child.stdin.take().unwrap();
+        // splitby may stop reading once it finds the pipe closed
+        let _ = stdin.write_all(&input);
+        drop(stdin);
+
+        let output = child.wait_with_output().unwrap();
+        assert_eq!(output.status.code(), Some(0));
+        assert!(
+            output.stderr.is_empty(),
+            "unexpected stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
         );
     }
+}
 
-    #[test]
-    fn join_before_next_basic() {
-        run_success_test(
-            "Join with before-next (basic)",
-            b"apple,banana,cherry\n",
-            &["-d", ",", "--join=before-next", "1", "3"],
-            b"apple,cherry\n",
-        );
+mod explain {
+    use super::*;
+
+    fn explain(arguments: &[&str]) -> (Option<i32>, String, Vec<u8>) {
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(arguments)
+            .write_stdin("a,b,c,d\n")
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+            output.stdout,
+        )
     }
 
     #[test]
-    fn join_before_next_mixed_delimiters() {
-        run_success_test(
-            "Join with before-next (mixed delimiters)",
-            b"apple,banana;cherry:date\n",
-            &["-d", "/[,;:]/", "--join=before-next", "1", "4", "2"],
-            b"apple:date,banana\n",
+    fn describes_the_plan_without_reading_input() {
+        let (code, stderr, stdout) = explain(&["-d", ",", "1-3", "--invert", "--explain"]);
+        assert_eq!(code, Some(0));
+        assert!(stdout.is_empty(), "input should not be processed");
+        assert!(stderr.contains("input mode: per-line"), "{stderr}");
+        assert!(stderr.contains("selection mode: fields"), "{stderr}");
+        assert!(stderr.contains("delimiter: \",\""), "{stderr}");
+        assert!(stderr.contains("selections: 1-3 (inverted)"), "{stderr}");
+        assert!(
+            !stderr.contains("taken from the first argument"),
+            "{stderr}"
         );
     }
 
     #[test]
-    fn join_before_next_non_adjacent() {
-        run_success_test(
-            "Join with before-next (non-adjacent fields)",
-            b"apple,banana,cherry,date\n",
-            &["-d", ",", "--join=before-next", "1", "4"],
-            b"apple,date\n",
+    fn shows_an_inferred_delimiter() {
+        let (_, stderr, _) = explain(&["/[,;]/", "2", "-j", "|", "--strict", "--explain"]);
+        assert!(
+            stderr.contains("delimiter: /[,;]/ (regex), taken from the first argument"),
+            "{stderr}"
         );
+        assert!(stderr.contains("join: \"|\""), "{stderr}");
+        assert!(stderr.contains("strict: bounds on, return on"), "{stderr}");
     }
 
     #[test]
-    fn join_before_next_multiple_selections() {
+    fn shows_strict_require_and_empty() {
+        let (_, stderr, _) = explain(&["-d", ",", "1", "--explain"]);
+        assert!(stderr.contains("require off, empty off"), "{stderr}");
+        let (_, stderr, _) = explain(&[
+            "-d",
+            ",",
+            "1",
+            "--strict-require",
+            "--strict-empty",
+            "--explain",
+        ]);
+        assert!(stderr.contains("require on, empty on"), "{stderr}");
+    }
+}
+
+mod exit_codes {
+    use super::*;
+
+    fn exit_code(input: &[u8], arguments: &[&str]) -> Option<i32> {
+        Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(arguments)
+            .write_stdin(input)
+            .output()
+            .expect("running splitby should not fail")
+            .status
+            .code()
+    }
+
+    #[test]
+    fn usage_and_io_errors_exit_with_2() {
+        assert_eq!(
+            exit_code(b"a,b\n", &["-d", ",", "1", "--no-such-flag"]),
+            Some(2)
+        );
+        assert_eq!(
+            exit_code(b"", &["-d", ",", "1", "--input", "no/such/file.txt"]),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn data_errors_exit_with_1() {
+        assert_eq!(
+            exit_code(b"a,b\n", &["-d", ",", "--strict-bounds", "3"]),
+            Some(1)
+        );
+        assert_eq!(
+            exit_code(b"b\na\n", &["-d", ",", "--assert-sorted=1", "1"]),
+            Some(1)
+        );
+    }
+}
+
+mod range_and_selection {
+    use super::*;
+
+    #[test]
+    fn negative_number() {
         run_success_test(
-            "Join with before-next (multiple selections)",
-            b"apple,banana;cherry:date\n",
-            &["-d", "/[,;:]/", "--join=before-next", "1", "4", "2"],
-            b"apple:date,banana\n",
+            "Negative number",
+            b"this is a test\n",
+            &["-d", " ", "-1"],
+            b"test\n",
         );
     }
 
     #[test]
-    fn join_before_next_out_of_bounds() {
+    fn negative_split_by_comma() {
         run_success_test(
-            "Join with before-next (out-of-bounds field, no delimiter available)",
-            b"apple\n",
-            &["-d", ",", "--join=before-next", "1", "5"],
-            b"apple\n",
+            "Negative split by comma",
+            b"apple,banana,plum,cherry\n",
+            &["-d", ",", "-2"],
+            b"plum\n",
         );
     }
 
     #[test]
-    fn join_auto_basic() {
+    fn split_by_space_empty_selection() {
         run_success_test(
-            "Join with auto (basic, preserves delimiter)",
-            b"apple,banana,cherry\n",
-            &["-d", ",", "--join=auto", "1", "3"],
-            b"apple,cherry\n",
+            "Split by space, empty selection",
+            b"this is a test\n",
+            &["-d", " "],
+            b"this is a test\n",
         );
     }
 
     #[test]
-    fn join_auto_fallback_to_space() {
+    fn split_by_space_empty_selection_whole_string() {
         run_success_test(
-            "Join with auto (no delimiter, falls back to space)",
-            b"apple\n",
-            &["-d", ",", "--join=auto", "1", "1"],
-            b"apple apple\n",
+            "Split by space, empty selection whole-string",
+            b"this is a test\n",
+            &["-w", "-d", " "],
+            b"this is a test\n",
         );
     }
 
     #[test]
-    fn join_auto_mixed_delimiters() {
+    fn range_selection() {
         run_success_test(
-            "Join with auto (mixed delimiters)",
-            b"apple,banana;cherry:date\n",
-            &["-d", "/[,;:]/", "--join=auto", "1", "3"],
-            b"apple,cherry\n",
+            "Range selection",
+            b"this is a test\n",
+            &["-d", " ", "1-2"],
+            b"this is\n",
         );
     }
 
     #[test]
-    fn join_none_basic() {
+    fn negative_range_selection() {
         run_success_test(
-            "Join with none (no separator)",
-            b"apple,banana,cherry\n",
-            &["-d", ",", "--join=none", "1", "3"],
-            b"applecherry\n",
+            "Negative range selection",
+            b"this is a test\n",
+            &["-d", " ", "-3--1"],
+            b"is a test\n",
         );
     }
 
     #[test]
-    fn join_none_multiple_selections() {
+    fn positive_to_negative_range() {
         run_success_test(
-            "Join with none (multiple selections)",
-            b"apple,banana,cherry\n",
-            &["-d", ",", "--join=none", "1", "2", "3"],
-            b"applebananacherry\n",
+            "Positive to negative range",
+            b"this is a test\n",
+            &["-d", " ", "2--1"],
+            b"is a test\n",
         );
     }
 
     #[test]
-    fn join_none_with_ranges() {
+    fn negative_to_positive_range() {
         run_success_test(
-            "Join with none (with ranges, no join between ranges)",
+            "Negative to positive range",
+            b"this is a test\n",
+            &["-d", " ", "-3-4"],
+            b"is a test\n",
+        );
+    }
+
+    #[test]
+    fn split_by_space_with_multiple_indexes() {
+        run_success_test(
+            "Split by space with multiple indexes",
+            b"this is a test\n",
+            &["-d", " ", "1", "2", "3-4"],
+            b"this is a test\n",
+        );
+    }
+
+    #[test]
+    fn split_by_space_whole_string() {
+        run_success_test(
+            "Split by space whole-string",
+            b"this is a test\n",
+            &["-w", "-d", " ", "1", "3-4"],
+            b"this a test\n",
+        );
+    }
+
+    #[test]
+    fn whole_string_ends_with_newline_when_missing() {
+        // Input has no trailing newline. When stdout is a terminal we add newline; in tests (pipe) we don't.
+        run_success_test(
+            "Whole-string: no trailing newline when not a terminal",
+            b"foo,bar",
+            &["-w", "-d", ",", "1"],
+            b"foo",
+        );
+    }
+}
+
+mod stepped_range {
+    use super::*;
+
+    #[test]
+    fn every_other_field() {
+        run_success_test(
+            "Stepped range selects every other field",
+            b"a b c d e f g h i\n",
+            &["-d", " ", "1-9:2"],
+            b"a c e g i\n",
+        );
+    }
+
+    #[test]
+    fn negative_step_walks_backwards() {
+        run_success_test(
+            "Negative step walks backwards",
+            b"a b c d e\n",
+            &["-d", " ", "-1-1:-2"],
+            b"e c a\n",
+        );
+    }
+
+    #[test]
+    fn step_with_keywords() {
+        run_success_test(
+            "Stepped range with keywords",
+            b"a b c d e f g\n",
+            &["-d", " ", "first-last:3"],
+            b"a d g\n",
+        );
+    }
+
+    #[test]
+    fn step_in_comma_list() {
+        run_success_test(
+            "Stepped range in a comma-separated list",
+            b"a,b,c,d,e,f\n",
+            &["-d", ",", "1,2-6:2"],
+            b"a,b,d,f\n",
+        );
+    }
+
+    #[test]
+    fn invert_expands_before_inverting() {
+        run_success_test(
+            "Invert applies to the expanded stepped set",
+            b"a b c d e f\n",
+            &["-d", " ", "--invert", "1-6:2"],
+            b"b d f\n",
+        );
+    }
+
+    #[test]
+    fn step_in_byte_mode() {
+        run_success_test(
+            "Stepped range in byte mode",
+            b"abcdef\n",
+            &["-b", "1-6:2"],
+            b"ace\n",
+        );
+    }
+
+    #[test]
+    fn step_in_char_mode() {
+        run_success_test(
+            "Stepped range in char mode",
+            b"abcdef\n",
+            &["-c", "-1-1:-2"],
+            b"fdb\n",
+        );
+    }
+
+    #[test]
+    fn step_with_placeholder_past_end() {
+        run_success_test(
+            "Stepped range with placeholder past the end",
+            b"a b c\n",
+            &["-d", " ", "--placeholder=X", "1-5:2"],
+            b"a c X\n",
+        );
+    }
+
+    #[test]
+    fn zero_step_errors() {
+        run_error_test("Zero step errors", b"a b c\n", &["-d", " ", "1-3:0"]);
+    }
+
+    #[test]
+    fn negative_step_with_ascending_range_errors() {
+        run_error_test(
+            "Negative step with ascending range errors",
+            b"a b c\n",
+            &["-d", " ", "1-3:-1"],
+        );
+    }
+
+    #[test]
+    fn strict_bounds_checks_stepped_range() {
+        run_error_test(
+            "Strict bounds checks stepped range ends",
+            b"a b c\n",
+            &["-d", " ", "--strict-bounds", "1-5:2"],
+        );
+    }
+}
+
+mod comma_separated_selection {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        run_success_test(
+            "Comma-separated selections: basic",
             b"apple,banana,cherry,date\n",
-            &["-d", ",", "--join=none", "1-2", "4"],
-            b"applebananadate\n",
+            &["-d", ",", "1,2,3"],
+            b"apple,banana,cherry\n",
         );
     }
 
     #[test]
-    fn join_space_basic() {
+    fn with_ranges() {
         run_success_test(
-            "Join with space (basic)",
+            "Comma-separated selections: with ranges",
+            b"apple,banana,cherry,date,elderberry\n",
+            &["-d", ",", "1-2,4,5"],
+            b"apple,banana,date,elderberry\n",
+        );
+    }
+
+    #[test]
+    fn leading_comma() {
+        run_success_test(
+            "Comma-separated selections: leading comma",
             b"apple,banana,cherry\n",
-            &["-d", ",", "--join=space", "1", "3"],
-            b"apple cherry\n",
+            &["-d", ",", ",1"],
+            b"apple\n",
         );
     }
 
     #[test]
-    fn join_space_multiple_selections() {
+    fn trailing_comma() {
         run_success_test(
-            "Join with space (multiple selections)",
+            "Comma-separated selections: trailing comma",
             b"apple,banana,cherry\n",
-            &["-d", ",", "--join=space", "1", "2", "3"],
-            b"apple banana cherry\n",
+            &["-d", ",", "1,"],
+            b"apple\n",
         );
     }
 
     #[test]
-    fn join_first_with_placeholder() {
+    fn mixed_with_spaces() {
         run_success_test(
-            "Join with first (with placeholder out-of-bounds)",
-            b"apple,banana\n",
-            &["-d", ",", "--join=first", "--placeholder=X", "1", "5", "2"],
-            b"apple,X,banana\n",
+            "Comma-separated selections: mixed with spaces",
+            b"apple,banana,cherry,date\n",
+            &["-d", ",", "1,2", "3", "4"],
+            b"apple,banana,cherry,date\n",
         );
     }
 
     #[test]
-    fn join_last_with_placeholder() {
+    fn multiple_comma_strings() {
         run_success_test(
-            "Join with last (with placeholder out-of-bounds)",
-            b"apple,banana\n",
-            &["-d", ",", "--join=last", "--placeholder=X", "1", "5", "2"],
-            b"apple,X,banana\n",
+            "Comma-separated selections: multiple comma strings",
+            b"apple,banana,cherry,date\n",
+            &["-d", ",", "1,2", "3,4"],
+            b"apple,banana,cherry,date\n",
         );
     }
 
     #[test]
-    fn join_after_previous_with_placeholder() {
+    fn negative_indices() {
         run_success_test(
-            "Join with after-previous (with placeholder out-of-bounds)",
-            b"apple,banana\n",
-            &[
-                "-d",
+            "Comma-separated selections: negative indices",
+            b"apple,banana,cherry,date\n",
+            &["-d", ",", "-3,-1"],
+            b"banana,date\n",
+        );
+    }
+
+    #[test]
+    fn mixed_positive_and_negative() {
+        run_success_test(
+            "Comma-separated selections: mixed positive and negative",
+            b"apple,banana,cherry,date\n",
+            &["-d", ",", "1,-2"],
+            b"apple,cherry\n",
+        );
+    }
+
+    #[test]
+    fn with_join() {
+        run_success_test(
+            "Comma-separated selections: with join",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "--join=|", "1", "2", "3"],
+            b"apple|banana|cherry\n",
+        );
+    }
+
+    #[test]
+    fn whole_string_mode() {
+        run_success_test(
+            "Comma-separated selections: whole-string mode",
+            b"apple,banana\ncherry,date\n",
+            &["-w", "-d", ",", "1,2"],
+            b"apple,banana\ncherry",
+        );
+    }
+
+    #[test]
+    fn byte_mode() {
+        run_success_test(
+            "Comma-separated selections: byte mode",
+            b"hello\n",
+            &["--bytes", "1", "3", "5"],
+            b"hlo\n",
+        );
+    }
+
+    #[test]
+    fn char_mode() {
+        run_success_test(
+            "Comma-separated selections: char mode",
+            b"hello\n",
+            &["--characters", "1", "3", "5"],
+            b"hlo\n",
+        );
+    }
+
+    #[test]
+    fn with_invert() {
+        run_success_test(
+            "Comma-separated selections: with invert",
+            b"a,b,c,d\n",
+            &["-d", ",", "--invert", "1,3"],
+            b"b,d\n",
+        );
+    }
+
+    #[test]
+    fn empty_parts_ignored() {
+        run_success_test(
+            "Comma-separated selections: empty parts ignored",
+            b"apple,banana,cherry\n",
+            &["-d", ",", ",,1,,"],
+            b"apple\n",
+        );
+    }
+
+    #[test]
+    fn with_d_flag_letter_treated_as_selection_errors() {
+        run_error_test(
+            "Comma-separated selections: with -d flag, letter treated as selection (errors)",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "a", "1"],
+        );
+    }
+
+    #[test]
+    fn with_d_flag_mixed_letter_number_letter_part_errors() {
+        run_error_test(
+            "Comma-separated selections: with -d flag, mixed letter-number (letter part errors)",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "1,a", "1"],
+        );
+    }
+}
+
+mod optional_delimiter {
+    use super::*;
+
+    #[test]
+    fn comma_as_first_argument() {
+        run_success_test(
+            "Optional delimiter: comma as first argument",
+            b"apple,banana,cherry\n",
+            &[",", "1"],
+            b"apple\n",
+        );
+    }
+
+    #[test]
+    fn comma_with_multiple_selections() {
+        run_success_test(
+            "Optional delimiter: comma with multiple selections",
+            b"apple,banana,cherry\n",
+            &[",", "1", "3"],
+            b"apple,cherry\n",
+        );
+    }
+
+    #[test]
+    fn regex_pattern_as_first_argument() {
+        run_success_test(
+            "Optional delimiter: regex pattern as first argument",
+            b"this is a test\n",
+            &["/\\s+/", "1", "2"],
+            b"this is\n",
+        );
+    }
+
+    #[test]
+    fn bare_regex_syntax_stays_literal() {
+        run_success_test(
+            "Optional delimiter: a bare \\s+ is a literal, not whitespace",
+            b"a\\s+b c\n",
+            &["\\s+", "2"],
+            b"b c\n",
+        );
+        run_success_test(
+            "Optional delimiter: the same pattern in /.../ is a regex",
+            b"this  is a test\n",
+            &["/\\s+/", "1", "2"],
+            b"this  is\n",
+        );
+    }
+
+    #[test]
+    fn literal_pattern_as_first_argument() {
+        run_success_test(
+            "Optional delimiter: literal pattern as first argument",
+            b"apple,banana;cherry\n",
+            &["[,;]", "1"],
+            b"apple,banana;cherry\n",
+        );
+    }
+
+    #[test]
+    fn d_flag_takes_priority() {
+        run_error_test(
+            "Optional delimiter: -d flag takes priority",
+            b"apple,banana,cherry\n",
+            &["-d", ",", ".", "1"],
+        );
+    }
+
+    #[test]
+    fn selection_takes_priority_over_delimiter() {
+        run_error_test(
+            "Optional delimiter: selection takes priority over delimiter",
+            b"apple,banana,cherry\n",
+            &["1", "2"],
+        );
+    }
+
+    #[test]
+    fn single_letter_as_delimiter() {
+        run_success_test(
+            "Optional delimiter: single letter as delimiter",
+            b"apple,banana,cherry\n",
+            &["a", "2"],
+            b"pple,b\n",
+        );
+    }
+
+    #[test]
+    fn regex_delimiter_with_d_flag() {
+        run_success_test(
+            "Optional delimiter: regex delimiter with -d flag",
+            b"apple,banana;cherry\n",
+            &["-d", "/[,;]/", "1", "3"],
+            b"apple,cherry\n",
+        );
+    }
+
+    #[test]
+    fn literal_pipe_with_d_flag() {
+        run_success_test(
+            "Optional delimiter: literal pipe with -d flag",
+            b"apple|banana|cherry\n",
+            &["-d", "|", "1", "3"],
+            b"apple|cherry\n",
+        );
+    }
+
+    #[test]
+    fn regex_delimiter_implicit() {
+        run_success_test(
+            "Optional delimiter: regex delimiter implicit",
+            b"apple,banana;cherry\n",
+            &["/[,;]/", "1", "3"],
+            b"apple,cherry\n",
+        );
+    }
+
+    #[test]
+    fn regex_delimiter_literal_slash() {
+        run_success_test(
+            "Optional delimiter: regex delimiter for literal slash",
+            b"apple/banana/cherry\n",
+            &["/\\//", "1", "3"],
+            b"apple/cherry\n",
+        );
+    }
+}
+
+mod multiple_delimiters {
+    use super::*;
+
+    #[test]
+    fn comma_or_space() {
+        run_success_test(
+            "Repeated -d splits on any of the delimiters",
+            b"a, b c\n",
+            &["-d", ",", "-d", " ", "--skip-empty", "1-3", "-j", "|"],
+            b"a|b|c\n",
+        );
+    }
+
+    #[test]
+    fn adjacent_matches_leave_an_empty_field() {
+        run_success_test(
+            "Touching delimiters split separately, unless squashed",
+            b"a, b c\n",
+            &["-d", ",", "-d", " ", "1-4", "-j", "|"],
+            b"a||b|c\n",
+        );
+        run_success_test(
+            "Squashing treats touching delimiters as one",
+            b"a, b c\n",
+            &[
+                "-d",
                 ",",
-                "--join=after-previous",
-                "--placeholder=X",
-                "1",
-                "5",
-                "2",
+                "-d",
+                " ",
+                "--squash-delimiters",
+                "1-3",
+                "-j",
+                "|",
             ],
-            b"apple,X banana\n",
+            b"a|b|c\n",
+        );
+    }
+
+    #[test]
+    fn literals_are_escaped() {
+        run_success_test(
+            "Literal delimiters in an alternation match literally",
+            b"a.b|cxd\n",
+            &["-d", ".", "-d", "|", "3"],
+            b"cxd\n",
+        );
+    }
+
+    #[test]
+    fn regex_alternatives_stay_grouped() {
+        run_success_test(
+            "A regex alternative keeps its inline flags to itself",
+            b"aXbxcYd\n",
+            &["-d", "/(?i)x/", "-d", "Y", "4"],
+            b"d\n",
+        );
+    }
+
+    #[test]
+    fn mixed_with_fixed_is_rejected() {
+        run_error_test(
+            "Repeated -d with --fixed is ambiguous",
+            b"a,b\n",
+            &["-F", "-d", ",", "-d", ";", "1"],
+        );
+    }
+
+    #[test]
+    fn mixed_with_csv_is_rejected() {
+        run_error_test(
+            "Repeated -d with --csv is rejected",
+            b"a,b\n",
+            &["--csv", "-d", ",", "-d", ";", "1"],
+        );
+    }
+}
+
+mod fixed_delimiter {
+    use super::*;
+
+    #[test]
+    fn dot_is_literal() {
+        run_success_test(
+            "Fixed delimiter treats dot literally",
+            b"a.b.c\n",
+            &["-F", "-d", ".", "2"],
+            b"b\n",
+        );
+    }
+
+    #[test]
+    fn pipe_is_literal() {
+        run_success_test(
+            "Fixed delimiter treats pipe literally",
+            b"a|b|c\n",
+            &["--fixed", "-d", "|", "3"],
+            b"c\n",
+        );
+    }
+
+    #[test]
+    fn slashes_are_kept() {
+        run_success_test(
+            "Fixed delimiter keeps /.../ as literal text",
+            b"a/x/b,c\n",
+            &["-F", "-d", "/x/", "2"],
+            b"b,c\n",
+        );
+    }
+
+    #[test]
+    fn multi_byte_delimiter_does_not_overlap() {
+        run_success_test(
+            "Fixed delimiter matches are non-overlapping",
+            b"1aaa2\n",
+            &["-F", "-d", "aa", "2"],
+            b"a2\n",
+        );
+    }
+
+    #[test]
+    fn count_matches_regex_path() {
+        run_success_test(
+            "Fixed delimiter count matches regex path",
+            b"a.b..c\n",
+            &["-F", "-d", ".", "--count"],
+            b"4\n",
+        );
+    }
+
+    #[test]
+    fn invert_matches_regex_path() {
+        run_success_test(
+            "Fixed delimiter invert matches regex path",
+            b"a.b.c\n",
+            &["-F", "-d", ".", "--invert", "2"],
+            b"a.c\n",
+        );
+    }
+
+    #[test]
+    fn whole_string_drops_trailing_empty_field() {
+        run_success_test(
+            "Fixed delimiter whole-string drops trailing empty field",
+            b"a.b.",
+            &["-w", "-F", "-d", ".", "--count"],
+            b"2",
+        );
+    }
+
+    #[test]
+    fn align_uses_literal_delimiter() {
+        run_success_test(
+            "Fixed delimiter works with align",
+            b"apple.b\na.banana\n",
+            &["-F", "-d", ".", "--align"],
+            b"apple.b\na    .banana\n",
+        );
+    }
+}
+
+mod csv_fields {
+    use super::*;
+
+    #[test]
+    fn quoted_field_keeps_delimiter() {
+        run_success_test(
+            "CSV keeps commas inside quotes",
+            b"a,\"b,c\",d\n",
+            &["--csv", "2"],
+            b"b,c\n",
+        );
+    }
+
+    #[test]
+    fn doubled_quotes_unescape() {
+        run_success_test(
+            "CSV collapses doubled quotes",
+            b"\"say \"\"hi\"\"\",x\n",
+            &["--csv", "1"],
+            b"say \"hi\"\n",
+        );
+    }
+
+    #[test]
+    fn count_and_invert() {
+        run_success_test(
+            "CSV count uses parsed fields",
+            b"a,\"b,c\",d\n",
+            &["--csv", "--count"],
+            b"3\n",
+        );
+        run_success_test(
+            "CSV invert uses parsed fields",
+            b"a,\"b,c\",d\n",
+            &["--csv", "--invert", "2"],
+            b"a,d\n",
+        );
+    }
+
+    #[test]
+    fn custom_join_and_delimiter() {
+        run_success_test(
+            "CSV with a semicolon delimiter and join",
+            b"a;\"b;c\";d\n",
+            &["--csv", "-d", ";", "-j", "|", "1-3"],
+            b"a|b;c|d\n",
+        );
+    }
+
+    #[test]
+    fn spaces_around_quotes_are_dropped() {
+        run_success_test(
+            "CSV drops spaces around quoted values",
+            b"a, \"b\" ,c\n",
+            &["--csv", "2"],
+            b"b\n",
+        );
+    }
+
+    #[test]
+    fn newline_inside_quotes_needs_whole_string() {
+        run_error_test(
+            "CSV quote spanning lines errors per-line",
+            b"a,\"b\nc\",d\n",
+            &["--csv", "2"],
+        );
+        run_success_test(
+            "CSV quote spanning lines works with whole-string",
+            b"a,\"b\nc\",d",
+            &["--csv", "-w", "2"],
+            b"b\nc",
+        );
+    }
+
+    #[test]
+    fn rejects_regex_delimiter_and_other_modes() {
+        run_error_test(
+            "CSV rejects regex delimiter",
+            b"a,b\n",
+            &["--csv", "-d", "/,/", "1"],
+        );
+        run_error_test("CSV rejects bytes mode", b"a,b\n", &["--csv", "-b", "1"]);
+        run_error_test(
+            "CSV rejects an empty delimiter",
+            b"a,b\n",
+            &["--csv", "-d", "", "1"],
+        );
+    }
+}
+
+mod field_pattern {
+    use super::*;
+
+    #[test]
+    fn extracts_digits_from_fields() {
+        run_success_test(
+            "Field pattern extracts the first capture group",
+            b"id:123,name:bob,age:42\n",
+            &["-d", ",", "--field-pattern=(\\d+)", "1-3"],
+            b"123,42\n",
+        );
+    }
+
+    #[test]
+    fn whole_match_without_groups() {
+        run_success_test(
+            "Field pattern without groups uses the whole match",
+            b"id:123 x:7\n",
+            &["-d", " ", "--field-pattern", "[a-z]+:\\d", "1,2"],
+            b"id:1 x:7\n",
+        );
+    }
+
+    #[test]
+    fn placeholder_for_no_match() {
+        run_success_test(
+            "Field pattern uses the placeholder when nothing matches",
+            b"id:123,name:bob,age:42\n",
+            &["-d", ",", "--field-pattern", "(\\d+)", "-p", "NA", "1-3"],
+            b"123,NA,42\n",
+        );
+    }
+
+    #[test]
+    fn repeated_selection_extracts_once() {
+        run_success_test(
+            "Field pattern on a field selected twice",
+            b"id:123,age:42\n",
+            &["-d", ",", "--field-pattern", "id:(\\d+)", "1,1"],
+            b"123,123\n",
+        );
+    }
+
+    #[test]
+    fn rejects_other_modes() {
+        run_error_test(
+            "Field pattern is fields mode only",
+            b"abc\n",
+            &["-b", "--field-pattern", "a", "1"],
+        );
+        run_error_test(
+            "Field pattern needs a value",
+            b"a,b\n",
+            &["-d", ",", "1", "--field-pattern"],
+        );
+    }
+}
+
+mod assert_uniform {
+    use super::*;
+
+    #[test]
+    fn uniform_input_passes() {
+        run_success_test(
+            "Assert uniform accepts rectangular data",
+            b"a,b,c\nd,e,f\n",
+            &["-d", ",", "--assert-uniform", "2"],
+            b"b\ne\n",
+        );
+    }
+
+    #[test]
+    fn ragged_input_reports_first_offender() {
+        let mut command = Command::new(assert_cmd::cargo::cargo_bin!("splitby"));
+        command.args(["-d", ",", "--assert-uniform", "1"]);
+        command.write_stdin("a,b\nc,d,e\nf\n");
+        let output = command.output().unwrap();
+        assert!(!output.status.success());
+        assert_eq!(output.stdout, b"a\nc\nf\n");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("line 2: assert-uniform error: record has 3 fields, expected 2"),
+            "{stderr}"
+        );
+    }
+
+    #[test]
+    fn skipped_records_are_ignored() {
+        run_success_test(
+            "Assert uniform ignores comments",
+            b"# note\na,b\nc,d\n",
+            &["-d", ",", "--assert-uniform", "--comment", "#", "1"],
+            b"a\nc\n",
+        );
+    }
+
+    #[test]
+    fn rejects_unordered() {
+        run_error_test(
+            "Assert uniform needs ordered output",
+            b"a,b\n",
+            &["-d", ",", "--assert-uniform", "--unordered"],
+        );
+    }
+}
+
+mod regex_errors {
+    use super::*;
+
+    fn compile_error(pattern: &str) -> String {
+        let mut command = Command::new(assert_cmd::cargo::cargo_bin!("splitby"));
+        command.args(["--capture", pattern]);
+        command.write_stdin("a\n");
+        let output = command.output().unwrap();
+        assert!(!output.status.success());
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    }
+
+    #[test]
+    fn unbalanced_brackets() {
+        let error = compile_error("[[");
+        assert!(error.contains("regex: unclosed character class"), "{error}");
+        assert!(error.contains("fancy-regex: "), "{error}");
+        assert!(
+            error.contains("hint: brackets or parentheses are unbalanced"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn variable_length_lookbehind() {
+        let error = compile_error("(?<=a+)b");
+        assert!(error.contains("regex: look-around"), "{error}");
+        assert!(error.contains("without constant size"), "{error}");
+        assert!(
+            error.contains("hint: lookbehind must match a fixed length"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn no_hint_when_nothing_obvious() {
+        let error = compile_error("a{2,1}");
+        assert!(
+            error.contains("regex: invalid repetition count range"),
+            "{error}"
+        );
+        assert!(!error.contains("hint:"), "{error}");
+    }
+}
+
+mod header {
+    use super::*;
+
+    #[test]
+    fn header_names() {
+        run_success_test(
+            "Header names select their columns, header record included",
+            b"id,name,email\n1,ann,a@x\n2,bob,b@x\n",
+            &["-d", ",", "--header", "email,name"],
+            b"email,name\na@x,ann\nb@x,bob\n",
+        );
+    }
+
+    #[test]
+    fn header_names_mixed_with_numbers() {
+        run_success_test(
+            "Header names mix with numeric selections",
+            b"id,name,email\n1,ann,a@x\n",
+            &["-d", ",", "--header=name,1"],
+            b"name,id\nann,1\n",
+        );
+    }
+
+    #[test]
+    fn header_follows_positional_selections() {
+        run_success_test(
+            "Header selections follow positional selections",
+            b"id,name,email\n1,ann,a@x\n",
+            &["-d", ",", "3", "--header", "id"],
+            b"email,id\na@x,1\n",
+        );
+    }
+
+    #[test]
+    fn header_skips_comments() {
+        run_success_test(
+            "The header is the first record that isn't a comment",
+            b"# export\nid,name\n1,ann\n",
+            &["-d", ",", "--comment", "#", "--header", "name"],
+            b"name\nann\n",
+        );
+    }
+
+    #[test]
+    fn header_with_align() {
+        run_success_test(
+            "Header names resolve before align widths are measured",
+            b"id,name\n1,annabel\n",
+            &["-d", ",", "--header", "name,id", "--align"],
+            b"name   ,id\nannabel,1\n",
+        );
+    }
+
+    #[test]
+    fn header_missing_name() {
+        run_error_test(
+            "Unknown header name errors",
+            b"id,name\n1,ann\n",
+            &["-d", ",", "--header", "email"],
+        );
+    }
+
+    #[test]
+    fn header_outside_fields_mode() {
+        run_error_test(
+            "Header only works in fields mode",
+            b"id,name\n",
+            &["-c", "--header", "name"],
+        );
+    }
+}
+
+mod selection_files {
+    use super::*;
+
+    #[test]
+    fn selections_file_with_comments_and_blank_lines() {
+        let file = TempFile::new(
+            "selections-comments",
+            b"# first field\n1\n\n3, 5 # the rest\n",
+        );
+        run_success_test(
+            "Selections file skips comments and blank lines",
+            b"a,b,c,d,e\n",
+            &["-d", ",", "--selections-file", file.path()],
+            b"a,c,e\n",
+        );
+    }
+
+    #[test]
+    fn selections_file_follows_argument_selections() {
+        let file = TempFile::new("selections-after", b"-1\n");
+        run_success_test(
+            "Selections from a file follow those given as arguments",
+            b"a,b,c\n",
+            &[
+                "-d",
+                ",",
+                "1",
+                &format!("--selections-file={}", file.path()),
+            ],
+            b"a,c\n",
+        );
+    }
+
+    #[test]
+    fn selections_file_bad_token_reports_line() {
+        let file = TempFile::new("selections-bad", b"1\n2\nx-\n");
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["-d", ",", "--selections-file", file.path()])
+            .write_stdin("a,b\n")
+            .output()
+            .expect("running splitby should not fail");
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!("{}:3:", file.path())),
+            "stderr should point at the file and line, got: {stderr}"
+        );
+    }
+
+    #[test]
+    fn selections_file_missing() {
+        run_error_test(
+            "Missing selections file errors",
+            b"a,b\n",
+            &[
+                "-d",
+                ",",
+                "--selections-file",
+                "/nonexistent/splitby-selections",
+            ],
+        );
+    }
+
+    #[test]
+    fn delimiter_file_regex() {
+        let file = TempFile::new("delimiter-regex", b"/[;,]/\n");
+        run_success_test(
+            "Delimiter file holds a regex, trailing newline dropped",
+            b"a;b,c\n",
+            &["--delimiter-file", file.path(), "2"],
+            b"b\n",
+        );
+    }
+
+    #[test]
+    fn delimiter_file_with_delimiter_errors() {
+        let file = TempFile::new("delimiter-conflict", b",\n");
+        run_error_test(
+            "Delimiter file can't be combined with -d",
+            b"a,b\n",
+            &["-d", ",", "--delimiter-file", file.path(), "1"],
+        );
+    }
+
+    #[test]
+    fn delimiter_file_empty_errors() {
+        let file = TempFile::new("delimiter-empty", b"\n");
+        run_error_test(
+            "Empty delimiter file errors",
+            b"a,b\n",
+            &["--delimiter-file", file.path(), "1"],
+        );
+    }
+}
+
+mod spec {
+    use super::*;
+
+    fn run(arguments: &[&str], input: &str) -> (Option<i32>, String, Vec<u8>) {
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(arguments)
+            .write_stdin(input)
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+            output.stdout,
+        )
+    }
+
+    #[test]
+    fn matches_the_equivalent_flags() {
+        let cases: [(&str, &[&str]); 4] = [
+            (
+                "d=/,/ sel=1,3-4 join=|",
+                &["-d", "/,/", "1", "3-4", "-j", "|"],
+            ),
+            ("d=, sel=-1", &["-d", ",", "-1"]),
+            (
+                "d=, sel=2,9 placeholder=?",
+                &["-d", ",", "2", "9", "-p", "?"],
+            ),
+            ("sel=p50 d=,", &["-d", ",", "p50"]),
+        ];
+        for (spec, flags) in cases {
+            let with_spec = ["--spec", spec];
+            assert_eq!(
+                run(&with_spec, "a,b,c,d\n"),
+                run(flags, "a,b,c,d\n"),
+                "output differs for {spec}"
+            );
+            let explained: Vec<&str> = with_spec.iter().copied().chain(["--explain"]).collect();
+            let flags_explained: Vec<&str> = flags.iter().copied().chain(["--explain"]).collect();
+            assert_eq!(
+                run(&explained, ""),
+                run(&flags_explained, ""),
+                "--explain differs for {spec}"
+            );
+        }
+    }
+
+    #[test]
+    fn quoted_values_and_regex() {
+        run_success_test(
+            "Quotes keep spaces in a value, and a regex needs no extra escaping",
+            b"a  b c\n",
+            &["--spec=d=/\\s+/ sel=1,2 join=', '"],
+            b"a, b\n",
+        );
+        run_success_test(
+            "Double quotes allow an escaped quote",
+            b"a,b\n",
+            &["--spec", r#"d=, sel=1,2 join=" \" ""#],
+            b"a \" b\n",
+        );
+    }
+
+    #[test]
+    fn flags_win_over_the_spec() {
+        run_success_test(
+            "Selections given as arguments replace the spec's",
+            b"a,b,c\n",
+            &["--spec", "d=, sel=1", "3"],
+            b"c\n",
+        );
+        run_success_test(
+            "-d replaces the spec's delimiter",
+            b"a;b,c\n",
+            &["--spec", "d=, sel=2", "-d", ";"],
+            b"b,c\n",
+        );
+        run_success_test(
+            "-j replaces the spec's join",
+            b"a,b\n",
+            &["--spec", "d=, sel=1-2 join=|", "-j", ":"],
+            b"a:b\n",
+        );
+    }
+
+    #[test]
+    fn bare_argument_is_a_file_when_the_spec_has_a_delimiter() {
+        let file = TempFile::new("spec-input", b"a,b,c\n");
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["--spec", "d=, sel=2", file.path()])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"b\n");
+    }
+
+    #[test]
+    fn bad_specs_error() {
+        for spec in [
+            "d=, size=2",
+            "d=, sel",
+            "d=, d=;",
+            "d=, join='|",
+            "d=, join=\"|",
+            "d=, sel=1,x",
+        ] {
+            run_error_test(&format!("Bad spec {spec}"), b"a,b\n", &["--spec", spec]);
+        }
+        run_error_test("--spec with no value", b"a,b\n", &["--spec"]);
+    }
+}
+
+mod count_unit {
+    use super::*;
+
+    #[test]
+    fn fields_mode_counts_bytes() {
+        run_success_test(
+            "fields mode with --count-unit=bytes counts the whole record",
+            b"boo,hoo\na\n",
+            &["-d", ",", "--count-unit=bytes"],
+            b"7\n1\n",
+        );
+    }
+
+    #[test]
+    fn other_units_and_modes() {
+        run_success_test(
+            "bytes mode counting characters",
+            "h\u{e9}llo\n".as_bytes(),
+            &["-b", "--count-unit", "chars"],
+            b"5\n",
+        );
+        run_success_test(
+            "chars mode counting bytes",
+            "h\u{e9}llo\n".as_bytes(),
+            &["-c", "--count-unit=bytes"],
+            b"6\n",
+        );
+        run_success_test(
+            "fields is the default unit in fields mode",
+            b"a,b,c\n",
+            &["-d", ",", "--count-unit=fields"],
+            b"3\n",
+        );
+        run_success_test(
+            "the total sums the chosen unit",
+            b"a,b\nc\n",
+            &["-d", ",", "--count-unit=bytes", "--count-total"],
+            b"4\n",
+        );
+    }
+
+    #[test]
+    fn rejects_units_the_mode_cannot_count() {
+        run_error_test(
+            "bytes mode has no fields",
+            b"a,b\n",
+            &["-b", "--count-unit=fields"],
+        );
+        run_error_test(
+            "nonempty only counts fields",
+            b"a,b\n",
+            &["-d", ",", "--count=nonempty", "--count-unit=bytes"],
+        );
+        run_error_test(
+            "an unknown unit",
+            b"a,b\n",
+            &["-d", ",", "--count-unit=words"],
+        );
+    }
+}
+
+mod count_total {
+    use super::*;
+
+    #[test]
+    fn sums_across_records() {
+        run_success_test(
+            "--count-total sums the field counts of every record",
+            b"a,b,c\nd,e\n,f\n",
+            &["-d", ",", "--count-total"],
+            b"7\n",
+        );
+    }
+
+    #[test]
+    fn nonempty_mode() {
+        run_success_test(
+            "--count-total follows --count=nonempty",
+            b"a,b,c\nd,e\n,f\n",
+            &["-d", ",", "--count=nonempty", "--count-total"],
+            b"6\n",
+        );
+    }
+
+    #[test]
+    fn unordered_total_is_the_same() {
+        let mut input = String::new();
+        for index in 0..5000 {
+            writeln!(input, "{index},x,y").unwrap();
+        }
+        run_success_test(
+            "--count-total does not depend on output order",
+            input.as_bytes(),
+            &[
+                "-d",
+                ",",
+                "--count-total",
+                "--unordered",
+                "--batch-bytes=64",
+            ],
+            b"15000\n",
+        );
+    }
+
+    #[test]
+    fn empty_input_is_zero() {
+        run_success_test(
+            "--count-total on empty input is 0",
+            b"",
+            &["-d", ",", "--count-total"],
+            b"0",
+        );
+    }
+
+    #[test]
+    fn with_number_is_rejected() {
+        run_error_test(
+            "--count-total cannot be combined with --number",
+            b"a,b\n",
+            &["-d", ",", "--count-total", "--number"],
+        );
+    }
+}
+
+mod stats {
+    use super::*;
+
+    #[test]
+    fn stats_per_record() {
+        run_success_test(
+            "Stats gives the field count and each field's byte length",
+            b"a,bb,ccc\nd\n",
+            &["-d", ",", "--stats"],
+            b"3\t1\t2\t3\n1\t1\n",
+        );
+    }
+
+    #[test]
+    fn stats_whole_string() {
+        run_success_test(
+            "Stats in whole-string mode covers the whole input",
+            b"a,bb\nc\n",
+            &["-w", "-d", "/[,\n]/", "--stats"],
+            b"3\t1\t2\t1",
+        );
+    }
+
+    #[test]
+    fn stats_with_count_errors() {
+        run_error_test(
+            "Stats and count are mutually exclusive",
+            b"a,b\n",
+            &["-d", ",", "--stats", "--count"],
+        );
+    }
+}
+
+mod offsets {
+    use super::*;
+
+    #[test]
+    fn fields_two_and_three() {
+        run_success_test(
+            "--offsets reports start:length of each selected field",
+            b"aa,bbb,c\n",
+            &["-d", ",", "--offsets", "2", "3"],
+            b"3:3\t7:1\n",
+        );
+    }
+
+    #[test]
+    fn follows_trim() {
+        run_success_test(
+            "--offsets points past whitespace removed by --trim",
+            b" aa , bbb,c\n",
+            &["-d", ",", "--trim", "--offsets", "1", "2"],
+            b"1:2\t6:3\n",
+        );
+    }
+
+    #[test]
+    fn regex_delimiter() {
+        run_success_test(
+            "--offsets accounts for multi-byte delimiter matches",
+            b"a  b   c\n",
+            &["-d", "/ +/", "--offsets", "-1"],
+            b"7:1\n",
+        );
+    }
+
+    #[test]
+    fn missing_fields_are_left_out() {
+        run_success_test(
+            "--offsets skips fields past the end of the record",
+            b"aa,bbb\n",
+            &["-d", ",", "--offsets", "1", "3"],
+            b"0:2\n",
+        );
+    }
+
+    #[test]
+    fn rejects_csv() {
+        run_error_test(
+            "--offsets cannot be used with --csv",
+            b"a,b\n",
+            &["--csv", "--offsets", "1"],
+        );
+    }
+}
+
+mod redact {
+    use super::*;
+
+    #[test]
+    fn redact_column() {
+        run_success_test(
+            "Redact swaps the selected field for the placeholder",
+            b"a,b,c\n",
+            &["-d", ",", "--redact", "--placeholder=***", "2"],
+            b"a,***,c\n",
+        );
+    }
+
+    #[test]
+    fn redact_keeps_original_delimiters() {
+        run_success_test(
+            "Redact keeps each field's own delimiter",
+            b"a, b;c\n",
+            &["-d", "/[,;] ?/", "--redact", "--placeholder=X", "1", "3"],
+            b"X, b;X\n",
+        );
+    }
+
+    #[test]
+    fn redact_with_invert() {
+        run_success_test(
+            "Redact with invert replaces everything but the selection",
+            b"a,b,c\n",
+            &["-d", ",", "--redact", "--invert", "--placeholder=X", "2"],
+            b"X,b,X\n",
+        );
+    }
+
+    #[test]
+    fn redact_uses_the_last_placeholder() {
+        run_success_test(
+            "A later placeholder overrides an earlier one",
+            b"a,b,c\n",
+            &["-d", ",", "--redact", "-p", "X", "-p", "Y", "1", "3"],
+            b"Y,b,Y\n",
+        );
+    }
+
+    #[test]
+    fn redact_needs_placeholder() {
+        run_error_test(
+            "Redact without a placeholder errors",
+            b"a,b,c\n",
+            &["-d", ",", "--redact", "2"],
+        );
+    }
+}
+
+mod byte_order_mark {
+    use super::*;
+
+    #[test]
+    fn bom_stripped_by_default() {
+        run_success_test(
+            "A leading UTF-8 BOM isn't part of the first field",
+            b"\xEF\xBB\xBFa,b",
+            &["-d", ",", "1"],
+            b"a",
+        );
+    }
+
+    #[test]
+    fn strip_bom_whole_string_and_zero_terminated() {
+        run_success_test(
+            "Whole-string mode strips the BOM from the start of the buffer",
+            b"\xEF\xBB\xBFa,b",
+            &["-w", "-d", ",", "--strip-bom", "1"],
+            b"a",
+        );
+        run_success_test(
+            "Zero-terminated mode strips the BOM from the first record",
+            b"\xEF\xBB\xBFa,b\0",
+            &["-z", "-d", ",", "1"],
+            b"a\0",
+        );
+    }
+
+    #[test]
+    fn keep_bom() {
+        run_hex_output_test(
+            "--keep-bom leaves the BOM in place",
+            b"\xEF\xBB\xBFa,b\n",
+            &["-d", ",", "--keep-bom", "1"],
+            "ef bb bf 61 0a",
+        );
+    }
+
+    #[test]
+    fn bom_mid_stream_untouched() {
+        run_hex_output_test(
+            "BOM bytes after the start are left alone",
+            b"a\n\xEF\xBB\xBFb\n",
+            &["-d", ",", "1"],
+            "61 0a ef bb bf 62 0a",
+        );
+    }
+
+    #[test]
+    fn partial_bom_kept() {
+        run_hex_output_test(
+            "Bytes that only start like a BOM are kept",
+            b"\xEF\xBBx\n",
+            &["-b", "1-3"],
+            "ef bb 78 0a",
+        );
+    }
+}
+
+mod input_encoding {
+    use super::*;
+
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| match big_endian {
+                true => unit.to_be_bytes(),
+                false => unit.to_le_bytes(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn utf16le_fields() {
+        run_success_test(
+            "UTF-16LE input splits like UTF-8",
+            &utf16("a,b,c\n", false),
+            &["--encoding=utf16le", "-d", ",", "2"],
+            b"b\n",
+        );
+    }
+
+    #[test]
+    fn utf16be_multiple_records() {
+        run_success_test(
+            "UTF-16BE input splits into records on its newlines",
+            &utf16("a,b\nc,d\n", true),
+            &["--encoding", "utf16be", "-d", ",", "2"],
+            b"b\nd\n",
+        );
+    }
+
+    #[test]
+    fn read_buffer_applies_after_decoding() {
+        // Records longer than the buffer span several reads of the decoded text
+        let mut text = String::new();
+        let mut expected: Vec<u8> = Vec::new();
+        for line in 0..200 {
+            let padding = "é".repeat(line * 7);
+            text.push_str(&format!("{line},{padding},end\n"));
+            expected.extend_from_slice(format!("{line},end\n").as_bytes());
+        }
+        run_success_test(
+            "UTF-16 input reads the same through a small buffer",
+            &utf16(&text, false),
+            &[
+                "--encoding=utf16le",
+                "--read-buffer=1024",
+                "-d",
+                ",",
+                "1",
+                "3",
+            ],
+            &expected,
+        );
+    }
+
+    #[test]
+    fn bom_sets_byte_order() {
+        let mut input = vec![0xFE, 0xFF];
+        input.extend(utf16("a,b\n", true));
+        run_success_test(
+            "A big-endian BOM overrides utf16le and is dropped",
+            &input,
+            &["--encoding=utf16le", "-d", ",", "1"],
+            b"a\n",
+        );
+    }
+
+    #[test]
+    fn bytes_mode_counts_utf8() {
+        run_success_test(
+            "Byte mode works on the transcoded UTF-8",
+            &utf16("é\n", false),
+            &["--encoding=utf16le", "-b", "1-2"],
+            "é\n".as_bytes(),
+        );
+    }
+
+    #[test]
+    fn unpaired_surrogate() {
+        run_success_test(
+            "Unpaired surrogates become U+FFFD",
+            &[0x00, 0xD8, b'a', 0x00, b'\n', 0x00],
+            &["--encoding=utf16le", "-c", "1-2"],
+            "\u{FFFD}a\n".as_bytes(),
+        );
+        run_error_test(
+            "Unpaired surrogates error under --strict-utf8",
+            &[0x00, 0xD8, b'a', 0x00, b'\n', 0x00],
+            &["--encoding=utf16le", "--strict-utf8", "-c", "1-2"],
+        );
+    }
+
+    #[test]
+    fn invalid_encoding() {
+        run_error_test(
+            "Unknown encodings error",
+            b"a\n",
+            &["--encoding=latin1", "-c", "1"],
+        );
+    }
+}
+
+mod max_record_bytes {
+    use super::*;
+
+    #[test]
+    fn records_within_limit() {
+        run_success_test(
+            "Records up to the limit pass, terminators excluded",
+            b"abc\r\nab\n",
+            &["-d", ",", "--max-record-bytes", "3"],
+            b"abc\nab\n",
+        );
+    }
+
+    #[test]
+    fn huge_line_errors_with_record_number() {
+        let mut input = b"ok\n".to_vec();
+        input.extend(std::iter::repeat_n(b'a', 4 * 1024 * 1024));
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["-d", ",", "--max-record-bytes=16"])
+            .write_stdin(input)
+            .output()
+            .expect("running splitby should not fail");
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("record 2") && stderr.contains("16 bytes"),
+            "stderr should name the record and the limit, got: {stderr}"
+        );
+    }
+
+    #[test]
+    fn separated_record_over_limit() {
+        run_error_test(
+            "Records split by --input-record-separator are checked too",
+            b"ab;;abcdef;;",
+            &[
+                "-d",
+                ",",
+                "--input-record-separator",
+                ";;",
+                "--max-record-bytes",
+                "3",
+            ],
+        );
+    }
+
+    #[test]
+    fn whole_string_over_limit() {
+        run_error_test(
+            "Whole-string input longer than the limit errors",
+            b"abcd",
+            &["-w", "-d", ",", "--max-record-bytes", "3"],
+        );
+    }
+
+    #[test]
+    fn zero_is_invalid() {
+        run_error_test(
+            "Max record bytes must be positive",
+            b"a\n",
+            &["-d", ",", "--max-record-bytes", "0"],
+        );
+    }
+}
+
+mod max_records {
+    use super::*;
+
+    #[test]
+    fn outputs_first_records() {
+        run_success_test(
+            "Max records keeps the first N records",
+            b"a,1\nb,2\nc,3\nd,4\n",
+            &["-d", ",", "--max-records", "2", "2"],
+            b"1\n2\n",
+        );
+    }
+
+    #[test]
+    fn stops_early_on_large_input() {
+        let input = "x,y\n".repeat(200_000);
+        run_success_test(
+            "Max records stops without needing the rest of a large input",
+            input.as_bytes(),
+            &["-d", ",", "--max-records=3", "--batch-bytes=0", "2"],
+            b"y\ny\ny\n",
+        );
+    }
+
+    #[test]
+    fn later_sources_are_not_opened() {
+        let file = TempFile::new("max-records", b"a\nb\n");
+        run_success_test(
+            "Max records reached in the first file skips the missing second file",
+            b"",
+            &[
+                "-d",
+                ",",
+                "--max-records=2",
+                "-i",
+                file.path(),
+                "-i",
+                "/nonexistent/splitby-input",
+                "1",
+            ],
+            b"a\nb\n",
+        );
+    }
+
+    #[test]
+    fn zero_reads_nothing() {
+        run_success_test(
+            "Max records 0 reads no records",
+            b"a\nb\n",
+            &["-d", ",", "--max-records=0", "1"],
+            b"",
+        );
+    }
+
+    #[test]
+    fn rejects_whole_string() {
+        run_error_test(
+            "Max records cannot be combined with whole-string",
+            b"a\n",
+            &["-d", ",", "-w", "--max-records=1"],
+        );
+    }
+}
+
+mod histogram {
+    use super::*;
+
+    #[test]
+    fn tallies_field_counts() {
+        run_success_test(
+            "mixed records, sorted by field count",
+            b"a,b,c\nd\ne,f\ng,h,i\nj,k,l\n",
+            &[",", "--histogram"],
+            b"1 field: 1 line\n2 fields: 1 line\n3 fields: 3 lines\n",
+        );
+        run_success_test(
+            "follows --count=nonempty",
+            b"a,,b\nc,d\n",
+            &[",", "--histogram", "--count=nonempty"],
+            b"2 fields: 2 lines\n",
+        );
+        run_success_test("no records means no rows", b"", &[",", "--histogram"], b"");
+    }
+
+    #[test]
+    fn names_the_unit_and_records() {
+        run_success_test(
+            "bytes mode counts bytes",
+            b"ab\ncd\nx\n",
+            &["-b", "--histogram"],
+            b"1 byte: 1 line\n2 bytes: 2 lines\n",
+        );
+        run_success_test(
+            "zero-terminated records aren't lines",
+            b"a,b\0c\0",
+            &["-z", ",", "--histogram"],
+            b"1 field: 1 record\x002 fields: 1 record\x00",
+        );
+    }
+
+    #[test]
+    fn conflicting_flags() {
+        run_error_test(
+            "--count-total",
+            b"a\n",
+            &[",", "--histogram", "--count-total"],
+        );
+        run_error_test("--number", b"a\n", &[",", "--histogram", "--number"]);
+    }
+}
+
+mod replace {
+    use super::*;
+
+    #[test]
+    fn remaps_one_delimiter_kind() {
+        run_success_test(
+            "; becomes , while , is kept",
+            b"a;b,c\n",
+            &["-d", "/[;,]/", "--replace=0x3b=,", "1-3"],
+            b"a,b,c\n",
+        );
+        run_success_test(
+            "with no selections the whole record is remapped",
+            b"a;b,c;d\n",
+            &["-d", "/[;,]/", "--replace", ";=|"],
+            b"a|b,c|d\n",
+        );
+        run_success_test(
+            "several pairs, each matched against the original delimiter",
+            b"a;b,c\n",
+            &["-d", "/[;,]/", "--replace=;=,", "--replace=,=:"],
+            b"a,b:c\n",
+        );
+        run_success_test(
+            "an = delimiter is written as hex",
+            b"a=b=c\n",
+            &["-d", "=", "--replace=0x3d=:", "1", "3"],
+            b"a:c\n",
+        );
+    }
+
+    #[test]
+    fn join_takes_precedence() {
+        run_success_test(
+            "--join overrides every delimiter",
+            b"a;b,c\n",
+            &["-d", "/[;,]/", "--replace=;=+", "-j", "-", "1-3"],
+            b"a-b-c\n",
+        );
+        run_success_test(
+            "--separators overrides its positions only",
+            b"a;b;c\n",
+            &["-d", ";", "--replace=;=+", "--separators=|", "1-3"],
+            b"a|b+c\n",
+        );
+    }
+
+    #[test]
+    fn invalid_usage() {
+        run_error_test("no =", b"a;b\n", &["-d", ";", "--replace=x", "1"]);
+        run_error_test("empty FROM", b"a;b\n", &["-d", ";", "--replace==x", "1"]);
+        run_error_test("bytes mode", b"a;b\n", &["-b", "--replace=;=,", "1"]);
+        run_error_test(
+            "json output",
+            b"a;b\n",
+            &["-d", ";", "--replace=;=,", "--json", "1"],
+        );
+    }
+}
+
+mod strict_empty {
+    use super::*;
+
+    fn exit_code(input: &[u8], arguments: &[&str]) -> Option<i32> {
+        Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(arguments)
+            .write_stdin(input)
+            .output()
+            .expect("running splitby should not fail")
+            .status
+            .code()
+    }
+
+    #[test]
+    fn empty_input_under_each_strict_flag() {
+        assert_eq!(exit_code(b"", &[",", "1"]), Some(0));
+        assert_eq!(exit_code(b"", &["--strict-empty", ",", "1"]), Some(3));
+        assert_eq!(exit_code(b"", &["--strict-return", ",", "1"]), Some(1));
+        assert_eq!(exit_code(b"", &["--strict-bounds", ",", "1"]), Some(1));
+        assert_eq!(exit_code(b"", &["--strict-utf8", ",", "1"]), Some(0));
+        assert_eq!(exit_code(b"", &["--strict", ",", "1"]), Some(3));
+        assert_eq!(
+            exit_code(b"", &["--strict", "--no-strict-empty", ",", "1"]),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn strict_empty_comes_before_other_flags() {
+        assert_eq!(
+            exit_code(b"", &["--strict-empty", "--strict-return", ",", "1"]),
+            Some(3)
+        );
+        assert_eq!(exit_code(b"", &["--strict-empty", "--count", ","]), Some(3));
+        run_success_test(
+            "--count answers 0 rather than failing --strict-return",
+            b"",
+            &["--count", "--strict-return", ","],
+            b"0",
+        );
+    }
+
+    #[test]
+    fn input_with_records_passes() {
+        run_success_test(
+            "a record, even an empty one, is not an empty input",
+            b"\n",
+            &["--strict-empty", ",", "1"],
+            b"\n",
+        );
+        assert_eq!(exit_code(b"a,b\n", &["--strict-empty", ",", "3"]), Some(0));
+        assert_eq!(exit_code(b"a", &["--bad-flag"]), Some(2));
+    }
+}
+
+mod json_input {
+    use super::*;
+
+    #[test]
+    fn selects_by_path() {
+        run_success_test(
+            "a top-level key",
+            b"{\"name\":\"bob\",\"age\":3}\n",
+            &["--json-input", "name"],
+            b"bob\n",
+        );
+        run_success_test(
+            "nested keys and array indexes, with non-strings as JSON",
+            b"{\"user\":{\"name\":\"bob\"},\"items\":[3,{\"a\":1}]}\n",
+            &["--json-input=user.name,items.0,items.1"],
+            b"bob 3 {\"a\":1}\n",
+        );
+        run_success_test(
+            "join between values",
+            b"{\"name\":\"bob\",\"age\":3}\n",
+            &["--json-input", "age,name", "-j", ","],
+            b"3,bob\n",
+        );
+    }
+
+    #[test]
+    fn missing_paths() {
+        run_success_test(
+            "a missing path is left out",
+            b"{\"name\":\"bob\"}\n[1]\n",
+            &["--json-input", "name"],
+            b"bob\n\n",
+        );
+        run_success_test(
+            "a missing path or non-object root takes the placeholder",
+            b"{\"name\":\"bob\"}\n[1]\n\n",
+            &["--json-input", "name,age", "--placeholder=-"],
+            b"bob -\n- -\n- -\n",
+        );
+        run_error_test(
+            "a record with nothing selected fails --strict-return",
+            b"[1]\n",
+            &["--json-input", "name", "--strict-return"],
+        );
+    }
+
+    #[test]
+    fn usage_errors() {
+        run_error_test("invalid JSON", b"name\n", &["--json-input", "name"]);
+        run_error_test("index selections", b"{}\n", &["--json-input", "name", "1"]);
+        run_error_test("an empty path segment", b"{}\n", &["--json-input=a..b"]);
+        run_error_test("no paths", b"{}\n", &["--json-input"]);
+    }
+}
+
+mod split_once {
+    use super::*;
+
+    #[test]
+    fn value_keeps_its_delimiters() {
+        run_success_test(
+            "the value keeps every later delimiter",
+            b"key=val=ue\na=b=c\n",
+            &["-d", "=", "--split-once", "2"],
+            b"val=ue\nb=c\n",
+        );
+        run_success_test(
+            "the key is the first field",
+            b"key=val=ue\n",
+            &["-d", "=", "--split-once", "1"],
+            b"key\n",
+        );
+    }
+
+    #[test]
+    fn lines_without_the_delimiter() {
+        run_success_test(
+            "a line without = has no second field",
+            b"key=value\nplain\n",
+            &["-d", "=", "--split-once", "2"],
+            b"value\n\n",
+        );
+        run_error_test(
+            "a line without = errors under --strict-bounds",
+            b"plain\n",
+            &["-d", "=", "--split-once", "--strict-bounds", "2"],
+        );
+    }
+}
+
+mod max_fields {
+    use super::*;
+
+    #[test]
+    fn last_field_keeps_remaining_delimiters() {
+        run_success_test(
+            "Max fields 2 leaves the rest in field 2",
+            b"a:b:c:d\n",
+            &["-d", ":", "--max-fields", "2", "2"],
+            b"b:c:d\n",
+        );
+        run_success_test(
+            "Max fields 2 gives exactly two fields",
+            b"a:b:c:d\n",
+            &["-d", ":", "--max-fields=2", "-j", "|"],
+            b"a|b:c:d\n",
+        );
+    }
+
+    #[test]
+    fn zero_and_one_do_not_split() {
+        run_success_test(
+            "Max fields 0 keeps the whole record as field 1",
+            b"a:b:c\n",
+            &["-d", ":", "--max-fields=0", "1"],
+            b"a:b:c\n",
+        );
+        run_success_test(
+            "Max fields 1 keeps the whole record as field 1",
+            b"a:b:c\n",
+            &["-d", ":", "--max-fields=1", "-1"],
+            b"a:b:c\n",
+        );
+    }
+
+    #[test]
+    fn skip_empty_runs_on_truncated_fields() {
+        run_success_test(
+            "Skip empty applies after max fields",
+            b"a::b:c\n",
+            &["-d", ":", "--max-fields=3", "-e", "2"],
+            b"b:c\n",
+        );
+    }
+
+    #[test]
+    fn regex_delimiter() {
+        run_success_test(
+            "Max fields with a regex delimiter",
+            b"a  b  c d\n",
+            &["-d", "/\\s+/", "--max-fields=3", "-1"],
+            b"c d\n",
+        );
+    }
+
+    #[test]
+    fn rejects_csv() {
+        run_error_test(
+            "Max fields cannot be combined with CSV",
+            b"a,b\n",
+            &["-d", ",", "--csv", "--max-fields=2"],
+        );
+    }
+}
+
+mod separators {
+    use super::*;
+
+    #[test]
+    fn distinct_separator_per_position() {
+        run_success_test(
+            "Separators give each gap its own join",
+            b"2024-01-02,10:00,42\n",
+            &["-d", ",", "--separators= ,|"],
+            b"2024-01-02 10:00|42\n",
+        );
+    }
+
+    #[test]
+    fn falls_back_to_join() {
+        run_success_test(
+            "Positions past the list use --join",
+            b"a,b,c,d\n",
+            &["-d", ",", "--separators", "|", "-j", "-"],
+            b"a|b-c-d\n",
+        );
+        run_success_test(
+            "Positions past the list use the delimiter by default",
+            b"a;b;c;d\n",
+            &["-d", ";", "--separators", "|,+"],
+            b"a|b+c;d\n",
+        );
+    }
+
+    #[test]
+    fn hex_separator() {
+        run_success_test(
+            "Separators accept hex, so a comma can be given as 0x2c",
+            b"a,b,c\n",
+            &["-d", ",", "--separators=0x2c20,::"],
+            b"a, b::c\n",
+        );
+    }
+
+    #[test]
+    fn rejects_json() {
+        run_error_test(
+            "Separators cannot be combined with JSON",
+            b"a,b\n",
+            &["-d", ",", "--separators=|", "--json"],
+        );
+    }
+}
+
+mod reverse {
+    use super::*;
+
+    #[test]
+    fn separate_selections() {
+        run_success_test(
+            "Reverse emits separate selections last to first",
+            b"a,b,c,d\n",
+            &["-d", ",", "--reverse", "1", "2", "3"],
+            b"c,b,a\n",
+        );
+    }
+
+    #[test]
+    fn mixed_range_and_single() {
+        run_success_test(
+            "Reverse also reverses the fields within a range",
+            b"a,b,c,d\n",
+            &["-d", ",", "-r", "1-2", "4"],
+            b"d,b,a\n",
+        );
+    }
+
+    #[test]
+    fn keeps_original_delimiters_between_neighbours() {
+        run_success_test(
+            "Reverse joins neighbours with the delimiter between them",
+            b"a;b,c\n",
+            &["-d", "/[;,]/", "-r"],
+            b"c,b;a\n",
+        );
+    }
+
+    #[test]
+    fn with_join() {
+        run_success_test(
+            "Reverse with an explicit join",
+            b"a,b,c\n",
+            &["-d", ",", "-r", "-j", "|"],
+            b"c|b|a\n",
+        );
+    }
+
+    #[test]
+    fn rejects_bytes_mode() {
+        run_error_test("Reverse is only for fields", b"abc\n", &["-b", "-r", "1-3"]);
+    }
+}
+
+mod sort_fields {
+    use super::*;
+
+    #[test]
+    fn lexicographic() {
+        run_success_test(
+            "Sort orders fields by bytes",
+            b"c,a,b\nz,y,x\n",
+            &["-d", ",", "--sort"],
+            b"a,b,c\nx,y,z\n",
+        );
+    }
+
+    #[test]
+    fn numeric_with_fallback() {
+        run_success_test(
+            "Sort numeric uses leading numbers, then puts non-numbers last by bytes",
+            b"10,9,x,-1.5kg,100,b\n",
+            &["-d", ",", "--sort-numeric"],
+            b"-1.5kg,9,10,100,b,x\n",
+        );
+    }
+
+    #[test]
+    fn numeric_mixed_widths() {
+        run_success_test(
+            "Sort numeric puts 2 before 10",
+            b"10,2,1\n",
+            &["-d", ",", "--sort-numeric"],
+            b"1,2,10\n",
+        );
+        run_success_test(
+            "Sort numeric orders negative numbers first",
+            b"1,-1,0\n",
+            &["-d", ",", "--sort-numeric"],
+            b"-1,0,1\n",
+        );
+    }
+
+    #[test]
+    fn numeric_thousands_separators() {
+        run_success_test(
+            "Sort numeric reads commas between digit groups",
+            b"1,234\t-2,000\t999\t12,34\n",
+            &["-t", "--sort-numeric"],
+            b"-2,000\t12,34\t999\t1,234\n",
+        );
+        run_success_test(
+            "A comma after more than three digits ends the number",
+            b"12345,678 999999\n",
+            &["-d", " ", "--sort-numeric"],
+            b"12345,678 999999\n",
+        );
+    }
+
+    #[test]
+    fn reverse_with_join() {
+        run_success_test(
+            "Sort reverse joins the sorted order",
+            b"c,a,b\n",
+            &["-d", ",", "--sort-reverse", "-j", " | "],
+            b"c | b | a\n",
+        );
+    }
+
+    #[test]
+    fn only_selected_fields() {
+        run_success_test(
+            "Sort only reorders the selected fields",
+            b"d,c,b,a\n",
+            &["-d", ",", "--sort", "1", "3"],
+            b"b,d\n",
+        );
+    }
+
+    #[test]
+    fn rejects_chars_mode() {
+        run_error_test(
+            "Sort is only for fields",
+            b"cba\n",
+            &["-c", "--sort", "1-3"],
+        );
+    }
+}
+
+mod unique {
+    use super::*;
+
+    #[test]
+    fn whole_string_repeated_tokens() {
+        run_success_test(
+            "Unique drops repeated tokens in whole-string mode, keeping first-seen order",
+            b"b a b c a b",
+            &["-w", "-d", " ", "--unique"],
+            b"b a c",
+        );
+    }
+
+    #[test]
+    fn with_join() {
+        run_success_test(
+            "Unique drops repeats without leaving stray joins",
+            b"a,b,a,c,b\n",
+            &["-d", ",", "-u", "-j", "|"],
+            b"a|b|c\n",
+        );
+    }
+
+    #[test]
+    fn repeated_selections() {
+        run_success_test(
+            "Unique drops a field selected twice",
+            b"x,y,z\n",
+            &["-d", ",", "-u", "1", "2", "1", "3"],
+            b"x,y,z\n",
+        );
+    }
+
+    #[test]
+    fn scoped_per_record() {
+        run_success_test(
+            "Unique does not dedupe across records",
+            b"a,a\na,b\n",
+            &["-d", ",", "-u"],
+            b"a\na,b\n",
+        );
+    }
+
+    #[test]
+    fn rejects_chars_mode() {
+        run_error_test("Unique is only for fields", b"aa\n", &["-c", "-u", "1-2"]);
+    }
+}
+
+mod with_original {
+    use super::*;
+
+    #[test]
+    fn prefix_by_default() {
+        run_success_test(
+            "With original prefixes each record with a tab",
+            b"a,b,c\nd,e,f\n",
+            &["-d", ",", "--with-original", "2"],
+            b"a,b,c\tb\nd,e,f\te\n",
+        );
+    }
+
+    #[test]
+    fn suffix_with_separator() {
+        run_success_test(
+            "With original as suffix with a custom separator",
+            b"a,b,c\n",
+            &[
+                "-d",
+                ",",
+                "--with-original=suffix",
+                "--original-separator",
+                " <- ",
+                "1",
+                "3",
+            ],
+            b"a,c <- a,b,c\n",
+        );
+    }
+
+    #[test]
+    fn accompanies_empty_selection() {
+        run_success_test(
+            "With original keeps the source line when nothing is selected",
+            b"a,b\n",
+            &["-d", ",", "--with-original", "5"],
+            b"a,b\t\n",
+        );
+    }
+
+    #[test]
+    fn works_in_chars_mode() {
+        run_success_test(
+            "With original in chars mode",
+            b"hello\n",
+            &["-c", "--with-original", "1-2"],
+            b"hello\the\n",
+        );
+    }
+
+    #[test]
+    fn separator_requires_with_original() {
+        run_error_test(
+            "Original separator without with-original",
+            b"a,b\n",
+            &["-d", ",", "--original-separator=x", "1"],
+        );
+        run_error_test(
+            "With original rejects an unknown placement",
+            b"a,b\n",
+            &["-d", ",", "--with-original=middle", "1"],
+        );
+    }
+}
+
+mod trim {
+    use super::*;
+
+    #[test]
+    fn both_ends_keep_internal_whitespace() {
+        run_success_test(
+            "Trim strips both ends but keeps internal whitespace",
+            b" a  b ,\t c\td  ,e\n",
+            &["-d", ",", "--trim"],
+            b"a  b,c\td,e\n",
+        );
+    }
+
+    #[test]
+    fn one_sided() {
+        run_success_test(
+            "Trim left only",
+            b" a , b \n",
+            &["-d", ",", "--trim-left"],
+            b"a ,b \n",
+        );
+        run_success_test(
+            "Trim right only",
+            b" a , b \n",
+            &["-d", ",", "--trim-right"],
+            b" a, b\n",
+        );
+        run_success_test(
+            "Trim left and right together trim both",
+            b" a , b \n",
+            &["-d", ",", "--trim-left", "--trim-right"],
+            b"a,b\n",
+        );
+    }
+
+    #[test]
+    fn unicode_whitespace() {
+        run_success_test(
+            "Trim strips Unicode whitespace",
+            "\u{3000}a\u{a0},b\n".as_bytes(),
+            &["-d", ",", "--trim"],
+            b"a,b\n",
+        );
+    }
+
+    #[test]
+    fn alignment_uses_trimmed_widths() {
+        run_success_test(
+            "Trim applies before align measures widths",
+            b" x , yy\n longer ,z\n",
+            &["-d", ",", "--trim", "--align"],
+            b"x     ,yy\nlonger,z\n",
+        );
+    }
+
+    #[test]
+    fn count_is_unaffected() {
+        run_success_test(
+            "Trim does not change nonempty counts",
+            b" , a\n",
+            &["-d", ",", "--trim", "--count=nonempty"],
+            b"2\n",
+        );
+    }
+
+    #[test]
+    fn chars_mode_trims_record() {
+        run_success_test(
+            "Trim in chars mode strips whitespace graphemes from the record ends",
+            b"  a b  \n",
+            &["-c", "--trim", "1-3"],
+            b"a b\n",
+        );
+    }
+
+    #[test]
+    fn rejects_bytes_mode() {
+        run_error_test(
+            "Trim is not supported in bytes mode",
+            b" a\n",
+            &["-b", "--trim", "1"],
+        );
+    }
+}
+
+mod shell_quote {
+    use super::*;
+
+    #[test]
+    fn quotes_spaces_and_specials() {
+        run_success_test(
+            "Shell quote wraps fields with spaces and special characters",
+            b"a b,$HOME;rm -rf *,x|y\n",
+            &["-d", ",", "--shell-quote"],
+            b"'a b','$HOME;rm -rf *','x|y'\n",
+        );
+    }
+
+    #[test]
+    fn escapes_embedded_quotes() {
+        run_success_test(
+            "Shell quote escapes single quotes",
+            b"it's,\"ok\"\n",
+            &["-d", ",", "--shell-quote"],
+            b"'it'\\''s','\"ok\"'\n",
+        );
+    }
+
+    #[test]
+    fn empty_field_and_placeholder() {
+        run_success_test(
+            "Shell quote keeps empty fields as '' and quotes the placeholder",
+            b"a,,b\n",
+            &["-d", ",", "--shell-quote", "-p", "n a", "1-4"],
+            b"'a','','b','n a'\n",
+        );
+    }
+
+    #[test]
+    fn squash_gives_shell_arguments() {
+        run_success_test(
+            "Shell quote with squash gives space-separated arguments",
+            b"it's here  a b\n",
+            &["-d", "  ", "--shell-quote", "--align=squash"],
+            b"'it'\\''s here' 'a b'\n",
+        );
+    }
+
+    #[test]
+    fn rejects_json() {
+        run_error_test(
+            "Shell quote cannot be combined with JSON",
+            b"a\n",
+            &["-d", ",", "--shell-quote", "--json"],
+        );
+    }
+}
+
+mod field_length {
+    use super::*;
+
+    #[test]
+    fn keeps_lengths_in_range() {
+        run_success_test(
+            "Field length keeps fields of length 3-5",
+            b"ab,abc,abcde,abcdef,abcd,x\n",
+            &["-d", ",", "--field-length", "3-5"],
+            b"abc,abcde,abcd\n",
+        );
+    }
+
+    #[test]
+    fn placeholder_replaces_out_of_range() {
+        run_success_test(
+            "Field length replaces out-of-range fields with the placeholder",
+            b"ab,abc,abcdef\n",
+            &["-d", ",", "--field-length=3-5", "-p", "NA", "1-3"],
+            b"NA,abc,NA\n",
+        );
+    }
+
+    #[test]
+    fn open_ranges_and_exact() {
+        run_success_test(
+            "Field length with an open upper bound",
+            b"a,abc,abcdefgh\n",
+            &["-d", ",", "--field-length=3-"],
+            b"abc,abcdefgh\n",
+        );
+        run_success_test(
+            "Field length with an exact length",
+            b"a,ab,abc\n",
+            &["-d", ",", "--field-length=2"],
+            b"ab\n",
+        );
+    }
+
+    #[test]
+    fn basis_changes_measurement() {
+        run_success_test(
+            "Field length defaults to display width",
+            "日本,ab,c\n".as_bytes(),
+            &["-d", ",", "--field-length=2"],
+            b"ab\n",
+        );
+        run_success_test(
+            "Field length counts characters with the chars basis",
+            "日本,ab,c\n".as_bytes(),
+            &[
+                "-d",
+                ",",
+                "--field-length=2",
+                "--field-length-basis",
+                "chars",
+            ],
+            "日本,ab\n".as_bytes(),
+        );
+        run_success_test(
+            "Field length counts bytes with the bytes basis",
+            "é,ab,c\n".as_bytes(),
+            &["-d", ",", "--field-length=2", "--field-length-basis=bytes"],
+            "é,ab\n".as_bytes(),
+        );
+    }
+
+    #[test]
+    fn invalid_ranges_error() {
+        run_error_test(
+            "Field length minimum above maximum",
+            b"a\n",
+            &["--field-length=5-3"],
+        );
+        run_error_test("Field length needs numbers", b"a\n", &["--field-length=x"]);
+        run_error_test(
+            "Field length basis needs field length",
+            b"a\n",
+            &["--field-length-basis=bytes"],
+        );
+    }
+}
+
+mod decode_fields {
+    use super::*;
+
+    #[test]
+    fn base64_to_original_bytes() {
+        run_success_test(
+            "Decode base64 fields back to their original bytes",
+            b"a,aGVsbG8=,d29yPz5sZA\n",
+            &["-d", ",", "--decode", "2-3"],
+            b"hello,wor?>ld\n",
+        );
+    }
+
+    #[test]
+    fn binary_output_passes_strict_utf8() {
+        run_hex_output_test(
+            "Decoded binary is emitted as-is under strict UTF-8",
+            b"+/8=\n",
+            &["-d", ",", "--decode", "--strict-utf8", "1"],
+            "fb ff 0a",
+        );
+    }
+
+    #[test]
+    fn hex_then_encode() {
+        run_success_test(
+            "Decode hex and re-encode as base64",
+            b"68656c6c6f\n",
+            &["-d", ",", "--decode=hex", "--encode", "1"],
+            b"aGVsbG8=\n",
+        );
+    }
+
+    #[test]
+    fn invalid_input_errors() {
+        run_error_test(
+            "Invalid hex is a decode error",
+            b"68656c6c6\n",
+            &["-d", ",", "--decode=hex", "1"],
+        );
+        run_error_test(
+            "A sign is not a hex digit",
+            b"2b,+f\n",
+            &["-d", ",", "--decode=hex", "2"],
+        );
+    }
+
+    #[test]
+    fn rejects_bytes_mode() {
+        run_error_test(
+            "Decode requires fields mode",
+            b"aGk=\n",
+            &["-b", "--decode", "1"],
+        );
+    }
+}
+
+mod encode_fields {
+    use super::*;
+
+    #[test]
+    fn base64_per_field() {
+        run_success_test(
+            "Encode base64s each field before joining",
+            b"hello,wor?>ld,a\n",
+            &["-d", ",", "--encode", "1-3"],
+            b"aGVsbG8=,d29yPz5sZA==,YQ==\n",
+        );
+    }
+
+    #[test]
+    fn hex_per_field_keeps_placeholder() {
+        run_success_test(
+            "Encode hex leaves the placeholder as given",
+            b"hello,x\n",
+            &["-d", ",", "--encode=hex", "-p", "NA", "1-3"],
+            b"68656c6c6f,78,NA\n",
+        );
+    }
+
+    #[test]
+    fn base64url_alphabet() {
+        run_success_test(
+            "Encode base64url uses the URL-safe alphabet",
+            b"\xfb\xff\n",
+            &["-b", "--encode=base64url"],
+            b"-_8=\n",
+        );
+        run_success_test(
+            "Encode base64 uses the standard alphabet",
+            b"\xfb\xff\n",
+            &["-b", "--encode=base64"],
+            b"+/8=\n",
+        );
+    }
+
+    #[test]
+    fn bytes_mode_encodes_whole_output() {
+        run_success_test(
+            "Encode in bytes mode covers the whole selection",
+            b"hello\n",
+            &["-b", "--encode=hex", "1-2,4"],
+            b"68656c\n",
+        );
+    }
+
+    #[test]
+    fn round_trips_through_decoding() {
+        let output = assert_cmd::Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["-d", ",", "--encode=hex", "2"])
+            .write_stdin(b"key,caf\xc3\xa9\t\x00\x01\n".as_slice())
+            .output()
+            .expect("splitby should run");
+        let encoded = String::from_utf8(output.stdout).expect("hex output is ASCII");
+        let decoded: Vec<u8> = (0..encoded.trim_end().len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&encoded[index..index + 2], 16).unwrap())
+            .collect();
+        assert_eq!(decoded, b"caf\xc3\xa9\t\x00\x01");
+    }
+
+    #[test]
+    fn rejects_unknown_encoding_and_align() {
+        run_error_test(
+            "Encode rejects unknown encodings",
+            b"a\n",
+            &["-b", "--encode=rot13"],
+        );
+        run_error_test(
+            "Encode can't be combined with align",
+            b"a,b\n",
+            &["-d", ",", "--encode", "--align"],
+        );
+    }
+}
+
+mod transpose {
+    use super::*;
+
+    #[test]
+    fn transpose_grid() {
+        run_success_test(
+            "A 2x3 grid transposes to 3x2",
+            b"a,b,c\n1,2,3\n",
+            &["-w", "-d", ",", "--transpose"],
+            b"a,1\nb,2\nc,3",
+        );
+    }
+
+    #[test]
+    fn transpose_ragged_rows_padded() {
+        run_success_test(
+            "Short rows are padded with empty cells",
+            b"a,b,c\n1,2\n",
+            &["-w", "-d", ",", "--transpose"],
+            b"a,1\nb,2\nc,",
+        );
+        run_success_test(
+            "Short rows are padded with the placeholder",
+            b"a,b,c\n1,2\n",
+            &["-w", "-d", ",", "--transpose", "--placeholder=-"],
+            b"a,1\nb,2\nc,-",
+        );
+    }
+
+    #[test]
+    fn transpose_selects_columns() {
+        run_success_test(
+            "Selections pick the columns that become rows",
+            b"a,b,c\n1,2,3\n",
+            &["-w", "-d", ",", "--transpose", "--join= ", "3", "1"],
+            b"c 3\na 1",
+        );
+    }
+
+    #[test]
+    fn transpose_regex_delimiter_joins_with_first_match() {
+        run_success_test(
+            "Rows are joined with the first delimiter matched",
+            b"a  b\n1 2\n",
+            &["-w", "-d", "/ +/", "--transpose"],
+            b"a  1\nb  2",
+        );
+    }
+
+    #[test]
+    fn transpose_needs_whole_string() {
+        run_error_test(
+            "Transpose needs whole-string mode",
+            b"a,b\n",
+            &["-d", ",", "--transpose"],
+        );
+    }
+
+    #[test]
+    fn transpose_with_align_errors() {
+        run_error_test(
+            "Transpose cannot be combined with align",
+            b"a,b\n",
+            &["-w", "-d", ",", "--transpose", "--align"],
+        );
+    }
+
+    #[test]
+    fn transpose_with_field_rewrites_errors() {
+        for flag in [
+            "--shell-quote",
+            "--sort",
+            "--unique",
+            "--reverse",
+            "--field-pattern=a",
+            "--encode",
+            "--decode",
+        ] {
+            run_error_test(
+                &format!("Transpose cannot be combined with {flag}"),
+                b"a,b\n",
+                &["-w", "-d", ",", "--transpose", flag],
+            );
+        }
+    }
+}
+
+mod json_output {
+    use super::*;
+
+    #[test]
+    fn fields_as_json_lines() {
+        run_success_test(
+            "JSON outputs one array per line",
+            b"a,b,c\nd,e,f\n",
+            &["-d", ",", "--json", "1,3"],
+            b"[\"a\",\"c\"]\n[\"d\",\"f\"]\n",
+        );
+    }
+
+    #[test]
+    fn escapes_strings() {
+        run_success_test(
+            "JSON escapes quotes, backslashes and control characters",
+            b"\"q\",b\\s,t\tx\x01\n",
+            &["-d", ",", "--json"],
+            b"[\"\\\"q\\\"\",\"b\\\\s\",\"t\\tx\\u0001\"]\n",
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_becomes_replacement_character() {
+        run_success_test(
+            "JSON replaces invalid UTF-8",
+            b"a\xffb\n",
+            &["-b", "--json", "2"],
+            "[\"\u{FFFD}\"]\n".as_bytes(),
+        );
+    }
+
+    #[test]
+    fn bytes_and_chars_use_one_string_per_selection() {
+        run_success_test(
+            "JSON bytes mode",
+            b"hello\n",
+            &["-b", "--json", "1-2,4"],
+            b"[\"he\",\"l\"]\n",
+        );
+        run_success_test(
+            "JSON chars mode",
+            b"hello\n",
+            &["-c", "--json", "2-3,5"],
+            b"[\"el\",\"o\"]\n",
+        );
+    }
+
+    #[test]
+    fn count_emits_number() {
+        run_success_test(
+            "JSON count is a plain number",
+            b"a,b,c\n",
+            &["-d", ",", "--json", "--count"],
+            b"3\n",
+        );
+    }
+
+    #[test]
+    fn rejects_join_and_align() {
+        run_error_test(
+            "JSON rejects join",
+            b"a,b\n",
+            &["-d", ",", "--json", "-j", "x"],
+        );
+        run_error_test(
+            "JSON rejects align",
+            b"a,b\n",
+            &["-d", ",", "--json", "--align"],
+        );
+    }
+}
+
+mod placeholder_list {
+    use super::*;
+
+    #[test]
+    fn missing_selections_take_placeholders_in_turn() {
+        run_success_test(
+            "two missing selections with two placeholders",
+            b"a,b\n",
+            &["-d", ",", "-p", "X", "--placeholder=Y", "1", "3", "4"],
+            b"a,X,Y\n",
+        );
+    }
+
+    #[test]
+    fn last_placeholder_repeats() {
+        run_success_test(
+            "more missing selections than placeholders",
+            b"a,b\nc,d\n",
+            &["-d", ",", "-p", "X", "-p", "Y", "3", "4", "5"],
+            b"X,Y,Y\nX,Y,Y\n",
+        );
+    }
+
+    #[test]
+    fn bytes_and_chars_modes() {
+        run_success_test(
+            "bytes mode",
+            b"ab\n",
+            &["-b", "-p", "X", "-p", "Y", "1", "4-5"],
+            b"aXY\n",
+        );
+        run_success_test(
+            "chars mode",
+            b"ab\n",
+            &["-c", "-p", "X", "-p", "Y", "3", "4"],
+            b"XY\n",
+        );
+    }
+}
+
+mod null_on_missing {
+    use super::*;
+
+    #[test]
+    fn out_of_range_index_is_nul() {
+        run_hex_output_test(
+            "an out-of-range field becomes a NUL byte",
+            b"a,b\n",
+            &["-d", ",", "--null-on-missing", "1", "3"],
+            "61 2c 00 0a",
+        );
+    }
+
+    #[test]
+    fn works_in_bytes_and_chars_modes() {
+        run_hex_output_test(
+            "bytes mode",
+            b"ab\n",
+            &["-b", "--null-on-missing", "5"],
+            "00 0a",
+        );
+        run_hex_output_test(
+            "chars mode",
+            b"ab\n",
+            &["-c", "--null-on-missing", "1", "5"],
+            "61 00 0a",
+        );
+    }
+
+    #[test]
+    fn counts_for_strict_return() {
+        run_hex_output_test(
+            "the NUL is output, so --strict-return passes",
+            b"a,b\n",
+            &["-d", ",", "--null-on-missing", "--strict-return", "5"],
+            "00 0a",
+        );
+    }
+
+    #[test]
+    fn rejects_placeholder() {
+        run_error_test(
+            "--null-on-missing with --placeholder",
+            b"a,b\n",
+            &["-d", ",", "--null-on-missing", "--placeholder=x", "3"],
+        );
+    }
+}
+
+mod percent_selections {
+    use super::*;
+
+    #[test]
+    fn resolves_against_each_record() {
+        run_success_test(
+            "p50 on a 4-field line is field 2",
+            b"a,b,c,d\n",
+            &["-d", ",", "p50"],
+            b"b\n",
+        );
+        run_success_test(
+            "p50 rounds halves up",
+            b"a,b,c,d,e\na,b\n",
+            &["-d", ",", "p50"],
+            b"c\na\n",
+        );
+    }
+
+    #[test]
+    fn clamps_to_the_record() {
+        run_success_test(
+            "p0 is the first field and p100 the last",
+            b"a,b,c\n",
+            &["-d", ",", "p0", "p100"],
+            b"a,c\n",
+        );
+    }
+
+    #[test]
+    fn ranges_and_mixed_selections() {
+        run_success_test(
+            "a percent range",
+            b"a,b,c,d,e,f,g,h\n",
+            &["-d", ",", "p25-p75"],
+            b"b,c,d,e,f\n",
+        );
+        run_success_test(
+            "percents keep their place among other selections",
+            b"a,b,c,d\n",
+            &["-d", ",", "4,p50,1"],
+            b"d,b,a\n",
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_percents() {
+        run_error_test("over 100", b"a,b\n", &["-d", ",", "p101"]);
+        run_error_test("a reversed range", b"a,b\n", &["-d", ",", "p75-p25"]);
+        run_error_test("bytes mode", b"a,b\n", &["-b", "p50"]);
+    }
+}
+
+mod slice_syntax {
+    use super::*;
+
+    #[test]
+    fn end_is_exclusive() {
+        run_success_test(
+            "1:3 selects fields 1 and 2",
+            b"a,b,c,d\n",
+            &["-d", ",", "--slice-syntax", "1:3"],
+            b"a,b\n",
+        );
+    }
+
+    #[test]
+    fn open_slices() {
+        run_success_test(
+            "a bare colon selects every field",
+            b"a,b,c,d\n",
+            &["-d", ",", "--slice-syntax", ":"],
+            b"a,b,c,d\n",
+        );
+        run_success_test(
+            "an open end runs to the last field",
+            b"a,b,c,d\n",
+            &["-d", ",", "--slice-syntax", "2:"],
+            b"b,c,d\n",
+        );
+        run_success_test(
+            "an end of -1 leaves out the last field",
+            b"a,b,c,d\n",
+            &["-d", ",", "--slice-syntax", ":-1"],
+            b"a,b,c\n",
+        );
+    }
+
+    #[test]
+    fn step_and_single_index() {
+        run_success_test(
+            "a step after a second colon",
+            b"a,b,c,d,e\n",
+            &["-d", ",", "--slice-syntax", "1:6:2", "last"],
+            b"a,c,e,e\n",
+        );
+    }
+
+    #[test]
+    fn flag_after_selections() {
+        run_success_test(
+            "selections before the flag use slice syntax too",
+            b"a,b,c,d\n",
+            &["-d", ",", "2:4", "--slice-syntax"],
+            b"b,c\n",
+        );
+    }
+
+    #[test]
+    fn ends_on_opposite_sides_resolve_per_record() {
+        run_success_test(
+            "2:-3 is empty on four fields and selects two of six",
+            b"a,b,c,d\na,b,c,d,e,f\n",
+            &["-d", ",", "--slice-syntax", "--", "2:-3"],
+            b"\nb,c\n",
+        );
+    }
+
+    #[test]
+    fn starts_before_the_record_are_clamped() {
+        run_success_test(
+            "a stepped slice from before the first field steps from the first",
+            b"a,b,c,d\n",
+            &["-d", ",", "--slice-syntax", "--", "-9::2", "-5:3"],
+            b"a,c,a,b\n",
+        );
+    }
+
+    #[test]
+    fn invalid_slices() {
+        run_error_test(
+            "an empty slice",
+            b"a,b,c\n",
+            &["-d", ",", "--slice-syntax", "2:2"],
+        );
+        run_error_test(
+            "a zero end",
+            b"a,b,c\n",
+            &["-d", ",", "--slice-syntax", "1:0"],
+        );
+        run_error_test(
+            "a negative step",
+            b"a,b,c\n",
+            &["-d", ",", "--slice-syntax", "1:3:-1"],
+        );
+    }
+}
+
+mod trim_newline {
+    use super::*;
+
+    #[test]
+    fn per_line_drops_only_the_last_terminator() {
+        run_success_test(
+            "per-line output keeps every terminator but the last",
+            b"a,1\nb,2\nc,3\n",
+            &["-d", ",", "--trim-newline", "2"],
+            b"1\n2\n3",
+        );
+    }
+
+    #[test]
+    fn single_line() {
+        run_success_test(
+            "a single record has no terminator",
+            b"a,1\n",
+            &["-d", ",", "--trim-newline", "1"],
+            b"a",
+        );
+    }
+
+    #[test]
+    fn whole_string_drops_the_final_newline() {
+        run_success_test(
+            "whole-string mode drops the input's final newline",
+            b"a,b\n",
+            &["-w", "-d", ",", "--trim-newline", "2"],
+            b"b",
+        );
+    }
+
+    #[test]
+    fn zero_terminated_and_many_batches() {
+        run_hex_output_test(
+            "zero-terminated output drops the last NUL",
+            b"a,1\0b,2\0",
+            &["-z", "-d", ",", "--trim-newline", "2"],
+            "31 00 32",
+        );
+        let input: String = (1..=5_000).map(|n| format!("{n}\n")).collect();
+        run_success_test(
+            "the last record is found across many batches and workers",
+            input.as_bytes(),
+            &[
+                "-J",
+                "8",
+                "--batch-bytes=0",
+                "-d",
+                ",",
+                "--trim-newline",
+                "1",
+            ],
+            input.trim_end().as_bytes(),
+        );
+    }
+}
+
+mod output_append {
+    use super::*;
+
+    #[test]
+    fn appends_on_each_run() {
+        let file = TempFile::absent("append");
+        for input in ["a,1\n", "b,2\n"] {
+            Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+                .args(["-d", ",", "2", "--output-append", "-o", file.path()])
+                .write_stdin(input)
+                .assert()
+                .success();
+        }
+        let contents = std::fs::read(file.path()).expect("output file should exist");
+        assert_eq!(contents, b"1\n2\n");
+    }
+
+    #[test]
+    fn without_append_truncates() {
+        let file = TempFile::new("truncate", b"old\n");
+        Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["-d", ",", "2", "-o", file.path()])
+            .write_stdin("a,1\n")
+            .assert()
+            .success();
+        let contents = std::fs::read(file.path()).unwrap();
+        assert_eq!(contents, b"1\n");
+    }
+
+    #[test]
+    fn needs_output() {
+        run_error_test(
+            "--append without --output",
+            b"a,1\n",
+            &["-d", ",", "--append", "2"],
+        );
+    }
+}
+
+mod multiple_inputs {
+    use super::*;
+
+    #[test]
+    fn positional_files_in_order() {
+        let first = TempFile::new("positional-1", b"a,1\nb,2\n");
+        let second = TempFile::new("positional-2", b"c,3\n");
+        run_success_test(
+            "Positional input files are read in order",
+            b"",
+            &["-d", ",", "2", first.path(), second.path()],
+            b"1\n2\n3\n",
+        );
+    }
+
+    #[test]
+    fn repeated_input_flags() {
+        let first = TempFile::new("repeated-1", b"a,1\n");
+        let second = TempFile::new("repeated-2", b"b,2\n");
+        run_success_test(
+            "Repeated -i flags are read in order",
+            b"",
+            &[
+                "-i",
+                second.path(),
+                "--input",
+                first.path(),
+                "-d",
+                ",",
+                "-n",
+                "1",
+            ],
+            b"1\tb\n2\ta\n",
+        );
+    }
+
+    #[test]
+    fn records_do_not_span_files() {
+        let first = TempFile::new("span-1", b"a,1\nb,2");
+        let second = TempFile::new("span-2", b"c,3");
+        run_success_test(
+            "A file's partial last record ends at EOF",
+            b"",
+            &["-d", ",", "2", first.path(), second.path()],
+            b"1\n2\n3",
+        );
+    }
+
+    #[test]
+    fn bytes_mode_bare_argument_is_a_file() {
+        let first = TempFile::new("bytes-1", b"xyz\n");
+        run_success_test(
+            "Bytes mode treats a bare argument as a file",
+            b"",
+            &["-b", "2", first.path()],
+            b"y\n",
+        );
+    }
+
+    #[test]
+    fn dash_reads_stdin() {
+        let first = TempFile::new("dash-1", b"a,1\n");
+        run_success_test(
+            "A dash reads stdin in sequence",
+            b"b,2\n",
+            &["-d", ",", "2", first.path(), "-"],
+            b"1\n2\n",
+        );
+    }
+
+    #[test]
+    fn missing_file_errors() {
+        let first = TempFile::new("missing-1", b"a,1\n");
+        run_error_test(
+            "A missing input file errors",
+            b"",
+            &["-d", ",", "2", first.path(), "/nonexistent/splitby-input"],
+        );
+    }
+
+    #[test]
+    fn mistyped_selection_is_not_a_file() {
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["-d", ",", "1", "x"])
+            .write_stdin("a,b\n")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("invalid selection: x"), "{stderr}");
+
+        run_error_test(
+            "After --, the same word is read as a file",
+            b"a,b\n",
+            &["-d", ",", "1", "--", "x"],
+        );
+    }
+}
+
+mod ignore_case {
+    use super::*;
+
+    #[test]
+    fn delimiter_is_case_sensitive_by_default() {
+        run_success_test(
+            "Delimiter matching is case-sensitive without the flag",
+            b"a X b x c\n",
+            &["-d", "x", "-j", "|", "1-3"],
+            b"a X b | c\n",
+        );
+    }
+
+    #[test]
+    fn ignore_case_matches_both_cases() {
+        run_success_test(
+            "Ignore-case splits on either case",
+            b"a X b x c\n",
+            &["--ignore-case", "-d", "x", "-j", "|", "1-3"],
+            b"a | b | c\n",
+        );
+    }
+
+    #[test]
+    fn works_with_fixed() {
+        run_success_test(
+            "Ignore-case with a fixed delimiter",
+            b"a.X.b.x.c\n",
+            &["-I", "-F", "-d", ".x.", "-j", "|", "1-3"],
+            b"a|b|c\n",
+        );
+    }
+
+    #[test]
+    fn inline_flags_still_apply() {
+        run_success_test(
+            "Inline flags in the pattern override ignore-case",
+            b"aXbxc\n",
+            &["-I", "-d", "/(?-i)x/", "--count"],
+            b"2\n",
+        );
+    }
+}
+
+mod edge_case {
+    use super::*;
+
+    #[test]
+    fn single_field_with_out_of_range_index() {
+        run_success_test(
+            "Single field with out-of-range index",
+            b"apple\n",
+            &["-d", " ", "2"],
+            b"\n",
+        );
+    }
+
+    #[test]
+    fn single_delimiter_at_beginning() {
+        run_success_test(
+            "Single delimiter at beginning",
+            b" apple\n",
+            &["-d", " ", "2"],
+            b"apple\n",
+        );
+    }
+
+    #[test]
+    fn single_delimiter_at_end() {
+        run_success_test(
+            "Single delimiter at end",
+            b"apple \n",
+            &["-d", " ", "1"],
+            b"apple\n",
+        );
+    }
+
+    #[test]
+    fn multiple_delimiters_with_spaces_and_commas() {
+        run_success_test(
+            "Multiple delimiters with spaces and commas",
+            b"apple, orange  banana, pear\n",
+            &["-d", "/[, ]+/", "1-3"],
+            b"apple, orange  banana\n",
+        );
+    }
+
+    #[test]
+    fn delimiter_appears_multiple_times() {
+        run_success_test(
+            "Delimiter appears multiple times",
+            b"apple,,orange\n",
+            &["-d", ",", "3"],
+            b"orange\n",
+        );
+    }
+
+    #[test]
+    fn delimiter_appears_multiple_times_with_range() {
+        run_success_test(
+            "Delimiter appears multiple times with range",
+            b"apple,,orange\n",
+            &["-d", ",", "1-3"],
+            b"apple,,orange\n",
+        );
+    }
+}
+
+mod join_and_trim {
+    use super::*;
+
+    #[test]
+    fn can_join_selections() {
+        run_success_test(
+            "Can join selections",
+            b"boo hoo foo\n",
+            &["-d", " ", "--join=,"],
+            b"boo,hoo,foo\n",
+        );
+    }
+
+    #[test]
+    fn can_join_whole_string() {
+        run_success_test(
+            "Can join whole string",
+            b"boo hoo foo\n",
+            &["-w", "-d", " ", "--join=,"],
+            b"boo,hoo,foo\n",
+        );
+    }
+
+    #[test]
+    fn doesnt_join_in_ranges() {
+        run_success_test(
+            "Doesn't join in ranges",
+            b"boo hoo foo\n",
+            &["-d", " ", "--join=,", "1", "2-3"],
+            b"boo,hoo,foo\n",
+        );
+    }
+
+    #[test]
+    fn join_space() {
+        run_success_test(
+            "Join with space",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "--join=space", "1", "2", "3"],
+            b"apple banana cherry\n",
+        );
+    }
+
+    #[test]
+    fn join_space_single_selection() {
+        run_success_test(
+            "Join with space (single selection, no join)",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "--join=space", "1"],
+            b"apple\n",
+        );
+    }
+
+    #[test]
+    fn join_first_mixed_delimiters() {
+        run_success_test(
+            "Join with first (mixed delimiters, uses first)",
+            b"apple;banana,cherry\n",
+            &["-d", "/[,;]/", "--join=first", "1", "2", "3"],
+            b"apple;banana;cherry\n",
+        );
+    }
+
+    #[test]
+    fn join_last_mixed_delimiters() {
+        run_success_test(
+            "Join with last (mixed delimiters, uses last)",
+            b"apple;banana,cherry\n",
+            &["-d", "/[,;]/", "--join=last", "1", "2", "3"],
+            b"apple,banana,cherry\n",
+        );
+    }
+
+    #[test]
+    fn join_first_no_delimiters() {
+        run_success_test(
+            "Join with first (no delimiters, falls back to space)",
+            b"apple\n",
+            &["-d", ",", "--join=first", "1"],
+            b"apple\n",
+        );
+    }
+
+    #[test]
+    fn join_last_no_delimiters() {
+        run_success_test(
+            "Join with last (no delimiters, falls back to space)",
+            b"apple\n",
+            &["-d", ",", "--join=last", "1"],
+            b"apple\n",
+        );
+    }
+
+    #[test]
+    fn join_with_empty_fields() {
+        run_success_test(
+            "Join with empty fields (still finds delimiter)",
+            b",,,\n",
+            &["-d", ",", "--join=first", "1", "2", "3"],
+            b",,\n",
+        );
+    }
+
+    #[test]
+    fn join_space_whole_string() {
+        run_success_test(
+            "Join with space in whole-string mode",
+            b"apple,banana,cherry",
+            &["-w", "-d", ",", "--join=space", "1", "2", "3"],
+            b"apple banana cherry",
+        );
+    }
+
+    #[test]
+    fn join_first_whole_string() {
+        run_success_test(
+            "Join with first in whole-string mode",
+            b"apple,banana,cherry",
+            &["-w", "-d", ",", "--join=first", "1", "2", "3"],
+            b"apple,banana,cherry",
+        );
+    }
+
+    #[test]
+    fn join_last_whole_string() {
+        run_success_test(
+            "Join with last in whole-string mode",
+            b"apple,banana,cherry",
+            &["-w", "-d", ",", "--join=last", "1", "2", "3"],
+            b"apple,banana,cherry",
+        );
+    }
+
+    #[test]
+    fn join_after_previous_basic() {
+        run_success_test(
+            "Join with after-previous (basic)",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "--join=after-previous", "1", "3"],
+            b"apple,cherry\n",
+        );
+    }
+
+    #[test]
+    fn join_after_previous_mixed_delimiters() {
+        run_success_test(
+            "Join with after-previous (mixed delimiters)",
+            b"apple,banana;cherry:date\n",
+            &["-d", "/[,;:]/", "--join=after-previous", "1", "3"],
+            b"apple,cherry\n",
+        );
+    }
+
+    #[test]
+    fn join_after_previous_non_adjacent() {
+        run_success_test(
+            "Join with after-previous (non-adjacent fields)",
+            b"apple,banana,cherry,date\n",
+            &["-d", ",", "--join=after-previous", "1", "4"],
+            b"apple,date\n",
+        );
+    }
+
+    #[test]
+    fn join_after_previous_multiple_selections() {
+        run_success_test(
+            "Join with after-previous (multiple selections)",
+            b"apple,banana;cherry:date\n",
+            &["-d", "/[,;:]/", "--join=after-previous", "1", "3", "1"],
+            b"apple,cherry:apple\n",
+        );
+    }
+
+    #[test]
+    fn join_after_previous_out_of_bounds() {
+        run_success_test(
+            "Join with after-previous (out-of-bounds field, no delimiter available)",
+            b"apple\n",
+            &["-d", ",", "--join=after-previous", "1", "5"],
+            b"apple\n",
+        );
+    }
+
+    #[test]
+    fn join_before_next_basic() {
+        run_success_test(
+            "Join with before-next (basic)",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "--join=before-next", "1", "3"],
+            b"apple,cherry\n",
+        );
+    }
+
+    #[test]
+    fn join_before_next_mixed_delimiters() {
+        run_success_test(
+            "Join with before-next (mixed delimiters)",
+            b"apple,banana;cherry:date\n",
+            &["-d", "/[,;:]/", "--join=before-next", "1", "4", "2"],
+            b"apple:date,banana\n",
+        );
+    }
+
+    #[test]
+    fn join_before_next_non_adjacent() {
+        run_success_test(
+            "Join with before-next (non-adjacent fields)",
+            b"apple,banana,cherry,date\n",
+            &["-d", ",", "--join=before-next", "1", "4"],
+            b"apple,date\n",
+        );
+    }
+
+    #[test]
+    fn join_before_next_multiple_selections() {
+        run_success_test(
+            "Join with before-next (multiple selections)",
+            b"apple,banana;cherry:date\n",
+            &["-d", "/[,;:]/", "--join=before-next", "1", "4", "2"],
+            b"apple:date,banana\n",
+        );
+    }
+
+    #[test]
+    fn join_before_next_out_of_bounds() {
+        run_success_test(
+            "Join with before-next (out-of-bounds field, no delimiter available)",
+            b"apple\n",
+            &["-d", ",", "--join=before-next", "1", "5"],
+            b"apple\n",
+        );
+    }
+
+    #[test]
+    fn join_auto_basic() {
+        run_success_test(
+            "Join with auto (basic, preserves delimiter)",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "--join=auto", "1", "3"],
+            b"apple,cherry\n",
+        );
+    }
+
+    #[test]
+    fn join_auto_fallback_to_space() {
+        run_success_test(
+            "Join with auto (no delimiter, falls back to space)",
+            b"apple\n",
+            &["-d", ",", "--join=auto", "1", "1"],
+            b"apple apple\n",
+        );
+    }
+
+    #[test]
+    fn join_auto_mixed_delimiters() {
+        run_success_test(
+            "Join with auto (mixed delimiters)",
+            b"apple,banana;cherry:date\n",
+            &["-d", "/[,;:]/", "--join=auto", "1", "3"],
+            b"apple,cherry\n",
+        );
+    }
+
+    #[test]
+    fn join_none_basic() {
+        run_success_test(
+            "Join with none (no separator)",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "--join=none", "1", "3"],
+            b"applecherry\n",
+        );
+    }
+
+    #[test]
+    fn join_none_multiple_selections() {
+        run_success_test(
+            "Join with none (multiple selections)",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "--join=none", "1", "2", "3"],
+            b"applebananacherry\n",
+        );
+    }
+
+    #[test]
+    fn join_none_with_ranges() {
+        run_success_test(
+            "Join with none (with ranges, no join between ranges)",
+            b"apple,banana,cherry,date\n",
+            &["-d", ",", "--join=none", "1-2", "4"],
+            b"applebananadate\n",
+        );
+    }
+
+    #[test]
+    fn join_space_basic() {
+        run_success_test(
+            "Join with space (basic)",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "--join=space", "1", "3"],
+            b"apple cherry\n",
+        );
+    }
+
+    #[test]
+    fn join_space_multiple_selections() {
+        run_success_test(
+            "Join with space (multiple selections)",
+            b"apple,banana,cherry\n",
+            &["-d", ",", "--join=space", "1", "2", "3"],
+            b"apple banana cherry\n",
+        );
+    }
+
+    #[test]
+    fn join_first_with_placeholder() {
+        run_success_test(
+            "Join with first (with placeholder out-of-bounds)",
+            b"apple,banana\n",
+            &["-d", ",", "--join=first", "--placeholder=X", "1", "5", "2"],
+            b"apple,X,banana\n",
+        );
+    }
+
+    #[test]
+    fn join_last_with_placeholder() {
+        run_success_test(
+            "Join with last (with placeholder out-of-bounds)",
+            b"apple,banana\n",
+            &["-d", ",", "--join=last", "--placeholder=X", "1", "5", "2"],
+            b"apple,X,banana\n",
+        );
+    }
+
+    #[test]
+    fn join_after_previous_with_placeholder() {
+        run_success_test(
+            "Join with after-previous (with placeholder out-of-bounds)",
+            b"apple,banana\n",
+            &[
+                "-d",
+                ",",
+                "--join=after-previous",
+                "--placeholder=X",
+                "1",
+                "5",
+                "2",
+            ],
+            b"apple,X banana\n",
+        );
+    }
+
+    #[test]
+    fn join_before_next_with_placeholder() {
+        run_success_test(
+            "Join with before-next (with placeholder out-of-bounds)",
+            b"apple,banana\n",
+            &[
+                "-d",
+                ",",
+                "--join=before-next",
+                "--placeholder=X",
+                "1",
+                "5",
+                "2",
+            ],
+            b"apple X,banana\n",
+        );
+    }
+
+    #[test]
+    fn join_after_previous_vs_before_next_difference() {
+        run_success_test(
+            "Join: after-previous vs before-next (shows difference with mixed delimiters)",
+            b"apple,banana;cherry:date\n",
+            &["-d", "/[,;:]/", "--join=after-previous", "1", "3"],
+            b"apple,cherry\n",
+        );
+    }
+
+    #[test]
+    fn join_before_next_vs_after_previous_difference() {
+        run_success_test(
+            "Join: before-next vs after-previous (shows difference with mixed delimiters)",
+            b"apple,banana;cherry:date\n",
+            &["-d", "/[,;:]/", "--join=before-next", "1", "3"],
+            b"apple;cherry\n",
+        );
+    }
+}
+
+mod index_pairs {
+    use super::*;
+
+    #[test]
+    fn emits_index_value_pairs() {
+        run_success_test(
+            "Emit field index pairs",
+            b"apple banana cherry\n",
+            &["-d", " ", "--emit-field-index-pairs", "1", "3"],
+            b"1=apple 3=cherry\n",
+        );
+    }
+
+    #[test]
+    fn keeps_original_indices_when_reordered() {
+        run_success_test(
+            "Emit field index pairs keeps original indices when reordered",
+            b"apple banana cherry\n",
+            &["-d", " ", "--emit-field-index-pairs", "3", "1", "-2"],
+            b"3=cherry 1=apple 2=banana\n",
+        );
+    }
+
+    #[test]
+    fn custom_separator() {
+        run_success_test(
+            "Emit field index pairs with a custom separator",
+            b"a,b,c\n",
+            &["-d", ",", "--emit-field-index-pairs=:", "--join=space"],
+            b"1:a 2:b 3:c\n",
+        );
+    }
+
+    #[test]
+    fn placeholder_keeps_index() {
+        run_success_test(
+            "Emit field index pairs with placeholder past the end",
+            b"a,b\n",
+            &["-d", ",", "--emit-field-index-pairs", "-p", "X", "1-3"],
+            b"1=a,2=b,3=X\n",
+        );
+    }
+
+    #[test]
+    fn with_align_errors() {
+        run_error_test(
+            "Emit field index pairs with align errors",
+            b"a,b\n",
+            &["-d", ",", "--emit-field-index-pairs", "--align"],
+        );
+    }
+}
+
+mod escape_output {
+    use super::*;
+
+    #[test]
+    fn control_byte_in_field() {
+        run_success_test(
+            "--escape-output writes 0x01 as \\x01",
+            b"a\x01b,c\n",
+            &["-d", ",", "--escape-output", "1"],
+            b"a\\x01b\n",
+        );
+    }
+
+    #[test]
+    fn named_escapes() {
+        run_success_test(
+            "--escape-output uses the same escapes --escape decodes",
+            b"a\tb\\c\rd\0e\n",
+            &["-d", ",", "--escape-output", "1"],
+            b"a\\tb\\\\c\\rd\\0e\n",
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_in_bytes_mode() {
+        run_success_test(
+            "--escape-output escapes bytes that aren't UTF-8, but keeps text",
+            b"\xffh\xc3\xa9\n",
+            &["-b", "--escape-output", "1-4"],
+            b"\\xffh\xc3\xa9\n",
+        );
+    }
+
+    #[test]
+    fn terminator_is_not_escaped() {
+        run_hex_output_test(
+            "--escape-output leaves the NUL terminator alone",
+            b"a,b\0c,d\0",
+            &["-z", "-d", ",", "--escape-output", "2"],
+            "62 00 64 00",
+        );
+    }
+
+    #[test]
+    fn whole_string_newlines() {
+        run_success_test(
+            "--escape-output escapes newlines inside a whole-string record",
+            b"a\nb\n",
+            &["-w", "-d", ",", "--escape-output"],
+            b"a\\nb\\n",
+        );
+    }
+
+    #[test]
+    fn inserted_separators_that_would_be_escaped_error() {
+        run_error_test(
+            "--escape-output with a NUL field terminator",
+            b"a,b\n",
+            &[
+                "-d",
+                ",",
+                "--escape-output",
+                "--field-terminator=nul",
+                "1-2",
+            ],
+        );
+        run_error_test(
+            "--escape-output with a tab join",
+            b"a,b\n",
+            &["-d", ",", "--escape-output", "-j", "\t", "1-2"],
+        );
+    }
+
+    #[test]
+    fn printable_join_is_kept() {
+        run_success_test(
+            "--escape-output leaves a printable join alone",
+            b"a\tx,b\n",
+            &["-d", ",", "--escape-output", "-j", "|", "1-2"],
+            b"a\\tx|b\n",
+        );
+    }
+}
+
+mod escape_sequences {
+    use super::*;
+
+    #[test]
+    fn join_tab_escape() {
+        run_success_test(
+            "Join decodes \\t",
+            b"a b c\n",
+            &["-d", " ", "--join=\\t"],
+            b"a\tb\tc\n",
+        );
+    }
+
+    #[test]
+    fn join_newline_escape_round_trips() {
+        run_success_test(
+            "Join decodes \\n inside a string",
+            b"a b\n",
+            &["-d", " ", "--join=x\\ny", "1", "2"],
+            b"ax\nyb\n",
+        );
+    }
+
+    #[test]
+    fn join_hex_escape() {
+        run_hex_output_test(
+            "Join decodes \\xNN and \\0",
+            b"a b\n",
+            &["-d", " ", "--join=\\x2C\\0", "1", "2"],
+            "61 2c 00 62 0a",
+        );
+    }
+
+    #[test]
+    fn join_escaped_backslash() {
+        run_success_test(
+            "Join decodes \\\\ to a single backslash",
+            b"a b\n",
+            &["-d", " ", "--join=\\\\", "1", "2"],
+            b"a\\b\n",
+        );
+    }
+
+    #[test]
+    fn unknown_escape_passes_through() {
+        run_success_test(
+            "Unknown escape passes through unchanged",
+            b"a b\n",
+            &["-d", " ", "--join=\\q", "1", "2"],
+            b"a\\qb\n",
+        );
+    }
+
+    #[test]
+    fn malformed_hex_escape_passes_through() {
+        run_success_test(
+            "A sign is not a hex digit, so \\x+f stays as written",
+            b"a b\n",
+            &["-d", " ", "--join=\\x+f", "1", "2"],
+            b"a\\x+fb\n",
+        );
+    }
+
+    #[test]
+    fn placeholder_escape() {
+        run_success_test(
+            "Placeholder decodes escapes",
+            b"a b\n",
+            &["-d", " ", "--placeholder=\\t", "--join=,", "1", "3"],
+            b"a,\t\n",
+        );
+    }
+
+    #[test]
+    fn no_escape_keeps_backslashes() {
+        run_success_test(
+            "No-escape keeps backslashes literal",
+            b"a b\n",
+            &["-d", " ", "--no-escape", "--join=\\t", "1", "2"],
+            b"a\\tb\n",
+        );
+    }
+
+    #[test]
+    fn hex_join_is_unchanged() {
+        run_success_test(
+            "Hex join is not affected by escape decoding",
+            b"a b\n",
+            &["-d", " ", "--join=0x2C", "1", "2"],
+            b"a,b\n",
+        );
+    }
+}
+
+mod pass_through {
+    use super::*;
+
+    #[test]
+    fn no_selections_output_record_verbatim() {
+        run_success_test(
+            "Records pass through untouched, empty and trailing fields included",
+            b"a,,b,\n,\n\nc\n",
+            &["-d", ","],
+            b"a,,b,\n,\n\nc\n",
+        );
+    }
+
+    #[test]
+    fn whole_string_still_drops_trailing_delimiter() {
+        run_success_test(
+            "Whole-string mode keeps dropping a trailing delimiter",
+            b"a,b,",
+            &["-w", "-d", ","],
+            b"a,b",
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_passes_through() {
+        run_hex_output_test(
+            "Invalid UTF-8 is left as it is without selections",
+            b"a\xffb\n",
+            &["-d", ","],
+            "61 ff 62 0a",
+        );
+        run_error_test(
+            "Invalid UTF-8 still errors under --strict-utf8 without selections",
+            b"a\xffb\n",
+            &["-d", ",", "--strict-utf8"],
+        );
+    }
+
+    #[test]
+    fn transform_flags_are_not_passed_through() {
+        // Each of these changes this record, so none of them can take the pass-through path
+        let input = b"b,a,,a, c\n";
+        let flags: [&[&str]; 23] = [
+            &["-j", "|"],
+            &["--skip-empty"],
+            &["--squash-delimiters"],
+            &["--trim"],
+            &["--unique"],
+            &["--sort"],
+            &["--reverse"],
+            &["--replace", ",=;"],
+            &["--separators", ";"],
+            &["--json"],
+            &["--shell-quote"],
+            &["--encode"],
+            &["--count"],
+            &["--stats"],
+            &["--complement-count"],
+            &["--emit-field-index-pairs"],
+            &["--offsets"],
+            &["--pick", "a"],
+            &["--require", "z"],
+            &["--from", "a"],
+            &["--field-pattern", "/(\\w)/"],
+            &["--field-length", "1"],
+            &["--max-fields", "2", "-j", "|"],
+        ];
+        for flag in flags {
+            let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+                .args(["-d", ","])
+                .args(flag)
+                .write_stdin(input.as_slice())
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{flag:?}");
+            assert_ne!(output.stdout, input, "{flag:?} left the record unchanged");
+        }
+    }
+}
+
+mod buffer_reuse {
+    use super::*;
+
+    #[test]
+    fn reused_buffers_hold_only_their_own_record() {
+        // Long and short records alternate, so a reused buffer would show any stale bytes
+        let mut input = String::new();
+        let mut expected = String::new();
+        for index in 0..100_000 {
+            let padding = "x".repeat(index % 7 * 20);
+            writeln!(input, "{index},{padding}").unwrap();
+            writeln!(expected, "{padding}:{index}").unwrap();
+        }
+        run_success_test(
+            "Records read into recycled buffers come out intact",
+            input.as_bytes(),
+            &["-d", ",", "--join=:", "--batch-bytes=4096", "2", "1"],
+            expected.as_bytes(),
+        );
+    }
+}
+
+mod byte_delimiter {
+    use super::*;
+
+    #[test]
+    fn large_input_matches_regex_path() {
+        let mut input = String::new();
+        let mut expected = String::new();
+        for index in 0..200_000 {
+            writeln!(input, "{index},,x{index},\u{e9}").unwrap();
+            writeln!(expected, "x{index}:\u{e9}:{index}").unwrap();
+        }
+        // A character class keeps the regex engine, so both paths must agree
+        for delimiter in [",", "/,/", "/[,]/"] {
+            run_success_test(
+                &format!("Large input split on {delimiter}"),
+                input.as_bytes(),
+                &["-d", delimiter, "--join=:", "3-4", "1"],
+                expected.as_bytes(),
+            );
+        }
+    }
+
+    #[test]
+    fn byte_delimiter_field_semantics() {
+        for delimiter in [",", "/,/", "/[,]/"] {
+            run_success_test(
+                "Empty and trailing fields split the same on every path",
+                b",a,,b,\n",
+                &["-d", delimiter, "--join=after-previous", "1-5"],
+                b",a,,b,\n",
+            );
+            run_success_test(
+                "Max fields leaves the rest in the last field",
+                b"a,b,c\n",
+                &["-d", delimiter, "--max-fields=2", "2"],
+                b"b,c\n",
+            );
+        }
+    }
+
+    #[test]
+    fn single_metacharacter_literal() {
+        run_success_test(
+            "A lone metacharacter given literally splits on that byte",
+            b"a.b\n",
+            &["-d", ".", "2"],
+            b"b\n",
+        );
+        run_success_test(
+            "A fixed single byte splits on that byte",
+            b"a|b\n",
+            &["-F", "-d", "|", "2"],
+            b"b\n",
+        );
+    }
+}
+
+mod field_terminator {
+    use super::*;
+
+    #[test]
+    fn print0_fields() {
+        run_success_test(
+            "--print0-fields joins the selected fields with NUL",
+            b"a,b,c\n",
+            &["-d", ",", "--print0-fields", "1", "2", "3"],
+            b"a\0b\0c\n",
+        );
+    }
+
+    #[test]
+    fn hex_terminator() {
+        run_success_test(
+            "--field-terminator takes hex",
+            b"a,b,c\n",
+            &["-d", ",", "--field-terminator=0x3b", "1", "3"],
+            b"a;c\n",
+        );
+    }
+
+    #[test]
+    fn none_terminator() {
+        run_success_test(
+            "--field-terminator=none joins with nothing",
+            b"a,b,c\n",
+            &["-d", ",", "--field-terminator", "none", "1-3"],
+            b"abc\n",
+        );
+    }
+
+    #[test]
+    fn byte_mode_separates_selections() {
+        run_hex_output_test(
+            "Byte mode puts the terminator between selections",
+            b"abcd\n",
+            &["-b", "--print0-fields", "1", "3-4"],
+            "61 00 63 64 0a",
+        );
+    }
+
+    #[test]
+    fn count_ignores_terminator() {
+        run_success_test(
+            "--count is unaffected by --print0-fields",
+            b"a,b,c\n",
+            &["-d", ",", "--print0-fields", "--count"],
+            b"3\n",
+        );
+    }
+
+    #[test]
+    fn with_join_is_rejected() {
+        run_error_test(
+            "--print0-fields cannot be combined with --join",
+            b"a,b\n",
+            &["-d", ",", "--print0-fields", "-j", ":", "1", "2"],
+        );
+    }
+}
+
+mod output_terminator {
+    use super::*;
+
+    #[test]
+    fn nul_in_newline_out() {
+        run_success_test(
+            "NUL-separated input converts to newline-separated output",
+            b"./a/x\0./b/y\0",
+            &["-z", "-d", "/", "--output-terminator=newline", "2"],
+            b"a\nb\n",
+        );
+    }
+
+    #[test]
+    fn newline_in_nul_out() {
+        run_hex_output_test(
+            "Newline-separated input converts to NUL-separated output",
+            b"a,1\nb,2\n",
+            &["-d", ",", "--output-terminator", "nul", "1"],
+            "61 00 62 00",
+        );
+    }
+
+    #[test]
+    fn hex_terminator_keeps_missing_final_terminator() {
+        run_success_test(
+            "A hex terminator replaces the newline, final record still unterminated",
+            b"a,1\nb,2",
+            &["-d", ",", "--output-terminator=0x3b20", "1"],
+            b"a; b",
+        );
+    }
+
+    #[test]
+    fn whole_string_ends_with_terminator() {
+        run_success_test(
+            "Whole-string output ends with the terminator even when piped",
+            b"a,b",
+            &["-w", "-d", ",", "--output-terminator=newline", "1"],
+            b"a\n",
+        );
+    }
+
+    #[test]
+    fn whole_string_terminator_survives_a_flush() {
+        let input = vec![b'a'; 200_000];
+        let mut expected = input.clone();
+        expected.push(0);
+        run_success_test(
+            "Whole-string output past the flush threshold still ends with the terminator",
+            &input,
+            &["-w", "-d", ",", "--output-terminator=nul", "1"],
+            &expected,
+        );
+    }
+
+    #[test]
+    fn invalid_terminator() {
+        run_error_test(
+            "Unknown terminator names error",
+            b"a\n",
+            &["-d", ",", "--output-terminator=tab", "1"],
+        );
+    }
+}
+
+mod terminator_behavior {
+    use super::*;
+
+    #[test]
+    fn per_line_keeps_final_newline_when_present() {
+        run_success_test(
+            "Per-line keeps final newline when present",
+            b"alpha\nbeta\n",
+            &["--bytes", "1"],
+            b"a\nb\n",
+        );
+    }
+
+    #[test]
+    fn per_line_does_not_add_final_newline_when_absent() {
+        run_success_test(
+            "Per-line does not add final newline when absent",
+            b"alpha\nbeta",
+            &["--bytes", "1"],
+            b"a\nb",
+        );
+    }
+
+    #[test]
+    fn per_line_preserves_empty_line_terminator() {
+        run_success_test(
+            "Per-line preserves empty line terminator",
+            b"\n",
+            &["--bytes"],
+            b"\n",
+        );
+    }
+
+    #[test]
+    fn crlf_output_terminates_each_record_with_crlf() {
+        run_success_test(
+            "CRLF output terminates each record with CRLF",
+            b"a,b\nc,d\n",
+            &["--crlf-output", "-d", ",", "2"],
+            b"b\r\nd\r\n",
+        );
+    }
+
+    #[test]
+    fn crlf_output_rewrites_crlf_input() {
+        run_success_test(
+            "CRLF output re-emits CRLF for CRLF input",
+            b"a,b\r\nc,d\r\n",
+            &["--crlf-output", "-d", ",", "1"],
+            b"a\r\nc\r\n",
+        );
+    }
+
+    #[test]
+    fn crlf_output_does_not_add_final_terminator_when_absent() {
+        run_success_test(
+            "CRLF output does not add final terminator when absent",
+            b"a,b\nc,d",
+            &["--crlf-output", "-d", ",", "2"],
+            b"b\r\nd",
+        );
+    }
+
+    #[test]
+    fn crlf_output_does_not_affect_zero_terminated() {
+        run_success_test(
+            "CRLF output does not affect zero-terminated records",
+            b"a,b\0c,d\0",
+            &["--crlf-output", "-z", "-d", ",", "2"],
+            b"b\0d\0",
+        );
+    }
+}
+
+mod invert_parity {
+    use super::*;
+
+    /// Runs the same inverted selection in fields, bytes and chars mode on `abcde`.
+    fn assert_same_in_every_mode(arguments: &[&str], expected: &[u8]) {
+        let mut fields_arguments = vec!["-d", ",", "-j", ""];
+        fields_arguments.extend_from_slice(arguments);
+        run_success_test("fields mode", b"a,b,c,d,e\n", &fields_arguments, expected);
+        for mode in ["-b", "-c"] {
+            let mut mode_arguments = vec![mode];
+            mode_arguments.extend_from_slice(arguments);
+            run_success_test(mode, b"abcde\n", &mode_arguments, expected);
+        }
+    }
+
+    #[test]
+    fn ranges_and_lists() {
+        assert_same_in_every_mode(&["--invert", "2-3,5"], b"ad\n");
+        assert_same_in_every_mode(&["--invert", "1-2,3-4"], b"e\n");
+        assert_same_in_every_mode(&["--invert", "1-3,2-4"], b"e\n");
+        assert_same_in_every_mode(&["--invert", "1-5:2"], b"bd\n");
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        assert_same_in_every_mode(&["--invert", "7-9"], b"abcde\n");
+        assert_same_in_every_mode(&["--invert", "2,7"], b"acde\n");
+        assert_same_in_every_mode(&["--invert", "-p", "X", "2,7"], b"acde\n");
+    }
+}
+
+mod invert_out_of_bounds {
+    use super::*;
+
+    /// (range, expected output for records of 1, 2, 3 and 4 fields)
+    const TABLE: &[(&str, [&str; 4])] = &[
+        ("2", ["a", "a", "a,c", "a,c,d"]),
+        ("2-3", ["a", "a", "a", "a,d"]),
+        ("2-4", ["a", "a", "a", "a"]),
+        ("2-5", ["a", "a", "a", "a"]),
+        ("4-5", ["a", "a,b", "a,b,c", "a,b,c"]),
+        ("5-9", ["a", "a,b", "a,b,c", "a,b,c,d"]),
+        ("1-9", ["", "", "", ""]),
+        ("-1", ["", "a", "a,b", "a,b,c"]),
+        ("-3", ["a", "a,b", "b,c", "a,c,d"]),
+        ("-5", ["a", "a,b", "a,b,c", "a,b,c,d"]),
+        ("-4-2", ["", "", "c", "c,d"]),
+        ("-9--1", ["", "", "", ""]),
+        ("-5--4", ["a", "a,b", "a,b,c", "b,c,d"]),
+    ];
+
+    #[test]
+    fn ranges_by_field_count() {
+        let records = ["a", "a,b", "a,b,c", "a,b,c,d"];
+        for (range, expected) in TABLE {
+            for (record, expected) in records.iter().zip(expected) {
+                let input = format!("{record}\n");
+                let output = format!("{expected}\n");
+                run_success_test(
+                    &format!("--invert {range} on {record}"),
+                    input.as_bytes(),
+                    &[",", "--invert", range],
+                    output.as_bytes(),
+                );
+                run_success_test(
+                    &format!("--invert {range} on {record} with a placeholder"),
+                    input.as_bytes(),
+                    &[",", "--invert", "-p", "X", range],
+                    output.as_bytes(),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_the_clamped_range() {
+        for (out_of_bounds, clamped) in [("2-4", "2-3"), ("-5-2", "1-2"), ("3-9", "3-3")] {
+            let expected = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+                .args([",", "--invert", clamped])
+                .write_stdin("a,b,c\n")
+                .output()
+                .expect("running splitby should not fail");
+            run_success_test(
+                &format!("{out_of_bounds} inverts like {clamped}"),
+                b"a,b,c\n",
+                &[",", "--invert", out_of_bounds],
+                &expected.stdout,
+            );
+        }
+    }
+
+    #[test]
+    fn strict_bounds_still_errors() {
+        run_error_test(
+            "the out-of-range end is rejected",
+            b"a,b,c\n",
+            &[",", "--invert", "--strict-bounds", "2-4"],
+        );
+    }
+}
+
+mod complement_count {
+    use super::*;
+
+    #[test]
+    fn counts_fields_left_out() {
+        run_success_test(
+            "Selecting 1 and 3 of four fields leaves two out",
+            b"a,b,c,d\n",
+            &["-d", ",", "1,3", "--complement-count"],
+            b"2\n",
+        );
+        run_success_test(
+            "Each record is counted against its own fields",
+            b"a,b,c,d\na\na,b,c\n",
+            &["-d", ",", "1-2", "--complement-count"],
+            b"2\n0\n1\n",
+        );
+        run_success_test(
+            "No selections select every field",
+            b"a,b,c\n",
+            &["-d", ",", "--complement-count"],
+            b"0\n",
+        );
+    }
+
+    #[test]
+    fn duplicates_and_placeholders() {
+        run_success_test(
+            "A field selected twice is only one field",
+            b"a,b,c,d\n",
+            &["-d", ",", "1", "1", "1-2", "--complement-count"],
+            b"2\n",
+        );
+        run_success_test(
+            "Placeholder positions past the end are not fields",
+            b"a,b\n",
+            &["-d", ",", "1", "5", "-p", "x", "--complement-count"],
+            b"1\n",
+        );
+    }
+
+    #[test]
+    fn differs_from_invert_count() {
+        run_success_test(
+            "--invert --count counts every field",
+            b"a,b,c,d\n",
+            &["-d", ",", "1,3", "--invert", "--count"],
+            b"4\n",
+        );
+        run_success_test(
+            "With --invert, the fields it removes are left out",
+            b"a,b,c,d\n",
+            &["-d", ",", "1", "--invert", "--complement-count"],
+            b"1\n",
+        );
+    }
+
+    #[test]
+    fn rejects_other_outputs() {
+        for flag in [
+            "--count",
+            "--stats",
+            "--json",
+            "--align",
+            "--shell-quote",
+            "--emit-field-index-pairs",
+        ] {
+            run_error_test(
+                &format!("--complement-count with {flag}"),
+                b"a,b\n",
+                &["-d", ",", "1", "--complement-count", flag],
+            );
+        }
+        run_error_test(
+            "--complement-count outside fields mode",
+            b"ab\n",
+            &["-b", "1", "--complement-count"],
+        );
+    }
+}
+
+mod count_and_invert {
+    use super::*;
+
+    #[test]
+    fn using_count_to_count_fields() {
+        run_success_test(
+            "Using --count to count fields",
+            b"this is a test\n",
+            &["-d", " ", "--count"],
+            b"4\n",
+        );
+    }
+
+    #[test]
+    fn count_nonempty_ignores_empty_fields() {
+        run_success_test(
+            "Using --count=nonempty ignores empty fields",
+            b"a,,b\n",
+            &["-d", ",", "--count=nonempty"],
+            b"2\n",
+        );
+    }
+
+    #[test]
+    fn count_all_includes_empty_fields() {
+        run_success_test(
+            "Using --count=all includes empty fields",
+            b"a,,b\n",
+            &["-d", ",", "--count=all"],
+            b"3\n",
+        );
+    }
+
+    #[test]
+    fn count_all_overrides_earlier_count_nonempty() {
+        run_success_test(
+            "Using --count=all after --count=nonempty counts every field",
+            b"a,,b\n",
+            &["-d", ",", "--count=nonempty", "--count=all"],
+            b"3\n",
+        );
+    }
+
+    #[test]
+    fn count_invalid_mode_errors() {
+        run_error_test(
+            "Using --count with an invalid mode errors",
+            b"a,,b\n",
+            &["-d", ",", "--count=some"],
+        );
+    }
+
+    #[test]
+    fn count_nonempty_in_byte_mode_errors() {
+        run_error_test(
+            "Using --count=nonempty in byte mode errors",
+            b"a,,b\n",
+            &["-b", "--count=nonempty"],
+        );
+    }
+
+    #[test]
+    fn using_count_with_newline_delimiter() {
+        run_success_test(
+            "Using --count with newline delimiter",
+            b"this\nis\na\ntest\n",
+            &["-d", "\\n", "--count"],
+            b"1\n1\n1\n1\n",
+        );
+    }
+
+    #[test]
+    fn using_count_with_newline_delimiter_whole_string() {
+        run_success_test(
+            "Using --count with newline delimiter whole-string",
+            b"this\nis\na\ntest\n",
+            &["--whole-string", "-d", "/\\n/", "--count"],
+            b"4",
+        );
+    }
+
+    #[test]
+    fn using_count_with_extra_newline() {
+        run_success_test(
+            "Using --count with extra newline",
+            b"this\nis\na\ntest\n\n",
+            &["-d", "/\\n/", "--count"],
+            b"1\n1\n1\n1\n1\n",
+        );
+    }
+
+    #[test]
+    fn using_count_with_extra_newline_whole_string() {
+        run_success_test(
+            "Using --count with extra newline whole-string",
+            b"this\nis\na\ntest\n\n",
+            &["--whole-string", "-d", "/\\n/", "--count"],
+            b"5",
+        );
+    }
+
+    #[test]
+    fn count_takes_precedence_over_join() {
+        run_success_test(
+            "Count takes precedence over join",
+            b"a b c\n",
+            &["-d", " ", "--count", "--join=,"],
+            b"3\n",
+        );
+    }
+
+    #[test]
+    fn per_line_default_with_count_per_row() {
+        run_success_test(
+            "Per-line default with count (per row)",
+            b"one two\nalpha beta gamma\n",
+            &["-d", " ", "--count"],
+            b"2\n3\n",
+        );
+    }
+
+    #[test]
+    fn invert_single_index() {
+        run_success_test(
+            "Invert single index",
+            b"a b c d\n",
+            &["-d", " ", "--invert", "2"],
+            b"a c d\n",
+        );
+    }
+
+    #[test]
+    fn invert_single_index_whole_string() {
+        run_success_test(
+            "Invert single index whole-string",
+            b"a b c d\n",
+            &["-d", " ", "--whole-string", "--invert", "2"],
+            b"a c d\n",
+        );
+    }
+
+    #[test]
+    fn invert_range_selection() {
+        run_success_test(
+            "Invert range selection",
+            b"a b c d\n",
+            &["-d", " ", "--invert", "2-3"],
+            b"a d\n",
+        );
+    }
+
+    #[test]
+    fn invert_range_with_join() {
+        run_success_test(
+            "Invert range with join",
+            b"a b c d\n",
+            &["-d", " ", "--invert", "--join=,", "2-3"],
+            b"a,d\n",
+        );
+    }
+
+    #[test]
+    fn invert_whole_set_empty_result() {
+        run_success_test(
+            "Invert whole set (empty result)",
+            b"a b\n",
+            &["-d", " ", "--invert", "1-2"],
+            b"\n",
+        );
+    }
+
+    #[test]
+    fn invert_whole_set_with_placeholder() {
+        run_success_test(
+            "Invert whole set with placeholder",
+            b"a b\n",
+            &["-d", " ", "--invert", "--placeholder=?", "1-2"],
+            b"\n",
+        );
+    }
+
+    #[test]
+    fn invert_with_count() {
+        run_success_test(
+            "Invert with count",
+            b"a b c\n",
+            &["-d", " ", "--count", "--invert", "2"],
+            b"3\n",
+        );
+    }
+}
+
+mod strictness {
+    use super::*;
+
+    #[test]
+    fn strict_bounds_feature() {
+        run_success_test(
+            "Strict bounds feature",
+            b"this is a test\n",
+            &["-d", " ", "--strict-bounds", "2-4"],
+            b"is a test\n",
+        );
+    }
+
+    #[test]
+    fn strict_utf8_rejects_invalid_fields() {
+        run_error_test(
+            "Strict utf8 rejects invalid fields",
+            b"\xFF,\n",
+            &["-d", ",", "--strict-utf8", "1"],
+        );
+    }
+
+    #[test]
+    fn no_strict_utf8_allows_invalid_fields() {
+        run_success_test(
+            "No-strict-utf8 allows invalid fields",
+            b"\xFF,\n",
+            &["-d", ",", "--no-strict-utf8", "1"],
+            b"\xFF\n",
+        );
+    }
+
+    #[test]
+    fn strict_bounds_with_out_of_range_index_0() {
+        run_error_test(
+            "Strict bounds with out-of-range index (0)",
+            b"this is a test\n",
+            &["-d", " ", "--strict-bounds", "0"],
+        );
+    }
+
+    #[test]
+    fn strict_bounds_with_out_of_range_index_5() {
+        run_error_test(
+            "Strict bounds with out-of-range index (5)",
+            b"this is a test\n",
+            &["-d", " ", "--strict-bounds", "5"],
+        );
+    }
+
+    #[test]
+    fn empty_string_with_strict_bounds() {
+        run_success_test(
+            "Empty string with strict bounds",
+            b"\n",
+            &["-d", " ", "--strict-bounds", "1"],
+            b"\n",
+        );
+    }
+
+    #[test]
+    fn strict_return_feature() {
+        run_error_test(
+            "Strict return feature",
+            b",boo\n",
+            &["--strict-return", "-d", ",", "1"],
+        );
+    }
+
+    #[test]
+    fn strict_return_with_out_of_range_index() {
+        run_error_test(
+            "Strict return with out-of-range index",
+            b"this is a test\n",
+            &["--strict-return", "-d", "z", "2"],
+        );
+    }
+
+    #[test]
+    fn strict_return_doesnt_allow_empty_fields() {
+        run_error_test(
+            "Strict return doesn't allow empty fields",
+            b",\n",
+            &["--strict-return", "-d", ","],
+        );
+    }
+
+    #[test]
+    fn strict_return_counts() {
+        run_success_test(
+            "Strict return counts",
+            b",\n",
+            &["--strict-return", "--count", "-d", ","],
+            b"2\n",
+        );
+    }
+
+    #[test]
+    fn start_after_end_no_strict_range_order() {
+        run_success_test(
+            "Start after end (no strict range order)",
+            b"this is a test\n",
+            &["--no-strict-range-order", "-d", " ", "2-1"],
+            b"\n",
+        );
+    }
+
+    #[test]
+    fn start_after_end_negative_no_strict_range_order() {
+        run_success_test(
+            "Start after end negative (no strict range order)",
+            b"this is a test\n",
+            &["--no-strict-range-order", "-d", " ", "-1--2"],
+            b"\n",
+        );
+    }
+
+    #[test]
+    fn start_after_end_positive_negative_no_strict_range_order() {
+        run_success_test(
+            "Start after end positive-negative (no strict range order)",
+            b"this is a test\n",
+            &["--no-strict-range-order", "-d", " ", "4--2"],
+            b"\n",
+        );
+    }
+
+    #[test]
+    fn start_after_end_negative_positive_no_strict_range_order() {
+        run_success_test(
+            "Start after end negative-positive (no strict range order)",
+            b"this is a test\n",
+            &["--no-strict-range-order", "-d", " ", "-1-3"],
+            b"\n",
+        );
+    }
+
+    #[test]
+    fn start_after_end_strict_range() {
+        run_error_test(
+            "Start after end (strict range)",
+            b"this is a test\n",
+            &["-d", " ", "2-1"],
+        );
+    }
+
+    #[test]
+    fn start_after_end_negative_strict_range() {
+        run_error_test(
+            "Start after end negative (strict range)",
+            b"this is a test\n",
+            &["-d", " ", "-1--2"],
+        );
+    }
+
+    #[test]
+    fn start_after_end_positive_negative_strict_range() {
+        run_error_test(
+            "Start after end positive-negative (strict range)",
+            b"this is a test\n",
+            &["-d", " ", "4--2"],
+        );
+    }
+
+    #[test]
+    fn start_after_end_negative_positive_strict_range() {
+        run_error_test(
+            "Start after end negative-positive (strict range)",
+            b"this is a test\n",
+            &["-d", " ", "-1-3"],
+        );
+    }
+
+    #[test]
+    fn strict_return_only_delimiter() {
+        run_error_test(
+            "Strict return fails with no valid fields",
+            b",\n",
+            &["--strict", "-d", ","],
+        );
+    }
+
+    #[test]
+    fn strict_enables_strict_return() {
+        run_error_test(
+            "Strict enables strict-return",
+            b",\n",
+            &["--strict", "-d", ","],
         );
     }
 
     #[test]
-    fn join_before_next_with_placeholder() {
+    fn no_strict_clears_strict_flags() {
         run_success_test(
-            "Join with before-next (with placeholder out-of-bounds)",
-            b"apple,banana\n",
-            &[
-                "-d",
-                ",",
-                "--join=before-next",
-                "--placeholder=X",
-                "1",
-                "5",
-                "2",
-            ],
-            b"apple X,banana\n",
+            "No-strict clears strict flags",
+            b"a b\n",
+            &["--strict", "--no-strict", "-d", " ", "5"],
+            b"\n",
         );
     }
+}
+
+mod fail_on_empty_output {
+    use super::*;
 
     #[test]
-    fn join_after_previous_vs_before_next_difference() {
-        run_success_test(
-            "Join: after-previous vs before-next (shows difference with mixed delimiters)",
-            b"apple,banana;cherry:date\n",
-            &["-d", "/[,;:]/", "--join=after-previous", "1", "3"],
-            b"apple,cherry\n",
+    fn out_of_range_selection_fails() {
+        run_error_test(
+            "Fail-on-empty-output errors when every selection is out of range",
+            b"a,b\nc,d\n",
+            &["-d", ",", "--fail-on-empty-output", "5"],
         );
     }
 
     #[test]
-    fn join_before_next_vs_after_previous_difference() {
-        run_success_test(
-            "Join: before-next vs after-previous (shows difference with mixed delimiters)",
-            b"apple,banana;cherry:date\n",
-            &["-d", "/[,;:]/", "--join=before-next", "1", "3"],
-            b"apple;cherry\n",
+    fn all_records_suppressed_fails() {
+        run_error_test(
+            "Fail-on-empty-output errors when every record is suppressed",
+            b"one\ntwo\n",
+            &["-d", ",", "-s", "--fail-on-empty-output", "1"],
         );
     }
-}
-
-mod terminator_behavior {
-    use super::*;
 
     #[test]
-    fn per_line_keeps_final_newline_when_present() {
+    fn some_output_succeeds() {
         run_success_test(
-            "Per-line keeps final newline when present",
-            b"alpha\nbeta\n",
-            &["--bytes", "1"],
-            b"a\nb\n",
+            "Fail-on-empty-output passes when any record has output",
+            b"a,b\nc\n",
+            &["-d", ",", "--fail-on-empty-output", "2"],
+            b"b\n\n",
         );
     }
 
     #[test]
-    fn per_line_does_not_add_final_newline_when_absent() {
+    fn no_input_succeeds() {
         run_success_test(
-            "Per-line does not add final newline when absent",
-            b"alpha\nbeta",
-            &["--bytes", "1"],
-            b"a\nb",
+            "Fail-on-empty-output does not apply when there is no input",
+            b"",
+            &["-d", ",", "--fail-on-empty-output", "2"],
+            b"",
         );
     }
 
     #[test]
-    fn per_line_preserves_empty_line_terminator() {
+    fn without_flag_succeeds() {
         run_success_test(
-            "Per-line preserves empty line terminator",
-            b"\n",
-            &["--bytes"],
+            "Empty output succeeds without the flag",
+            b"a,b\n",
+            &["-d", ",", "5"],
             b"\n",
         );
     }
 }
 
-mod count_and_invert {
+mod skip_empty {
     use super::*;
 
     #[test]
-    fn using_count_to_count_fields() {
+    fn starting_empty_field() {
         run_success_test(
-            "Using --count to count fields",
-            b"this is a test\n",
-            &["-d", " ", "--count"],
-            b"4\n",
+            "Starting empty field",
+            b",orange\n",
+            &["--skip-empty", "-d", ",", "1"],
+            b"orange\n",
         );
     }
 
     #[test]
-    fn using_count_with_newline_delimiter() {
+    fn middle_field_empty() {
         run_success_test(
-            "Using --count with newline delimiter",
-            b"this\nis\na\ntest\n",
-            &["-d", "\\n", "--count"],
-            b"1\n1\n1\n1\n",
+            "Middle field empty",
+            b"apple,,orange\n",
+            &["--skip-empty", "-d", ",", "2"],
+            b"orange\n",
         );
     }
 
     #[test]
-    fn using_count_with_newline_delimiter_whole_string() {
+    fn blank_fields_are_skipped_after_trimming() {
         run_success_test(
-            "Using --count with newline delimiter whole-string",
-            b"this\nis\na\ntest\n",
-            &["--whole-string", "-d", "/\\n/", "--count"],
-            b"4",
+            "--trim --skip-empty drops whitespace-only fields",
+            b"apple, ,orange\n",
+            &["--trim", "--skip-empty", "-d", ",", "2"],
+            b"orange\n",
+        );
+        run_success_test(
+            "--trim --skip-empty counts only fields with text",
+            b"apple, ,orange\n",
+            &["--trim", "--skip-empty", "-d", ",", "--count"],
+            b"2\n",
+        );
+        run_success_test(
+            "Without --trim a blank field is kept",
+            b"apple, ,orange\n",
+            &["--skip-empty", "-d", ",", "2"],
+            b" \n",
         );
     }
 
     #[test]
-    fn using_count_with_extra_newline() {
+    fn final_field_empty() {
         run_success_test(
-            "Using --count with extra newline",
-            b"this\nis\na\ntest\n\n",
-            &["-d", "/\\n/", "--count"],
-            b"1\n1\n1\n1\n1\n",
+            "Final field empty",
+            b"orange,\n",
+            &["--skip-empty", "-d", ",", "2"],
+            b"\n",
         );
     }
 
     #[test]
-    fn using_count_with_extra_newline_whole_string() {
+    fn all_fields_empty() {
         run_success_test(
-            "Using --count with extra newline whole-string",
-            b"this\nis\na\ntest\n\n",
-            &["--whole-string", "-d", "/\\n/", "--count"],
-            b"5",
+            "All fields empty",
+            b",\n",
+            &["--skip-empty", "-d", ","],
+            b"\n",
         );
     }
 
     #[test]
-    fn count_takes_precedence_over_join() {
+    fn known_failure() {
         run_success_test(
-            "Count takes precedence over join",
+            "Known failure",
+            b"a  b   c\n",
+            &["-d", " ", "--skip-empty", "1-3"],
             b"a b c\n",
-            &["-d", " ", "--count", "--join=,"],
-            b"3\n",
         );
     }
 
     #[test]
-    fn per_line_default_with_count_per_row() {
+    fn skip_with_strict_bounds_works() {
         run_success_test(
-            "Per-line default with count (per row)",
-            b"one two\nalpha beta gamma\n",
-            &["-d", " ", "--count"],
-            b"2\n3\n",
+            "Skip with strict bounds works",
+            b"orange,\n",
+            &["--skip-empty", "--strict-bounds", "-d", ",", "1"],
+            b"orange\n",
         );
     }
 
     #[test]
-    fn invert_single_index() {
-        run_success_test(
-            "Invert single index",
-            b"a b c d\n",
-            &["-d", " ", "--invert", "2"],
-            b"a c d\n",
+    fn skip_with_strict_bounds_fails() {
+        run_error_test(
+            "Skip with strict bounds fails",
+            b"orange,\n",
+            &["--skip-empty", "--strict-bounds", "-d", ",", "2"],
         );
     }
 
     #[test]
-    fn invert_single_index_whole_string() {
+    fn skip_with_strict_return_works() {
         run_success_test(
-            "Invert single index whole-string",
-            b"a b c d\n",
-            &["-d", " ", "--whole-string", "--invert", "2"],
-            b"a c d\n",
+            "Skip with strict return works",
+            b"orange,\n",
+            &["--skip-empty", "--strict-return", "-d", ",", "1"],
+            b"orange\n",
         );
     }
 
     #[test]
-    fn invert_range_selection() {
+    fn skip_with_strict_return_fails() {
+        run_error_test(
+            "Skip with strict return fails",
+            b",,\n",
+            &["--skip-empty", "--strict-return", "-d", ",", "1"],
+        );
+    }
+
+    #[test]
+    fn starting_empty_field_with_count() {
         run_success_test(
-            "Invert range selection",
-            b"a b c d\n",
-            &["-d", " ", "--invert", "2-3"],
-            b"a d\n",
+            "Starting empty field with count",
+            b",orange\n",
+            &["--skip-empty", "-d", ",", "--count"],
+            b"1\n",
         );
     }
 
     #[test]
-    fn invert_range_with_join() {
+    fn middle_field_empty_with_count() {
         run_success_test(
-            "Invert range with join",
-            b"a b c d\n",
-            &["-d", " ", "--invert", "--join=,", "2-3"],
-            b"a,d\n",
+            "Middle field empty with count",
+            b"apple,,orange\n",
+            &["--skip-empty", "-d", ",", "--count"],
+            b"2\n",
         );
     }
 
     #[test]
-    fn invert_whole_set_empty_result() {
+    fn final_field_empty_with_count() {
         run_success_test(
-            "Invert whole set (empty result)",
-            b"a b\n",
-            &["-d", " ", "--invert", "1-2"],
-            b"\n",
+            "Final field empty with count",
+            b"orange,\n",
+            &["--skip-empty", "-d", ",", "--count"],
+            b"1\n",
         );
     }
 
     #[test]
-    fn invert_whole_set_with_placeholder() {
+    fn all_fields_empty_with_count() {
         run_success_test(
-            "Invert whole set with placeholder",
-            b"a b\n",
-            &["-d", " ", "--invert", "--placeholder=?", "1-2"],
-            b"\n",
+            "All fields empty with count",
+            b",\n",
+            &["--skip-empty", "-d", ",", "--count"],
+            b"0\n",
         );
     }
 
     #[test]
-    fn invert_with_count() {
+    fn no_skip_empty_overrides_skip_empty() {
         run_success_test(
-            "Invert with count",
-            b"a b c\n",
-            &["-d", " ", "--count", "--invert", "2"],
+            "No-skip-empty overrides skip-empty",
+            b"a,,b\n",
+            &["-d", ",", "--count", "--skip-empty", "--no-skip-empty"],
             b"3\n",
         );
     }
+
+    #[test]
+    fn skip_empty_overrides_no_skip_empty() {
+        run_success_test(
+            "Skip-empty overrides no-skip-empty",
+            b"a,,b\n",
+            &["-d", ",", "--count", "--no-skip-empty", "--skip-empty"],
+            b"2\n",
+        );
+    }
 }
 
-mod strictness {
+mod comment_lines {
     use super::*;
 
     #[test]
-    fn strict_bounds_feature() {
+    fn drops_comment_lines() {
         run_success_test(
-            "Strict bounds feature",
-            b"this is a test\n",
-            &["-d", " ", "--strict-bounds", "2-4"],
-            b"is a test\n",
+            "Comment lines are dropped before field extraction",
+            b"# name,value\na,1\n# skipped\nb,2\n",
+            &["-d", ",", "--comment", "#", "2"],
+            b"1\n2\n",
         );
     }
 
     #[test]
-    fn strict_utf8_rejects_invalid_fields() {
-        run_error_test(
-            "Strict utf8 rejects invalid fields",
-            b"\xFF,\n",
-            &["-d", ",", "--strict-utf8", "1"],
+    fn leading_whitespace_before_prefix() {
+        run_success_test(
+            "Comment prefix can follow leading whitespace",
+            b"a,1\n   # indented\n\t#tabbed\nb,2\n",
+            &["-d", ",", "--comment=#", "1"],
+            b"a\nb\n",
         );
     }
 
     #[test]
-    fn no_strict_utf8_allows_invalid_fields() {
+    fn multiple_prefixes() {
         run_success_test(
-            "No-strict-utf8 allows invalid fields",
-            b"\xFF,\n",
-            &["-d", ",", "--no-strict-utf8", "1"],
-            b"\xEF\xBF\xBD\n",
+            "Comment flag can be repeated",
+            b"; ini comment\n# hash comment\nkey=value\n",
+            &["-d", "=", "--comment", "#", "--comment", ";", "2"],
+            b"value\n",
         );
     }
 
     #[test]
-    fn strict_bounds_with_out_of_range_index_0() {
-        run_error_test(
-            "Strict bounds with out-of-range index (0)",
-            b"this is a test\n",
-            &["-d", " ", "--strict-bounds", "0"],
+    fn numbers_keep_input_positions() {
+        run_success_test(
+            "Numbering counts dropped comment lines",
+            b"# header\na\nb\n",
+            &["-b", "--comment", "#", "-n", "1"],
+            b"2\ta\n3\tb\n",
         );
     }
 
     #[test]
-    fn strict_bounds_with_out_of_range_index_5() {
+    fn rejects_empty_prefix() {
         run_error_test(
-            "Strict bounds with out-of-range index (5)",
-            b"this is a test\n",
-            &["-d", " ", "--strict-bounds", "5"],
+            "Comment prefix can't be empty",
+            b"a\n",
+            &["-b", "--comment=", "1"],
+        );
+        run_error_test("Comment needs a prefix", b"a\n", &["-b", "1", "--comment"]);
+    }
+}
+
+mod only_delimited {
+    use super::*;
+
+    #[test]
+    fn drops_lines_without_delimiter() {
+        run_success_test(
+            "Only-delimited drops lines without a delimiter",
+            b"a,b\nnone\nc,d\nalso none\ne,f\n",
+            &["-d", ",", "--only-delimited", "2"],
+            b"b\nd\nf\n",
+        );
+    }
+
+    #[test]
+    fn short_flag() {
+        run_success_test(
+            "Only-delimited short flag",
+            b"none\na,b\n",
+            &["-s", "-d", ",", "1"],
+            b"a\n",
         );
     }
 
     #[test]
-    fn empty_string_with_strict_bounds() {
+    fn keeps_lines_with_empty_fields() {
         run_success_test(
-            "Empty string with strict bounds",
-            b"\n",
-            &["-d", " ", "--strict-bounds", "1"],
-            b"\n",
+            "Only-delimited keeps lines that are just a delimiter",
+            b",\nnone\n",
+            &["-s", "-d", ",", "--count"],
+            b"2\n",
         );
     }
 
     #[test]
-    fn strict_return_feature() {
-        run_error_test(
-            "Strict return feature",
-            b",boo\n",
-            &["--strict-return", "-d", ",", "1"],
+    fn without_flag_passes_lines_through() {
+        run_success_test(
+            "Lines without a delimiter pass through by default",
+            b"a,b\nnone\n",
+            &["-d", ",", "2"],
+            b"b\n\n",
         );
     }
 
     #[test]
-    fn strict_return_with_out_of_range_index() {
-        run_error_test(
-            "Strict return with out-of-range index",
-            b"this is a test\n",
-            &["--strict-return", "-d", "z", "2"],
+    fn all_lines_suppressed() {
+        run_success_test(
+            "Only-delimited with no delimited lines outputs nothing",
+            b"one\ntwo\n",
+            &["-s", "-d", ",", "1"],
+            b"",
         );
     }
 
     #[test]
-    fn strict_return_doesnt_allow_empty_fields() {
+    fn in_byte_mode_errors() {
         run_error_test(
-            "Strict return doesn't allow empty fields",
-            b",\n",
-            &["--strict-return", "-d", ","],
+            "Only-delimited in byte mode errors",
+            b"a,b\n",
+            &["-s", "-b", "1"],
         );
     }
+}
+
+mod field_dedup_key {
+    use super::*;
 
     #[test]
-    fn strict_return_counts() {
+    fn keeps_first_row_per_key() {
         run_success_test(
-            "Strict return counts",
-            b",\n",
-            &["--strict-return", "--count", "-d", ","],
-            b"2\n",
+            "Dedup key keeps the first row for each key",
+            b"id1,a\nid2,b\nid1,c\nid3,d\nid2,e\n",
+            &["-d", ",", "--field-dedup-key=1"],
+            b"id1,a\nid2,b\nid3,d\n",
         );
     }
 
     #[test]
-    fn start_after_end_no_strict_range_order() {
+    fn key_is_independent_of_selection() {
         run_success_test(
-            "Start after end (no strict range order)",
-            b"this is a test\n",
-            &["--no-strict-range-order", "-d", " ", "2-1"],
-            b"\n",
+            "Dedup key can differ from the selected fields",
+            b"id1,a\nid2,b\nid1,c\n",
+            &["-d", ",", "--field-dedup-key", "1", "2"],
+            b"a\nb\n",
         );
     }
 
     #[test]
-    fn start_after_end_negative_no_strict_range_order() {
+    fn negative_key_index() {
         run_success_test(
-            "Start after end negative (no strict range order)",
-            b"this is a test\n",
-            &["--no-strict-range-order", "-d", " ", "-1--2"],
-            b"\n",
+            "Dedup key with a negative index",
+            b"a,x\nb,y\nc,x\n",
+            &["-d", ",", "--field-dedup-key=-1", "1"],
+            b"a\nb\n",
         );
     }
 
     #[test]
-    fn start_after_end_positive_negative_no_strict_range_order() {
+    fn missing_key_field_is_empty_key() {
         run_success_test(
-            "Start after end positive-negative (no strict range order)",
-            b"this is a test\n",
-            &["--no-strict-range-order", "-d", " ", "4--2"],
-            b"\n",
+            "Records missing the key field share the empty key",
+            b"a\nb\nc,1\n",
+            &["-d", ",", "--field-dedup-key=2"],
+            b"a\nc,1\n",
         );
     }
 
     #[test]
-    fn start_after_end_negative_positive_no_strict_range_order() {
-        run_success_test(
-            "Start after end negative-positive (no strict range order)",
-            b"this is a test\n",
-            &["--no-strict-range-order", "-d", " ", "-1-3"],
-            b"\n",
+    fn zero_key_errors() {
+        run_error_test(
+            "Dedup key of 0 errors",
+            b"a,b\n",
+            &["-d", ",", "--field-dedup-key=0"],
         );
     }
 
     #[test]
-    fn start_after_end_strict_range() {
+    fn in_byte_mode_errors() {
         run_error_test(
-            "Start after end (strict range)",
-            b"this is a test\n",
-            &["-d", " ", "2-1"],
+            "Dedup key in byte mode errors",
+            b"a,b\n",
+            &["-b", "--field-dedup-key=1"],
         );
     }
+}
+
+mod assert_sorted {
+    use super::*;
 
     #[test]
-    fn start_after_end_negative_strict_range() {
-        run_error_test(
-            "Start after end negative (strict range)",
-            b"this is a test\n",
-            &["-d", " ", "-1--2"],
+    fn sorted_input_passes() {
+        run_success_test(
+            "Assert-sorted passes sorted input, including ties",
+            b"a,1\nb,2\nb,3\n",
+            &["-d", ",", "--assert-sorted", "1", "2"],
+            b"1\n2\n3\n",
         );
     }
 
     #[test]
-    fn start_after_end_positive_negative_strict_range() {
+    fn unsorted_input_errors() {
         run_error_test(
-            "Start after end positive-negative (strict range)",
-            b"this is a test\n",
-            &["-d", " ", "4--2"],
+            "Assert-sorted errors on unsorted input",
+            b"a,1\nc,2\nb,3\n",
+            &["-d", ",", "--assert-sorted=1", "2"],
         );
     }
 
     #[test]
-    fn start_after_end_negative_positive_strict_range() {
+    fn byte_comparison_by_default() {
         run_error_test(
-            "Start after end negative-positive (strict range)",
-            b"this is a test\n",
-            &["-d", " ", "-1-3"],
+            "Assert-sorted compares bytes by default",
+            b"x,9\ny,10\n",
+            &["-d", ",", "--assert-sorted", "2", "1"],
         );
     }
 
     #[test]
-    fn strict_return_only_delimiter() {
+    fn numeric_comparison() {
+        run_success_test(
+            "Assert-sorted with --numeric compares numbers",
+            b"x,9\ny,10\nz,10.5\n",
+            &["-d", ",", "--assert-sorted", "2", "--numeric", "1"],
+            b"x\ny\nz\n",
+        );
         run_error_test(
-            "Strict return fails with no valid fields",
-            b",\n",
-            &["--strict", "-d", ","],
+            "Assert-sorted with --numeric errors on non-numbers",
+            b"x,9\ny,z\n",
+            &["-d", ",", "--assert-sorted", "2", "--numeric", "1"],
         );
     }
 
     #[test]
-    fn strict_enables_strict_return() {
+    fn rejects_invalid_combinations() {
         run_error_test(
-            "Strict enables strict-return",
-            b",\n",
-            &["--strict", "-d", ","],
+            "Numeric needs assert-sorted",
+            b"a,1\n",
+            &["-d", ",", "--numeric", "1"],
+        );
+        run_error_test(
+            "Assert-sorted can't be unordered",
+            b"a,1\n",
+            &["-d", ",", "--assert-sorted", "1", "--unordered"],
+        );
+        run_error_test(
+            "Assert-sorted rejects field 0",
+            b"a,1\n",
+            &["-d", ",", "--assert-sorted", "0"],
         );
     }
+}
+
+mod number_records {
+    use super::*;
 
     #[test]
-    fn no_strict_clears_strict_flags() {
+    fn prefixes_each_record() {
         run_success_test(
-            "No-strict clears strict flags",
-            b"a b\n",
-            &["--strict", "--no-strict", "-d", " ", "5"],
-            b"\n",
+            "Number prefixes each record with its index and a tab",
+            b"a,b\nc,d\ne,f\n",
+            &["-d", ",", "--number", "2"],
+            b"1\tb\n2\td\n3\tf\n",
         );
     }
-}
-
-mod skip_empty {
-    use super::*;
 
     #[test]
-    fn starting_empty_field() {
+    fn numbers_counts() {
         run_success_test(
-            "Starting empty field",
-            b",orange\n",
-            &["--skip-empty", "-d", ",", "1"],
-            b"orange\n",
+            "Number with count numbers the per-line counts",
+            b"a,b\nc\n",
+            &["-d", ",", "-n", "--count"],
+            b"1\t2\n2\t1\n",
         );
     }
 
     #[test]
-    fn middle_field_empty() {
+    fn zero_terminated() {
         run_success_test(
-            "Middle field empty",
-            b"apple,,orange\n",
-            &["--skip-empty", "-d", ",", "2"],
-            b"orange\n",
+            "Number in zero-terminated mode",
+            b"a,b\0c,d\0",
+            &["-z", "-d", ",", "-n", "1"],
+            b"1\ta\x002\tc\x00",
         );
     }
 
     #[test]
-    fn final_field_empty() {
+    fn keeps_input_numbers_when_records_are_dropped() {
         run_success_test(
-            "Final field empty",
-            b"orange,\n",
-            &["--skip-empty", "-d", ",", "2"],
-            b"\n",
+            "Number uses input record numbers when records are dropped",
+            b"a,b\nnone\nc,d\n",
+            &["-d", ",", "-n", "-s", "2"],
+            b"1\tb\n3\td\n",
         );
     }
 
     #[test]
-    fn all_fields_empty() {
+    fn whole_string_numbers_once() {
         run_success_test(
-            "All fields empty",
-            b",\n",
-            &["--skip-empty", "-d", ","],
-            b"\n",
+            "Number in whole-string mode applies once",
+            b"a,b",
+            &["-w", "-d", ",", "-n", "2"],
+            b"1\tb",
         );
     }
+}
+
+mod jobs {
+    use super::*;
 
     #[test]
-    fn known_failure() {
+    fn single_worker() {
         run_success_test(
-            "Known failure",
-            b"a  b   c\n",
-            &["-d", " ", "--skip-empty", "1-3"],
-            b"a b c\n",
+            "Jobs 1 processes everything in order",
+            b"a,1\nb,2\nc,3\n",
+            &["-J", "1", "-d", ",", "2"],
+            b"1\n2\n3\n",
         );
     }
 
     #[test]
-    fn skip_with_strict_bounds_works() {
+    fn explicit_count_and_auto() {
         run_success_test(
-            "Skip with strict bounds works",
-            b"orange,\n",
-            &["--skip-empty", "--strict-bounds", "-d", ",", "1"],
-            b"orange\n",
+            "Jobs with an explicit worker count",
+            b"a,1\nb,2\n",
+            &["--jobs=4", "-d", ",", "1"],
+            b"a\nb\n",
+        );
+        run_success_test(
+            "Jobs auto",
+            b"a,1\nb,2\n",
+            &["--jobs", "auto", "-d", ",", "1"],
+            b"a\nb\n",
+        );
+        run_success_test(
+            "Jobs 0 means auto",
+            b"a,1\n",
+            &["--jobs", "0", "-d", ",", "1"],
+            b"a\n",
         );
     }
 
     #[test]
-    fn skip_with_strict_bounds_fails() {
+    fn rejects_invalid_counts() {
         run_error_test(
-            "Skip with strict bounds fails",
-            b"orange,\n",
-            &["--skip-empty", "--strict-bounds", "-d", ",", "2"],
+            "Jobs rejects negative values",
+            b"a\n",
+            &["--jobs", "-1", "-b", "1"],
         );
+        run_error_test("Jobs rejects words", b"a\n", &["--jobs=many", "-b", "1"]);
+        run_error_test("Jobs needs a value", b"a\n", &["-b", "1", "--jobs"]);
     }
+}
+
+mod progress {
+    use super::*;
 
     #[test]
-    fn skip_with_strict_return_works() {
+    fn silent_for_piped_input() {
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["--progress", "-d", ",", "2"])
+            .write_stdin("a,1\nb,2\n")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"1\n2\n");
+        assert!(
+            output.stderr.is_empty(),
+            "stdin has no size to show progress against"
+        );
+    }
+
+    #[test]
+    fn silent_when_stderr_is_not_a_terminal() {
+        let contents: Vec<u8> = (0..50_000)
+            .flat_map(|line| format!("{line},x\n").into_bytes())
+            .collect();
+        let file = TempFile::new("progress", &contents);
+
+        for arguments in [
+            vec!["--progress", "-d", ",", "1"],
+            vec!["--progress", "--align", "-d", ",", "1-2"],
+        ] {
+            let with_progress = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+                .args(&arguments)
+                .arg(file.path())
+                .output()
+                .unwrap();
+            let without_progress = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+                .args(&arguments[1..])
+                .arg(file.path())
+                .output()
+                .unwrap();
+            assert!(with_progress.status.success());
+            assert!(with_progress.stderr.is_empty(), "{arguments:?}");
+            assert_eq!(
+                with_progress.stdout, without_progress.stdout,
+                "{arguments:?}"
+            );
+        }
+    }
+}
+
+mod buffering {
+    use super::*;
+
+    #[test]
+    fn line_buffered_output_matches() {
         run_success_test(
-            "Skip with strict return works",
-            b"orange,\n",
-            &["--skip-empty", "--strict-return", "-d", ",", "1"],
-            b"orange\n",
+            "Line-buffered output is unchanged",
+            b"a,1\nb,2\n\nc,3\n",
+            &["--line-buffered", "-d", ",", "2"],
+            b"1\n2\n\n3\n",
         );
     }
 
     #[test]
-    fn skip_with_strict_return_fails() {
+    fn batch_bytes_sizes() {
+        run_success_test(
+            "Batch-bytes 0 sends each record alone",
+            b"a,1\nb,2\nc,3\n",
+            &["--batch-bytes", "0", "-d", ",", "2"],
+            b"1\n2\n3\n",
+        );
+        run_success_test(
+            "Batch-bytes with a small quota",
+            b"a,1\nb,2\nc,3\n",
+            &["--batch-bytes=5", "-d", ",", "1"],
+            b"a\nb\nc\n",
+        );
+    }
+
+    #[test]
+    fn line_buffered_flushes_each_record() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::process::{Command as ProcessCommand, Stdio};
+
+        let mut child = ProcessCommand::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args(["--line-buffered", "-d", ",", "2"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("splitby should start");
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+
+        // The first record must come back while stdin is still open
+        stdin
+            .write_all(b"a,1\n")
+            .expect("writing to splitby should not fail");
+        stdin.flush().expect("flushing stdin should not fail");
+        let mut line = String::new();
+        stdout
+            .read_line(&mut line)
+            .expect("reading from splitby should not fail");
+        assert_eq!(line, "1\n");
+
+        drop(stdin);
+        child.wait().expect("splitby should exit");
+    }
+
+    #[test]
+    fn rejects_invalid_batch_bytes() {
         run_error_test(
-            "Skip with strict return fails",
-            b",,\n",
-            &["--skip-empty", "--strict-return", "-d", ",", "1"],
+            "Batch-bytes rejects negatives",
+            b"a\n",
+            &["--batch-bytes=-1", "-b", "1"],
         );
+        run_error_test(
+            "Batch-bytes needs a value",
+            b"a\n",
+            &["-b", "1", "--batch-bytes"],
+        );
+    }
+}
+
+mod read_buffer {
+    use super::*;
+
+    #[test]
+    fn large_file_matches_across_buffer_sizes() {
+        // Some records run past the smallest buffer, so they span several reads
+        let mut input: Vec<u8> = Vec::new();
+        let mut expected: Vec<u8> = Vec::new();
+        for line in 0..20_000 {
+            let padding = "x".repeat(line % 3000);
+            input.extend_from_slice(format!("{line},{padding},end\n").as_bytes());
+            expected.extend_from_slice(format!("{line},end\n").as_bytes());
+        }
+        let file = TempFile::new("read-buffer", &input);
+
+        for size in ["1024", "4097", "65536", "1048576"] {
+            let read_buffer = format!("--read-buffer={size}");
+            run_success_test(
+                &format!("{read_buffer} on a file"),
+                b"",
+                &[",", &read_buffer, "-i", file.path(), "1", "3"],
+                &expected,
+            );
+            run_success_test(
+                &format!("{read_buffer} on stdin"),
+                &input,
+                &[",", &read_buffer, "1", "3"],
+                &expected,
+            );
+        }
     }
 
     #[test]
-    fn starting_empty_field_with_count() {
+    fn align_reads_with_the_buffer() {
+        let file = TempFile::new("read-buffer-align", b"apple,1\nfig,200\n");
         run_success_test(
-            "Starting empty field with count",
-            b",orange\n",
-            &["--skip-empty", "-d", ",", "--count"],
-            b"1\n",
+            "the align width scan reads through the same buffer",
+            b"",
+            &[",", "--read-buffer=1024", "--align", "-i", file.path()],
+            b"apple,1\nfig  ,200\n",
         );
     }
 
     #[test]
-    fn middle_field_empty_with_count() {
-        run_success_test(
-            "Middle field empty with count",
-            b"apple,,orange\n",
-            &["--skip-empty", "-d", ",", "--count"],
-            b"2\n",
+    fn too_small() {
+        run_error_test("below the minimum", b"a\n", &[",", "--read-buffer=1023"]);
+        run_error_test("not a number", b"a\n", &[",", "--read-buffer=big"]);
+    }
+
+    #[test]
+    fn too_large() {
+        run_error_test(
+            "above the maximum",
+            b"a\n",
+            &[",", "--read-buffer=1073741825"],
         );
     }
+}
+
+mod reorder_window {
+    use super::*;
 
     #[test]
-    fn final_field_empty_with_count() {
-        run_success_test(
-            "Final field empty with count",
-            b"orange,\n",
-            &["--skip-empty", "-d", ",", "--count"],
-            b"1\n",
+    fn keeps_order_with_many_workers() {
+        let input: String = (1..=20_000).map(|n| format!("{n},x\n")).collect();
+        let expected: String = (1..=20_000).map(|n| format!("{n}\n")).collect();
+        for window in ["1", "3", "64"] {
+            run_success_test(
+                "A small reorder window with many workers keeps the input order",
+                input.as_bytes(),
+                &[
+                    "-J",
+                    "16",
+                    "--batch-bytes=0",
+                    "--reorder-window",
+                    window,
+                    "-d",
+                    ",",
+                    "1",
+                ],
+                expected.as_bytes(),
+            );
+        }
+    }
+
+    #[test]
+    fn failed_writer_stops_the_reader() {
+        // Far more batches than the channels hold, so the reader has to notice the writer stop
+        let input = "a\n".repeat(200_000);
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+            .args([
+                "--batch-bytes=0",
+                "--reorder-window=4",
+                "-d",
+                ",",
+                "--strict-bounds",
+                "2",
+            ])
+            .write_stdin(input)
+            .timeout(std::time::Duration::from_secs(30))
+            .output()
+            .expect("running splitby should not fail");
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("strict-bounds error"),
+            "stderr should have the writer's error, got: {stderr}"
         );
     }
 
     #[test]
-    fn all_fields_empty_with_count() {
-        run_success_test(
-            "All fields empty with count",
-            b",\n",
-            &["--skip-empty", "-d", ",", "--count"],
-            b"0\n",
+    fn rejects_invalid_windows() {
+        run_error_test(
+            "Reorder-window rejects 0",
+            b"a\n",
+            &["--reorder-window=0", "-b", "1"],
+        );
+        run_error_test(
+            "Reorder-window needs a value",
+            b"a\n",
+            &["-b", "1", "--reorder-window"],
         );
     }
+}
+
+mod unordered_output {
+    use super::*;
 
     #[test]
-    fn no_skip_empty_overrides_skip_empty() {
-        run_success_test(
-            "No-skip-empty overrides skip-empty",
-            b"a,,b\n",
-            &["-d", ",", "--count", "--skip-empty", "--no-skip-empty"],
-            b"3\n",
+    fn outputs_every_record_once() {
+        let input: String = (1..=5000).map(|value| format!("{value},x\n")).collect();
+        let mut command = Command::new(assert_cmd::cargo::cargo_bin!("splitby"));
+        command.args(["-d", ",", "--unordered", "1"]);
+        command.env("SPLITBY_BATCH_QUOTA", "64");
+        command.write_stdin(input.as_bytes());
+
+        let output = command.output().expect("failed to run splitby");
+        assert!(output.status.success(), "expected success, got failure");
+
+        let mut actual: Vec<u32> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.parse().expect("each line should be a number"))
+            .collect();
+        actual.sort_unstable();
+        let expected: Vec<u32> = (1..=5000).collect();
+        assert_eq!(
+            actual, expected,
+            "unordered output should contain every record once"
+        );
+    }
+
+    #[test]
+    fn ordered_overrides_unordered() {
+        run_success_test(
+            "Ordered after unordered restores input order",
+            b"a,1\nb,2\nc,3\n",
+            &["-d", ",", "--unordered", "--ordered", "1"],
+            b"a\nb\nc\n",
         );
     }
 
     #[test]
-    fn skip_empty_overrides_no_skip_empty() {
+    fn count_with_no_input() {
         run_success_test(
-            "Skip-empty overrides no-skip-empty",
-            b"a,,b\n",
-            &["-d", ",", "--count", "--no-skip-empty", "--skip-empty"],
-            b"2\n",
+            "Unordered count with no input still prints 0",
+            b"",
+            &["-d", ",", "--unordered", "--count"],
+            b"0",
         );
     }
 }
@@ -1579,6 +7026,160 @@ mod invalid_input {
     }
 }
 
+mod record_separator {
+    use super::*;
+
+    #[test]
+    fn splits_on_multi_char_separator() {
+        run_success_test(
+            "Record separator splits on a multi-character pattern",
+            b"a1--b2--c3",
+            &["--input-record-separator", "--", "-b", "2"],
+            b"1\n2\n3",
+        );
+    }
+
+    #[test]
+    fn paragraphs_as_records() {
+        run_success_test(
+            "Record separator splits blank-line separated paragraphs",
+            b"title one\nbody\n\ntitle two\nbody\n\n",
+            &["--input-record-separator=\\n\\n", "-d", "/\\n/", "1"],
+            b"title one\ntitle two\n",
+        );
+    }
+
+    #[test]
+    fn final_record_without_separator() {
+        run_success_test(
+            "A final record without a separator has no terminator",
+            b"a;b",
+            &["--input-record-separator", ";", "-b", "1"],
+            b"a\nb",
+        );
+    }
+
+    #[test]
+    fn zero_terminated_output() {
+        run_hex_output_test(
+            "Record separator with -z writes NUL terminators",
+            b"a;b;",
+            &["--input-record-separator", ";", "-z", "-b", "1"],
+            "61 00 62 00",
+        );
+    }
+
+    #[test]
+    fn rejects_empty_matches_and_whole_string() {
+        run_error_test(
+            "Record separator can't match an empty string",
+            b"ab",
+            &["--input-record-separator", "x*", "-b", "1"],
+        );
+        run_error_test(
+            "Record separator can't be used with whole-string",
+            b"ab",
+            &["--input-record-separator", ";", "-w", "-b", "1"],
+        );
+    }
+
+    #[test]
+    fn one_large_record() {
+        let mut input = vec![b'x'; 4 * 1024 * 1024];
+        input.extend_from_slice(b";y;");
+        let mut expected = vec![b'x'; 4 * 1024 * 1024];
+        expected.extend_from_slice(b"\ny\n");
+        run_success_test(
+            "A record much longer than the read buffer is found once it ends",
+            &input,
+            &[
+                "--input-record-separator=/;/",
+                "--max-record-bytes=8388608",
+                "-d",
+                ",",
+                "1",
+            ],
+            &expected,
+        );
+    }
+
+    #[test]
+    fn earlier_separator_beats_one_touching_the_end() {
+        // The first read ends after `xac`, where `c` matches but `acc` has yet to arrive
+        run_success_test(
+            "A separator completed by the next read wins over a later one",
+            b"xaccyy",
+            &["--input-record-separator=a.c|c", "-d", ",", "1"],
+            b"x\nyy",
+        );
+    }
+
+    #[test]
+    fn separators_across_read_boundaries() {
+        // Records of every length up to a few read buffers, so separators of each length
+        // land across the boundary between reads
+        let mut input: Vec<u8> = Vec::new();
+        let mut expected: Vec<u8> = Vec::new();
+        for length in 0..3000 {
+            input.extend(std::iter::repeat_n(b'x', length));
+            input.extend_from_slice(match length % 3 {
+                0 => b"ac".as_slice(),
+                1 => b"abc",
+                _ => b"abbbc",
+            });
+            expected.extend(std::iter::repeat_n(b'x', length));
+            expected.push(b'\n');
+        }
+        for separator in [
+            "--input-record-separator=/ab{0,3}c/",
+            "--input-record-separator=/ab*c/",
+        ] {
+            run_success_test(
+                separator,
+                &input,
+                &[separator, "--read-buffer=1024", "-d", ",", "1"],
+                &expected,
+            );
+        }
+    }
+}
+
+mod strip_cr {
+    use super::*;
+
+    #[test]
+    fn zero_terminated_records_ending_in_cr() {
+        run_hex_output_test(
+            "each NUL-separated record loses its trailing \\r",
+            b"a,1\r\0b,2\r\0",
+            &["-z", "-d", ",", "--strip-cr", "2"],
+            "31 00 32 00",
+        );
+        run_hex_output_test(
+            "without the flag the \\r is kept",
+            b"a,1\r\0",
+            &["-z", "-d", ",", "2"],
+            "31 0d 00",
+        );
+    }
+
+    #[test]
+    fn whole_string_and_separated_records() {
+        run_success_test(
+            "whole-string mode strips the \\r before the final newline",
+            b"a,1\r\n",
+            &["-w", "-d", ",", "--strip-cr", "2"],
+            b"1\n",
+        );
+        run_success_test(
+            "records split on a separator regex",
+            b"a\r;b\r;",
+            &["--input-record-separator=;", "-d", ",", "--strip-cr", "1"],
+            b"a\nb\n",
+        );
+    }
+}
+
 mod zero_terminated_mode {
     use super::*;
 
@@ -1839,6 +7440,49 @@ mod byte_mode {
     }
 }
 
+mod codepoints {
+    use super::*;
+
+    #[test]
+    fn graphemes_by_default() {
+        run_success_test(
+            "A decomposed e and its accent are one grapheme",
+            "be\u{301}t\n".as_bytes(),
+            &["-c", "--count"],
+            b"3\n",
+        );
+    }
+
+    #[test]
+    fn counts_code_points() {
+        run_success_test(
+            "--codepoints counts the combining accent separately",
+            "be\u{301}t\n".as_bytes(),
+            &["-c", "--codepoints", "--count"],
+            b"4\n",
+        );
+    }
+
+    #[test]
+    fn selects_code_points() {
+        run_success_test(
+            "--codepoints can select the base letter without its accent",
+            "be\u{301}t\n".as_bytes(),
+            &["-c", "--codepoints", "1-2"],
+            b"be\n",
+        );
+    }
+
+    #[test]
+    fn needs_chars_mode() {
+        run_error_test(
+            "--codepoints outside chars mode is rejected",
+            b"a,b\n",
+            &["-d", ",", "--codepoints", "1"],
+        );
+    }
+}
+
 mod char_mode {
     use super::*;
 
@@ -2076,6 +7720,124 @@ mod char_mode {
     }
 }
 
+mod capture_mode {
+    use super::*;
+
+    #[test]
+    fn selects_groups_in_order_given() {
+        run_success_test(
+            "Capture mode selects groups in the order given",
+            b"key=value\nfoo=bar\n",
+            &["--capture", "(\\w+)=(\\w+)", "2", "1"],
+            b"value key\nbar foo\n",
+        );
+    }
+
+    #[test]
+    fn group_zero_is_whole_match() {
+        run_success_test(
+            "Capture group 0 is the whole match",
+            b"x key=value y\n",
+            &["--capture=(\\w+)=(\\w+)", "0"],
+            b"key=value\n",
+        );
+    }
+
+    #[test]
+    fn no_selection_outputs_whole_match() {
+        run_success_test(
+            "Capture mode without selections outputs the whole match",
+            b"x key=value y\n",
+            &["--capture", "/(\\w+)=(\\w+)/"],
+            b"key=value\n",
+        );
+    }
+
+    #[test]
+    fn unmatched_group_uses_placeholder() {
+        run_success_test(
+            "Capture group that did not participate uses placeholder",
+            b"key=value\n",
+            &[
+                "--capture",
+                "(\\w+)=(\\w+)(!)?",
+                "--placeholder=NA",
+                "--join=,",
+                "1-3",
+            ],
+            b"key,value,NA\n",
+        );
+    }
+
+    #[test]
+    fn unmatched_group_is_empty_without_placeholder() {
+        run_success_test(
+            "Capture group that did not participate is empty without placeholder",
+            b"key=value\n",
+            &["--capture", "(\\w+)=(\\w+)(!)?", "--join=,", "1-3"],
+            b"key,value,\n",
+        );
+    }
+
+    #[test]
+    fn no_match_gives_empty_record() {
+        run_success_test(
+            "Capture mode with no match gives an empty record",
+            b"nothing here\nk=v\n",
+            &["--capture", "(\\w+)=(\\w+)", "1"],
+            b"\nk\n",
+        );
+    }
+
+    #[test]
+    fn fancy_regex_pattern() {
+        run_success_test(
+            "Capture mode falls back to fancy regex",
+            b"price: 42 dollars\n",
+            &["--capture", "(?<=price: )(\\d+)", "1"],
+            b"42\n",
+        );
+    }
+
+    #[test]
+    fn count_reports_group_count() {
+        run_success_test(
+            "Capture mode count reports the number of groups",
+            b"a=b\n",
+            &["--capture", "(\\w+)=(\\w+)", "--count"],
+            b"2\n",
+        );
+    }
+
+    #[test]
+    fn invert_excludes_whole_match() {
+        run_success_test(
+            "Capture mode invert selects other groups, not the whole match",
+            b"a=b\n",
+            &["--capture", "(\\w+)=(\\w+)", "--invert", "1"],
+            b"b\n",
+        );
+    }
+
+    #[test]
+    fn missing_pattern_errors() {
+        run_error_test(
+            "Capture mode without a pattern errors",
+            b"a=b\n",
+            &["--capture"],
+        );
+    }
+
+    #[test]
+    fn invalid_pattern_errors() {
+        run_error_test(
+            "Capture mode with an invalid pattern errors",
+            b"a=b\n",
+            &["--capture", "(unclosed", "1"],
+        );
+    }
+}
+
 mod hex_parsing {
     use super::*;
 
@@ -2420,6 +8182,107 @@ mod hex_parsing {
     }
 }
 
+mod align_two_pass {
+    use super::*;
+
+    const TABLE: &[u8] = b"# note\nname,qty,price\napple,3,1.5\nwatermelon,12,0.25\nfig,100,10";
+
+    #[test]
+    fn file_matches_buffered_stdin() {
+        let file = TempFile::new("align-two-pass", TABLE);
+        for align in ["--align", "--align=right", "--align=center"] {
+            let arguments = ["-d", ",", "--comment", "#", align, "3", "1"];
+            let buffered = Command::new(assert_cmd::cargo::cargo_bin!("splitby"))
+                .args(arguments)
+                .write_stdin(TABLE)
+                .output()
+                .expect("running splitby should not fail");
+            assert!(buffered.status.success());
+            let mut file_arguments = arguments.to_vec();
+            file_arguments.extend(["-i", file.path()]);
+            run_success_test(
+                &format!("{align} on a file matches the buffered stdin path"),
+                b"",
+                &file_arguments,
+                &buffered.stdout,
+            );
+        }
+    }
+
+    #[test]
+    fn several_files_share_widths() {
+        let first = TempFile::new("align-two-pass-1", b"a,bb\n");
+        let second = TempFile::new("align-two-pass-2", b"cccc,d\n");
+        run_success_test(
+            "Widths span every file read twice",
+            b"",
+            &["-d", ",", "--align", first.path(), second.path()],
+            b"a   ,bb\ncccc,d\n",
+        );
+    }
+
+    #[test]
+    fn header_and_max_records_on_second_pass() {
+        let file = TempFile::new("align-two-pass-header", b"id,name\n1,annabel\n2,bo\n");
+        run_success_test(
+            "Header names and max records apply the same on both passes",
+            b"",
+            &[
+                "-d",
+                ",",
+                "--align",
+                "--header",
+                "name,id",
+                "--max-records=2",
+                file.path(),
+            ],
+            b"name   ,id\nannabel,1\n",
+        );
+    }
+}
+
+mod align_with_tabs {
+    use super::*;
+
+    #[test]
+    fn tabs_at_column_seams() {
+        run_success_test(
+            "a ragged table gets one tab between columns, no padding",
+            b"apple,banana,cherry\na,b\nlonger,x,y,z\n",
+            &["--align", "--align-with=tabs", ","],
+            b"apple\tbanana\tcherry\na\tb\nlonger\tx\ty\tz\n",
+        );
+        run_success_test(
+            "selections are joined the same way",
+            b"a,bb,ccc\ndddd,e,f\n",
+            &["-a", "--align-with", "tabs", ",", "1", "3"],
+            b"a\tccc\ndddd\tf\n",
+        );
+        run_success_test(
+            "spaces is the usual padding",
+            b"apple,b\na,b\n",
+            &["--align", "--align-with=spaces", ","],
+            b"apple,b\na    ,b\n",
+        );
+    }
+
+    #[test]
+    fn invalid_combinations() {
+        run_error_test("needs --align", b"a,b\n", &["--align-with=tabs", ","]);
+        run_error_test(
+            "only left alignment",
+            b"a,b\n",
+            &["--align=right", "--align-with=tabs", ","],
+        );
+        run_error_test(
+            "--join",
+            b"a,b\n",
+            &["-a", "--align-with=tabs", "-j", ";", ","],
+        );
+        run_error_test("unknown value", b"a,b\n", &["-a", "--align-with=dots", ","]);
+    }
+}
+
 mod align {
     use super::*;
 
@@ -2433,6 +8296,97 @@ mod align {
         );
     }
 
+    #[test]
+    fn right_alignment_numeric_columns() {
+        run_success_test(
+            "Align: right alignment lines up numeric columns, including the last",
+            b"1,22,333\n4444,5,66\n10,200,7\n",
+            &["-d", ",", "--align=right", "1", "2", "3"],
+            b"   1, 22,333\n4444,  5, 66\n  10,200,  7\n",
+        );
+    }
+
+    #[test]
+    fn right_alignment_single_column() {
+        run_success_test(
+            "Align: right alignment pads a lone final field",
+            b"7\n1234\n56\n",
+            &["-d", ",", "--align=right", "1"],
+            b"   7\n1234\n  56\n",
+        );
+    }
+
+    #[test]
+    fn squash_collapses_irregular_spacing() {
+        run_success_test(
+            "Align: squash collapses runs of spaces to one",
+            b"a   b  c\nxx y z\n",
+            &["-d", " ", "--align=squash"],
+            b"a b c\nxx y z\n",
+        );
+        run_success_test(
+            "Align: squash selections index the collapsed fields",
+            b"a   b  c\n",
+            &["-d", " ", "--align=squash", "2", "3"],
+            b"b c\n",
+        );
+    }
+
+    #[test]
+    fn squash_uses_join_string() {
+        run_success_test(
+            "Align: squash joins with --join when given",
+            b"a   b  c\n",
+            &["-d", " ", "--align=squash", "-j", ","],
+            b"a,b,c\n",
+        );
+    }
+
+    #[test]
+    fn squash_streams_whole_string() {
+        run_success_test(
+            "Align: squash works outside per-line mode",
+            b"a,,b",
+            &["-d", ",", "--align=squash", "-w"],
+            b"a b",
+        );
+    }
+
+    #[test]
+    fn center_alignment() {
+        run_success_test(
+            "Align: center alignment biases odd padding to the right",
+            b"apple,banana,cherry\na,bb,ccc\nx,y,z\n",
+            &["-d", ",", "--align=center", "1", "2", "3"],
+            b"apple,banana,cherry\n  a  ,  bb  , ccc\n  x  ,  y   ,  z\n",
+        );
+    }
+
+    #[test]
+    fn custom_pad_char() {
+        run_success_test(
+            "Align: custom pad character",
+            b"apple,banana,cherry\na,bb,ccc\nx,y,z\n",
+            &["-d", ",", "--align", "--pad-char=.", "1", "2", "3"],
+            b"apple,banana,cherry\na....,bb....,ccc\nx....,y.....,z\n",
+        );
+        run_success_test(
+            "Align: hex pad character with right alignment",
+            b"apple,banana\na,bb\n",
+            &["-d", ",", "--align=right", "--pad-char", "0x30", "1", "2"],
+            b"apple,banana\n0000a,0000bb\n",
+        );
+    }
+
+    #[test]
+    fn pad_char_must_be_single_character() {
+        run_error_test(
+            "Align: multi-character pad is rejected",
+            b"a,b\n",
+            &["-d", ",", "--align", "--pad-char", "ab"],
+        );
+    }
+
     #[test]
     fn align_no_padding_after_final_field() {
         run_success_test(
@@ -2686,23 +8640,21 @@ mod flag_syntax {
 
     #[test]
     fn equals_syntax_align_right() {
-        // Note: align=right currently behaves like left (feature not fully implemented)
         run_success_test(
             "Equals syntax: --align=right",
             b"apple,banana\na,bb\n",
             &["-d", ",", "--align=right", "1", "2"],
-            b"apple,banana\n    a,    bb\n", // Current behavior matches left
+            b"apple,banana\n    a,    bb\n",
         );
     }
 
     #[test]
     fn equals_syntax_align_squash() {
-        // Note: align=squash currently behaves like left (feature not fully implemented)
         run_success_test(
             "Equals syntax: --align=squash",
             b"apple,banana\na,bb\n",
             &["-d", ",", "--align=squash", "1", "2"],
-            b"apple,banana\na,    bb\n", // Current behavior matches left
+            b"apple banana\na bb\n",
         );
     }
 